@@ -1,24 +1,151 @@
+use super::manifest::{self, Manifest};
+use compiler::lint::Lint;
 use std::convert::{From, Into};
-use std::{env, fmt, path::PathBuf};
+use std::{
+    env,
+    ffi::OsString,
+    fmt,
+    io::{self, IsTerminal},
+    iter,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone)]
 enum Setting {
     Version,
     Help,
     Untrusted,
+    UndefinedAsNil,
+    Test,
+    Dependencies,
+    Format,
+    Docs,
+    DocsFormat(DocsFormat),
+    Lint,
+    /// Written as `<setting>=<lint>`.
+    Allow(Lint),
+    /// An allow setting whose value isn't the name of a lint.
+    UnknownLint(String),
+    InvariantChecks,
+    Trace,
+    /// Written as `<setting>=<limit>`.
+    Limit(Limit, usize),
+    /// A limit setting whose value isn't a non-negative integer.
+    InvalidLimit(String),
+    /// Written as `<setting>=<instructions>`.
+    Budget(u64),
+    /// Written as `<setting>=<instructions>`.
+    InterruptAfter(u64),
+    /// Written as `<setting> <source>`, the program to run instead of a file.
+    Inline(String),
+    /// An inline setting that isn't followed by the source.
+    MissingSource,
+    /// A budget setting whose value isn't a positive integer.
+    InvalidBudget(String),
+    /// Written as `<setting>` or `<setting>=<function>`, the name is `None` in the former.
+    Entry(Option<String>),
     Unknown(String),
 }
 
+#[derive(Debug, Clone, Copy)]
+enum Limit {
+    CallDepth,
+    Elements,
+    OutputBytes,
+    ListLength,
+}
+
 const VERSION: &str = "--الإصدار";
 const HELP: &str = "--ساعد";
 const UNTRUSTED: &str = "--غير-موثوق";
+const UNDEFINED_AS_NIL: &str = "--غير-المعرف-عدم";
+const TEST: &str = "--اختبر";
+const DEPENDENCIES: &str = "--اعتماديات";
+const FORMAT: &str = "--نسق";
+const DOCS: &str = "--وثائق";
+const JSON: &str = "--json";
+const MARKDOWN: &str = "--markdown";
+const LINT: &str = "--تحقّق";
+const ALLOW: &str = "--اسمح";
+const INVARIANT_CHECKS: &str = "--تحقق-داخلي";
+const TRACE: &str = "--تتبع";
+const CALL_DEPTH_LIMIT: &str = "--حد-العمق";
+const ELEMENTS_LIMIT: &str = "--حد-العناصر";
+const OUTPUT_LIMIT: &str = "--حد-المخرجات";
+const LIST_LENGTH_LIMIT: &str = "--حد-طول-القائمة";
+const BUDGET: &str = "--ميزانية";
+const INTERRUPT_AFTER: &str = "--قاطع-بعد";
+const INLINE: &str = "--نفّذ";
+const ENTRY: &str = "--الرئيسية";
+/// The function `ENTRY` calls when it isn't given one.
+const DEFAULT_ENTRY: &str = "الرئيسية";
+/// Used in place of the path to read the program from the standard input.
+const STDIN: &str = "-";
+/// Used in place of the path to create a new project, followed by its path.
+const NEW: &str = "جديد";
+
+impl Limit {
+    fn setting(&self) -> &'static str {
+        match self {
+            Self::CallDepth => CALL_DEPTH_LIMIT,
+            Self::Elements => ELEMENTS_LIMIT,
+            Self::OutputBytes => OUTPUT_LIMIT,
+            Self::ListLength => LIST_LENGTH_LIMIT,
+        }
+    }
+}
 
 impl From<String> for Setting {
     fn from(value: String) -> Self {
+        if let Some((setting, limit)) = value.split_once('=') {
+            if setting == ALLOW {
+                return match Lint::from_name(limit) {
+                    Some(lint) => Self::Allow(lint),
+                    None => Self::UnknownLint(value),
+                };
+            }
+            if setting == ENTRY {
+                return Self::Entry(Some(limit.to_owned()));
+            }
+            if setting == BUDGET {
+                return match limit.parse() {
+                    Ok(budget) if budget > 0 => Self::Budget(budget),
+                    _ => Self::InvalidBudget(value),
+                };
+            }
+            if setting == INTERRUPT_AFTER {
+                return match limit.parse() {
+                    Ok(instructions) => Self::InterruptAfter(instructions),
+                    Err(_) => Self::InvalidLimit(value),
+                };
+            }
+            let typ = match setting {
+                CALL_DEPTH_LIMIT => Limit::CallDepth,
+                ELEMENTS_LIMIT => Limit::Elements,
+                OUTPUT_LIMIT => Limit::OutputBytes,
+                LIST_LENGTH_LIMIT => Limit::ListLength,
+                _ => return Self::Unknown(value),
+            };
+            return match limit.parse() {
+                Ok(limit) => Self::Limit(typ, limit),
+                Err(_) => Self::InvalidLimit(value),
+            };
+        }
         match value.as_str() {
             VERSION => Self::Version,
             HELP => Self::Help,
             UNTRUSTED => Self::Untrusted,
+            UNDEFINED_AS_NIL => Self::UndefinedAsNil,
+            TEST => Self::Test,
+            DEPENDENCIES => Self::Dependencies,
+            FORMAT => Self::Format,
+            DOCS => Self::Docs,
+            JSON => Self::DocsFormat(DocsFormat::Json),
+            MARKDOWN => Self::DocsFormat(DocsFormat::Markdown),
+            LINT => Self::Lint,
+            INVARIANT_CHECKS => Self::InvariantChecks,
+            TRACE => Self::Trace,
+            ENTRY => Self::Entry(None),
             string => Self::Unknown(string.to_owned()),
         }
     }
@@ -30,6 +157,27 @@ impl Into<String> for Setting {
             Self::Version => VERSION.to_owned(),
             Self::Help => HELP.to_owned(),
             Self::Untrusted => UNTRUSTED.to_owned(),
+            Self::UndefinedAsNil => UNDEFINED_AS_NIL.to_owned(),
+            Self::Test => TEST.to_owned(),
+            Self::Dependencies => DEPENDENCIES.to_owned(),
+            Self::Format => FORMAT.to_owned(),
+            Self::Docs => DOCS.to_owned(),
+            Self::DocsFormat(DocsFormat::Json) => JSON.to_owned(),
+            Self::DocsFormat(DocsFormat::Markdown) => MARKDOWN.to_owned(),
+            Self::Lint => LINT.to_owned(),
+            Self::Allow(lint) => format!("{ALLOW}={}", lint.name()),
+            Self::UnknownLint(string) => string,
+            Self::InvariantChecks => INVARIANT_CHECKS.to_owned(),
+            Self::Trace => TRACE.to_owned(),
+            Self::Limit(typ, limit) => format!("{}={limit}", typ.setting()),
+            Self::InvalidLimit(string) => string,
+            Self::Budget(budget) => format!("{BUDGET}={budget}"),
+            Self::InvalidBudget(string) => string,
+            Self::InterruptAfter(instructions) => format!("{INTERRUPT_AFTER}={instructions}"),
+            Self::Entry(None) => ENTRY.to_owned(),
+            Self::Entry(Some(name)) => format!("{ENTRY}={name}"),
+            Self::Inline(source) => format!("{INLINE} {source}"),
+            Self::MissingSource => INLINE.to_owned(),
             Self::Unknown(string) => string,
         }
     }
@@ -41,13 +189,32 @@ enum Token {
     Path(PathBuf),
 }
 
-fn lex(iter: &mut env::Args) -> Result<Vec<Token>, ParseError> {
+/// Paths are taken as they are so that the ones that aren't valid UTF-8 still work, but settings (and the sources of
+/// inline programs) must be.
+///
+/// What follows the first path is taken as paths too, as it's the program's arguments (or the path of a new project).
+fn lex(iter: &mut impl Iterator<Item = OsString>) -> Result<Vec<Token>, ParseError> {
     iter.next();
     let mut tokens = vec![];
-    while let Some(string) = iter.next() {
-        match string.as_str() {
-            x if x.starts_with("--") => tokens.push(Token::Setting(Setting::from(string))),
-            path => tokens.push(Token::Path(PathBuf::from(path))),
+    while let Some(arg) = iter.next() {
+        if let Some(Token::Path(_)) = tokens.last() {
+            tokens.push(Token::Path(PathBuf::from(arg)));
+            continue;
+        }
+        match arg.to_str() {
+            Some(INLINE) => tokens.push(Token::Setting(
+                match iter.next().map(|source| source.into_string()) {
+                    Some(Ok(source)) => Setting::Inline(source),
+                    _ => Setting::MissingSource,
+                },
+            )),
+            Some(x) if x.starts_with("--") => {
+                tokens.push(Token::Setting(Setting::from(x.to_owned())))
+            }
+            None if arg.to_string_lossy().starts_with("--") => tokens.push(Token::Setting(
+                Setting::Unknown(arg.to_string_lossy().into_owned()),
+            )),
+            _ => tokens.push(Token::Path(PathBuf::from(arg))),
         }
     }
     Ok(tokens)
@@ -57,17 +224,36 @@ fn lex(iter: &mut env::Args) -> Result<Vec<Token>, ParseError> {
 struct Args {
     settings: Vec<Setting>,
     path: Option<PathBuf>,
+    /// What follows the path, which is passed to the entry function.
+    program_args: Vec<String>,
+    /// The path of the project to create.
+    new: Option<PathBuf>,
 }
 
 impl Args {
-    fn new(settings: Vec<Setting>, path: Option<PathBuf>) -> Self {
-        Self { settings, path }
+    fn new(
+        settings: Vec<Setting>,
+        path: Option<PathBuf>,
+        program_args: Vec<String>,
+        new: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            settings,
+            path,
+            program_args,
+            new,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum ParseError {
     ExpectedPathOrSetting(String),
+    ExpectedProjectPath,
+    ExpectedLimit(String),
+    ExpectedBudget(String),
+    UnknownLint(String),
+    ExpectedSource,
 }
 
 impl fmt::Display for ParseError {
@@ -79,6 +265,21 @@ impl fmt::Display for ParseError {
                     "توقعت مسار ملف أو أحد الإعدادات ولكن حصلت على \"{string}\""
                 )
             }
+            Self::ExpectedProjectPath => write!(f, "توقعت مسار المشروع الجديد بعد \"{NEW}\""),
+            Self::ExpectedLimit(string) => {
+                write!(f, "توقعت عدداً صحيحاً غير سالب بعد \"=\" في \"{string}\"")
+            }
+            Self::ExpectedBudget(string) => {
+                write!(f, "توقعت عدداً صحيحاً موجباً بعد \"=\" في \"{string}\"")
+            }
+            Self::UnknownLint(string) => {
+                let lints = Lint::ALL.map(|lint| lint.name()).join("، ");
+                write!(
+                    f,
+                    "توقعت اسم أحد التحذيرات ({lints}) بعد \"=\" في \"{string}\""
+                )
+            }
+            Self::ExpectedSource => write!(f, "توقعت شيفرة البرنامج بعد \"{INLINE}\""),
         }
     }
 }
@@ -91,6 +292,12 @@ fn parse(tokens: Vec<Token>) -> Result<Args, ParseError> {
             Setting::Unknown(string) => {
                 return Err(ParseError::ExpectedPathOrSetting(string.clone()))
             }
+            Setting::InvalidLimit(string) => return Err(ParseError::ExpectedLimit(string.clone())),
+            Setting::InvalidBudget(string) => {
+                return Err(ParseError::ExpectedBudget(string.clone()))
+            }
+            Setting::UnknownLint(string) => return Err(ParseError::UnknownLint(string.clone())),
+            Setting::MissingSource => return Err(ParseError::ExpectedSource),
             _ => {}
         }
         settings.push(setting.to_owned());
@@ -101,31 +308,109 @@ fn parse(tokens: Vec<Token>) -> Result<Args, ParseError> {
     } else {
         None
     };
-    Ok(Args::new(settings, path))
+    if path.as_deref() == Some(Path::new(NEW)) {
+        return match iter.next() {
+            Some(Token::Path(new)) => Ok(Args::new(settings, None, vec![], Some(new.to_owned()))),
+            _ => Err(ParseError::ExpectedProjectPath),
+        };
+    }
+    let program_args = iter
+        .map(|token| match token {
+            Token::Path(arg) => arg.to_string_lossy().into_owned(),
+            Token::Setting(setting) => setting.to_owned().into(),
+        })
+        .collect();
+    Ok(Args::new(settings, path, program_args, None))
+}
+
+/// The function called with the program's arguments after the top level runs.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    /// Whether the name was given, which makes it an error for the function not to be defined.
+    pub required: bool,
+    /// A list of strings is passed to the function.
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub untrusted: bool,
+    pub undefined_as_nil: bool,
+    /// Makes the VM verify its stacks after every instruction, which it already does in debug builds.
+    pub invariant_checks: bool,
+    /// Prints the line about to run and the locals of its frame whenever the line changes.
+    pub trace: bool,
+    pub max_call_depth: Option<usize>,
+    pub max_elements: Option<usize>,
+    pub max_output_bytes: Option<usize>,
+    pub max_list_len: Option<usize>,
+    /// Runs the program this many instructions at a time, resuming it whenever it suspends.
+    pub budget: Option<u64>,
+    /// Interrupts the program once this many instructions have run, as if Ctrl-C was pressed.
+    pub interrupt_after: Option<u64>,
+    pub entry: Option<Entry>,
 }
 
 #[derive(Debug, Clone)]
 pub enum EvalMode {
-    File(PathBuf, bool),
-    Repl,
+    File(PathBuf, Options),
+    Test(PathBuf, Options),
+    /// Reads the whole program from the standard input.
+    Stdin(Options),
+    /// Runs the source passed on the command line.
+    Inline(String, Options),
+    Repl(Options),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DocsFormat {
+    #[default]
+    Markdown,
+    Json,
 }
 
 #[derive(Clone)]
 pub enum Action {
     Eval(EvalMode),
+    New(PathBuf),
+    /// Prints the import graph of the file instead of running it.
+    Dependencies(PathBuf),
+    /// Prints the source of the file in the canonical style instead of running it.
+    Format(PathBuf),
+    /// Prints the documentation of the natives (ignores the file).
+    Docs(DocsFormat),
+    /// Prints the warnings of the lints that aren't allowed instead of running the file.
+    Lint(PathBuf, Vec<Lint>),
     Version,
     Help,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum CompileError {
     ExpectedPath,
+    /// A documentation format was given without asking for the documentation.
+    ExpectedDocs(DocsFormat),
+    /// A lint was allowed without asking for the lints.
+    ExpectedLint,
+    /// An inline program was given with a setting that needs a file.
+    ExpectedFileNotInline,
+    Manifest(manifest::Error),
 }
 
 impl fmt::Display for CompileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::ExpectedPath => write!(f, "توقعت مسار لملف"),
+            Self::ExpectedDocs(format) => {
+                let setting: String = Setting::DocsFormat(*format).into();
+                write!(f, "يستخدم \"{setting}\" مع \"{DOCS}\" فقط")
+            }
+            Self::ExpectedLint => write!(f, "يستخدم \"{ALLOW}\" مع \"{LINT}\" فقط"),
+            Self::ExpectedFileNotInline => {
+                write!(f, "لا يستخدم \"{INLINE}\" مع الإعدادات التي تحتاج إلى ملف")
+            }
+            Self::Manifest(err) => write!(f, "{err}"),
         }
     }
 }
@@ -134,32 +419,125 @@ impl TryFrom<Args> for Action {
     type Error = CompileError;
     fn try_from(value: Args) -> Result<Self, Self::Error> {
         let mut expect_path = false;
-        let mut untrusted = false;
+        let mut test = false;
+        let mut dependencies = false;
+        let mut format = false;
+        let mut docs = false;
+        let mut docs_format = None;
+        let mut lint = false;
+        let mut allowed = vec![];
+        let mut inline = None;
+        let mut options = Options::default();
         for setting in value.settings {
             match setting {
                 Setting::Help => return Ok(Self::Help),
                 Setting::Version => return Ok(Self::Version),
                 Setting::Untrusted => {
                     expect_path = true;
-                    untrusted = true;
+                    options.untrusted = true;
+                }
+                Setting::UndefinedAsNil => options.undefined_as_nil = true,
+                Setting::InvariantChecks => options.invariant_checks = true,
+                Setting::Trace => options.trace = true,
+                Setting::Limit(Limit::CallDepth, limit) => options.max_call_depth = Some(limit),
+                Setting::Limit(Limit::Elements, limit) => options.max_elements = Some(limit),
+                Setting::Limit(Limit::OutputBytes, limit) => options.max_output_bytes = Some(limit),
+                Setting::Limit(Limit::ListLength, limit) => options.max_list_len = Some(limit),
+                Setting::Budget(budget) => options.budget = Some(budget),
+                Setting::InterruptAfter(instructions) => {
+                    options.interrupt_after = Some(instructions)
+                }
+                Setting::Entry(name) => {
+                    options.entry = Some(Entry {
+                        required: name.is_some(),
+                        name: name.unwrap_or_else(|| DEFAULT_ENTRY.to_owned()),
+                        args: vec![],
+                    })
+                }
+                Setting::Test => {
+                    expect_path = true;
+                    test = true;
                 }
+                Setting::Dependencies => {
+                    expect_path = true;
+                    dependencies = true;
+                }
+                Setting::Format => {
+                    expect_path = true;
+                    format = true;
+                }
+                Setting::Docs => docs = true,
+                Setting::DocsFormat(typ) => docs_format = Some(typ),
+                Setting::Lint => {
+                    expect_path = true;
+                    lint = true;
+                }
+                Setting::Allow(typ) => allowed.push(typ),
+                Setting::Inline(source) => inline = Some(source),
                 _ => unreachable!(),
             }
         }
-        match value.path {
-            Some(path) => Ok(Self::Eval(EvalMode::File(path, untrusted))),
+        match docs_format {
+            _ if docs => return Ok(Self::Docs(docs_format.unwrap_or_default())),
+            Some(typ) => return Err(CompileError::ExpectedDocs(typ)),
+            None => {}
+        }
+        if !lint && !allowed.is_empty() {
+            return Err(CompileError::ExpectedLint);
+        }
+        if let Some(new) = value.new {
+            return Ok(Self::New(new));
+        }
+        if let Some(entry) = &mut options.entry {
+            // What follows the source is the program's input like what follows the file.
+            entry.args = match (&inline, &value.path) {
+                (Some(_), Some(path)) => iter::once(path.to_string_lossy().into_owned())
+                    .chain(value.program_args)
+                    .collect(),
+                _ => value.program_args,
+            };
+        }
+        if let Some(source) = inline {
+            return if expect_path {
+                Err(CompileError::ExpectedFileNotInline)
+            } else {
+                Ok(Self::Eval(EvalMode::Inline(source, options)))
+            };
+        }
+        // Inside a project the entry is run when no path is given.
+        let path = match value.path {
+            Some(path) => Some(path),
+            None if test || dependencies || format || lint || io::stdin().is_terminal() => {
+                Manifest::find(Path::new(""))
+                    .map_err(CompileError::Manifest)?
+                    .map(|manifest| manifest.entry)
+            }
+            None => None,
+        };
+        match path {
+            Some(path) if (test || dependencies || format || lint) && path == Path::new(STDIN) => {
+                Err(CompileError::ExpectedPath)
+            }
+            Some(path) if dependencies => Ok(Self::Dependencies(path)),
+            Some(path) if format => Ok(Self::Format(path)),
+            Some(path) if lint => Ok(Self::Lint(path, allowed)),
+            Some(path) if test => Ok(Self::Eval(EvalMode::Test(path, options))),
+            Some(path) if path == Path::new(STDIN) => Ok(Self::Eval(EvalMode::Stdin(options))),
+            Some(path) => Ok(Self::Eval(EvalMode::File(path, options))),
             None => {
-                if expect_path {
+                if !test && !dependencies && !format && !lint && !io::stdin().is_terminal() {
+                    Ok(Self::Eval(EvalMode::Stdin(options)))
+                } else if expect_path {
                     Err(CompileError::ExpectedPath)
                 } else {
-                    Ok(Self::Eval(EvalMode::Repl))
+                    Ok(Self::Eval(EvalMode::Repl(options)))
                 }
             }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Error {
     Parse(ParseError),
     Compile(CompileError),
@@ -187,7 +565,49 @@ impl From<CompileError> for Error {
 }
 
 pub fn get_action() -> Result<Action, Error> {
-    let tokens = lex(&mut env::args())?;
+    let tokens = lex(&mut env::args_os())?;
     let args = parse(tokens)?;
     Ok(Action::try_from(args)?)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex_args(args: &[&str]) -> Vec<Token> {
+        let mut args = iter::once("قتام")
+            .chain(args.iter().copied())
+            .map(OsString::from);
+        lex(&mut args).unwrap()
+    }
+
+    #[test]
+    fn takes_what_follows_the_path_as_the_program_input() {
+        let tokens = lex_args(&["--غير-موثوق", "ملف.قتام", "--ساعد", "مدخل"]);
+        assert!(matches!(
+            &tokens[..],
+            [Token::Setting(Setting::Untrusted), Token::Path(path), Token::Path(help), Token::Path(input)]
+                if path == Path::new("ملف.قتام") && help == Path::new("--ساعد") && input == Path::new("مدخل")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_paths_that_are_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let path = OsStr::from_bytes(b"\xff.\xd9\x82");
+        let setting = OsStr::from_bytes(b"--\xff");
+        let args = [OsStr::new("قتام"), path, setting].map(OsStr::to_owned);
+        let tokens = lex(&mut args.clone().into_iter()).unwrap();
+        assert!(
+            matches!(&tokens[..], [Token::Path(lexed), Token::Path(_)] if lexed.as_os_str() == path)
+        );
+        // A setting that isn't valid UTF-8 is reported rather than taken for a path.
+        let [name, path, setting] = args;
+        let tokens = lex(&mut [name, setting, path].into_iter()).unwrap();
+        assert!(matches!(
+            parse(tokens),
+            Err(ParseError::ExpectedPathOrSetting(setting)) if setting == "--\u{fffd}"
+        ));
+    }
+}
@@ -1,21 +1,83 @@
 mod args;
+mod manifest;
+mod scaffold;
 
-use args::{get_action, Action, EvalMode};
-use compiler::error::{CompileError, RuntimeError};
-use compiler::{Compiler, CompilerType};
-use lexer::Lexer;
+use args::{get_action, Action, DocsFormat, Entry, EvalMode, Options};
+use colored::Colorize;
+use compiler::chunk::{value::Value, Chunk, OpCode::*};
+use compiler::error::{CompileError, CompileWarning, RuntimeError};
+use compiler::lint::{self, Lint};
+use compiler::{deps, Compiler, CompilerType, INLINE_PATH, STDIN_PATH};
+use lexer::{
+    token::{display_path, Token},
+    Lexer,
+};
 use parser::Parser;
 use rustyline::{error::ReadlineError, Editor};
-use std::{fmt, fs, io, path::PathBuf, rc::Rc};
-use vm::Vm;
+use std::{
+    env, fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use vm::{docs, memory::CountingAllocator, BudgetedResult, Stats, Step, Vm};
+
+/// Counts the allocated bytes for `ذاكرة`.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 const HELP_MSG: &str = "
 طريقة الإستخدام:
   قتام [الإعدادات] [الملف [مدخلات البرنامج]]
-في حالة عدم توافر الملف ستعمل اللغة على الوضع التفاعلي.
+  قتام [الإعدادات] --نفّذ شيفرة [مدخلات البرنامج]
+  قتام جديد مسار_المشروع
+في حالة عدم توافر الملف سيشغل مدخل المشروع المحدد في \"قتام.توصيف\" إن وجد، وإلا ستعمل اللغة على الوضع التفاعلي، إلا إن كان المدخل القياسي غير تفاعلي (مثل الأنابيب) فسيقرأ البرنامج منه.
+يمكنكم أيضاً كتابة \"-\" بدلاً من الملف لقراءة البرنامج من المدخل القياسي.
+يشغل \"--نفّذ\" الشيفرة المكتوبة بعده بدلاً من الملف، ويبحث عن الوحدات التي تستوردها في مجلد العمل الحالي كما في المدخل القياسي.
+يوقف الضغط على Ctrl-C البرنامج الذي يعمل (ولا يمكن لـ\"حاول\" إمساك ذلك)، وفي الوضع التفاعلي يعود إلى المحث، أما الضغط عليه مرة ثانية قبل توقف البرنامج فيخرج فوراً.
+يقوم \"جديد\" بإنشاء مشروع فيه ملف رئيسي ووحدة واختبار وملف توصيف.
+في الوضع التفاعلي يجعل \":إحصاء تشغيل\" اللغة تطبع بعد كل تنفيذ عدد التعليمات المنفذة وأقصى عدد للمؤقتات وعدد التخصيصات والزمن المستغرق، ويوقف \":إحصاء إيقاف\" ذلك.
+يحفظ الوضع التفاعلي الأوامر المكتوبة في \"قتام/سجل_الأوامر\" داخل مجلد الإعدادات ويسترجعها عند تشغيله مرة أخرى، ويمكن تغيير مسار هذا الملف بمتغير البيئة \"قتام_سجل_الأوامر\" (أو إيقافه بجعله فارغاً)، وتغيير المحث \">>> \" بمتغير البيئة \"قتام_المحث\".
 الإعدادات:
   --غير-موثوق
     يمنع المستخدم من استخدام الخواص الخطيرة مثل قراءة الملفات وتغيير محتواها (لاحظ: يجب عليكم توفير الملف).
+  --غير-المعرف-عدم
+    يجعل قيمة المتغيرات العامة غير المعرّفة عدم بدلاً من إلقاء خطأ، مما يسمح بالتحقق من وجود الدوال المدمجة الإختيارية.
+  --اختبر
+    يشغل الملف كاختبار ويطبع ملخصاً بنتيجته، ويعتبر أي خطأ لم يمسك (مثل فشل \"توقّع\") فشلاً للإختبار (لاحظ: يجب عليكم توفير الملف).
+  --اعتماديات
+    يطبع الوحدات التي يستوردها الملف والتي تستوردها هي بدون تشغيله، كل سطر على شكل \"مستورِد -> مستورَد\" ويُعلّم الاستيراد الدائري بـ\"(دائري)\" والوحدات التي تعذر الوصول إليها بسبب الخطأ (لاحظ: يجب عليكم توفير الملف).
+  --نسق
+    يطبع الملف منسقاً بالشكل المعتمد بدون تشغيله: كل عبارة في سطر مزاحة بمسافتين لكل كتلة، ومسافة حول العمليات وبعد الفواصل، وسطر فارغ واحد على الأكثر بين العبارات مع الإبقاء على التعليقات (لاحظ: يجب عليكم توفير الملف).
+  --تحقّق
+    يطبع تحذيرات عن الأخطاء الشائعة في الملف بدون تشغيله: المتغيرات المحلية غير المستخدمة، والعبارات التي لا تصل إليها بعد \"أرجع\" و\"إكسر\" وأمثالهما، والإسناد في الشروط، وحجب المتغيرات لمتغيرات بنفس الاسم من نطاقات خارجية (لاحظ: يجب عليكم توفير الملف).
+  --اسمح=تحذير
+    يوقف أحد تحذيرات \"--تحقّق\": متغير-غير-مستخدم، شيفرة-لا-تصل، إسناد-في-شرط، تظليل، ويمكن تكراره.
+  --وثائق [--markdown|--json]
+    يطبع وثائق الدوال المدمجة مقسمة حسب الفئة مع معاملاتها وأمثلة على استخدامها، بصيغة ماركداون افتراضياً أو جسون (لاحظ: هذا الأمر يتجاهل الملف).
+  --تحقق-داخلي
+    يجعل الآلة الافتراضية تتحقق من سلامة حالتها الداخلية بعد كل تعليمة وتتوقف عند أول خلل (لاحظ: هذا مفعّل دائماً في نسخ التطوير، وهو مخصص لاكتشاف أخطاء اللغة نفسها).
+  --تتبع
+    يطبع في المخرج القياسي للأخطاء كل سطر قبل تنفيذه مع الدالة التي تنفذه وقيم متغيراتها المحلية.
+  --حد-العمق=عدد
+    يحدد أقصى عدد من استدعاءات الدوال المتداخلة.
+  --حد-العناصر=عدد
+    يحدد أقصى عدد من عناصر القوائم والكائنات التي يمكن للبرنامج إنشاؤها طوال تشغيله.
+  --حد-المخرجات=عدد
+    يحدد أقصى عدد من البايتات التي يمكن للبرنامج طباعتها.
+  --حد-طول-القائمة=عدد
+    يحدد أقصى طول للقائمة الواحدة عند إنشائها (مثل \"مصفوفة\") وللنص الذي ينشئه \"كرّر_نص\"، وهو 134217728 افتراضياً.
+  --الرئيسية[=اسم_الدالة]
+    يستدعي بعد تشغيل البرنامج الدالة \"الرئيسية\" (أو الدالة المحددة) إن كانت معرّفة، ويمرر لها مدخلات البرنامج كقائمة من النصوص، ويعتبر عدم تعريف الدالة المحددة باسمها خطأ.
+  --ميزانية=عدد
+    يشغل البرنامج على دفعات من التعليمات بهذا العدد ويستأنفه بعد كل دفعة، ونتيجته مطابقة لتشغيله مرة واحدة (لاحظ: هذا مخصص لاختبار إيقاف البرامج واستئنافها).
+  --قاطع-بعد=عدد
+    يتصرف كأن Ctrl-C ضغط بعد تنفيذ هذا العدد من التعليمات (لاحظ: هذا مخصص لاختبار مقاطعة البرامج).
   --الإصدار
     يقوم بطباعة الإصدار المستخدم حالياً (لاحظ: هذا الأمر يتجاهل الملف).
   --ساعد
@@ -23,6 +85,7 @@ const HELP_MSG: &str = "
 ";
 
 fn main() {
+    init_console();
     match try_main() {
         Ok(_) => {}
         Err(err) => {
@@ -31,10 +94,50 @@ fn main() {
     }
 }
 
+/// Windows consoles garble Arabic unless they use UTF-8, and older ones print the escape codes of colors as they are.
+#[cfg(windows)]
+fn init_console() {
+    const UTF8_CODE_PAGE: u32 = 65001;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetConsoleOutputCP(code_page: u32) -> i32;
+        fn SetConsoleCP(code_page: u32) -> i32;
+    }
+    // Both fail harmlessly when there's no console (e.g. when the output is piped).
+    unsafe {
+        SetConsoleOutputCP(UTF8_CODE_PAGE);
+        SetConsoleCP(UTF8_CODE_PAGE);
+    }
+    colored::control::set_virtual_terminal(true).ok();
+}
+
+#[cfg(not(windows))]
+fn init_console() {}
+
 fn try_main() -> Result<(), Error> {
     match get_action()? {
-        Action::Eval(EvalMode::File(path, untrusted)) => file(path, untrusted)?,
-        Action::Eval(EvalMode::Repl) => repl()?,
+        Action::Eval(EvalMode::File(path, options)) => file(path, options)?,
+        Action::Eval(EvalMode::Test(path, options)) => test(path, options)?,
+        Action::Eval(EvalMode::Stdin(options)) => stdin(options)?,
+        Action::Eval(EvalMode::Inline(source, options)) => inline(source, options)?,
+        Action::Eval(EvalMode::Repl(options)) => repl(options)?,
+        Action::New(path) => {
+            scaffold::create(&path)?;
+            println!("تم إنشاء المشروع في \"{}\"", display_path(&path))
+        }
+        Action::Dependencies(path) => {
+            for dependency in deps::dependencies(&path)? {
+                println!("{dependency}")
+            }
+        }
+        Action::Format(path) => print!("{}", format_file(&path)?),
+        Action::Lint(path, allowed) => {
+            for warning in lint_file(&path, &allowed)? {
+                eprintln!("{warning}")
+            }
+        }
+        Action::Docs(DocsFormat::Markdown) => print!("{}", docs::markdown(&Vm::natives_docs())),
+        Action::Docs(DocsFormat::Json) => print!("{}", docs::json(&Vm::natives_docs())),
         Action::Version => println!("{}", env!("CARGO_PKG_VERSION")),
         Action::Help => {
             println!(
@@ -54,6 +157,10 @@ enum Error {
     Runtime(RuntimeError),
     Readline(ReadlineError),
     Io(io::Error),
+    Scaffold(scaffold::Error),
+    Dependencies(deps::Error),
+    /// The entry function was named but the program didn't define it.
+    MissingEntry(String),
 }
 
 impl From<args::Error> for Error {
@@ -86,6 +193,18 @@ impl From<Vec<CompileError>> for Error {
     }
 }
 
+impl From<scaffold::Error> for Error {
+    fn from(err: scaffold::Error) -> Self {
+        Self::Scaffold(err)
+    }
+}
+
+impl From<deps::Error> for Error {
+    fn from(err: deps::Error) -> Self {
+        Self::Dependencies(err)
+    }
+}
+
 impl From<RuntimeError> for Error {
     fn from(err: RuntimeError) -> Self {
         Self::Runtime(err)
@@ -124,50 +243,471 @@ impl fmt::Display for Error {
             Self::Io(err) => {
                 write!(f, "{err}")
             }
+            Self::Scaffold(err) => {
+                write!(f, "{err}")
+            }
+            Self::Dependencies(err) => {
+                write!(f, "{err}")
+            }
+            Self::MissingEntry(name) => {
+                write!(
+                    f,
+                    "لم يعرّف البرنامج الدالة \"{name}\" ليستدعيها \"--الرئيسية\""
+                )
+            }
         }
     }
 }
 
-fn repl() -> Result<(), ReadlineError> {
+/// Parses the file and returns its source in the canonical style.
+fn format_file(path: &Path) -> Result<String, Error> {
+    let source = fs::read_to_string(path)?;
+    let tokens = Lexer::new(source, Some(&path.to_owned())).lex();
+    let ast = Parser::new(tokens.clone()).parse()?;
+    Ok(parser::format::format(&ast, &tokens))
+}
+
+/// Returns the parser's warnings followed by the lints'.
+fn lint_file(path: &Path, allowed: &[Lint]) -> Result<Vec<CompileWarning>, Error> {
+    let source = fs::read_to_string(path)?;
+    let tokens = Lexer::new(source, Some(&path.to_owned())).lex();
+    let mut parser = Parser::new(tokens.clone());
+    let ast = parser.parse()?;
+    let mut warnings = parser
+        .warnings()
+        .iter()
+        .cloned()
+        .map(CompileWarning::Parse)
+        .collect::<Vec<_>>();
+    warnings.append(&mut lint::lint(&ast, &tokens, allowed));
+    Ok(warnings)
+}
+
+fn new_vm(options: &Options) -> Vm {
+    let mut vm = Vm::new()
+        .undefined_as_nil(options.undefined_as_nil)
+        .untrusted(options.untrusted);
+    if options.invariant_checks {
+        vm = vm.enable_invariant_checks()
+    }
+    if let Some(limit) = options.max_call_depth {
+        vm = vm.max_call_depth(limit)
+    }
+    if let Some(limit) = options.max_elements {
+        vm = vm.max_elements(limit)
+    }
+    if let Some(limit) = options.max_output_bytes {
+        vm = vm.max_output_bytes(limit)
+    }
+    if let Some(limit) = options.max_list_len {
+        vm = vm.max_list_len(limit)
+    }
+    let interrupt = Arc::new(AtomicBool::new(false));
+    let handler_interrupt = Arc::clone(&interrupt);
+    // The VM only notices the flag between instructions, so pressing it again (e.g. while a native waits for input)
+    // exits right away.
+    ctrlc::set_handler(move || {
+        if handler_interrupt.swap(true, Ordering::Relaxed) {
+            process::exit(INTERRUPTED_CODE)
+        }
+    })
+    .ok();
+    let mut tracer = options.trace.then(tracer);
+    let mut interrupt_after = options
+        .interrupt_after
+        .map(|instructions| (instructions, Arc::clone(&interrupt)));
+    if tracer.is_some() || interrupt_after.is_some() {
+        vm = vm.on_step(move |step| {
+            if let Some(tracer) = &mut tracer {
+                tracer(step)
+            }
+            if let Some((left, interrupt)) = &mut interrupt_after {
+                if *left == 0 {
+                    interrupt.store(true, Ordering::Relaxed);
+                    interrupt_after = None
+                } else {
+                    *left -= 1
+                }
+            }
+        })
+    }
+    vm.interrupt(interrupt)
+}
+
+/// What the process exits with when Ctrl-C is pressed twice, like the shells do for programs killed by it.
+const INTERRUPTED_CODE: i32 = 130;
+
+/// Prints a step whenever the line or the frame changes, indented by how deep the frame is.
+fn tracer() -> impl FnMut(&Step<'_, '_>) {
+    let mut last = None;
+    move |step| {
+        // Instructions the compiler adds on its own (e.g. popping the locals of a block) would print lines that were
+        // already left.
+        let token = step.token();
+        if token.is_synthetic() {
+            return;
+        }
+        let current = (step.depth(), token.line());
+        if last == Some(current) {
+            return;
+        }
+        last = Some(current);
+        let (depth, line) = current;
+        let function = match step.function() {
+            Some(name) => format!(" في الدالة {name}"),
+            None if depth == 0 => String::new(),
+            None => " في دالة غير مسماة".to_owned(),
+        };
+        let locals = step
+            .locals()
+            .into_iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect::<Vec<_>>();
+        let locals = if locals.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", locals.join("، "))
+        };
+        eprintln!(
+            "[تتبع] {}السطر {line}{function}{locals}",
+            "  ".repeat(depth)
+        )
+    }
+}
+
+/// REPL lines starting with it are commands to the REPL rather than code.
+const COMMAND_PREFIX: char = ':';
+/// The name of the environment variable that replaces the REPL prompt, it defaults to ">>> ".
+const PROMPT_VAR: &str = "قتام_المحث";
+const DEFAULT_PROMPT: &str = ">>> ";
+/// The name of the environment variable that replaces the path of the REPL history file, an empty value disables it.
+const HISTORY_VAR: &str = "قتام_سجل_الأوامر";
+
+/// The file the REPL history is kept in, `None` if it's disabled or there's no config directory to put it in.
+fn history_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(HISTORY_VAR) {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config_dir.join("قتام").join("سجل_الأوامر"))
+}
+
+/// Losing the history isn't worth stopping the REPL, so failing to save it is only a warning.
+fn save_history(rl: &mut Editor<()>, path: &Path) {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).ok();
+    }
+    if let Err(err) = rl.save_history(path) {
+        eprintln!(
+            "{}",
+            format!("تعذر حفظ سجل الأوامر في \"{}\": {err}", display_path(path)).dimmed()
+        )
+    }
+}
+
+fn repl(options: Options) -> Result<(), ReadlineError> {
     let mut rl = Editor::<()>::new()?;
-    let mut vm = Vm::new();
-    loop {
-        let readline = rl.readline(">>> ");
+    let history = history_path();
+    if let Some(path) = &history {
+        // A missing file is the first run, and an unreadable one is replaced when the history is saved.
+        rl.load_history(path).ok();
+    }
+    let prompt = env::var(PROMPT_VAR).unwrap_or_else(|_| DEFAULT_PROMPT.to_string());
+    let mut vm = new_vm(&options);
+    let mut stats = false;
+    let result = loop {
+        let readline = rl.readline(&prompt);
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str());
-                match run(&mut vm, line, None, false) {
-                    Ok(_) => {}
-                    Err(err) => {
-                        eprintln!("{err}")
+                if let Some(command) = line.trim().strip_prefix(COMMAND_PREFIX) {
+                    match command.split_whitespace().collect::<Vec<_>>()[..] {
+                        ["إحصاء", "تشغيل"] => stats = true,
+                        ["إحصاء", "إيقاف"] => stats = false,
+                        _ => eprintln!(
+                            "أمر غير معروف \"{command}\"، الأوامر المتاحة هي \":إحصاء تشغيل\" و\":إحصاء إيقاف\""
+                        ),
                     }
+                    continue;
+                }
+                if let Some(summary) = eval_line(&mut vm, line, &options, stats) {
+                    eprintln!("{}", summary.dimmed())
                 }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
-                break;
+                break Ok(());
             }
             Err(ReadlineError::Eof) => {
                 println!("CTRL-D");
-                break;
+                break Ok(());
             }
-            Err(err) => return Err(err),
+            Err(err) => break Err(err),
         }
+    };
+    if let Some(path) = &history {
+        save_history(&mut rl, path)
     }
-    Ok(())
+    result
+}
+
+/// Runs a line of the REPL printing its error if it fails, and returns the summary of its evaluation if `stats` is set
+/// and the line ran (i.e. it parsed and compiled).
+fn eval_line(vm: &mut Vm, line: String, options: &Options, stats: bool) -> Option<String> {
+    let result = run(
+        vm,
+        line,
+        None,
+        CompilerType::Repl,
+        options.trace,
+        options.budget,
+        None,
+    );
+    if let Err(err) = &result {
+        eprintln!("{err}")
+    }
+    match result {
+        // The line didn't run so there's nothing to report.
+        Err(Error::Parser(_) | Error::Compile(_)) => None,
+        _ => stats.then(|| stats_line(vm.stats(), result.is_err())),
+    }
+}
+
+/// Summarizes a single REPL evaluation, `failed` marks the ones that threw.
+fn stats_line(stats: Stats, failed: bool) -> String {
+    let mut parts = vec![
+        format!("التعليمات: {}", stats.instructions),
+        format!("أقصى عدد للمؤقتات: {}", stats.peak_tmps),
+    ];
+    if let Some(allocations) = stats.allocations {
+        parts.push(format!("التخصيصات: {allocations}"))
+    }
+    parts.push(format!(
+        "الزمن: {:.3} مللي ثانية",
+        stats.elapsed.as_secs_f64() * 1000.0
+    ));
+    let marker = if failed { " (فشل)" } else { "" };
+    format!("[إحصاء{marker}] {}", parts.join("، "))
+}
+
+fn file(path: PathBuf, options: Options) -> Result<(), Error> {
+    let source = fs::read_to_string(&path)?;
+    let mut vm = new_vm(&options);
+    run(
+        &mut vm,
+        source,
+        Some(path),
+        CompilerType::Script,
+        options.trace,
+        options.budget,
+        options.entry.as_ref(),
+    )
 }
 
-fn file(path: PathBuf, untrusted: bool) -> Result<(), Error> {
+/// Like `file` but imports and files are resolved relative to the working directory.
+fn stdin(options: Options) -> Result<(), Error> {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    let mut vm = new_vm(&options);
+    run(
+        &mut vm,
+        source,
+        Some(PathBuf::from(STDIN_PATH)),
+        CompilerType::Script,
+        options.trace,
+        options.budget,
+        options.entry.as_ref(),
+    )
+}
+
+/// Like `stdin` but the source is passed on the command line.
+fn inline(source: String, options: Options) -> Result<(), Error> {
+    let mut vm = new_vm(&options);
+    run(
+        &mut vm,
+        source,
+        Some(PathBuf::from(INLINE_PATH)),
+        CompilerType::Script,
+        options.trace,
+        options.budget,
+        options.entry.as_ref(),
+    )
+}
+
+/// Like `file` but prints a summary of the result and exits with 1 if an error isn't caught.
+fn test(path: PathBuf, options: Options) -> Result<(), Error> {
     let source = fs::read_to_string(&path)?;
-    let mut vm = Vm::new();
-    run(&mut vm, source, Some(path), untrusted)
+    let mut vm = new_vm(&options);
+    let result = run(
+        &mut vm,
+        source,
+        Some(path),
+        CompilerType::Script,
+        options.trace,
+        options.budget,
+        options.entry.as_ref(),
+    );
+    let passed = vm.passed_assertions();
+    match result {
+        Ok(_) => {
+            println!("نجح الإختبار: تحقق {passed} توقع");
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            println!("فشل الإختبار: تحقق {passed} توقع قبل الفشل");
+            // Exiting skips the destructors, and dropping the VM closes the files the test left open.
+            drop(vm);
+            process::exit(1)
+        }
+    }
 }
 
-fn run(vm: &mut Vm, source: String, path: Option<PathBuf>, _untrusted: bool) -> Result<(), Error> {
+/// `debug_info` must be set for the tracer to see the locals.
+fn run(
+    vm: &mut Vm,
+    source: String,
+    path: Option<PathBuf>,
+    typ: CompilerType,
+    debug_info: bool,
+    budget: Option<u64>,
+    entry: Option<&Entry>,
+) -> Result<(), Error> {
     let tokens = Lexer::new(source.clone(), path.as_ref()).lex();
     let token = Rc::clone(tokens.last().unwrap());
-    let ast = Parser::new(tokens).parse()?;
-    let chunk = Compiler::new(CompilerType::Script, &ast, token).compile()?;
-    vm.run(chunk)?;
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse()?;
+    for warning in parser.warnings() {
+        eprintln!("{warning}")
+    }
+    let mut compiler = Compiler::new(typ, &ast, Rc::clone(&token)).debug_info(debug_info);
+    // The warnings would only bury the errors of a program that doesn't compile.
+    let chunk = compiler.compile()?;
+    for warning in compiler.warnings() {
+        eprintln!("{warning}")
+    }
+    match budget {
+        Some(budget) => run_budgeted(vm, chunk, budget)?,
+        None => vm.run(chunk)?,
+    }
+    if let Some(entry) = entry {
+        call_entry(vm, entry, &token, budget)?
+    }
+    Ok(())
+}
+
+/// Calls the entry function with its arguments as a list of strings. The call isn't in the source, so its errors are
+/// reported where the function is defined (or at `eof` for natives).
+fn call_entry(
+    vm: &mut Vm,
+    entry: &Entry,
+    eof: &Rc<Token>,
+    budget: Option<u64>,
+) -> Result<(), Error> {
+    if vm.global(&entry.name).is_none() {
+        return if entry.required {
+            Err(Error::MissingEntry(entry.name.clone()))
+        } else {
+            Ok(())
+        };
+    }
+    let token = vm
+        .global_token(&entry.name)
+        .unwrap_or_else(|| Rc::clone(eof));
+    let token = &Rc::new(token.synthetic());
+    let mut chunk = Chunk::new();
+    chunk.set_path(token.path().cloned());
+    let name = Value::from(entry.name.clone());
+    chunk
+        .write_instr_const((GET_GLOBAL8, GET_GLOBAL16), token, name)
+        .ok();
+    for arg in &entry.args {
+        let arg = Value::from(arg.clone());
+        chunk.write_instr_const((CONST8, CONST16), token, arg).ok();
+    }
+    chunk.write_build(BUILD_LIST, token, entry.args.len()).ok();
+    chunk.write_call(token, 1).ok();
+    chunk.write_instr_no_operands(POP, token);
+    match budget {
+        Some(budget) => run_budgeted(vm, chunk, budget)?,
+        None => vm.run(chunk)?,
+    }
     Ok(())
 }
+
+/// Resumes the program whenever it runs out of budget until it finishes.
+fn run_budgeted(vm: &mut Vm, chunk: Chunk, budget: u64) -> Result<(), RuntimeError> {
+    let mut result = vm.run_budgeted(chunk, budget);
+    loop {
+        match result {
+            BudgetedResult::Completed(_) => return Ok(()),
+            BudgetedResult::Errored(err) => return Err(err),
+            BudgetedResult::Suspended(handle) => result = vm.resume(handle, budget),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(vm: &mut Vm, line: &str, stats: bool) -> Option<String> {
+        eval_line(vm, line.to_owned(), &Options::default(), stats)
+    }
+
+    #[test]
+    fn stats_are_opt_in() {
+        let mut vm = new_vm(&Options::default());
+        assert_eq!(eval(&mut vm, "1 + 2", false), None);
+    }
+
+    #[test]
+    fn stats_count_a_single_evaluation() {
+        let mut vm = new_vm(&Options::default());
+        for _ in 0..2 {
+            // Two constants, adding them, and printing the result.
+            let summary = eval(&mut vm, "1 + 2", true).unwrap();
+            assert!(
+                summary.starts_with("[إحصاء] التعليمات: 4، أقصى عدد للمؤقتات: 2، التخصيصات: "),
+                "{summary}"
+            );
+            assert_eq!(vm.stats().instructions, 4);
+            assert_eq!(vm.stats().peak_tmps, 2);
+        }
+        eval(&mut vm, "[1، 2، 3]", true).unwrap();
+        assert_eq!(vm.stats().instructions, 5);
+        assert_eq!(vm.stats().peak_tmps, 3);
+        assert!(vm
+            .stats()
+            .allocations
+            .is_some_and(|allocations| allocations > 0));
+    }
+
+    #[test]
+    fn stats_mark_the_evaluations_that_threw() {
+        let mut vm = new_vm(&Options::default());
+        let summary = eval(&mut vm, "ألقي 1", true).unwrap();
+        assert!(
+            summary.starts_with("[إحصاء (فشل)] التعليمات: "),
+            "{summary}"
+        );
+    }
+
+    #[test]
+    fn stats_skip_the_lines_that_did_not_run() {
+        let mut vm = new_vm(&Options::default());
+        assert_eq!(eval(&mut vm, "1 +", true), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn initializing_the_console_is_harmless() {
+        // Without a console (e.g. when the output is captured) the calls fail, and doing it twice changes nothing.
+        init_console();
+        init_console();
+    }
+}
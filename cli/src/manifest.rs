@@ -0,0 +1,172 @@
+use lexer::token::display_path;
+use std::{fmt, fs, io, path::Path, path::PathBuf};
+
+/// The name of the manifest file, its presence makes a directory a project.
+pub const FILE_NAME: &str = "قتام.توصيف";
+
+const NAME: &str = "الاسم";
+const VERSION: &str = "الإصدار";
+const ENTRY: &str = "المدخل";
+
+/// A project's manifest, which is made of `مفتاح: قيمة` lines (empty lines are ignored).
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    /// Relative to the directory of the manifest.
+    pub entry: PathBuf,
+}
+
+/// Lines are counted from 1.
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    ExpectedColon(usize),
+    UnknownKey(String, usize),
+    DuplicateKey(String, usize),
+    MissingKey(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "خطأ في ملف التوصيف: ")?;
+        match self {
+            Self::Io(path, err) => write!(f, "تعذر الوصول إلى \"{}\": {err}", display_path(path)),
+            Self::ExpectedColon(line) => {
+                write!(f, "توقعت \"مفتاح: قيمة\" في السطر رقم {line}")
+            }
+            Self::UnknownKey(key, line) => {
+                write!(f, "المفتاح \"{key}\" غير معروف في السطر رقم {line}")
+            }
+            Self::DuplicateKey(key, line) => {
+                write!(f, "المفتاح \"{key}\" مكرر في السطر رقم {line}")
+            }
+            Self::MissingKey(key) => write!(f, "المفتاح \"{key}\" غير موجود"),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let (mut name, mut version, mut entry) = (None, None, None);
+        for (idx, line) in source.lines().enumerate() {
+            let line_number = idx + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or(Error::ExpectedColon(line_number))?;
+            let (key, value) = (key.trim(), value.trim().to_owned());
+            let slot = match key {
+                NAME => &mut name,
+                VERSION => &mut version,
+                ENTRY => &mut entry,
+                _ => return Err(Error::UnknownKey(key.to_owned(), line_number)),
+            };
+            if slot.replace(value).is_some() {
+                return Err(Error::DuplicateKey(key.to_owned(), line_number));
+            }
+        }
+        Ok(Self {
+            name: name.ok_or(Error::MissingKey(NAME))?,
+            version: version.ok_or(Error::MissingKey(VERSION))?,
+            entry: PathBuf::from(entry.ok_or(Error::MissingKey(ENTRY))?),
+        })
+    }
+
+    /// Reads the manifest of the project in `dir` if there's one.
+    pub fn find(dir: &Path) -> Result<Option<Self>, Error> {
+        let path = dir.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                let mut manifest = Self::parse(&source)?;
+                manifest.entry = dir.join(manifest.entry);
+                Ok(Some(manifest))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Error::Io(path, err)),
+        }
+    }
+
+    pub fn to_source(&self) -> String {
+        format!(
+            "{NAME}: {}\n{VERSION}: {}\n{ENTRY}: {}\n",
+            self.name,
+            self.version,
+            display_path(&self.entry)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn parses_the_keys_in_any_order() {
+        let manifest =
+            Manifest::parse("\n  المدخل :  مجلد/رئيسي.قتام \n\nالاسم: مشروع: تجريبي\nالإصدار: 1.0\n")
+                .unwrap();
+        assert_eq!(manifest.name, "مشروع: تجريبي");
+        assert_eq!(manifest.version, "1.0");
+        assert_eq!(manifest.entry, PathBuf::from("مجلد/رئيسي.قتام"));
+    }
+
+    #[test]
+    fn reports_the_lines_of_errors() {
+        for (source, expected) in [
+            (
+                "الاسم: مشروع\n\nالإصدار 1.0\n",
+                "خطأ في ملف التوصيف: توقعت \"مفتاح: قيمة\" في السطر رقم 3",
+            ),
+            (
+                "الاسم: مشروع\nالمؤلف: أحد\n",
+                "خطأ في ملف التوصيف: المفتاح \"المؤلف\" غير معروف في السطر رقم 2",
+            ),
+            (
+                "الاسم: مشروع\nالاسم: آخر\n",
+                "خطأ في ملف التوصيف: المفتاح \"الاسم\" مكرر في السطر رقم 2",
+            ),
+            (
+                "الاسم: مشروع\nالمدخل: رئيسي.قتام\n",
+                "خطأ في ملف التوصيف: المفتاح \"الإصدار\" غير موجود",
+            ),
+            ("", "خطأ في ملف التوصيف: المفتاح \"الاسم\" غير موجود"),
+        ] {
+            assert_eq!(Manifest::parse(source).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let manifest = Manifest {
+            name: "مشروع".to_owned(),
+            version: "0.1.0".to_owned(),
+            entry: PathBuf::from("مجلد/رئيسي.قتام"),
+        };
+        let parsed = Manifest::parse(&manifest.to_source()).unwrap();
+        assert_eq!(parsed.name, manifest.name);
+        assert_eq!(parsed.version, manifest.version);
+        assert_eq!(parsed.entry, manifest.entry);
+    }
+
+    #[test]
+    fn finds_the_entry_relative_to_the_project() {
+        let dir = env::temp_dir().join(format!("قتام-توصيف-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(Manifest::find(&dir).unwrap().is_none());
+        fs::write(
+            dir.join(FILE_NAME),
+            "الاسم: مشروع\nالإصدار: 0.1.0\nالمدخل: رئيسي.قتام\n",
+        )
+        .unwrap();
+        let entry = Manifest::find(&dir).unwrap().map(|manifest| manifest.entry);
+        fs::write(dir.join(FILE_NAME), "الاسم مشروع\n").unwrap();
+        let invalid = Manifest::find(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entry, Some(dir.join("رئيسي.قتام")));
+        assert!(matches!(invalid, Err(Error::ExpectedColon(1))));
+    }
+}
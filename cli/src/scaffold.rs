@@ -0,0 +1,71 @@
+use super::manifest::{self, Manifest};
+use lexer::token::display_path;
+use std::{fmt, fs, io, path::Path, path::PathBuf};
+
+const ENTRY: &str = "رئيسي.قتام";
+const ENTRY_SOURCE: &str = "استورد تحية من \"./وحدات/تحية.قتام\"
+
+دالة رئيسي() {
+  إطبع(تحية.حيي(\"العالم\"))
+}
+
+رئيسي()
+";
+const MODULE: &str = "وحدات/تحية.قتام";
+const MODULE_SOURCE: &str = "صدّر دالة حيي(الاسم) {
+  أرجع \"مرحباً يا \" + الاسم
+}
+";
+const TEST: &str = "اختبارات/مثال_اختبار.قتام";
+const TEST_SOURCE: &str = "استورد تحية من \"../وحدات/تحية.قتام\"
+
+توقّع_يساوي(تحية.حيي(\"قتام\")، \"مرحباً يا قتام\")
+";
+
+#[derive(Debug)]
+pub enum Error {
+    NotEmpty(PathBuf),
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotEmpty(path) => write!(
+                f,
+                "لا يمكن إنشاء المشروع في \"{}\" لأنه مجلد غير فارغ",
+                display_path(path)
+            ),
+            Self::Io(path, err) => write!(f, "تعذر إنشاء \"{}\": {err}", display_path(path)),
+        }
+    }
+}
+
+fn write(dir: &Path, path: &str, content: &str) -> Result<(), Error> {
+    let path = dir.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::Io(parent.to_owned(), err))?;
+    }
+    fs::write(&path, content).map_err(|err| Error::Io(path, err))
+}
+
+/// Creates a starter project in `dir`, which must be either missing or empty.
+pub fn create(dir: &Path) -> Result<(), Error> {
+    if let Ok(mut entries) = fs::read_dir(dir) {
+        if entries.next().is_some() {
+            return Err(Error::NotEmpty(dir.to_owned()));
+        }
+    }
+    let name = dir
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let manifest = Manifest {
+        name,
+        version: "0.1.0".to_owned(),
+        entry: PathBuf::from(ENTRY),
+    };
+    write(dir, manifest::FILE_NAME, &manifest.to_source())?;
+    write(dir, ENTRY, ENTRY_SOURCE)?;
+    write(dir, MODULE, MODULE_SOURCE)?;
+    write(dir, TEST, TEST_SOURCE)
+}
@@ -0,0 +1,34 @@
+//! What differs between platforms, which the binary should hide.
+
+use std::{env, fs, process::Command};
+
+#[cfg(unix)]
+#[test]
+fn runs_files_whose_paths_are_not_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+    let dir = env::temp_dir().join(format!("قتام-منصة-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(OsStr::from_bytes(b"\xff.\xd9\x82"));
+    fs::write(&path, "إطبع(هذا_الملف)\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_قتام"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{}\n", path.to_string_lossy())
+    );
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[cfg(windows)]
+#[test]
+fn prints_arabic_as_utf8() {
+    let output = Command::new(env!("CARGO_BIN_EXE_قتام"))
+        .args(["--نفّذ", "إطبع(\"مرحباً\")"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "مرحباً\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
@@ -0,0 +1,44 @@
+//! Runs the project `جديد` creates, which newcomers start from so it has to work.
+
+use std::{env, fs, path::Path, process::Command};
+
+fn run(dir: &Path, args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_قتام"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn the_new_project_runs() {
+    let dir = env::temp_dir().join(format!("قتام-جديد-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let created = run(&dir, &["جديد", "مشروع"]);
+    let project = dir.join("مشروع");
+    let entry = run(&project, &["رئيسي.قتام"]);
+    let test = run(&project, &["--اختبر", "اختبارات/مثال_اختبار.قتام"]);
+    let again = run(&dir, &["جديد", "مشروع"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        created,
+        (
+            true,
+            "تم إنشاء المشروع في \"مشروع\"\n".to_owned(),
+            String::new()
+        )
+    );
+    assert_eq!(entry, (true, "مرحباً يا العالم\n".to_owned(), String::new()));
+    assert_eq!(
+        test,
+        (true, "نجح الإختبار: تحقق 1 توقع\n".to_owned(), String::new())
+    );
+    // An existing project isn't overwritten.
+    assert!(again.2.contains("مجلد غير فارغ"), "{again:?}");
+}
@@ -0,0 +1,410 @@
+//! Writing chunks by hand, for testing the VM on bytecode the compiler doesn't (or can't) emit.
+//!
+//! The format mirrors the disassembler's: an instruction per line, its name followed by its operands, and without the
+//! parenthesized notes. Jumps take either their offset or a label, which is defined on a line of its own as `name:`.
+//! `.bytes` writes raw bytes (e.g. to truncate an instruction), and `#` starts a comment.
+//!
+//! ```text
+//!     CONST8 3
+//!     POP_JUMP_IF_FALSY else
+//!     CLOSURE8 4 (true, 0) (false, 1)
+//! else:
+//!     UNPACK_HASH_MAP true false
+//!     RET
+//! ```
+//!
+//! Every instruction's token is its name in the source, so errors point at the line that wrote it.
+
+use super::value::Value;
+use super::{Chunk, Instruction, OpCode, OpCode::*};
+use lexer::token::{Token, TokenType};
+use std::{collections::HashMap, rc::Rc};
+
+const JUMPS: [OpCode; 8] = [
+    JUMP,
+    JUMP_IF_FALSY_OR_POP,
+    JUMP_IF_TRUTHY_OR_POP,
+    POP_JUMP_IF_FALSY,
+    POP_JUMP_IF_TRUTHY,
+    FOR_ITER,
+    APPEND_HANDLER,
+    LOOP,
+];
+
+fn op_code(name: &str) -> Option<OpCode> {
+    (0..u8::MAX)
+        .map(OpCode::from)
+        .take_while(|op_code| *op_code != UNKNOWN)
+        .find(|op_code| format!("{op_code:?}") == name)
+}
+
+fn number(line: usize, operand: &str, max: usize) -> usize {
+    match operand.parse::<usize>() {
+        Ok(number) if number <= max => number,
+        _ => panic!("line {line}: expected a number up to {max} but got \"{operand}\""),
+    }
+}
+
+fn flag(line: usize, operand: &str) -> usize {
+    match operand {
+        "true" => 1,
+        "false" => 0,
+        _ => panic!("line {line}: expected true or false but got \"{operand}\""),
+    }
+}
+
+/// Returns a chunk with `constants` added after the ones every chunk starts with (`عدم`, `صحيح`, and `خطأ`), so the
+/// first of them is at 3.
+///
+/// Panics on invalid source, or when a jump's label is undefined or too far.
+pub fn assemble(source: &str, constants: Vec<Value>) -> Chunk {
+    let source = Rc::new(source.to_owned());
+    let mut chunk = Chunk::new();
+    chunk.constants.extend(constants);
+    let mut labels = HashMap::new();
+    // The ips of the jumps to labels along with their lines.
+    let mut unsettled = vec![];
+    let mut start = 0;
+    for (idx, text) in source.split('\n').enumerate() {
+        let line = idx + 1;
+        let offset = start;
+        start += text.len() + 1;
+        let code = text.split('#').next().unwrap();
+        let mut operands = code
+            .split(|c: char| c.is_whitespace() || "(),".contains(c))
+            .filter(|operand| !operand.is_empty());
+        let Some(name) = operands.next() else {
+            continue;
+        };
+        if let Some(label) = name.strip_suffix(':') {
+            if labels.insert(label, chunk.len()).is_some() {
+                panic!("line {line}: the label \"{label}\" is already defined")
+            }
+            continue;
+        }
+        let operands = operands.collect::<Vec<_>>();
+        let token = Rc::new(Token::new(
+            TokenType::Identifier,
+            Rc::clone(&source),
+            None,
+            offset + code.find(name).unwrap(),
+            name.len(),
+        ));
+        // Any of them can be read as an instruction, so they all have tokens.
+        if name == ".bytes" {
+            for operand in operands {
+                chunk
+                    .bytes
+                    .push(number(line, operand, u8::MAX.into()) as u8);
+                chunk.tokens.push(Some(Rc::clone(&token)))
+            }
+            continue;
+        }
+        let op_code = op_code(name)
+            .unwrap_or_else(|| panic!("line {line}: there's no instruction called \"{name}\""));
+        let ip = chunk.len();
+        chunk.write_op_code(op_code, &token);
+        let expect = |count: usize| {
+            if operands.len() != count {
+                panic!(
+                    "line {line}: {name} takes {count} operands but got {}",
+                    operands.len()
+                )
+            }
+        };
+        match op_code {
+            _ if JUMPS.contains(&op_code) => {
+                expect(1);
+                if operands[0].starts_with(|c: char| c.is_ascii_digit()) {
+                    chunk.write_two_bytes_unchecked(number(line, operands[0], u16::MAX.into()))
+                } else {
+                    unsettled.push((ip, operands[0], line));
+                    chunk.write_two_bytes_unchecked(0)
+                }
+            }
+            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW | CONST8 | GET_GLOBAL8
+            | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 | CALL => {
+                expect(1);
+                chunk.write_byte_unchecked(number(line, operands[0], u8::MAX.into()))
+            }
+            CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 | GET_PROP16 | SET_PROP16
+            | BUILD_LIST | BUILD_HASH_MAP | UNPACK_LIST | UNPACK_LIST_REST | MATCH_LIST
+            | MATCH_LIST_REST | MATCH_HASH_MAP => {
+                expect(1);
+                chunk.write_two_bytes_unchecked(number(line, operands[0], u16::MAX.into()))
+            }
+            UNPACK_HASH_MAP => {
+                if operands.len() > u16::MAX.into() {
+                    panic!("line {line}: {name} takes up to {} flags", u16::MAX)
+                }
+                chunk.write_two_bytes_unchecked(operands.len());
+                for operand in operands {
+                    chunk.write_byte_unchecked(flag(line, operand))
+                }
+            }
+            CLOSURE8 | CLOSURE16 => {
+                if operands.len() % 2 != 1 || operands.len() / 2 > u8::MAX.into() {
+                    panic!("line {line}: {name} takes an index and up to 255 (local, index) pairs")
+                }
+                match op_code {
+                    CLOSURE8 => {
+                        chunk.write_byte_unchecked(number(line, operands[0], u8::MAX.into()))
+                    }
+                    _ => {
+                        chunk.write_two_bytes_unchecked(number(line, operands[0], u16::MAX.into()))
+                    }
+                }
+                chunk.write_byte_unchecked(operands.len() / 2);
+                for pair in operands[1..].chunks(2) {
+                    chunk.write_byte_unchecked(flag(line, pair[0]));
+                    chunk.write_byte_unchecked(number(line, pair[1], u8::MAX.into()))
+                }
+            }
+            _ => expect(0),
+        }
+    }
+    for (ip, label, line) in unsettled {
+        let target = *labels
+            .get(label)
+            .unwrap_or_else(|| panic!("line {line}: the label \"{label}\" isn't defined"));
+        // Loops jump backwards, the rest forwards.
+        let offset = match chunk.bytes[ip].into() {
+            LOOP => ip.checked_sub(target),
+            _ => target.checked_sub(ip),
+        };
+        match offset {
+            Some(offset) if offset <= u16::MAX.into() => {
+                chunk.rewrite_two_bytes(ip + 1, offset).ok();
+            }
+            _ => panic!("line {line}: can't jump to \"{label}\" from here"),
+        }
+    }
+    chunk
+}
+
+/// The size of the instruction at `ip`, `None` if there isn't a whole one.
+fn size(chunk: &Chunk, ip: usize) -> Option<usize> {
+    let byte = |offset: usize| chunk.byte(ip + offset).map(usize::from);
+    let size = match OpCode::from(chunk.byte(ip)?) {
+        UNKNOWN => return None,
+        op_code if JUMPS.contains(&op_code) => 3,
+        GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW | CONST8 | GET_GLOBAL8
+        | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 | CALL => 2,
+        CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 | GET_PROP16 | SET_PROP16
+        | BUILD_LIST | BUILD_HASH_MAP | UNPACK_LIST | UNPACK_LIST_REST | MATCH_LIST
+        | MATCH_LIST_REST | MATCH_HASH_MAP => 3,
+        UNPACK_HASH_MAP => 3 + u16::from_ne_bytes([byte(1)? as u8, byte(2)? as u8]) as usize,
+        CLOSURE8 => 3 + byte(2)? * 2,
+        CLOSURE16 => 4 + byte(3)? * 2,
+        _ => 1,
+    };
+    (ip + size <= chunk.len()).then_some(size)
+}
+
+/// Writes the chunk in the format `assemble` reads, with a label for every jump's target, so that assembling the result
+/// with the chunk's constants after the first three gives the same bytes.
+///
+/// Bytes that don't make up a whole instruction at the end are written with `.bytes`.
+pub fn disassemble(chunk: &Chunk) -> String {
+    let mut instrs = vec![];
+    let mut ip = 0;
+    while let Some(size) = size(chunk, ip) {
+        instrs.push((ip, chunk.read(ip).unwrap()));
+        ip += size
+    }
+    // Jumps to the middle of an instruction keep their offsets.
+    let starts = instrs
+        .iter()
+        .map(|(ip, _)| *ip)
+        .chain([ip])
+        .collect::<Vec<_>>();
+    let target = |ip: usize, instr: &Instruction| {
+        let offset = instr.read_two_bytes_oper(0);
+        match instr.op_code() {
+            LOOP => ip.checked_sub(offset),
+            _ => Some(ip + offset),
+        }
+        .filter(|target| starts.contains(target))
+    };
+    let targets = instrs
+        .iter()
+        .filter(|(_, instr)| JUMPS.contains(&instr.op_code()))
+        .filter_map(|(ip, instr)| target(*ip, instr))
+        .collect::<Vec<_>>();
+    let label = |ip: usize| format!("L{ip}");
+    let mut buf = String::new();
+    for (ip, instr) in &instrs {
+        if targets.contains(ip) {
+            buf += &format!("{}:\n", label(*ip))
+        }
+        buf += &format!("    {:?}", instr.op_code());
+        let op_code = instr.op_code();
+        match op_code {
+            _ if JUMPS.contains(&op_code) => match target(*ip, instr) {
+                Some(target) => buf += &format!(" {}", label(target)),
+                None => buf += &format!(" {}", instr.read_two_bytes_oper(0)),
+            },
+            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW | CONST8 | GET_GLOBAL8
+            | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 | CALL => {
+                buf += &format!(" {}", instr.read_byte_oper(0))
+            }
+            CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 | GET_PROP16 | SET_PROP16
+            | BUILD_LIST | BUILD_HASH_MAP | UNPACK_LIST | UNPACK_LIST_REST | MATCH_LIST
+            | MATCH_LIST_REST | MATCH_HASH_MAP => {
+                buf += &format!(" {}", instr.read_two_bytes_oper(0))
+            }
+            UNPACK_HASH_MAP => {
+                for idx in 0..instr.read_two_bytes_oper(0) {
+                    buf += &format!(" {}", instr.read_byte_oper(2 + idx) != 0)
+                }
+            }
+            CLOSURE8 | CLOSURE16 => {
+                let (idx, size) = match op_code {
+                    CLOSURE8 => (instr.read_byte_oper(0), 1),
+                    _ => (instr.read_two_bytes_oper(0), 2),
+                };
+                buf += &format!(" {idx}");
+                for upvalue in 0..instr.read_byte_oper(size) {
+                    let local = instr.read_byte_oper(size + 1 + upvalue * 2) != 0;
+                    let idx = instr.read_byte_oper(size + 2 + upvalue * 2);
+                    buf += &format!(" {:?}", (local, idx))
+                }
+            }
+            _ => {}
+        }
+        buf.push('\n')
+    }
+    // Labels can point to the end of the chunk.
+    if targets.contains(&ip) {
+        buf += &format!("{}:\n", label(ip))
+    }
+    if ip < chunk.len() {
+        let bytes = chunk.bytes[ip..]
+            .iter()
+            .map(u8::to_string)
+            .collect::<Vec<_>>();
+        buf += &format!(".bytes {}\n", bytes.join(" "))
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// xorshift64, enough to generate the corpus without a dependency.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    fn op_codes() -> Vec<OpCode> {
+        (0..u8::MAX)
+            .map(OpCode::from)
+            .take_while(|op_code| *op_code != UNKNOWN)
+            .collect()
+    }
+
+    /// Writes `op_code` with random operands, jumps are kept close so that some of them land on instructions.
+    fn write(chunk: &mut Chunk, rng: &mut Rng, op_code: OpCode, token: &Rc<Token>) {
+        chunk.write_op_code(op_code, token);
+        match op_code {
+            _ if JUMPS.contains(&op_code) => chunk.write_two_bytes_unchecked(rng.next(64)),
+            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW | CONST8 | GET_GLOBAL8
+            | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 | CALL => {
+                chunk.write_byte_unchecked(rng.next(u8::MAX as usize + 1))
+            }
+            CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 | GET_PROP16 | SET_PROP16
+            | BUILD_LIST | BUILD_HASH_MAP | UNPACK_LIST | UNPACK_LIST_REST | MATCH_LIST
+            | MATCH_LIST_REST | MATCH_HASH_MAP => {
+                chunk.write_two_bytes_unchecked(rng.next(u16::MAX as usize + 1))
+            }
+            UNPACK_HASH_MAP => {
+                let count = rng.next(4);
+                chunk.write_two_bytes_unchecked(count);
+                for _ in 0..count {
+                    chunk.write_byte_unchecked(rng.next(2))
+                }
+            }
+            CLOSURE8 | CLOSURE16 => {
+                match op_code {
+                    CLOSURE8 => chunk.write_byte_unchecked(rng.next(u8::MAX as usize + 1)),
+                    _ => chunk.write_two_bytes_unchecked(rng.next(u16::MAX as usize + 1)),
+                }
+                let count = rng.next(4);
+                chunk.write_byte_unchecked(count);
+                for _ in 0..count {
+                    chunk.write_byte_unchecked(rng.next(2));
+                    chunk.write_byte_unchecked(rng.next(u8::MAX as usize + 1))
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Every op code in a random order, some of them twice, and sometimes a truncated instruction at the end.
+    fn corpus() -> Vec<Chunk> {
+        let token = Rc::new(Token::new(
+            TokenType::Identifier,
+            Rc::new(String::from("س")),
+            None,
+            0,
+            "س".len(),
+        ));
+        let op_codes = op_codes();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        (0..256)
+            .map(|_| {
+                let mut chunk = Chunk::new();
+                let mut order = op_codes.clone();
+                order.extend((0..8).map(|_| op_codes[rng.next(op_codes.len())]));
+                for idx in (1..order.len()).rev() {
+                    order.swap(idx, rng.next(idx + 1))
+                }
+                for op_code in order {
+                    write(&mut chunk, &mut rng, op_code, &token)
+                }
+                if rng.next(2) == 0 {
+                    write(&mut chunk, &mut rng, CONST16, &token);
+                    chunk.bytes.pop();
+                    chunk.tokens.pop();
+                }
+                chunk
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reassembling_gives_the_same_bytes() {
+        for chunk in corpus() {
+            let source = disassemble(&chunk);
+            let reassembled = assemble(&source, vec![]);
+            assert_eq!(reassembled.bytes, chunk.bytes, "{source}");
+            assert_eq!(disassemble(&reassembled), source);
+        }
+    }
+
+    #[test]
+    fn labels_settle_on_their_instructions() {
+        let chunk = assemble(
+            "
+            start:
+                POP_JUMP_IF_FALSY end
+                LOOP start
+            end:
+                RET
+            ",
+            vec![],
+        );
+        assert_eq!(
+            disassemble(&chunk),
+            "L0:\n    POP_JUMP_IF_FALSY L6\n    LOOP L0\nL6:\n    RET\n"
+        );
+    }
+}
@@ -1,7 +1,9 @@
+#[cfg(any(test, feature = "testing"))]
+pub mod asm;
 pub mod value;
 
 use lexer::token::Token;
-use std::{fmt, rc::Rc};
+use std::{fmt, path::PathBuf, rc::Rc};
 use value::{Function, Object, Value};
 
 /// Implements `Into<u8>` and `From<u8> for the enum created inside.
@@ -94,7 +96,7 @@ byte_enum! {
         ///
         /// Keeps advancing the iterator (TOT) pushing the result to tmps until `None` is returned.
         ///
-        /// When `None` is returned by the iterator it pops TOT and jumps.
+        /// When `None` is returned by the iterator it jumps, leaving the iterator and the iterable under it on tmps.
         FOR_ITER,
         /// `LOOP <offset: u16>`
         ///
@@ -126,13 +128,13 @@ byte_enum! {
         ///
         /// Pushes `globals[constants[idx]]` to tmps.
         ///
-        /// Fails if `globals[constants[idx]]` is undefined.
+        /// Fails if `globals[constants[idx]]` is undefined, unless the VM is told to treat undefined globals as `Nil`.
         GET_GLOBAL8,
         /// `GET_GLOBAL16 <idx: u16>`
         ///
         /// Pushes `globals[constants[idx]]` to tmps.
         ///
-        /// Fails if `globals[constants[idx]]` is undefined.
+        /// Fails if `globals[constants[idx]]` is undefined, unless the VM is told to treat undefined globals as `Nil`.
         GET_GLOBAL16,
         /// `SET_GLOBAL8 <idx: u8>`
         ///
@@ -184,10 +186,19 @@ byte_enum! {
         BUILD_VARIADIC,
         /// Closes any upvalue associate to one of the closure's locals, pops the locals, and returns TOT, TOT is popped.
         RET,
+        /// Suspends the generator running the chunk and makes it produce TOT, TOT is popped.
+        ///
+        /// Execution continues from the next instruction when the generator is resumed.
+        YIELD,
         /// `BUILD_LIST <size: u16>`
         ///
         /// Takes the last `size`th values from tmps and creates a list with them.
         BUILD_LIST,
+        /// Appends TOT to the list at TOT1, TOT is popped.
+        ///
+        /// The list stays on tmps so that it can be appended to again, which is what building a list element by
+        /// element (e.g. in a loop) needs without calling a native for each one.
+        LIST_APPEND,
         /// `BUILD_HASH_MAP <size: u16>`
         ///
         /// Expects key-value pairs to be on tmps.
@@ -212,6 +223,22 @@ byte_enum! {
         ///
         /// For strings and lists TOT must be an integer, but for hash maps, It must be a string.
         SET,
+        /// `GET_PROP8 <idx: u8>`
+        ///
+        /// Like `GET` but the key is `constants[idx]` (which must be a string) instead of TOT, TOT is popped.
+        GET_PROP8,
+        /// `GET_PROP16 <idx: u16>`
+        ///
+        /// Like `GET` but the key is `constants[idx]` (which must be a string) instead of TOT, TOT is popped.
+        GET_PROP16,
+        /// `SET_PROP8 <idx: u8>`
+        ///
+        /// Like `SET` but the key is `constants[idx]` (which must be a string) instead of TOT, TOT is popped.
+        SET_PROP8,
+        /// `SET_PROP16 <idx: u16>`
+        ///
+        /// Like `SET` but the key is `constants[idx]` (which must be a string) instead of TOT, TOT is popped.
+        SET_PROP16,
         /// `APPEND_HANDLER <offset: u16>`
         ///
         /// `offset` represents the difference between this instruction and the catch's block start.
@@ -222,6 +249,11 @@ byte_enum! {
         POP_HANDLER,
         /// Throws TOT.
         THROW,
+        /// `RETHROW <idx: u8>`
+        ///
+        /// Throws the error caught in `locals[slots + idx]` again as it was, keeping where it was thrown and its
+        /// backtrace.
+        RETHROW,
         /// Turns TOT into an iterator.
         ///
         /// Expects TOT to be a string or list.
@@ -240,6 +272,27 @@ byte_enum! {
         ///
         /// Puts the values on tmps in the same order.
         UNPACK_HASH_MAP,
+        /// `UNPACK_LIST_REST <to: u16>`
+        ///
+        /// Like `UNPACK_LIST` but the elements after the first `to` are put in a list on top of them, TOT must have at
+        /// least `to` elements.
+        UNPACK_LIST_REST,
+        /// `MATCH_LIST <len: u16>`
+        ///
+        /// Replaces TOT with whether it's a list of `len` elements.
+        MATCH_LIST,
+        /// `MATCH_LIST_REST <len: u16>`
+        ///
+        /// Replaces TOT with whether it's a list of `len` elements or more.
+        MATCH_LIST_REST,
+        /// `MATCH_HASH_MAP <keyc: u16>`
+        ///
+        /// Expects the keys to be on tmps above the value, replaces them and the value with whether it's a hash map
+        /// having all of them.
+        MATCH_HASH_MAP,
+        /// Pops TOT and closes it, files and channels are closed directly while hash maps have their `أغلق` method
+        /// called.
+        CLOSE_RESOURCE,
         /// Pops TOT.
         POP,
         /// Duplicates TOT.
@@ -295,11 +348,33 @@ const NIL_CONST: usize = 0;
 const TRUE_CONST: usize = 1;
 const FALSE_CONST: usize = 2;
 
+/// Where a named local lives, kept only when the chunk is compiled with debug info.
+#[derive(Debug, Clone)]
+pub struct LocalInfo {
+    pub token: Rc<Token>,
+    /// Relative to the frame's locals.
+    pub slot: usize,
+    /// The ip of the instruction defining it, it's alive after that instruction runs.
+    pub start: usize,
+    /// The ip of the instruction popping it (or the chunk's length), it's alive until that instruction runs.
+    pub end: usize,
+}
+
+impl LocalInfo {
+    /// Whether the local holds its value right before the instruction at `ip` runs.
+    pub fn is_alive(&self, ip: usize) -> bool {
+        self.start < ip && ip <= self.end
+    }
+}
+
 #[derive(Clone)]
 pub struct Chunk {
     bytes: Vec<u8>,
     constants: Vec<Value>,
     tokens: Vec<Option<Rc<Token>>>,
+    locals: Vec<LocalInfo>,
+    /// Of the file the chunk was compiled from, its tokens can't tell as some of them are of the modules it imports.
+    path: Option<PathBuf>,
 }
 
 impl Chunk {
@@ -308,6 +383,8 @@ impl Chunk {
             bytes: vec![],
             constants: vec![Value::Nil, Value::Bool(true), Value::Bool(false)],
             tokens: vec![],
+            locals: vec![],
+            path: None,
         }
     }
 
@@ -323,13 +400,75 @@ impl Chunk {
         self.constants.get(idx).unwrap().clone()
     }
 
+    /// Like `constant` but without cloning.
+    pub fn constant_ref(&self, idx: usize) -> &Value {
+        self.constants.get(idx).unwrap()
+    }
+
+    /// The path of the file the chunk was compiled from, `None` if it wasn't read from a file (e.g. in the REPL).
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    pub fn set_path(&mut self, path: Option<PathBuf>) {
+        self.path = path
+    }
+
     pub fn token(&self, ip: usize) -> Rc<Token> {
-        Rc::clone(&self.tokens[ip].as_ref().unwrap())
+        Rc::clone(self.tokens[ip].as_ref().unwrap())
     }
 
-    fn write_op_code(&mut self, op_code: OpCode, token: Rc<Token>) {
+    /// Replaces the token of the last instruction written, for instructions whose source isn't known when they're
+    /// written (e.g. the implicit read of `+=`).
+    pub fn retoken_last(&mut self, token: &Rc<Token>) {
+        if let Some(last) = self.tokens.iter_mut().rev().flatten().next() {
+            *last = Rc::clone(token)
+        }
+    }
+
+    /// The named locals of the chunk in the order they were defined, empty if it was compiled without debug info.
+    pub fn locals(&self) -> &[LocalInfo] {
+        &self.locals
+    }
+
+    /// The named locals a reference written at `ip` would resolve to, i.e. the alive ones that aren't shadowed by an inner
+    /// local of the same name.
+    pub fn visible_locals(&self, ip: usize) -> Vec<&LocalInfo> {
+        let alive = self
+            .locals
+            .iter()
+            .filter(|local| local.is_alive(ip))
+            .collect::<Vec<_>>();
+        alive
+            .iter()
+            .filter(|local| {
+                !alive.iter().any(|other| {
+                    other.slot > local.slot && other.token.lexeme() == local.token.lexeme()
+                })
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Returns the local's index to be passed to `end_local`.
+    pub fn start_local(&mut self, token: &Rc<Token>, slot: usize) -> usize {
+        self.locals.push(LocalInfo {
+            token: Rc::clone(token),
+            slot,
+            start: self.len(),
+            end: usize::MAX,
+        });
+        self.locals.len() - 1
+    }
+
+    /// Marks the next instruction as the one popping the local.
+    pub fn end_local(&mut self, idx: usize) {
+        self.locals[idx].end = self.len()
+    }
+
+    fn write_op_code(&mut self, op_code: OpCode, token: &Rc<Token>) {
         self.bytes.push(op_code as u8);
-        self.tokens.push(Some(token));
+        self.tokens.push(Some(Rc::clone(token)));
     }
 
     fn write_byte(&mut self, byte: usize) -> Result<(), ()> {
@@ -348,15 +487,19 @@ impl Chunk {
 
     fn write_two_bytes(&mut self, two_bytes: usize) -> Result<(), ()> {
         if two_bytes <= u16::MAX.into() {
-            let [byte1, byte2] = u16::to_ne_bytes(two_bytes as u16);
-            self.write_byte(byte1 as usize).ok();
-            self.write_byte(byte2 as usize).ok();
+            self.write_two_bytes_unchecked(two_bytes);
             Ok(())
         } else {
             Err(())
         }
     }
 
+    fn write_two_bytes_unchecked(&mut self, two_bytes: usize) {
+        for byte in u16::to_ne_bytes(two_bytes as u16) {
+            self.write_byte_unchecked(byte.into())
+        }
+    }
+
     fn rewrite_two_bytes(&mut self, idx: usize, two_bytes: usize) -> Result<(), ()> {
         if two_bytes <= u16::MAX.into() {
             let [byte1, byte2] = u16::to_ne_bytes(two_bytes as u16);
@@ -385,31 +528,31 @@ impl Chunk {
         idx
     }
 
-    /// `op_code` must be `NEG`, `NOT`, `ADD`, `SUB`, `MUL`, `DIV`, `REM`, `EQ`, `GREATER`, `GREATER_EQ`, `LESS`, `LESS_EQ`, `DEF_LOCAL`, `GET`, `SET`, `CLOSE_UPVALUE`, `BUILD_VARIADIC`, `RET`, `POP_HANDLER`, `THROW`, `ITER`, `POP`, or `DUP`.
-    pub fn write_instr_no_operands(&mut self, op_code: OpCode, token: Rc<Token>) {
+    /// `op_code` must be `NEG`, `NOT`, `ADD`, `SUB`, `MUL`, `DIV`, `REM`, `EQ`, `GREATER`, `GREATER_EQ`, `LESS`, `LESS_EQ`, `DEF_LOCAL`, `GET`, `SET`, `CLOSE_UPVALUE`, `BUILD_VARIADIC`, `LIST_APPEND`, `RET`, `YIELD`, `POP_HANDLER`, `THROW`, `ITER`, `CLOSE_RESOURCE`, `POP`, or `DUP`.
+    pub fn write_instr_no_operands(&mut self, op_code: OpCode, token: &Rc<Token>) {
         self.write_op_code(op_code, token)
     }
 
-    /// `op_code` must be `GET_LOCAL`, `SET_LOCAL`, `GET_UPVALUE`, or `SET_UPVALUE`.
+    /// `op_code` must be `GET_LOCAL`, `SET_LOCAL`, `GET_UPVALUE`, `SET_UPVALUE`, or `RETHROW`.
     ///
     /// Fails when `idx` is greater than 255.
     pub fn write_instr_idx(
         &mut self,
         op_code: OpCode,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         idx: usize,
     ) -> Result<(), ()> {
         self.write_op_code(op_code, token);
         self.write_byte(idx)
     }
 
-    /// `op_code` must be (`CONST8`, `CONST16`), (`GET_GLOBAL8`, `GET_GLOBAL16`), (`SET_GLOBAL8`, `SET_GLOBAL16`), (`DEF_GLOBAL8`, `DEF_GLOBAL16`), (`GET8`, `GET_16`), or (`SET8`, `SET16`).
+    /// `op_code` must be (`CONST8`, `CONST16`), (`GET_GLOBAL8`, `GET_GLOBAL16`), (`SET_GLOBAL8`, `SET_GLOBAL16`), (`DEF_GLOBAL8`, `DEF_GLOBAL16`), (`GET_PROP8`, `GET_PROP16`), or (`SET_PROP8`, `SET_PROP16`).
     ///
     /// Fails when the chunk already has 65536 constants.
     pub fn write_instr_const(
         &mut self,
         (u8_op_code, u16_op_code): (OpCode, OpCode),
-        token: Rc<Token>,
+        token: &Rc<Token>,
         value: Value,
     ) -> Result<(), ()> {
         match self.add_constant(value) {
@@ -430,7 +573,7 @@ impl Chunk {
     /// `op_code` must be `JUMP`, `POP_JUMP_IF_FALSE`, `POP_JUMP_IF_TRUE`, `JUMP_IF_FALSE_OR_POP`, `JUMP_IF_TRUE_OR_POP`, `FOR_ITER`, or `APPEND_HANDLER`.
     ///
     /// Returns its indx
-    pub fn write_jump(&mut self, op_code: OpCode, token: Rc<Token>) -> usize {
+    pub fn write_jump(&mut self, op_code: OpCode, token: &Rc<Token>) -> usize {
         let idx = self.len();
         self.write_op_code(op_code, token);
         self.write_two_bytes(0).ok();
@@ -450,7 +593,7 @@ impl Chunk {
     }
 
     /// Fails when chunk length - `ip` is greater than 65535.
-    pub fn write_loop(&mut self, token: Rc<Token>, ip: usize) -> Result<(), ()> {
+    pub fn write_loop(&mut self, token: &Rc<Token>, ip: usize) -> Result<(), ()> {
         let offset = self.len() - ip;
         self.write_op_code(LOOP, token);
         self.write_two_bytes(offset)
@@ -459,7 +602,7 @@ impl Chunk {
     /// Can fail while appending `function` or because `upvalues` length is greater than 255.
     pub fn write_closure(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         function: Function,
         upvalues: Vec<(bool, usize)>,
     ) -> Result<(), ()> {
@@ -473,18 +616,18 @@ impl Chunk {
     }
 
     /// Fails when `argc` is greater than 255.
-    pub fn write_call(&mut self, token: Rc<Token>, argc: usize) -> Result<(), ()> {
+    pub fn write_call(&mut self, token: &Rc<Token>, argc: usize) -> Result<(), ()> {
         self.write_op_code(CALL, token);
         self.write_byte(argc)
     }
 
-    /// `op_code` must be `BUILD_LIST` or `BUILD_HASH_MAP`.
+    /// `op_code` must be `BUILD_LIST`, `BUILD_HASH_MAP`, `MATCH_LIST`, `MATCH_LIST_REST`, or `MATCH_HASH_MAP`.
     ///
     /// Fails when `size` is greater than 65535.
     pub fn write_build(
         &mut self,
         op_code: OpCode,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         size: usize,
     ) -> Result<(), ()> {
         self.write_op_code(op_code, token);
@@ -498,7 +641,7 @@ impl Chunk {
     /// Fails when `defaults` length is greater than 65535.
     pub fn write_hash_map_unpack(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         defaults: Vec<bool>,
     ) -> Result<(), ()> {
         self.write_op_code(UNPACK_HASH_MAP, token);
@@ -509,9 +652,16 @@ impl Chunk {
         Ok(())
     }
 
+    /// Writes `UNPACK_LIST_REST` instead of `UNPACK_LIST` if `rest` is set.
+    ///
     /// Fails when `to` is greater than 65535
-    pub fn write_list_unpack(&mut self, token: Rc<Token>, to: usize) -> Result<(), ()> {
-        self.write_op_code(UNPACK_LIST, token);
+    pub fn write_list_unpack(
+        &mut self,
+        token: &Rc<Token>,
+        to: usize,
+        rest: bool,
+    ) -> Result<(), ()> {
+        self.write_op_code(if rest { UNPACK_LIST_REST } else { UNPACK_LIST }, token);
         self.write_two_bytes(to)
     }
 
@@ -534,16 +684,20 @@ impl Chunk {
         let op_code = self.byte(ip)?.into();
         match op_code {
             NEG | NOT | ADD | SUB | MUL | DIV | REM | EQ | NOT_EQ | GREATER | GREATER_EQ | LESS
-            | LESS_EQ | POP_LOCAL | CLOSE_UPVALUE | BUILD_VARIADIC | RET | POP_HANDLER | THROW
-            | ITER | POP | DUP | GET | SET | DEF_LOCAL => {
+            | LESS_EQ | POP_LOCAL | CLOSE_UPVALUE | BUILD_VARIADIC | LIST_APPEND | RET | YIELD
+            | POP_HANDLER | THROW | ITER | CLOSE_RESOURCE | POP | DUP | GET | SET | DEF_LOCAL => {
                 Some(Instruction::new(op_code, operands!(1)))
             }
-            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | CONST8 | GET_GLOBAL8
-            | SET_GLOBAL8 | DEF_GLOBAL8 | CALL => Some(Instruction::new(op_code, operands!(2))),
+            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW | CONST8 | GET_GLOBAL8
+            | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 | CALL => {
+                Some(Instruction::new(op_code, operands!(2)))
+            }
             CONST16
             | GET_GLOBAL16
             | SET_GLOBAL16
             | DEF_GLOBAL16
+            | GET_PROP16
+            | SET_PROP16
             | JUMP
             | POP_JUMP_IF_FALSY
             | POP_JUMP_IF_TRUTHY
@@ -554,7 +708,11 @@ impl Chunk {
             | LOOP
             | BUILD_LIST
             | BUILD_HASH_MAP
-            | UNPACK_LIST => Some(Instruction::new(op_code, operands!(3))),
+            | UNPACK_LIST
+            | UNPACK_LIST_REST
+            | MATCH_LIST
+            | MATCH_LIST_REST
+            | MATCH_HASH_MAP => Some(Instruction::new(op_code, operands!(3))),
             UNPACK_HASH_MAP => Some(Instruction::new(op_code, operands!(3 + two_bytes_oper!()))),
             CLOSURE8 => Some(Instruction::new(op_code, operands!(3 + byte_oper!(1) * 2))),
             CLOSURE16 => Some(Instruction::new(op_code, operands!(4 + byte_oper!(2) * 2))),
@@ -569,17 +727,17 @@ impl Chunk {
         buf += format!("{:>5} {:20}", ip, format!("{:?}", instr.op_code())).as_str();
         match instr.op_code() {
             NEG | NOT | ADD | SUB | MUL | DIV | REM | EQ | NOT_EQ | GREATER | GREATER_EQ | LESS
-            | LESS_EQ | POP_LOCAL | CLOSE_UPVALUE | BUILD_VARIADIC | RET | POP_HANDLER | THROW
-            | ITER | POP | DUP | GET | SET => {}
+            | LESS_EQ | POP_LOCAL | CLOSE_UPVALUE | BUILD_VARIADIC | LIST_APPEND | RET | YIELD
+            | POP_HANDLER | THROW | ITER | CLOSE_RESOURCE | POP | DUP | GET | SET => {}
             DEF_LOCAL => buf += format!(" ({})", token.lexeme()).as_str(),
-            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE => {
+            GET_LOCAL | SET_LOCAL | GET_UPVALUE | SET_UPVALUE | RETHROW => {
                 buf += format!(" {} ({})", instr.read_byte_oper(0), token.lexeme()).as_str()
             }
-            CONST8 | GET_GLOBAL8 | SET_GLOBAL8 | DEF_GLOBAL8 => {
+            CONST8 | GET_GLOBAL8 | SET_GLOBAL8 | DEF_GLOBAL8 | GET_PROP8 | SET_PROP8 => {
                 let idx = instr.read_byte_oper(0);
                 buf += format!(" {idx} ({})", self.constant(idx)).as_str()
             }
-            CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 => {
+            CONST16 | GET_GLOBAL16 | SET_GLOBAL16 | DEF_GLOBAL16 | GET_PROP16 | SET_PROP16 => {
                 let idx = instr.read_two_bytes_oper(0);
                 buf += format!(" {idx} ({})", self.constant(idx)).as_str()
             }
@@ -626,7 +784,7 @@ impl Chunk {
                     buf += format!(" {default}").as_str()
                 }
             }
-            UNPACK_LIST => {
+            UNPACK_LIST | UNPACK_LIST_REST | MATCH_LIST | MATCH_LIST_REST | MATCH_HASH_MAP => {
                 let to = instr.read_two_bytes_oper(0);
                 buf += format!(" {to}").as_str()
             }
@@ -1,7 +1,19 @@
 use super::Chunk;
-use crate::error::RuntimeError;
+use crate::error::{Backtrace, RuntimeError};
+use lexer::token::{display_path, Token};
 use std::convert::{From, Into, TryFrom};
-use std::{cell::RefCell, cmp, collections::HashMap, fmt, fs, iter, ops, rc::Rc};
+use std::{
+    cell::RefCell,
+    cmp,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write},
+    fs,
+    hash::Hash,
+    io::{self, BufRead, BufReader, Read, Seek, Write as _},
+    iter, mem, ops,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -25,6 +37,12 @@ pub enum DataType {
     Closure,
     Native,
     Iterator,
+    Caught,
+    Set,
+    Store,
+    Channel,
+    Future,
+    Cell,
 }
 
 impl fmt::Display for DataType {
@@ -44,13 +62,19 @@ impl fmt::Display for DataType {
                 Self::Closure => "دالة",
                 Self::Native => "دالة مدمجة",
                 Self::Iterator => "مكرر",
+                Self::Caught => "خطأ ممسوك",
+                Self::Set => "مجموعة",
+                Self::Store => "مخزن",
+                Self::Channel => "قناة",
+                Self::Future => "قيمة مؤجلة",
+                Self::Cell => "خلية",
             }
         )
     }
 }
 
 impl Value {
-    /// `Nil`, `Bool(false)`, `Number(0)`, and empty sequences (i.e., empty strings, lists, hash maps) are falsy, the rest are truthy.
+    /// `Nil`, `Bool(false)`, `Number(0)`, and empty sequences (i.e., empty strings, lists, hash maps, sets) are falsy, the rest are truthy.
     pub fn truthy(&self) -> bool {
         match self {
             Self::Nil | Self::Bool(false) => false,
@@ -58,6 +82,7 @@ impl Value {
             Self::String(string) if string.len() == 0 => false,
             Self::Object(Object::List(list)) if list.borrow().len() == 0 => false,
             Self::Object(Object::HashMap(hash_map)) if hash_map.borrow().len() == 0 => false,
+            Self::Object(Object::Set(set)) if set.borrow().is_empty() => false,
             _ => true,
         }
     }
@@ -75,8 +100,178 @@ impl Value {
             Self::Object(Object::Closure(..)) => DataType::Closure,
             Self::Object(Object::Native(..)) => DataType::Native,
             Self::Object(Object::Iterator(..)) => DataType::Iterator,
+            Self::Object(Object::Caught(..)) => DataType::Caught,
+            Self::Object(Object::Set(..)) => DataType::Set,
+            Self::Object(Object::Store(..)) => DataType::Store,
+            Self::Object(Object::Channel(..)) => DataType::Channel,
+            Self::Object(Object::Future(..)) => DataType::Future,
+            Self::Object(Object::Cell(..)) => DataType::Cell,
         }
     }
+
+    /// Compares lists, hash maps, and sets by their contents, and the rest like `==` does.
+    pub fn deep_eq(&self, other: &Self, limits: TraversalLimits) -> Result<bool, TooLarge> {
+        let mut traversal = Traversal::new(limits);
+        // Pairs that are already being compared are assumed to be equal, which is what makes comparing cyclic
+        // structures terminate.
+        let mut memo = Memo::new(limits.memo);
+        traversal.push((self.clone(), other.clone()));
+        while let Some(pair) = traversal.next()? {
+            match pair {
+                (Self::Object(Object::List(a)), Self::Object(Object::List(b))) => {
+                    let key = (address(&a), address(&b));
+                    if Rc::ptr_eq(&a, &b) || memo.get(&key).is_some() {
+                        continue;
+                    }
+                    memo.insert(key, ());
+                    let (a, b) = (a.borrow(), b.borrow());
+                    if a.len() != b.len() {
+                        return Ok(false);
+                    }
+                    for pair in a.iter().cloned().zip(b.iter().cloned()) {
+                        traversal.push(pair)
+                    }
+                }
+                (Self::Object(Object::HashMap(a)), Self::Object(Object::HashMap(b))) => {
+                    let key = (address(&a), address(&b));
+                    if Rc::ptr_eq(&a, &b) || memo.get(&key).is_some() {
+                        continue;
+                    }
+                    memo.insert(key, ());
+                    let (a, b) = (a.borrow(), b.borrow());
+                    if a.len() != b.len() {
+                        return Ok(false);
+                    }
+                    for (key, a) in a.iter() {
+                        match b.get(key) {
+                            Some(b) => traversal.push((a.clone(), b.clone())),
+                            None => return Ok(false),
+                        }
+                    }
+                }
+                // Sets only hold strings and numbers, so there's nothing to traverse.
+                (Self::Object(Object::Set(a)), Self::Object(Object::Set(b)))
+                    if *a.borrow() != *b.borrow() =>
+                {
+                    return Ok(false)
+                }
+                (Self::Object(Object::Set(_)), Self::Object(Object::Set(_))) => {}
+                (a, b) if a != b => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// Copies lists, hash maps, and sets along with the ones nested inside them, cycles and shared parts are kept as such.
+    pub fn deep_clone(&self, limits: TraversalLimits) -> Result<Self, TooLarge> {
+        let mut traversal = Traversal::new(limits);
+        let mut memo: Memo<usize, Self> = Memo::new(limits.memo);
+        // Returns an empty copy of containers and schedules filling it.
+        let mut shell = |value: &Self, traversal: &mut Traversal<(Self, Self)>| {
+            let (key, copy) = match value {
+                Self::Object(Object::List(list)) => (address(list), Self::from(vec![])),
+                Self::Object(Object::HashMap(hash_map)) => {
+                    (address(hash_map), Self::from(HashMap::new()))
+                }
+                // Their elements aren't containers, so they're copied at once.
+                Self::Object(Object::Set(set)) => {
+                    let key = address(set);
+                    return match memo.get(&key) {
+                        Some(copy) => copy.clone(),
+                        None => {
+                            let copy = Self::from(set.borrow().clone());
+                            memo.insert(key, copy.clone());
+                            copy
+                        }
+                    };
+                }
+                _ => return value.clone(),
+            };
+            if let Some(copy) = memo.get(&key) {
+                return copy.clone();
+            }
+            memo.insert(key, copy.clone());
+            traversal.push((value.clone(), copy.clone()));
+            copy
+        };
+        let root = shell(self, &mut traversal);
+        while let Some(pair) = traversal.next()? {
+            match pair {
+                (Self::Object(Object::List(original)), Self::Object(Object::List(copy))) => {
+                    for value in original.borrow().iter() {
+                        let value = shell(value, &mut traversal);
+                        copy.borrow_mut().push(value)
+                    }
+                }
+                (Self::Object(Object::HashMap(original)), Self::Object(Object::HashMap(copy))) => {
+                    for (key, value) in original.borrow().iter() {
+                        let value = shell(value, &mut traversal);
+                        copy.borrow_mut().insert(key.clone(), value);
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(root)
+    }
+
+    /// Displays the value rounding numbers (including the ones inside lists and hash maps) to `precision` significant
+    /// digits.
+    pub fn render(
+        &self,
+        precision: Option<usize>,
+        limits: TraversalLimits,
+    ) -> Result<String, TooLarge> {
+        enum Piece {
+            /// Written after the text before it (e.g. a separator).
+            Value(String, Value),
+            Close(char),
+        }
+
+        let mut buf = String::new();
+        let mut traversal = Traversal::new(limits);
+        traversal.push(Piece::Value(String::new(), self.clone()));
+        while let Some(piece) = traversal.next()? {
+            let value = match piece {
+                Piece::Value(before, value) => {
+                    buf.push_str(&before);
+                    value
+                }
+                Piece::Close(c) => {
+                    buf.push(c);
+                    continue;
+                }
+            };
+            match value {
+                Self::Object(Object::HashMap(hash_map)) => {
+                    buf.push('{');
+                    traversal.push(Piece::Close('}'));
+                    let hash_map = hash_map.borrow();
+                    let pieces = hash_map
+                        .iter()
+                        .map(|(key, value)| Piece::Value(format!("{key}: "), value.clone()))
+                        .collect::<Vec<_>>();
+                    for piece in pieces.into_iter().rev() {
+                        traversal.push(piece)
+                    }
+                }
+                Self::Object(Object::List(list)) => {
+                    buf.push('[');
+                    traversal.push(Piece::Close(']'));
+                    for (idx, value) in list.borrow().iter().enumerate().rev() {
+                        let before = if idx == 0 { "" } else { "، " };
+                        traversal.push(Piece::Value(before.to_owned(), value.clone()))
+                    }
+                }
+                value => match precision {
+                    Some(digits) => write!(buf, "{value:.digits$}").unwrap(),
+                    None => write!(buf, "{value}").unwrap(),
+                },
+            }
+        }
+        Ok(buf)
+    }
 }
 
 impl PartialEq for Value {
@@ -186,6 +381,8 @@ impl PartialOrd for Value {
 }
 
 impl fmt::Display for Value {
+    /// The precision (e.g. `{:.3}`) is treated as the number of significant digits numbers are rounded to, including
+    /// the ones inside lists and hash maps.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Nil => write!(f, "عدم"),
@@ -196,9 +393,15 @@ impl fmt::Display for Value {
                     write!(f, "خطأ")
                 }
             }
-            Self::Number(number) => write!(f, "{number}"),
+            Self::Number(number) => match f.precision() {
+                Some(digits) if *number != 0.0 && number.is_finite() => {
+                    let rounded: f64 = format!("{number:.*e}", digits.max(1) - 1).parse().unwrap();
+                    write!(f, "{rounded}")
+                }
+                _ => write!(f, "{number}"),
+            },
             Self::String(string) => write!(f, "{string}"),
-            Self::Object(object) => write!(f, "{object}"),
+            Self::Object(object) => object.fmt(f),
         }
     }
 }
@@ -245,6 +448,12 @@ impl From<HashMap<String, Value>> for Value {
     }
 }
 
+impl From<Set> for Value {
+    fn from(set: Set) -> Self {
+        Self::Object(Object::Set(Rc::new(RefCell::new(set))))
+    }
+}
+
 impl From<Vec<Value>> for Value {
     fn from(list: Vec<Value>) -> Self {
         Self::Object(Object::List(Rc::new(RefCell::new(list))))
@@ -257,6 +466,24 @@ impl From<File> for Value {
     }
 }
 
+impl From<Store> for Value {
+    fn from(store: Store) -> Self {
+        Self::Object(Object::Store(Rc::new(RefCell::new(store))))
+    }
+}
+
+impl From<Channel> for Value {
+    fn from(channel: Channel) -> Self {
+        Self::Object(Object::Channel(Rc::new(RefCell::new(channel))))
+    }
+}
+
+impl From<Future> for Value {
+    fn from(future: Future) -> Self {
+        Self::Object(Object::Future(Rc::new(RefCell::new(future))))
+    }
+}
+
 impl From<Function> for Value {
     fn from(function: Function) -> Self {
         Self::Object(Object::Function(Rc::new(function)))
@@ -322,6 +549,61 @@ impl TryInto<Rc<RefCell<Vec<Value>>>> for Value {
     }
 }
 
+impl TryInto<Rc<RefCell<File>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<File>>, Self::Error> {
+        match self {
+            Self::Object(Object::File(file)) => Ok(file),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryInto<Rc<RefCell<Store>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<Store>>, Self::Error> {
+        match self {
+            Self::Object(Object::Store(store)) => Ok(store),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryInto<Rc<RefCell<Channel>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<Channel>>, Self::Error> {
+        match self {
+            Self::Object(Object::Channel(channel)) => Ok(channel),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryInto<Rc<RefCell<Future>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<Future>>, Self::Error> {
+        match self {
+            Self::Object(Object::Future(future)) => Ok(future),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryInto<Rc<RefCell<Value>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<Value>>, Self::Error> {
+        match self {
+            Self::Object(Object::Cell(cell)) => Ok(cell),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryInto<Rc<RefCell<HashMap<String, Value>>>> for Value {
     type Error = ();
 
@@ -333,6 +615,17 @@ impl TryInto<Rc<RefCell<HashMap<String, Value>>>> for Value {
     }
 }
 
+impl TryInto<Rc<RefCell<Set>>> for Value {
+    type Error = ();
+
+    fn try_into(self) -> Result<Rc<RefCell<Set>>, Self::Error> {
+        match self {
+            Self::Object(Object::Set(set)) => Ok(set),
+            _ => Err(()),
+        }
+    }
+}
+
 impl TryInto<Rc<Function>> for Value {
     type Error = ();
 
@@ -364,6 +657,14 @@ pub enum Object {
     Closure(Rc<Closure>),
     Native(Rc<Native>),
     Iterator(Rc<RefCell<Iterator>>),
+    /// The error a catch block caught, kept for `ألقي_مجدداً` where programs can't reach it.
+    Caught(Rc<RuntimeError>),
+    Set(Rc<RefCell<Set>>),
+    Store(Rc<RefCell<Store>>),
+    Channel(Rc<RefCell<Channel>>),
+    Future(Rc<RefCell<Future>>),
+    /// Shared by the tasks without being copied, it's only safe because they take turns on the same thread.
+    Cell(Rc<RefCell<Value>>),
 }
 
 impl PartialEq for Object {
@@ -376,6 +677,12 @@ impl PartialEq for Object {
             (Self::Closure(a), Self::Closure(b)) => Rc::ptr_eq(a, b),
             (Self::Native(a), Self::Native(b)) => Rc::ptr_eq(a, b),
             (Self::Iterator(a), Self::Iterator(b)) => Rc::ptr_eq(a, b),
+            (Self::Caught(a), Self::Caught(b)) => Rc::ptr_eq(a, b),
+            (Self::Set(a), Self::Set(b)) => Rc::ptr_eq(a, b),
+            (Self::Store(a), Self::Store(b)) => Rc::ptr_eq(a, b),
+            (Self::Channel(a), Self::Channel(b)) => Rc::ptr_eq(a, b),
+            (Self::Future(a), Self::Future(b)) => Rc::ptr_eq(a, b),
+            (Self::Cell(a), Self::Cell(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -384,54 +691,544 @@ impl PartialEq for Object {
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::HashMap(hash_map) => {
-                let tmp = hash_map.borrow();
-                let mut iter = tmp.keys();
-                write!(f, "{{")?;
-                if let Some(key) = iter.next() {
-                    write!(f, "{key}: {}", tmp.get(key).unwrap())?;
-                    while let Some(key) = iter.next() {
-                        write!(f, "{key}: {}", tmp.get(key).unwrap())?;
-                    }
+            // Callers that can fail (e.g. printing) use `Value::render` directly to report structures that are too
+            // large instead.
+            Self::HashMap(..) | Self::List(..) => {
+                match Value::Object(self.clone()).render(f.precision(), TraversalLimits::default())
+                {
+                    Ok(string) => write!(f, "{string}"),
+                    Err(TooLarge) => write!(f, "<بنية كبيرة جداً>"),
                 }
-                write!(f, "}}")
-            }
-            Self::List(list) => {
-                let tmp = list.borrow();
-                let mut iter = tmp.iter();
-                write!(f, "[")?;
-                if let Some(value) = iter.next() {
-                    write!(f, "{value}")?;
-                    while let Some(value) = iter.next() {
-                        write!(f, "، {value}")?;
-                    }
-                }
-                write!(f, "]")
             }
             Self::File(file) => write!(f, "{}", file.borrow()),
             Self::Function(function) => write!(f, "{function}"),
             Self::Closure(closure) => write!(f, "{}", closure.function),
             Self::Native(native) => write!(f, "<{native:?}دالة مدمجة مختزنة في >"),
             Self::Iterator(iterator) => write!(f, "{}", iterator.borrow()),
+            Self::Caught(err) => write!(f, "<خطأ ممسوك: {}>", err.msg()),
+            Self::Set(set) => write!(f, "{}", set.borrow()),
+            Self::Store(store) => write!(f, "{}", store.borrow()),
+            Self::Channel(channel) => write!(f, "{}", channel.borrow()),
+            Self::Future(future) => write!(f, "{}", future.borrow()),
+            // Not what it holds, which can be the cell itself.
+            Self::Cell(..) => write!(f, "<خلية>"),
+        }
+    }
+}
+
+/// Bounds the operations that walk nested lists and hash maps (deep equality, deep cloning, displaying, and JSON
+/// serialization), so that structures sharing the same part many times fail quickly instead of hanging.
+#[derive(Debug, Clone, Copy)]
+pub struct TraversalLimits {
+    /// The number of nodes a single traversal visits before failing.
+    pub nodes: usize,
+    /// The number of containers remembered as visited, the ones beyond it are walked every time they're reached.
+    pub memo: usize,
+}
+
+impl Default for TraversalLimits {
+    fn default() -> Self {
+        Self {
+            nodes: 1_000_000,
+            memo: 1 << 16,
+        }
+    }
+}
+
+/// The default of `Interpreter::max_list_len`, a list this long already takes gigabytes, and on 32-bit targets (e.g.
+/// WebAssembly) longer ones can't be addressed.
+pub const MAX_LIST_LEN: usize = {
+    let addressable = isize::MAX as usize / mem::size_of::<Value>();
+    if addressable < 1 << 27 {
+        addressable
+    } else {
+        1 << 27
+    }
+};
+
+/// Counts `len` list elements against the interpreter's limits, every list that the VM or the natives create passes
+/// through here (either by `checked_list_alloc` or `Value::list`).
+fn count_list(interpreter: &mut dyn Interpreter, len: usize) -> Result<(), RuntimeError> {
+    let limit = interpreter.max_list_len();
+    if len > limit {
+        return Err(RuntimeError::ListTooLong(
+            len as u64,
+            Some(limit),
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    interpreter.allocate(len)
+}
+
+/// Counts `len` elements against the interpreter's limits and makes an empty list with room for them, failing (instead of
+/// aborting) if it would be longer than `Interpreter::max_list_len` or the memory can't hold it.
+pub fn checked_list_alloc(
+    interpreter: &mut dyn Interpreter,
+    len: usize,
+) -> Result<Vec<Value>, RuntimeError> {
+    count_list(interpreter, len)?;
+    let mut list = Vec::new();
+    list.try_reserve_exact(len).map_err(|_| {
+        RuntimeError::ListTooLong(len as u64, None, interpreter.token(), Backtrace::default())
+    })?;
+    Ok(list)
+}
+
+impl Value {
+    /// Like `Value::from` but counts the elements against the interpreter's limits, for lists that are built before
+    /// they're known to fit.
+    pub fn list(interpreter: &mut dyn Interpreter, list: Vec<Value>) -> Result<Self, RuntimeError> {
+        count_list(interpreter, list.len())?;
+        Ok(Self::from(list))
+    }
+
+    /// Like `Value::list` but for hash maps.
+    pub fn hash_map(
+        interpreter: &mut dyn Interpreter,
+        hash_map: HashMap<String, Value>,
+    ) -> Result<Self, RuntimeError> {
+        interpreter.allocate(hash_map.len())?;
+        Ok(Self::from(hash_map))
+    }
+}
+
+/// A traversal visited more nodes than its limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooLarge;
+
+/// A work list of the nodes left to visit, walking it iteratively means deep structures can't overflow the stack.
+pub struct Traversal<T> {
+    work: Vec<T>,
+    remaining: usize,
+}
+
+impl<T> Traversal<T> {
+    pub fn new(limits: TraversalLimits) -> Self {
+        Self {
+            work: vec![],
+            remaining: limits.nodes,
+        }
+    }
+
+    /// The last pushed node is visited first.
+    pub fn push(&mut self, node: T) {
+        self.work.push(node)
+    }
+
+    /// Returns the next node to visit, `None` means it's done.
+    pub fn next(&mut self) -> Result<Option<T>, TooLarge> {
+        match self.work.pop() {
+            Some(_) if self.remaining == 0 => Err(TooLarge),
+            Some(node) => {
+                self.remaining -= 1;
+                Ok(Some(node))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// A map that stops growing once it has `cap` entries.
+pub struct Memo<K, V> {
+    entries: HashMap<K, V>,
+    cap: usize,
+}
+
+impl<K: Hash + Eq, V> Memo<K, V> {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            cap,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key)
+    }
+
+    /// Does nothing if it's full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.entries.len() < self.cap {
+            self.entries.insert(key, value);
+        }
+    }
+}
+
+/// Identifies a container by where it's allocated.
+pub fn address<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc) as *const () as usize
+}
+
+/// What sets can hold, numbers must be whole so that equal ones are always the same key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SetKey {
+    String(String),
+    Integer(i64),
+}
+
+impl TryFrom<Value> for SetKey {
+    /// Gives the value back.
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(Self::String(string)),
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+                Ok(Self::Integer(number as i64))
+            }
+            value => Err(value),
+        }
+    }
+}
+
+impl From<SetKey> for Value {
+    fn from(key: SetKey) -> Self {
+        match key {
+            SetKey::String(string) => Self::String(string),
+            SetKey::Integer(integer) => Self::Number(integer as f64),
         }
     }
 }
 
+/// The number of elements displayed before the rest are summarized.
+const SET_DISPLAY_LIMIT: usize = 20;
+
+/// A set that remembers the order its elements were added in, which is the order they're iterated and displayed in.
+#[derive(Debug, Clone, Default)]
+pub struct Set {
+    /// Removing an element leaves a hole behind, they're cleared once they're as many as the elements.
+    elements: Vec<Option<SetKey>>,
+    /// Where each element is in `elements`.
+    indices: HashMap<SetKey, usize>,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn contains(&self, key: &SetKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Returns whether it wasn't there already.
+    pub fn insert(&mut self, key: SetKey) -> bool {
+        if self.contains(&key) {
+            return false;
+        }
+        self.indices.insert(key.clone(), self.elements.len());
+        self.elements.push(Some(key));
+        true
+    }
+
+    /// Returns whether it was there.
+    pub fn remove(&mut self, key: &SetKey) -> bool {
+        match self.indices.remove(key) {
+            Some(idx) => {
+                self.elements[idx] = None;
+                if self.elements.len() >= 2 * self.len() {
+                    self.compact()
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn compact(&mut self) {
+        self.elements.retain(Option::is_some);
+        for (idx, key) in self.elements.iter().flatten().enumerate() {
+            *self.indices.get_mut(key).unwrap() = idx;
+        }
+    }
+
+    pub fn iter(&self) -> impl iter::Iterator<Item = &SetKey> {
+        self.elements.iter().flatten()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.iter().chain(other.iter()).cloned().collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.iter()
+            .filter(|key| other.contains(key))
+            .cloned()
+            .collect()
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.iter()
+            .filter(|key| !other.contains(key))
+            .cloned()
+            .collect()
+    }
+}
+
+impl FromIterator<SetKey> for Set {
+    fn from_iter<T: IntoIterator<Item = SetKey>>(iter: T) -> Self {
+        let mut set = Self::new();
+        for key in iter {
+            set.insert(key);
+        }
+        set
+    }
+}
+
+impl PartialEq for Set {
+    /// Sets with the same elements are equal whatever order they were added in.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl fmt::Display for Set {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let elements = self
+            .iter()
+            .take(SET_DISPLAY_LIMIT)
+            .map(|key| format!("{}", Value::from(key.clone())))
+            .collect::<Vec<_>>();
+        write!(f, "مجموعة{{{}", elements.join("، "))?;
+        if self.len() > SET_DISPLAY_LIMIT {
+            write!(f, "، ...و{} أخرى", self.len() - SET_DISPLAY_LIMIT)?;
+        }
+        write!(f, "}}")
+    }
+}
+
 #[derive(Debug)]
 pub struct File {
     name: String,
     mode: FileMode,
-    file: fs::File,
+    /// Writes are buffered until it's flushed, closed, or dropped, `None` once it's closed.
+    file: Option<io::BufWriter<fs::File>>,
+}
+
+impl File {
+    /// Opens the file at `path`, writing truncates it and both writing and "أي شئ" create it if it doesn't exist.
+    pub fn open(path: &Path, mode: FileMode) -> io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .read(mode.readable())
+            .write(mode.writable())
+            .create(mode.writable())
+            .truncate(mode == FileMode::Write)
+            .open(path)?;
+        Ok(Self {
+            name: path.to_string_lossy().into_owned(),
+            mode,
+            file: Some(io::BufWriter::new(file)),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mode(&self) -> FileMode {
+        self.mode
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.file.is_none()
+    }
+
+    fn inner(&mut self) -> io::Result<&mut io::BufWriter<fs::File>> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("the file is closed"))
+    }
+
+    /// Closes the file unless it's already closed, what was written is flushed first.
+    pub fn close(&mut self) -> io::Result<()> {
+        match self.file.take() {
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Read for File {
+    /// Flushes what was written first so that reading continues after it.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let file = self.inner()?;
+        file.flush()?;
+        file.get_mut().read(buf)
+    }
+}
+
+impl Seek for File {
+    /// Flushes what was written first so that it's written where it was meant to be.
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner()?.seek(pos)
+    }
+}
+
+impl io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner()?.flush()
+    }
 }
 
 impl fmt::Display for File {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<ملف {} مفتوح على وضع {}>", self.name, self.mode)
+        match self.file {
+            Some(_) => write!(f, "<ملف {} مفتوح على وضع {}>", self.name, self.mode),
+            None => write!(f, "<ملف {} مغلق>", self.name),
+        }
+    }
+}
+
+/// A hash map kept in a file, which the VM reads when it's opened and writes when it's saved, so changes are lost if
+/// the program ends before saving them. Nothing stops two programs from saving over each other's changes.
+#[derive(Debug)]
+pub struct Store {
+    path: PathBuf,
+    entries: HashMap<String, Value>,
+}
+
+impl Store {
+    pub fn new(path: PathBuf, entries: HashMap<String, Value>) -> Self {
+        Self { path, entries }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &HashMap<String, Value> {
+        &self.entries
+    }
+
+    pub fn entries_mut(&mut self) -> &mut HashMap<String, Value> {
+        &mut self.entries
+    }
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "<مخزن {} فيه {} مفتاح>",
+            display_path(&self.path),
+            self.entries.len()
+        )
+    }
+}
+
+/// The values tasks send to each other, in the order they were sent.
+#[derive(Debug, Default)]
+pub struct Channel {
+    values: VecDeque<Value>,
+    closed: bool,
+}
+
+impl Channel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Values can still be received after it's closed until there are none left.
+    pub fn close(&mut self) {
+        self.closed = true
+    }
+
+    pub fn send(&mut self, value: Value) {
+        self.values.push_back(value)
+    }
+
+    pub fn receive(&mut self) -> Option<Value> {
+        self.values.pop_front()
+    }
+
+    /// Whether receiving doesn't have to wait, because there's a value or because none will be sent anymore.
+    pub fn is_ready(&self) -> bool {
+        self.closed || !self.values.is_empty()
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.closed {
+            false => write!(f, "<قناة فيها {} قيمة>", self.values.len()),
+            true => write!(f, "<قناة مغلقة فيها {} قيمة>", self.values.len()),
+        }
+    }
+}
+
+/// What the function of a task will return, see `Interpreter::spawn`.
+#[derive(Debug, Default)]
+pub struct Future {
+    value: Option<Value>,
+}
+
+impl Future {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `None` until the task returns.
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    pub fn resolve(&mut self, value: Value) {
+        self.value = Some(value)
+    }
+}
+
+impl fmt::Display for Future {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(_) => write!(f, "<قيمة مؤجلة مكتملة>"),
+            None => write!(f, "<قيمة مؤجلة لم تكتمل>"),
+        }
+    }
+}
+
+/// What a task can wait for, see `Interpreter::wait`.
+#[derive(Debug, Clone)]
+pub enum Awaitable {
+    Channel(Rc<RefCell<Channel>>),
+    Future(Rc<RefCell<Future>>),
+}
+
+impl Awaitable {
+    pub fn is_ready(&self) -> bool {
+        match self {
+            Self::Channel(channel) => channel.borrow().is_ready(),
+            Self::Future(future) => future.borrow().value().is_some(),
+        }
+    }
+
+    /// Receives from the channel (`Nil` if it's closed), or returns the value of the future, once it's ready.
+    pub fn take(&self) -> Value {
+        match self {
+            Self::Channel(channel) => channel.borrow_mut().receive().unwrap_or(Value::Nil),
+            Self::Future(future) => future.borrow().value().cloned().unwrap_or(Value::Nil),
+        }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-enum FileMode {
+pub enum FileMode {
     Read,
     Write,
     All,
@@ -444,9 +1241,21 @@ impl fmt::Display for FileMode {
     }
 }
 
+impl FileMode {
+    pub fn readable(&self) -> bool {
+        *self != Self::Write
+    }
+
+    pub fn writable(&self) -> bool {
+        *self != Self::Read
+    }
+}
+
 const READ: &str = "قراءة";
 const WRITE: &str = "كتابة";
 const ALL: &str = "أي شئ";
+/// The names of the modes files can be opened in.
+pub const FILE_MODES: [&str; 3] = [READ, WRITE, ALL];
 
 impl TryFrom<String> for FileMode {
     type Error = ();
@@ -486,6 +1295,10 @@ pub struct Function {
     defaults: Vec<usize>,
     /// Represents the `ip` of the first instruction in the variadic param builder (if the function is variadic) or the code for destructuring otherwise.
     body: usize,
+    /// Calling a generator creates an iterator that runs its body lazily instead of running it.
+    generator: bool,
+    /// The number of upvalues its closures capture.
+    upvalues: usize,
 }
 
 impl Function {
@@ -495,6 +1308,8 @@ impl Function {
         arity: Arity,
         defaults: Vec<usize>,
         body: usize,
+        generator: bool,
+        upvalues: usize,
     ) -> Self {
         Self {
             name,
@@ -502,6 +1317,8 @@ impl Function {
             arity,
             defaults,
             body,
+            generator,
+            upvalues,
         }
     }
 
@@ -514,6 +1331,7 @@ impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "<")?;
         match &self.name {
+            Some(name) if self.generator => write!(f, "مولّد {name}")?,
             Some(name) => write!(f, "دالة {name}")?,
             None => write!(f, "دالة غير مسماة")?,
         };
@@ -590,7 +1408,14 @@ pub struct Closure {
 }
 
 impl Closure {
+    /// Panics if the number of upvalues isn't the one the function was compiled with, since `upvalue` would index out of
+    /// bounds (or capture the wrong variables) later.
     pub fn new(function: Rc<Function>, upvalues: Vec<Rc<RefCell<Upvalue>>>) -> Self {
+        assert_eq!(
+            upvalues.len(),
+            function.upvalues,
+            "a closure must capture the upvalues its function was compiled with"
+        );
         Self { function, upvalues }
     }
 
@@ -606,6 +1431,10 @@ impl Closure {
         self.function.chunk()
     }
 
+    pub fn is_generator(&self) -> bool {
+        self.function.generator
+    }
+
     pub fn upvalue(&self, idx: usize) -> Rc<RefCell<Upvalue>> {
         Rc::clone(&self.upvalues[idx])
     }
@@ -617,11 +1446,10 @@ impl Closure {
             required,
             optional,
         } = self.function.arity.clone();
+        // The defaults of the optional params that were passed are skipped.
         match argc {
-            x if x == required => self.function.body,
-            x if x > required && x <= required + optional => {
-                self.function.defaults[argc - required - 1]
-            }
+            x if x >= required && x < required + optional => self.function.defaults[x - required],
+            x if x == required + optional => self.function.body,
             x if x > required + optional && typ == ArityType::Variadic => self.function.body,
             _ => unreachable!(),
         }
@@ -631,13 +1459,62 @@ impl Closure {
 impl From<Chunk> for Closure {
     fn from(chunk: Chunk) -> Self {
         Self {
-            function: Rc::new(Function::new(None, chunk, Arity::default(), vec![], 0)),
+            function: Rc::new(Function::new(
+                None,
+                chunk,
+                Arity::default(),
+                vec![],
+                0,
+                false,
+                0,
+            )),
             upvalues: vec![],
         }
     }
 }
 
-type NativeFn = fn(Vec<Value>) -> Result<Value, RuntimeError>;
+/// The part of the VM that natives can reach.
+pub trait Interpreter {
+    /// Returns the token of the instruction that invoked the native.
+    fn token(&self) -> Rc<Token>;
+    /// Whether the natives that touch the file system should refuse to run.
+    fn untrusted(&self) -> bool;
+    /// Counts a succeeded assertion for the test summary.
+    fn assertion_passed(&mut self);
+    /// The number of significant digits numbers are displayed with, `None` means as many as needed.
+    fn display_precision(&self) -> Option<usize>;
+    fn set_display_precision(&mut self, digits: Option<usize>);
+    /// Calls a closure or native with `args` like a call expression does.
+    fn invoke(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, RuntimeError>;
+    /// The limits of the natives that walk nested lists and hash maps.
+    fn traversal_limits(&self) -> TraversalLimits;
+    /// Counts `count` list or hash map elements that are about to be created, failing if that exceeds the limit.
+    fn allocate(&mut self, count: usize) -> Result<(), RuntimeError>;
+    /// The length no single list can exceed, see `checked_list_alloc`, strings natives build by repeating are limited to
+    /// as many characters.
+    fn max_list_len(&self) -> usize;
+    /// Counts `bytes` that are about to be printed, failing if that exceeds the limit.
+    fn output(&mut self, bytes: usize) -> Result<(), RuntimeError>;
+    /// Returns the next value of any iterator (including the ones only the VM can advance), `None` means it's done.
+    fn advance(&mut self, iterator: &Rc<RefCell<Iterator>>) -> Result<Option<Value>, RuntimeError>;
+    /// The frames that are running, starting with the one that invoked the native, each at the call it's waiting for.
+    fn backtrace(&self) -> Backtrace;
+    /// Wraps a file the program opened, the VM closes it when it's dropped if the program doesn't.
+    fn open_file(&mut self, file: File) -> Value;
+    /// Schedules `callee` to be called with no arguments by `run_tasks`, `future` is resolved with what it returns.
+    fn spawn(&mut self, callee: Value, future: Option<Rc<RefCell<Future>>>);
+    /// Whether the natives are called from a task, or from something a task called.
+    fn tasks_running(&self) -> bool;
+    /// Runs the scheduled tasks (and the ones they schedule) in turns until all of them return.
+    fn run_tasks(&mut self) -> Result<(), RuntimeError>;
+    /// Makes the task that called the native give its turn to the next one once the native returns.
+    fn yield_task(&mut self) -> Result<(), RuntimeError>;
+    /// Like `yield_task`, but the task doesn't continue until `awaitable` is ready, and the native's call evaluates to
+    /// what `Awaitable::take` returns then.
+    fn wait(&mut self, awaitable: Awaitable) -> Result<(), RuntimeError>;
+}
+
+type NativeFn = fn(&mut dyn Interpreter, Vec<Value>) -> Result<Value, RuntimeError>;
 
 #[derive(Debug, Clone)]
 pub struct Native {
@@ -650,8 +1527,12 @@ impl Native {
         Self { function, arity }
     }
 
-    pub fn call(&self, args: Vec<Value>) -> Result<Value, RuntimeError> {
-        (self.function)(args)
+    pub fn call(
+        &self,
+        interpreter: &mut dyn Interpreter,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(interpreter, args)
     }
 
     pub fn arity(&self) -> &Arity {
@@ -665,13 +1546,65 @@ pub struct Iterator {
     counter: usize,
 }
 
+impl Iterator {
+    /// The path of the file being iterated if any.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.iterable {
+            Iterable::Lines(path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn iterable(&self) -> &Iterable {
+        &self.iterable
+    }
+
+    /// The method to call to advance a user-defined iterator, in which case `next` must not be used.
+    pub fn method(&self) -> Option<&Value> {
+        match &self.iterable {
+            Iterable::Custom(method) => Some(method),
+            _ => None,
+        }
+    }
+
+    /// The state of the generator being iterated, which only the VM can resume, in which case `next` must not be used.
+    pub fn generator_mut(&mut self) -> Option<&mut Generator> {
+        match &mut self.iterable {
+            Iterable::Generator(generator) => Some(generator),
+            _ => None,
+        }
+    }
+}
+
 impl iter::Iterator for Iterator {
-    type Item = Value;
+    /// Only iterating over lines can fail.
+    type Item = io::Result<Value>;
 
+    /// Strings are iterated by characters (i.e., unicode scalar values), which makes each step O(n).
     fn next(&mut self) -> Option<Self::Item> {
         let item = match &self.iterable {
-            Iterable::List(list) => list.borrow().get(self.counter).cloned(),
-            Iterable::String(s) => s.chars().nth(self.counter).map(|ch| Value::from(ch)),
+            Iterable::List(list) => list.borrow().get(self.counter).cloned().map(Ok),
+            Iterable::String(s) => s.chars().nth(self.counter).map(|ch| Ok(Value::from(ch))),
+            Iterable::Lines(_, reader) => {
+                let mut line = String::new();
+                match reader.borrow_mut().read_line(&mut line) {
+                    Ok(0) => None,
+                    Ok(_) => {
+                        if line.ends_with('\n') {
+                            line.pop();
+                            if line.ends_with('\r') {
+                                line.pop();
+                            }
+                        }
+                        Some(Ok(Value::from(line)))
+                    }
+                    Err(err) => Some(Err(err)),
+                }
+            }
+            Iterable::Custom(..)
+            | Iterable::Generator(..)
+            | Iterable::Map(..)
+            | Iterable::Filter(..) => unreachable!(),
         };
         self.counter += 1;
         item
@@ -688,6 +1621,34 @@ impl fmt::Display for Iterator {
 pub enum Iterable {
     List(Rc<RefCell<Vec<Value>>>),
     String(String),
+    /// Reads the lines of the file lazily, without their line endings.
+    Lines(PathBuf, Rc<RefCell<BufReader<fs::File>>>),
+    /// A user-defined iterator's `التالي` method, which only the VM can call.
+    Custom(Value),
+    Generator(Generator),
+    /// Calls the function with each value of the iterator as it's advanced, which only the VM can do.
+    Map(Rc<RefCell<Iterator>>, Value),
+    /// Skips the values of the iterator the function returns a falsy value for, which only the VM can do.
+    Filter(Rc<RefCell<Iterator>>, Value),
+}
+
+/// A generator is resumed from where it stopped each time it's iterated, so breaking out of a loop over it and iterating
+/// it again continues with the remaining values.
+#[derive(Debug, Clone)]
+pub enum Generator {
+    /// Everything the frame of the generator had when it yielded (or before it started), `handlers` are
+    /// `(ip, slots, tmps)` where `slots` and `tmps` are relative to the frame's.
+    Suspended {
+        closure: Rc<Closure>,
+        ip: usize,
+        locals: Vec<Value>,
+        tmps: Vec<Value>,
+        handlers: Vec<(usize, usize, usize)>,
+    },
+    /// Resuming a running generator (i.e. from its own body) fails.
+    Running,
+    /// Returned or failed.
+    Done,
 }
 
 impl From<Iterable> for Iterator {
@@ -706,6 +1667,10 @@ impl TryFrom<Value> for Iterable {
         match value {
             Value::String(s) => Ok(Self::String(s)),
             Value::Object(Object::List(list)) => Ok(Self::List(list)),
+            // Iterates over the elements the set had when the iteration started.
+            Value::Object(Object::Set(set)) => Ok(Self::List(Rc::new(RefCell::new(
+                set.borrow().iter().cloned().map(Value::from).collect(),
+            )))),
             _ => Err(()),
         }
     }
@@ -0,0 +1,155 @@
+//! Finding the imports of a file and the ones of its imports without compiling them.
+
+use super::{error::CompileError, import_path, path, stdlib, unquote};
+use lexer::{
+    token::{display_path, Token},
+    Lexer,
+};
+use parser::{ast::Stml, Parser};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Parser(PathBuf, Vec<parser::error::Error>),
+    Compile(CompileError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, err) => write!(f, "تعذر الوصول إلى \"{}\": {err}", display_path(path)),
+            Self::Parser(path, errors) => write!(
+                f,
+                "تعذر تحليل \"{}\" ({} خطأ)",
+                display_path(path),
+                errors.len()
+            ),
+            Self::Compile(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// An edge of the dependency graph.
+#[derive(Debug)]
+pub struct Dependency {
+    pub from: PathBuf,
+    /// Fails if the import couldn't be resolved, read, or parsed.
+    pub to: Result<PathBuf, Error>,
+    /// Whether `to` is already being imported (i.e. it's one of the ancestors of `from`).
+    pub cyclic: bool,
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> ", display_path(&self.from))?;
+        match &self.to {
+            Ok(to) => write!(f, "{}", display_path(to))?,
+            Err(err) => write!(f, "؟ ({err})")?,
+        }
+        if self.cyclic {
+            write!(f, " (دائري)")?
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path tokens of the imports in `path`, only the top level is scanned as imports can't be elsewhere.
+fn imports(path: &Path) -> Result<Vec<Rc<Token>>, Error> {
+    let source = fs::read_to_string(path).map_err(|err| Error::Io(path.to_owned(), err))?;
+    let tokens = Lexer::new(source, Some(&path.to_owned())).lex();
+    let ast = Parser::new(tokens)
+        .parse()
+        .map_err(|errors| Error::Parser(path.to_owned(), errors))?;
+    Ok(ast
+        .into_iter()
+        .filter_map(|stml| match stml {
+            Stml::Import(_, _, _, path) => Some(path),
+            _ => None,
+        })
+        .collect())
+}
+
+fn visit(
+    path: &Path,
+    tokens: Vec<Rc<Token>>,
+    ancestors: &mut Vec<PathBuf>,
+    visited: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<Dependency>,
+) {
+    ancestors.push(path::canonicalize(path));
+    for token in tokens {
+        // The standard library's modules are in the binary and import nothing.
+        if let Ok(specifier) = unquote(Rc::clone(&token)) {
+            if stdlib::is_reserved(&specifier) {
+                dependencies.push(Dependency {
+                    from: path.to_owned(),
+                    to: if stdlib::names().any(|name| name == specifier) {
+                        Ok(PathBuf::from(specifier))
+                    } else {
+                        Err(Error::Compile(CompileError::UnknownStdModule(token)))
+                    },
+                    cyclic: false,
+                });
+                continue;
+            }
+        }
+        let to = match import_path(token) {
+            Ok(to) => to,
+            Err(err) => {
+                dependencies.push(Dependency {
+                    from: path.to_owned(),
+                    to: Err(Error::Compile(err)),
+                    cyclic: false,
+                });
+                continue;
+            }
+        };
+        let key = path::canonicalize(&to);
+        let cyclic = ancestors.contains(&key);
+        if cyclic || visited.contains(&key) {
+            dependencies.push(Dependency {
+                from: path.to_owned(),
+                to: Ok(to),
+                cyclic,
+            });
+            continue;
+        }
+        visited.push(key);
+        match imports(&to) {
+            Ok(tokens) => {
+                dependencies.push(Dependency {
+                    from: path.to_owned(),
+                    to: Ok(to.clone()),
+                    cyclic: false,
+                });
+                visit(&to, tokens, ancestors, visited, dependencies)
+            }
+            Err(err) => dependencies.push(Dependency {
+                from: path.to_owned(),
+                to: Err(err),
+                cyclic: false,
+            }),
+        }
+    }
+    ancestors.pop();
+}
+
+/// Returns the edges of the dependency graph of `entry` in the order they're found, failing only if `entry` itself
+/// can't be read or parsed.
+pub fn dependencies(entry: &Path) -> Result<Vec<Dependency>, Error> {
+    let tokens = imports(entry)?;
+    let mut dependencies = vec![];
+    visit(
+        entry,
+        tokens,
+        &mut vec![],
+        &mut vec![path::canonicalize(entry)],
+        &mut dependencies,
+    );
+    Ok(dependencies)
+}
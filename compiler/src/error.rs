@@ -1,7 +1,13 @@
-use super::value::{Arity, DataType, Value};
+use super::value::{Arity, DataType, Object, Value};
+use super::{lint::Lint, path::PathError, stdlib, INLINE_PATH, STDIN_PATH};
 use colored::Colorize;
 use lexer::token::*;
-use std::{fmt, io, rc::Rc};
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 #[derive(Debug, Clone)]
 pub enum CompileError {
@@ -15,16 +21,51 @@ pub enum CompileError {
     SameVarInScope(Rc<Token>),
     InvalidDes(Rc<Token>),
     ReturnOutsideFunction(Rc<Token>),
+    YieldOutsideGenerator(Rc<Token>),
+    RethrowOutsideCatch(Rc<Token>),
     TooManyExports(Rc<Token>),
     OutsideLoopBreak(Rc<Token>),
     OutsideLoopContinue(Rc<Token>),
+    /// `إكسر` or `واصل` in a function defined inside a loop, which can't jump out of the function.
+    LoopAcrossFunction(Rc<Token>),
     InvalidImportUsage(Rc<Token>),
     InvalidExportUsage(Rc<Token>),
-    Io(Rc<Token>, Rc<io::Error>),
-    ModuleParser(Rc<Token>, Vec<parser::error::Error>),
+    /// The last one is the chain of imports that led to the file the token is in (see `CompileError::imported_from`).
+    Io(Rc<Token>, Rc<io::Error>, Vec<Rc<Token>>),
+    /// The import's path couldn't be resolved.
+    Path(Rc<Token>, PathError),
+    /// The import starts with `stdlib::PREFIX` but there's no such module.
+    UnknownStdModule(Rc<Token>),
+    ModuleParser(Rc<Token>, Vec<parser::error::Error>, Vec<Rc<Token>>),
     TooManyArgs(Rc<Token>),
 }
 
+impl CompileError {
+    /// Records that the file the error is in was imported by `import`, the errors that aren't about modules are left
+    /// as they are.
+    pub(crate) fn imported_from(mut self, import: Rc<Token>) -> Self {
+        if let Self::Io(.., chain) | Self::ModuleParser(.., chain) = &mut self {
+            chain.push(import)
+        }
+        self
+    }
+}
+
+/// Writes where the imports that led to an error are, the closest one first.
+fn write_import_chain(f: &mut fmt::Formatter<'_>, chain: &[Rc<Token>]) -> fmt::Result {
+    if chain.is_empty() {
+        return Ok(());
+    }
+    let imports = chain
+        .iter()
+        .map(|token| match token.path() {
+            Some(path) => format!("{}:{}", display_path(path), token.line()),
+            None => format!("السطر {}", token.line()),
+        })
+        .collect::<Vec<_>>();
+    write!(f, "\nتم استيراده من: {}", imports.join(" ← "))
+}
+
 impl TokenInside for CompileError {
     fn token(&self) -> Rc<Token> {
         match self {
@@ -38,12 +79,17 @@ impl TokenInside for CompileError {
             | Self::SameVarInScope(token, ..)
             | Self::InvalidDes(token, ..)
             | Self::ReturnOutsideFunction(token, ..)
+            | Self::YieldOutsideGenerator(token, ..)
+            | Self::RethrowOutsideCatch(token, ..)
             | Self::TooManyExports(token, ..)
             | Self::OutsideLoopBreak(token, ..)
             | Self::OutsideLoopContinue(token, ..)
+            | Self::LoopAcrossFunction(token, ..)
             | Self::InvalidImportUsage(token, ..)
             | Self::InvalidExportUsage(token, ..)
             | Self::Io(token, ..)
+            | Self::Path(token, ..)
+            | Self::UnknownStdModule(token, ..)
             | Self::ModuleParser(token, ..)
             | Self::TooManyArgs(token, ..) => Rc::clone(token),
         }
@@ -111,6 +157,14 @@ impl fmt::Display for CompileError {
                 writeln!(f, "لا يمكن الإرجاع من خارج دالة")?;
                 write!(f, "{token}")
             }
+            Self::YieldOutsideGenerator(token) => {
+                writeln!(f, "لا يمكن الإنتاج من خارج مولّد")?;
+                write!(f, "{token}")
+            }
+            Self::RethrowOutsideCatch(token) => {
+                writeln!(f, "لا يمكن استخدام \"ألقي_مجدداً\" خارج \"أمسك\"")?;
+                write!(f, "{token}")
+            }
             Self::TooManyExports(token) => {
                 writeln!(f, "لا يمكن تصدير أكثر من 65535 عنصر")?;
                 write!(f, "{token}")
@@ -123,6 +177,14 @@ impl fmt::Display for CompileError {
                 writeln!(f, "لا يمكن استخدام \"واصل\" خارج حلقة تكرارية")?;
                 write!(f, "{token}")
             }
+            Self::LoopAcrossFunction(token) => {
+                writeln!(
+                    f,
+                    "لا يمكن استخدام \"{}\" داخل دالة للتحكم في حلقة تكرارية خارجها",
+                    token.lexeme()
+                )?;
+                write!(f, "{token}")
+            }
             Self::InvalidImportUsage(token) => {
                 writeln!(f, "لا يمكن التصدير من داخل الدوال أو المجموعات")?;
                 write!(f, "{token}")
@@ -131,18 +193,40 @@ impl fmt::Display for CompileError {
                 writeln!(f, "لا يمكن الاستيراد من داخل الدوال أو المجموعات")?;
                 write!(f, "{token}")
             }
-            Self::Io(token, err) => {
+            Self::Io(token, err, chain) => {
+                writeln!(f, "{err}")?;
+                write!(f, "{token}")?;
+                if token.path() == Some(&PathBuf::from(STDIN_PATH)) {
+                    write!(
+                        f,
+                        "ملاحظة: تم البحث عن الوحدة في مجلد العمل الحالي لأن البرنامج قُرئ من المدخل القياسي"
+                    )?
+                } else if token.path() == Some(&PathBuf::from(INLINE_PATH)) {
+                    write!(
+                        f,
+                        "ملاحظة: تم البحث عن الوحدة في مجلد العمل الحالي لأن البرنامج كُتب في سطر الأوامر"
+                    )?
+                }
+                write_import_chain(f, chain)
+            }
+            Self::Path(token, err) => {
                 writeln!(f, "{err}")?;
                 write!(f, "{token}")
             }
-            Self::ModuleParser(token, errors) => {
+            Self::UnknownStdModule(token) => {
+                writeln!(f, "لا توجد وحدة قياسية بهذا الاسم")?;
+                write!(f, "{token}")?;
+                let names = stdlib::names().collect::<Vec<_>>();
+                write!(f, "الوحدات القياسية هي: {}", names.join("، "))
+            }
+            Self::ModuleParser(token, errors, chain) => {
                 writeln!(
                     f,
                     "{} أثناء تحليل الوحدة",
                     if errors.len() > 1 {
-                        "حدث خطأ"
-                    } else {
                         "حدثت بعض الأخطاء"
+                    } else {
+                        "حدث خطأ"
                     }
                 )?;
                 writeln!(f, "{token}")?;
@@ -153,7 +237,7 @@ impl fmt::Display for CompileError {
                         write!(f, "{err}")?
                     }
                 }
-                Ok(())
+                write_import_chain(f, chain)
             }
             Self::TooManyArgs(token) => {
                 writeln!(f, "لا يمكن استدعاء دالة بأكثر من 255 مدخل")?;
@@ -163,35 +247,264 @@ impl fmt::Display for CompileError {
     }
 }
 
+/// Reported without stopping the compilation.
+#[derive(Debug, Clone)]
+pub enum CompileWarning {
+    /// Spans the whole expression statement.
+    UnusedValue(Rc<Token>),
+    /// Of an imported module, or of the linted file.
+    Parse(parser::error::ParseWarning),
+    /// The variable's token.
+    UnusedVariable(Rc<Token>),
+    /// Spans the first unreachable statement, the token of the statement before it.
+    UnreachableCode(Rc<Token>, Rc<Token>),
+    /// Spans the condition.
+    AssignmentInCondition(Rc<Token>),
+    /// The declaration, the token of the variable it shadows.
+    Shadowing(Rc<Token>, Rc<Token>),
+    /// The `طابق` token, none of its arms matches every value.
+    NonExhaustiveMatch(Rc<Token>),
+}
+
+impl CompileWarning {
+    /// The lint that reported it, see `lint`.
+    pub fn lint(&self) -> Option<Lint> {
+        match self {
+            Self::UnusedValue(_) | Self::Parse(_) | Self::NonExhaustiveMatch(_) => None,
+            Self::UnusedVariable(_) => Some(Lint::UnusedVariable),
+            Self::UnreachableCode(..) => Some(Lint::UnreachableCode),
+            Self::AssignmentInCondition(_) => Some(Lint::AssignmentInCondition),
+            Self::Shadowing(..) => Some(Lint::Shadowing),
+        }
+    }
+}
+
+impl TokenInside for CompileWarning {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::UnusedValue(token)
+            | Self::UnusedVariable(token)
+            | Self::UnreachableCode(token, _)
+            | Self::AssignmentInCondition(token)
+            | Self::Shadowing(token, _)
+            | Self::NonExhaustiveMatch(token) => Rc::clone(token),
+            Self::Parse(warning) => warning.token(),
+        }
+    }
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnusedValue(token) => {
+                write!(f, "{}", "تحذير ترجمي: ".bright_yellow())?;
+                writeln!(f, "لم تستخدم قيمة هذا التعبير")?;
+                writeln!(f, "{token}")?;
+                write!(f, "إقتراح: هل نسيت إسنادها إلى متغير؟")
+            }
+            Self::Parse(warning) => write!(f, "{warning}"),
+            Self::UnusedVariable(token) => {
+                write!(f, "{}", "تحذير تدقيقي: ".bright_yellow())?;
+                writeln!(f, "لم تستخدم قيمة المتغير \"{}\"", token.lexeme())?;
+                writeln!(f, "{token}")?;
+                writeln!(
+                    f,
+                    "إقتراح: احذفه، أو ابدأ اسمه بـ\"_\" إن كان عدم استخدامه مقصوداً"
+                )?;
+                write_allow(f, Lint::UnusedVariable)
+            }
+            Self::UnreachableCode(span, token) => {
+                write!(f, "{}", "تحذير تدقيقي: ".bright_yellow())?;
+                writeln!(
+                    f,
+                    "لن تنفذ هذه العبارة وما بعدها لأنها تأتي بعد \"{}\"",
+                    token.lexeme()
+                )?;
+                writeln!(f, "{span}")?;
+                writeln!(
+                    f,
+                    "إقتراح: احذفها أو انقلها إلى ما قبل \"{}\"",
+                    token.lexeme()
+                )?;
+                write_allow(f, Lint::UnreachableCode)
+            }
+            Self::AssignmentInCondition(span) => {
+                write!(f, "{}", "تحذير تدقيقي: ".bright_yellow())?;
+                writeln!(f, "هذا الشرط إسناد وليس مقارنة")?;
+                writeln!(f, "{span}")?;
+                writeln!(
+                    f,
+                    "إقتراح: هل قصدت \"==\"؟ إن كان الإسناد مقصوداً فضعه بين قوسين إضافيين"
+                )?;
+                write_allow(f, Lint::AssignmentInCondition)
+            }
+            Self::Shadowing(token, shadowed) => {
+                write!(f, "{}", "تحذير تدقيقي: ".bright_yellow())?;
+                writeln!(
+                    f,
+                    "يحجب المتغير \"{}\" متغيراً بنفس الاسم من نطاق خارجي",
+                    token.lexeme()
+                )?;
+                writeln!(f, "{token}")?;
+                writeln!(f, "المتغير المحجوب معرّف هنا:")?;
+                writeln!(f, "{shadowed}")?;
+                writeln!(f, "إقتراح: غيّر اسم أحدهما")?;
+                write_allow(f, Lint::Shadowing)
+            }
+            Self::NonExhaustiveMatch(token) => {
+                write!(f, "{}", "تحذير ترجمي: ".bright_yellow())?;
+                writeln!(
+                    f,
+                    "لا يطابق أي من أنماط هذه العبارة كل القيم، فقد لا ينفذ أي منها"
+                )?;
+                writeln!(f, "{token}")?;
+                write!(f, "إقتراح: أضف \"_ => ...\" في آخرها للقيم الأخرى")
+            }
+        }
+    }
+}
+
+fn write_allow(f: &mut fmt::Formatter<'_>, lint: Lint) -> fmt::Result {
+    write!(f, "(يمكن إيقاف هذا التحذير بـ\"--اسمح={}\")", lint.name())
+}
+
+/// The operands of binary operators, the left one is the one written first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Left,
+    Right,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Left => "الطرف الأول",
+                Self::Right => "الطرف الثاني",
+            }
+        )
+    }
+}
+
+fn data_types(types: &[DataType]) -> String {
+    types
+        .iter()
+        .map(|dt| format!("{dt}"))
+        .collect::<Vec<_>>()
+        .join("أو ")
+}
+
+/// Writes the control characters of a string the messages quote between "‹" and "›" (e.g. "\r" as "‹\r›"), as invisible
+/// ones make different strings look the same.
+pub fn visible(string: &str) -> String {
+    let mut buf = String::new();
+    for c in string.chars() {
+        match c {
+            '\n' => buf += "‹\\n›",
+            '\r' => buf += "‹\\r›",
+            '\t' => buf += "‹\\t›",
+            c if c.is_control() => buf += &format!("‹\\u{{{:x}}}›", c as u32),
+            c => buf.push(c),
+        }
+    }
+    buf
+}
+
 #[derive(Debug, Clone)]
 pub enum RuntimeError {
     Type(Vec<DataType>, DataType, Rc<Token>, Backtrace),
-    Name(String, Rc<Token>, Backtrace),
-    AlreadyDefined(String, Rc<Token>, Backtrace),
+    /// Like `Type` but for an operand of a binary operator.
+    OperandType(Operand, Vec<DataType>, DataType, Rc<Token>, Backtrace),
+    /// name, a defined name that's close to it
+    Name(String, Option<String>, Rc<Token>, Backtrace),
+    /// name, the token of the first definition (`None` for natives)
+    AlreadyDefined(String, Option<Rc<Token>>, Rc<Token>, Backtrace),
     InvalidArgc(Arity, usize, Rc<Token>, Backtrace),
     InvalidIdx(Rc<Token>, Backtrace),
-    OutOfRange(usize, usize, Rc<Token>, Backtrace),
+    OutOfRange(i64, usize, Rc<Token>, Backtrace),
     User(Value, Rc<Token>, Backtrace),
     ListUnpack(usize, usize, Rc<Token>, Backtrace),
+    /// Unpacking `عدم`, which is usually returned by a function that forgot to return, the type that was expected.
+    NilUnpack(DataType, Rc<Token>, Backtrace),
     UndefinedKey(String, Rc<Token>, Backtrace),
-    Io(Rc<io::Error>, Rc<Token>, Backtrace),
+    ExpectedChar(String, Rc<Token>, Backtrace),
+    InvalidCodePoint(f64, Rc<Token>, Backtrace),
+    InvalidByte(f64, Rc<Token>, Backtrace),
+    Assertion(String, Rc<Token>, Backtrace),
+    Untrusted(Rc<Token>, Backtrace),
+    ExpectedInteger(f64, Rc<Token>, Backtrace),
+    IntegerOverflow(Rc<Token>, Backtrace),
+    NegativeSize(f64, Rc<Token>, Backtrace),
+    InvalidChunkSize(f64, Rc<Token>, Backtrace),
+    EmptyFill(Rc<Token>, Backtrace),
+    /// The number of characters to replace and the number to replace them with.
+    TranslationLengths(usize, usize, Rc<Token>, Backtrace),
+    UnknownNormalization(String, Rc<Token>, Backtrace),
+    /// The glob pattern and the index of the character it's invalid at.
+    InvalidPattern(String, usize, Rc<Token>, Backtrace),
+    /// The message of the JSON error.
+    Json(String, Rc<Token>, Backtrace),
+    /// The message of the CSV error.
+    Csv(String, Rc<Token>, Backtrace),
+    GeneratorRunning(Rc<Token>, Backtrace),
+    /// The tasks were run from inside one of them.
+    TasksRunning(Rc<Token>, Backtrace),
+    /// Yielding from outside a task, or from a function a native or an iterator called.
+    YieldOutsideTask(Rc<Token>, Backtrace),
+    /// Receiving from an empty channel outside the tasks, where nothing could send to it.
+    EmptyChannel(Rc<Token>, Backtrace),
+    ClosedChannel(Rc<Token>, Backtrace),
+    /// All of the tasks are waiting for channels that are empty or futures that aren't resolved.
+    Deadlock(Rc<Token>, Backtrace),
+    /// Awaiting a future whose task was dropped because another one failed.
+    AbandonedFuture(Rc<Token>, Backtrace),
+    /// Walking a structure visited more nodes than the traversal budget allows.
+    TooLarge(Rc<Token>, Backtrace),
+    /// The maximum number of nested calls.
+    CallDepth(usize, Rc<Token>, Backtrace),
+    /// The maximum number of list and hash map elements that can be created.
+    Elements(usize, Rc<Token>, Backtrace),
+    /// The maximum number of bytes that can be printed.
+    Output(usize, Rc<Token>, Backtrace),
+    /// A value sets can't hold.
+    InvalidSetElement(Value, Rc<Token>, Backtrace),
+    /// The length of a list that was about to be created, the maximum length (`None` when there isn't enough memory).
+    ListTooLong(u64, Option<usize>, Rc<Token>, Backtrace),
+    /// The length in characters and the limit, which is the same as the one of lists.
+    StringTooLong(u64, usize, Rc<Token>, Backtrace),
+    /// The VM's interrupt flag was set (e.g. by Ctrl-C), handlers don't catch it.
+    Interrupted(Rc<Token>, Backtrace),
 }
 
 impl RuntimeError {
     pub fn msg(&self) -> String {
         match self {
             Self::Type(expected, received, ..) => {
-                format!(
-                    "توقعت {} ولكن حصلت على {received}",
-                    expected
-                        .iter()
-                        .map(|dt| format!("{dt}"))
-                        .collect::<Vec<_>>()
-                        .join("أو ")
-                )
+                format!("توقعت {} ولكن حصلت على {received}", data_types(expected))
+            }
+            Self::OperandType(operand, expected, received, ..) => format!(
+                "توقعت {} في {operand} ولكن حصلت على {received}",
+                data_types(expected)
+            ),
+            Self::Name(name, None, ..) => format!("المتغير {name} غير معرّف"),
+            Self::Name(name, Some(suggestion), ..) => {
+                format!("المتغير {name} غير معرّف، هل تقصد {suggestion}؟")
+            }
+            Self::AlreadyDefined(name, None, ..) => {
+                format!("المتغير {name} معرّف مسبقاً، عُرّف سابقاً كدالة أصلية")
+            }
+            Self::AlreadyDefined(name, Some(first), ..) => {
+                let line = first.line();
+                match first.path() {
+                    Some(path) => format!(
+                        "المتغير {name} معرّف مسبقاً، عُرّف سابقاً في {}:{line}",
+                        display_path(path)
+                    ),
+                    None => format!("المتغير {name} معرّف مسبقاً، عُرّف سابقاً في السطر {line}"),
+                }
             }
-            Self::Name(name, ..) => format!("المتغير {name} غير معرّف"),
-            Self::AlreadyDefined(name, ..) => format!("المتغير {name} معرّف مسبقاً"),
             Self::InvalidArgc(arity, argc, ..) => {
                 let required = arity.required();
                 let optional = arity.optional();
@@ -210,18 +523,97 @@ impl RuntimeError {
             Self::OutOfRange(idx, len, ..) => {
                 format!("لا يمكن الفهرسة ب{idx} في مرتّب حجمه {len}")
             }
-            Self::User(value, ..) => format!("{value}"),
+            Self::User(value, ..) => error_message(value).unwrap_or_else(|| format!("{value}")),
             Self::ListUnpack(to, len, ..) => {
                 format!("لا يمكن توزيع قائمة حجمها {len} إلى عنصر {to}")
             }
-            Self::UndefinedKey(key, ..) => format!("لا توجد الخاصية {key} في هذا الكائن"),
-            Self::Io(err, ..) => format!("{err}"),
+            Self::NilUnpack(expected, ..) => {
+                format!("لا يمكن التوزيع من عدم (توقعت {expected})، هل نسيت إرجاع قيمة؟")
+            }
+            Self::UndefinedKey(key, ..) => {
+                format!("لا توجد الخاصية {} في هذا الكائن", visible(key))
+            }
+            Self::ExpectedChar(string, ..) => {
+                format!("توقعت حرفاً واحداً ولكن حصلت على \"{}\"", visible(string))
+            }
+            Self::InvalidCodePoint(number, ..) => {
+                format!("{number} ليس رمزاً صحيحاً لحرف")
+            }
+            Self::InvalidByte(number, ..) => {
+                format!("{number} ليس بايتاً صحيحاً، البايت عدد صحيح من 0 إلى 255")
+            }
+            Self::Assertion(msg, ..) => msg.clone(),
+            Self::Untrusted(..) => "لا يمكن استخدام هذه الدالة في الوضع غير الموثوق".to_owned(),
+            Self::ExpectedInteger(number, ..) => {
+                format!("توقعت عدداً صحيحاً آمناً ولكن حصلت على {number}")
+            }
+            Self::IntegerOverflow(..) => {
+                "تجاوز الناتج حدود الأعداد الصحيحة الآمنة (±9007199254740991)".to_owned()
+            }
+            Self::NegativeSize(size, ..) => {
+                format!("لا يمكن أن يكون الحجم سالباً ولكن حصلت على {size}")
+            }
+            Self::InvalidChunkSize(size, ..) => {
+                format!("يجب أن يكون حجم القطعة موجباً ولكن حصلت على {size}")
+            }
+            Self::EmptyFill(..) => "لا يمكن الحشو بنص فارغ".to_owned(),
+            Self::TranslationLengths(from, to, ..) => {
+                format!("يجب أن يتساوى عدد الحروف المستبدلة ({from}) وعدد ما تستبدل به ({to})")
+            }
+            Self::UnknownNormalization(form, ..) => format!(
+                "لا توجد صيغة تطبيع باسم \"{}\"، الصيغ المتاحة هي NFC و NFD و NFKC و NFKD",
+                visible(form)
+            ),
+            Self::InvalidPattern(pattern, idx, ..) => format!(
+                "النمط \"{}\" غير صالح عند الحرف رقم {}",
+                visible(pattern),
+                idx + 1
+            ),
+            Self::Json(msg, ..) | Self::Csv(msg, ..) => msg.clone(),
+            Self::GeneratorRunning(..) => "لا يمكن استئناف مولّد من داخله".to_owned(),
+            Self::TasksRunning(..) => "لا يمكن تنفيذ المهام من داخل إحداها".to_owned(),
+            Self::YieldOutsideTask(..) => {
+                "لا يمكن التسليم إلا من داخل مهمة، وليس من دالة تستدعيها دالة مدمجة أو مولّد"
+                    .to_owned()
+            }
+            Self::EmptyChannel(..) => "القناة فارغة ولا يمكن انتظارها إلا من داخل مهمة".to_owned(),
+            Self::ClosedChannel(..) => "لا يمكن الإرسال إلى قناة مغلقة".to_owned(),
+            Self::Deadlock(..) => "كل المهام تنتظر قنوات فارغة أو قيماً مؤجلة لم تكتمل".to_owned(),
+            Self::AbandonedFuture(..) => {
+                "لن تكتمل القيمة المؤجلة لأن مهمتها توقفت بفشل مهمة أخرى".to_owned()
+            }
+            Self::TooLarge(..) => "بنية كبيرة جداً للمقارنة".to_owned(),
+            Self::CallDepth(limit, ..) => {
+                format!("تجاوز عمق الاستدعاءات الحد الأقصى ({limit})")
+            }
+            Self::Elements(limit, ..) => {
+                format!("تجاوز عدد عناصر القوائم والكائنات المنشأة الحد الأقصى ({limit})")
+            }
+            Self::Output(limit, ..) => format!("تجاوزت المخرجات الحد الأقصى ({limit} بايت)"),
+            Self::ListTooLong(len, Some(limit), ..) => {
+                format!("لا يمكن إنشاء قائمة من {len} عنصر لأن الحد الأقصى لطول القائمة {limit}")
+            }
+            Self::ListTooLong(len, None, ..) => {
+                format!("لا توجد ذاكرة كافية لإنشاء قائمة من {len} عنصر")
+            }
+            Self::StringTooLong(len, limit, ..) => {
+                format!("لا يمكن إنشاء نص من {len} حرف لأن الحد الأقصى لطول النص {limit}")
+            }
+            Self::InvalidSetElement(Value::Number(number), ..) => {
+                format!("لا يمكن أن تحتوي المجموعة على {number} لأنه ليس عدداً صحيحاً")
+            }
+            Self::Interrupted(..) => "قوطع البرنامج".to_owned(),
+            Self::InvalidSetElement(value, ..) => format!(
+                "لا يمكن أن تحتوي المجموعة على {}، يمكنها أن تحتوي على النصوص والأعداد الصحيحة فقط",
+                value.typ()
+            ),
         }
     }
 
     pub fn backtrace(&self) -> &Backtrace {
         match self {
             Self::Type(.., backtrace)
+            | Self::OperandType(.., backtrace)
             | Self::Name(.., backtrace)
             | Self::AlreadyDefined(.., backtrace)
             | Self::InvalidArgc(.., backtrace)
@@ -229,14 +621,45 @@ impl RuntimeError {
             | Self::OutOfRange(.., backtrace)
             | Self::User(.., backtrace)
             | Self::ListUnpack(.., backtrace)
+            | Self::NilUnpack(.., backtrace)
             | Self::UndefinedKey(.., backtrace)
-            | Self::Io(.., backtrace) => backtrace,
+            | Self::ExpectedChar(.., backtrace)
+            | Self::InvalidCodePoint(.., backtrace)
+            | Self::InvalidByte(.., backtrace)
+            | Self::Assertion(.., backtrace)
+            | Self::Untrusted(.., backtrace)
+            | Self::ExpectedInteger(.., backtrace)
+            | Self::IntegerOverflow(.., backtrace)
+            | Self::NegativeSize(.., backtrace)
+            | Self::InvalidChunkSize(.., backtrace)
+            | Self::EmptyFill(_, backtrace)
+            | Self::TranslationLengths(.., backtrace)
+            | Self::UnknownNormalization(.., backtrace)
+            | Self::InvalidPattern(.., backtrace)
+            | Self::Json(.., backtrace)
+            | Self::Csv(.., backtrace)
+            | Self::GeneratorRunning(.., backtrace)
+            | Self::TasksRunning(.., backtrace)
+            | Self::YieldOutsideTask(.., backtrace)
+            | Self::EmptyChannel(.., backtrace)
+            | Self::ClosedChannel(.., backtrace)
+            | Self::Deadlock(.., backtrace)
+            | Self::AbandonedFuture(.., backtrace)
+            | Self::TooLarge(.., backtrace)
+            | Self::CallDepth(.., backtrace)
+            | Self::Elements(.., backtrace)
+            | Self::Output(.., backtrace)
+            | Self::InvalidSetElement(.., backtrace)
+            | Self::ListTooLong(.., backtrace)
+            | Self::StringTooLong(.., backtrace)
+            | Self::Interrupted(_, backtrace) => backtrace,
         }
     }
 
     pub fn backtrace_mut(&mut self) -> &mut Backtrace {
         match self {
             Self::Type(.., backtrace)
+            | Self::OperandType(.., backtrace)
             | Self::Name(.., backtrace)
             | Self::AlreadyDefined(.., backtrace)
             | Self::InvalidArgc(.., backtrace)
@@ -244,8 +667,38 @@ impl RuntimeError {
             | Self::OutOfRange(.., backtrace)
             | Self::User(.., backtrace)
             | Self::ListUnpack(.., backtrace)
+            | Self::NilUnpack(.., backtrace)
             | Self::UndefinedKey(.., backtrace)
-            | Self::Io(.., backtrace) => backtrace,
+            | Self::ExpectedChar(.., backtrace)
+            | Self::InvalidCodePoint(.., backtrace)
+            | Self::InvalidByte(.., backtrace)
+            | Self::Assertion(.., backtrace)
+            | Self::Untrusted(.., backtrace)
+            | Self::ExpectedInteger(.., backtrace)
+            | Self::IntegerOverflow(.., backtrace)
+            | Self::NegativeSize(.., backtrace)
+            | Self::InvalidChunkSize(.., backtrace)
+            | Self::EmptyFill(_, backtrace)
+            | Self::TranslationLengths(.., backtrace)
+            | Self::UnknownNormalization(.., backtrace)
+            | Self::InvalidPattern(.., backtrace)
+            | Self::Json(.., backtrace)
+            | Self::Csv(.., backtrace)
+            | Self::GeneratorRunning(.., backtrace)
+            | Self::TasksRunning(.., backtrace)
+            | Self::YieldOutsideTask(.., backtrace)
+            | Self::EmptyChannel(.., backtrace)
+            | Self::ClosedChannel(.., backtrace)
+            | Self::Deadlock(.., backtrace)
+            | Self::AbandonedFuture(.., backtrace)
+            | Self::TooLarge(.., backtrace)
+            | Self::CallDepth(.., backtrace)
+            | Self::Elements(.., backtrace)
+            | Self::Output(.., backtrace)
+            | Self::InvalidSetElement(.., backtrace)
+            | Self::ListTooLong(.., backtrace)
+            | Self::StringTooLong(.., backtrace)
+            | Self::Interrupted(_, backtrace) => backtrace,
         }
     }
 }
@@ -254,6 +707,7 @@ impl TokenInside for RuntimeError {
     fn token(&self) -> Rc<Token> {
         match self {
             Self::Type(.., token, _)
+            | Self::OperandType(.., token, _)
             | Self::Name(.., token, _)
             | Self::AlreadyDefined(.., token, _)
             | Self::InvalidArgc(.., token, _)
@@ -261,8 +715,38 @@ impl TokenInside for RuntimeError {
             | Self::OutOfRange(.., token, _)
             | Self::User(.., token, _)
             | Self::ListUnpack(.., token, _)
+            | Self::NilUnpack(.., token, _)
             | Self::UndefinedKey(.., token, _)
-            | Self::Io(.., token, _) => Rc::clone(token),
+            | Self::ExpectedChar(.., token, _)
+            | Self::InvalidCodePoint(.., token, _)
+            | Self::InvalidByte(.., token, _)
+            | Self::Assertion(.., token, _)
+            | Self::Untrusted(.., token, _)
+            | Self::ExpectedInteger(.., token, _)
+            | Self::IntegerOverflow(.., token, _)
+            | Self::NegativeSize(.., token, _)
+            | Self::InvalidChunkSize(.., token, _)
+            | Self::EmptyFill(token, _)
+            | Self::TranslationLengths(_, _, token, _)
+            | Self::UnknownNormalization(_, token, _)
+            | Self::InvalidPattern(.., token, _)
+            | Self::Json(.., token, _)
+            | Self::Csv(.., token, _)
+            | Self::GeneratorRunning(token, _)
+            | Self::TasksRunning(token, _)
+            | Self::YieldOutsideTask(token, _)
+            | Self::EmptyChannel(token, _)
+            | Self::ClosedChannel(token, _)
+            | Self::Deadlock(token, _)
+            | Self::AbandonedFuture(token, _)
+            | Self::TooLarge(token, _)
+            | Self::CallDepth(.., token, _)
+            | Self::Elements(.., token, _)
+            | Self::Output(.., token, _)
+            | Self::InvalidSetElement(.., token, _)
+            | Self::ListTooLong(.., token, _)
+            | Self::StringTooLong(.., token, _)
+            | Self::Interrupted(token, _) => Rc::clone(token),
         }
     }
 }
@@ -281,6 +765,153 @@ impl fmt::Display for RuntimeError {
     }
 }
 
+/// The keys of error objects, the hash maps `خطأ_برسالة` and `خطأ_بنوع` make.
+pub const ERROR_KIND: &str = "النوع";
+pub const ERROR_MESSAGE: &str = "الرسالة";
+/// Added when the error is thrown.
+pub const ERROR_PATH: &str = "الملف";
+pub const ERROR_LINE: &str = "السطر";
+
+pub fn error_object(kind: String, message: String) -> Value {
+    Value::from(HashMap::from([
+        (ERROR_KIND.to_owned(), Value::from(kind)),
+        (ERROR_MESSAGE.to_owned(), Value::from(message)),
+    ]))
+}
+
+/// Records where an error object is thrown unless it has been thrown before, other values are left untouched.
+pub fn stamp_error(value: &Value, token: &Token) {
+    if let Value::Object(Object::HashMap(hash_map)) = value {
+        let mut hash_map = hash_map.borrow_mut();
+        if !hash_map.contains_key(ERROR_KIND)
+            || !hash_map.contains_key(ERROR_MESSAGE)
+            || hash_map.contains_key(ERROR_LINE)
+        {
+            return;
+        }
+        let path = match token.path() {
+            Some(path) => Value::from(display_path(path)),
+            None => Value::Nil,
+        };
+        hash_map.insert(ERROR_PATH.to_owned(), path);
+        hash_map.insert(ERROR_LINE.to_owned(), Value::Number(token.line() as f64));
+    }
+}
+
+/// Adds what a native produced before it failed to the error object it fails with under `ERROR_PARTIAL`, unless a
+/// native it called already did. Built-in errors and thrown values that aren't error objects are left untouched.
+pub fn add_partial(err: &RuntimeError, partial: impl Into<Value>) {
+    if let RuntimeError::User(value, ..) = err {
+        if let (Some(_), Value::Object(Object::HashMap(hash_map))) = (error_message(value), value) {
+            hash_map
+                .borrow_mut()
+                .entry(ERROR_PARTIAL.to_owned())
+                .or_insert_with(|| partial.into());
+        }
+    }
+}
+
+/// Error objects are reported by their message like the built-in errors.
+fn error_message(value: &Value) -> Option<String> {
+    match value {
+        Value::Object(Object::HashMap(hash_map)) => {
+            let hash_map = hash_map.borrow();
+            match (hash_map.get(ERROR_KIND), hash_map.get(ERROR_MESSAGE)) {
+                (Some(_), Some(Value::String(message))) => Some(message.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The keys natives add to the error objects of failed file operations.
+pub const ERROR_FILE_PATH: &str = "المسار";
+/// The error code of the operating system, `عدم` if there isn't one.
+pub const ERROR_OS_CODE: &str = "رمز_النظام";
+/// What a native that failed halfway produced before failing (e.g. the text `إقرأ` read), see `add_partial`.
+pub const ERROR_PARTIAL: &str = "جزئي";
+
+/// The kinds of error objects of failed file operations, which programs can match on instead of the messages that
+/// depend on the operating system. Kinds that are added to `io::ErrorKind` later get "خطأ_إدخال_إخراج".
+pub fn io_error_kind(kind: io::ErrorKind) -> &'static str {
+    use io::ErrorKind::*;
+    match kind {
+        NotFound => "غير_موجود",
+        PermissionDenied => "غير_مسموح",
+        AlreadyExists => "موجود_مسبقاً",
+        IsADirectory => "مجلد",
+        NotADirectory => "ليس_مجلداً",
+        DirectoryNotEmpty => "مجلد_غير_فارغ",
+        ReadOnlyFilesystem => "للقراءة_فقط",
+        StorageFull | QuotaExceeded => "لا_مساحة",
+        FileTooLarge => "ملف_كبير_جداً",
+        ResourceBusy | ExecutableFileBusy => "مشغول",
+        CrossesDevices => "عبر_الأجهزة",
+        TooManyLinks => "روابط_كثيرة",
+        InvalidFilename => "اسم_غير_صالح",
+        InvalidInput => "مدخل_غير_صالح",
+        InvalidData => "بيانات_غير_صالحة",
+        UnexpectedEof => "نهاية_غير_متوقعة",
+        WriteZero => "كتابة_صفرية",
+        Interrupted => "مقاطعة",
+        TimedOut => "انتهت_المهلة",
+        WouldBlock => "سيتوقف",
+        BrokenPipe => "أنبوب_مكسور",
+        Unsupported => "غير_مدعوم",
+        OutOfMemory => "ذاكرة_غير_كافية",
+        _ => "خطأ_إدخال_إخراج",
+    }
+}
+
+/// An error object a native fails with, which unlike the built-in errors can be told apart by its kind when it's
+/// caught.
+#[derive(Debug, Clone)]
+pub struct NativeError {
+    kind: String,
+    message: String,
+    fields: Vec<(String, Value)>,
+}
+
+impl NativeError {
+    pub fn new(kind: &str, message: String) -> Self {
+        Self {
+            kind: kind.to_owned(),
+            message,
+            fields: vec![],
+        }
+    }
+
+    /// Adds a key to the error object.
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.push((key.to_owned(), value.into()));
+        self
+    }
+
+    /// The kind comes from `io_error_kind`, and the path and the OS error code are added to the object.
+    pub fn io(err: &io::Error, path: &Path) -> Self {
+        let code = err
+            .raw_os_error()
+            .map_or(Value::Nil, |code| Value::from(code as f64));
+        Self::new(
+            io_error_kind(err.kind()),
+            format!("تعذر الوصول إلى \"{}\": {err}", display_path(path)),
+        )
+        .with(ERROR_FILE_PATH, display_path(path))
+        .with(ERROR_OS_CODE, code)
+    }
+
+    /// Throws the error object from `token`, which is where it's recorded to be thrown.
+    pub fn into_runtime_error(self, token: Rc<Token>) -> RuntimeError {
+        let value = error_object(self.kind, self.message);
+        if let Value::Object(Object::HashMap(hash_map)) = &value {
+            hash_map.borrow_mut().extend(self.fields)
+        }
+        stamp_error(&value, &token);
+        RuntimeError::User(value, token, Backtrace::default())
+    }
+}
+
 impl Into<Value> for RuntimeError {
     fn into(self) -> Value {
         match self {
@@ -299,6 +930,12 @@ impl Backtrace {
     pub fn push(&mut self, name: Option<String>, token: Rc<Token>) {
         self.inner.push((name, token));
     }
+
+    /// The names of the functions (`None` for the top level and anonymous ones) and the tokens they were at, the innermost
+    /// first.
+    pub fn frames(&self) -> &[(Option<String>, Rc<Token>)] {
+        &self.inner
+    }
 }
 
 impl Default for Backtrace {
@@ -333,3 +970,88 @@ impl fmt::Display for Backtrace {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_every_io_error_kind() {
+        use io::ErrorKind::*;
+        for (kind, expected) in [
+            (NotFound, "غير_موجود"),
+            (PermissionDenied, "غير_مسموح"),
+            (AlreadyExists, "موجود_مسبقاً"),
+            (IsADirectory, "مجلد"),
+            (NotADirectory, "ليس_مجلداً"),
+            (DirectoryNotEmpty, "مجلد_غير_فارغ"),
+            (ReadOnlyFilesystem, "للقراءة_فقط"),
+            (StorageFull, "لا_مساحة"),
+            (QuotaExceeded, "لا_مساحة"),
+            (FileTooLarge, "ملف_كبير_جداً"),
+            (ResourceBusy, "مشغول"),
+            (ExecutableFileBusy, "مشغول"),
+            (CrossesDevices, "عبر_الأجهزة"),
+            (TooManyLinks, "روابط_كثيرة"),
+            (InvalidFilename, "اسم_غير_صالح"),
+            (InvalidInput, "مدخل_غير_صالح"),
+            (InvalidData, "بيانات_غير_صالحة"),
+            (UnexpectedEof, "نهاية_غير_متوقعة"),
+            (WriteZero, "كتابة_صفرية"),
+            (Interrupted, "مقاطعة"),
+            (TimedOut, "انتهت_المهلة"),
+            (WouldBlock, "سيتوقف"),
+            (BrokenPipe, "أنبوب_مكسور"),
+            (Unsupported, "غير_مدعوم"),
+            (OutOfMemory, "ذاكرة_غير_كافية"),
+            // The ones file operations don't fail with share the fallback.
+            (ConnectionRefused, "خطأ_إدخال_إخراج"),
+            (ConnectionReset, "خطأ_إدخال_إخراج"),
+            (HostUnreachable, "خطأ_إدخال_إخراج"),
+            (NetworkUnreachable, "خطأ_إدخال_إخراج"),
+            (ConnectionAborted, "خطأ_إدخال_إخراج"),
+            (NotConnected, "خطأ_إدخال_إخراج"),
+            (AddrInUse, "خطأ_إدخال_إخراج"),
+            (AddrNotAvailable, "خطأ_إدخال_إخراج"),
+            (NetworkDown, "خطأ_إدخال_إخراج"),
+            (StaleNetworkFileHandle, "خطأ_إدخال_إخراج"),
+            (NotSeekable, "خطأ_إدخال_إخراج"),
+            (Deadlock, "خطأ_إدخال_إخراج"),
+            (ArgumentListTooLong, "خطأ_إدخال_إخراج"),
+            (Other, "خطأ_إدخال_إخراج"),
+        ] {
+            assert_eq!(io_error_kind(kind), expected, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn io_errors_carry_their_kind_path_and_code() {
+        let token = Rc::new(Token::new(
+            TokenType::Identifier,
+            Rc::new(String::from("س")),
+            None,
+            0,
+            "س".len(),
+        ));
+        let err = io::Error::from_raw_os_error(2);
+        let path = Path::new("مجلد/ملف.txt");
+        let value: Value = NativeError::io(&err, path)
+            .with("إضافي", 1.0)
+            .into_runtime_error(token)
+            .into();
+        let Value::Object(Object::HashMap(hash_map)) = value else {
+            panic!("expected an error object but got {value}")
+        };
+        let hash_map = hash_map.borrow();
+        let get = |key: &str| hash_map.get(key).map(Value::to_string);
+        assert_eq!(get(ERROR_KIND).as_deref(), Some(io_error_kind(err.kind())));
+        assert_eq!(
+            get(ERROR_MESSAGE),
+            Some(format!("تعذر الوصول إلى \"مجلد/ملف.txt\": {err}"))
+        );
+        assert_eq!(get(ERROR_FILE_PATH).as_deref(), Some("مجلد/ملف.txt"));
+        assert_eq!(get(ERROR_OS_CODE).as_deref(), Some("2"));
+        assert_eq!(get("إضافي").as_deref(), Some("1"));
+        assert_eq!(get(ERROR_LINE).as_deref(), Some("1"));
+    }
+}
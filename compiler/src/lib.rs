@@ -1,17 +1,82 @@
 pub mod chunk;
+pub mod deps;
 pub mod error;
+pub mod lint;
+pub mod path;
+pub mod stdlib;
 
 use chunk::value::{self, Arity, ArityType, Value};
 use chunk::{Chunk, OpCode};
-use error::CompileError;
+use error::{CompileError, CompileWarning};
 use lexer::{token::*, Lexer};
-use parser::ast::{Expr, Literal, Stml};
+use parser::ast::{Arm, Expr, Literal, Pattern, Stml};
 use parser::Parser;
-use std::path::{Path, PathBuf};
-use std::{cell::RefCell, convert::From, fs, rc::Rc};
+use std::path::PathBuf;
+use std::{cell::RefCell, collections::HashMap, convert::From, fs, rc::Rc};
 
 use OpCode::*;
 
+/// The path of programs read from the standard input, paths inside them are relative to the working directory.
+pub const STDIN_PATH: &str = "<قياسي>";
+/// The path of programs passed on the command line, resolved like `STDIN_PATH`.
+pub const INLINE_PATH: &str = "<سطر_الأوامر>";
+
+fn unquote(token: Rc<Token>) -> Result<String, CompileError> {
+    let mut content = String::new();
+    let mut iter = token.lexeme().chars().skip(1);
+    while let Some(ch) = iter.next() {
+        if ch == '\\' {
+            match iter.next() {
+                Some('n') => content.push('\n'),
+                Some('r') => content.push('\r'),
+                Some('t') => content.push('\t'),
+                Some('\\') => content.push('\\'),
+                Some('"') => content.push('"'),
+                _ => return Err(CompileError::BackSlashMisuse(token)),
+            }
+        } else if ch == '"' {
+            break;
+        } else {
+            content.push(ch);
+        }
+    }
+    Ok(content)
+}
+
+/// Resolves the path of an import (i.e. the string token after `من`) relative to the file it's in.
+pub fn import_path(token: Rc<Token>) -> Result<PathBuf, CompileError> {
+    let specifier = unquote(Rc::clone(&token))?;
+    path::resolve_import(token.path().map(PathBuf::as_path), &specifier)
+        .map_err(|err| CompileError::Path(token, err))
+}
+
+/// Whether assigning to (or defining) `expr` unpacks the value.
+fn is_pattern(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Literal(Literal::List(..)) | Expr::Literal(Literal::Object(..))
+    )
+}
+
+/// Whether matching `pattern` defines any variable.
+fn has_bindings(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Wildcard(_) | Pattern::Literal(_) => false,
+        Pattern::Binding(_) => true,
+        Pattern::List(_, patterns, rest) => rest.is_some() || patterns.iter().any(has_bindings),
+        Pattern::Object(_, props) => props
+            .iter()
+            .any(|(_, pattern)| pattern.as_ref().is_none_or(has_bindings)),
+    }
+}
+
+/// A step from the value `طابق` matches to a part of it, the token is of the pattern the part is matched against.
+#[derive(Debug, Clone)]
+enum Step {
+    Index(Rc<Token>, usize),
+    Key(Rc<Token>),
+}
+
 #[derive(Debug, Clone)]
 struct Local {
     token: Rc<Token>,
@@ -38,8 +103,12 @@ impl Local {
 #[derive(Debug, Clone)]
 struct Locals {
     inner: Vec<Local>,
+    /// The indices of the locals by their names, the innermost last, so that resolving doesn't scan them all.
+    names: HashMap<String, Vec<usize>>,
     /// local, idx
     upvalues: Vec<(bool, usize)>,
+    /// The indices of `upvalues`.
+    upvalue_idxs: HashMap<(bool, usize), usize>,
     depth: usize,
     enclosing: Option<Rc<RefCell<Locals>>>,
 }
@@ -48,7 +117,9 @@ impl Locals {
     fn new(enclosing: Option<Rc<RefCell<Locals>>>) -> Self {
         Self {
             inner: Vec::with_capacity(256),
+            names: HashMap::new(),
             upvalues: Vec::with_capacity(256),
+            upvalue_idxs: HashMap::new(),
             depth: 0,
             enclosing,
         }
@@ -63,6 +134,10 @@ impl Locals {
         if self.inner.capacity() == self.inner.len() {
             Err(())
         } else {
+            self.names
+                .entry(token.lexeme().to_owned())
+                .or_default()
+                .push(self.inner.len());
             self.inner.push(Local::new(token, self.depth));
             Ok(())
         }
@@ -73,7 +148,13 @@ impl Locals {
     }
 
     fn pop(&mut self) -> Local {
-        self.inner.pop().unwrap()
+        let local = self.inner.pop().unwrap();
+        let idxs = self.names.get_mut(local.token.lexeme()).unwrap();
+        idxs.pop();
+        if idxs.is_empty() {
+            self.names.remove(local.token.lexeme());
+        }
+        local
     }
 
     fn last_mut(&mut self) -> &mut Local {
@@ -82,26 +163,27 @@ impl Locals {
 
     /// Fails when `self.upvalues` is larger than 256.
     fn add_upvalue(&mut self, local: bool, idx: usize) -> Result<usize, ()> {
-        for (upvalue_idx, upvalue) in self.upvalues.iter().enumerate() {
-            if upvalue.0 == local && upvalue.1 == idx {
-                return Ok(upvalue_idx);
-            }
+        if let Some(upvalue_idx) = self.upvalue_idxs.get(&(local, idx)) {
+            return Ok(*upvalue_idx);
         }
         if self.upvalues.capacity() == self.inner.len() {
             Err(())
         } else {
             let len = self.upvalues.len();
             self.upvalues.push((local, idx));
+            self.upvalue_idxs.insert((local, idx), len);
             Ok(len)
         }
     }
 
     /// `token` must be of type `Identifier`.
-    fn resolve_upvalue(&mut self, token: Rc<Token>) -> Result<Option<usize>, ()> {
+    fn resolve_upvalue(&mut self, token: &Rc<Token>) -> Result<Option<usize>, ()> {
         match self.enclosing.clone() {
             Some(enclosing) => {
                 let mut enclosing = enclosing.borrow_mut();
-                if let Some(idx) = enclosing.resolve_local(Rc::clone(&token)) {
+                if let Some(idx) = enclosing.resolve_local(token) {
+                    // Closed instead of popped when its scope ends (or when a loop is broken out of).
+                    enclosing.inner[idx].captured = true;
                     Ok(Some(self.add_upvalue(true, idx)?))
                 } else {
                     enclosing.resolve_upvalue(token)
@@ -112,19 +194,11 @@ impl Locals {
     }
 
     /// `token` must be of type `Identifier`.
-    fn resolve_local(&self, token: Rc<Token>) -> Option<usize> {
-        for (
-            idx,
-            Local {
-                token: local_token, ..
-            },
-        ) in self.inner.iter().enumerate().rev()
-        {
-            if token.lexeme() == local_token.lexeme() {
-                return Some(idx);
-            }
-        }
-        None
+    fn resolve_local(&self, token: &Rc<Token>) -> Option<usize> {
+        self.names
+            .get(token.lexeme())
+            .and_then(|idxs| idxs.last())
+            .copied()
     }
 
     fn start_scope(&mut self) {
@@ -146,9 +220,37 @@ impl Locals {
     }
 }
 
+/// What `إكسر` and `واصل` need to know about the loop they're in.
+#[derive(Debug)]
+struct Loop {
+    start: usize,
+    /// The number of locals before the body, the ones after it are popped before jumping out.
+    locals: usize,
+    /// The number of handlers before the body, the ones after it are popped before jumping out.
+    handlers: usize,
+    /// The jumps of `إكسر`, settled at the end of the loop.
+    breaks: Vec<usize>,
+    /// Whether it's the loop of a comprehension, which the code the user wrote can't be in.
+    hidden: bool,
+}
+
+impl Loop {
+    fn new(start: usize, locals: usize, handlers: usize, hidden: bool) -> Self {
+        Self {
+            start,
+            locals,
+            handlers,
+            breaks: vec![],
+            hidden,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CompilerType {
     Script,
+    /// Like `Script` but doesn't warn about unused values as they're intentional.
+    Repl,
     /// Writes an implicit `Nil` return at the end of the chunk.
     Function,
     /// Writes an implicit return for exported functions.
@@ -160,15 +262,26 @@ pub struct Compiler<'a> {
     ast: &'a Vec<Stml>,
     /// The token that represents the compiler.
     ///
-    /// It must be of type `Function` for a function compiler and `EOF` for the rest.
+    /// It must be of type `Function` (or `Generator`) for a function compiler and `EOF` for the rest.
     token: Rc<Token>,
     chunk: Chunk,
     locals: Rc<RefCell<Locals>>,
-    /// A vector containing jumps ips.
-    breaks: Vec<usize>,
-    /// A vector containing enclosing loops starts.
-    loops: Vec<usize>,
+    /// The enclosing loops, the innermost last.
+    loops: Vec<Loop>,
+    /// Whether the function is defined inside a loop of an enclosing compiler, which `إكسر` and `واصل` can't reach.
+    enclosing_loop: bool,
+    /// The number of `حاول` blocks the code being compiled is in.
+    handlers: usize,
+    /// The locals holding the errors of the enclosing catch blocks.
+    catches: Vec<usize>,
+    /// The locals holding the resources of the enclosing `مع` blocks with the number of handlers before each.
+    resources: Vec<(usize, usize)>,
+    /// Whether the chunk keeps where its named locals live for debuggers.
+    debug_info: bool,
+    /// The indices of the live locals in the chunk's debug info, `None` for the unnamed ones.
+    debug_locals: Vec<Option<usize>>,
     errors: Vec<CompileError>,
+    warnings: Vec<CompileWarning>,
 }
 
 impl<'a> Compiler<'a> {
@@ -179,9 +292,15 @@ impl<'a> Compiler<'a> {
             token,
             chunk: Chunk::new(),
             locals: Rc::new(RefCell::new(Locals::new(None))),
-            breaks: vec![],
             loops: vec![],
+            enclosing_loop: false,
+            handlers: 0,
+            catches: vec![],
+            resources: vec![],
+            debug_info: false,
+            debug_locals: vec![],
             errors: vec![],
+            warnings: vec![],
         }
     }
 
@@ -196,18 +315,44 @@ impl<'a> Compiler<'a> {
             token,
             chunk: Chunk::new(),
             locals: Rc::new(RefCell::new(Locals::new(Some(enclosing)))),
-            breaks: vec![],
             loops: vec![],
+            enclosing_loop: false,
+            handlers: 0,
+            catches: vec![],
+            resources: vec![],
+            debug_info: false,
+            debug_locals: vec![],
             errors: vec![],
+            warnings: vec![],
         }
     }
 
+    /// Makes the chunks (and the ones of its functions and modules) keep the names and lifetimes of their locals, which
+    /// `vm::Step::locals` needs.
+    pub fn debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
     fn err(&mut self, err: CompileError) {
         self.errors.push(err)
     }
 
+    fn warn(&mut self, warning: CompileWarning) {
+        self.warnings.push(warning)
+    }
+
+    /// The warnings of the compiler and the ones it created, available even if the compilation failed.
+    pub fn warnings(&self) -> &Vec<CompileWarning> {
+        &self.warnings
+    }
+
+    fn is_script(&self) -> bool {
+        matches!(self.typ, CompilerType::Script | CompilerType::Repl)
+    }
+
     fn in_global(&self) -> bool {
-        self.typ == CompilerType::Script && self.locals.borrow().depth == 0
+        self.is_script() && self.locals.borrow().depth == 0
     }
 
     fn in_loop(&self) -> bool {
@@ -221,41 +366,41 @@ impl<'a> Compiler<'a> {
     fn write_instr_const(
         &mut self,
         (u8_instr, u16_instr): (OpCode, OpCode),
-        token: Rc<Token>,
+        token: &Rc<Token>,
         value: Value,
     ) -> Result<(), ()> {
         self.chunk
-            .write_instr_const((u8_instr, u16_instr), Rc::clone(&token), value)
-            .map_err(|_| self.err(CompileError::TooManyConsts(token)))
+            .write_instr_const((u8_instr, u16_instr), token, value)
+            .map_err(|_| self.err(CompileError::TooManyConsts(Rc::clone(token))))
     }
 
-    fn write_const(&mut self, token: Rc<Token>, value: Value) -> Result<(), ()> {
+    fn write_const(&mut self, token: &Rc<Token>, value: Value) -> Result<(), ()> {
         self.write_instr_const((CONST8, CONST16), token, value)
     }
 
     #[allow(unused_must_use)]
-    fn nil(&mut self, token: Rc<Token>) {
+    fn nil(&mut self, token: &Rc<Token>) {
         self.write_const(token, Value::Nil);
     }
 
     #[allow(unused_must_use)]
-    fn bool(&mut self, token: Rc<Token>, value: bool) {
+    fn bool(&mut self, token: &Rc<Token>, value: bool) {
         self.write_const(token, Value::from(value));
     }
 
     #[allow(unused_must_use)]
-    fn write_instr_idx(&mut self, op_code: OpCode, token: Rc<Token>, idx: usize) {
+    fn write_instr_idx(&mut self, op_code: OpCode, token: &Rc<Token>, idx: usize) {
         self.chunk.write_instr_idx(op_code, token, idx);
     }
 
-    fn write_string_of_ident(&mut self, token: Rc<Token>) -> Result<(), ()> {
-        self.write_const(Rc::clone(&token), Value::from(token.lexeme().clone()))
+    fn write_string_of_ident(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        self.write_const(token, Value::from(token.lexeme()))
     }
 
-    fn write_build(&mut self, op_code: OpCode, token: Rc<Token>, size: usize) -> Result<(), ()> {
+    fn write_build(&mut self, op_code: OpCode, token: &Rc<Token>, size: usize) -> Result<(), ()> {
         self.chunk
-            .write_build(op_code, Rc::clone(&token), size)
-            .map_err(|_| self.err(CompileError::HugeSize(token)))
+            .write_build(op_code, token, size)
+            .map_err(|_| self.err(CompileError::HugeSize(Rc::clone(token))))
     }
 
     fn settle_jump(&mut self, ip: usize) -> Result<(), ()> {
@@ -264,91 +409,73 @@ impl<'a> Compiler<'a> {
             .map_err(|_| self.err(CompileError::HugeJump(self.chunk.token(ip))))
     }
 
-    fn write_loop(&mut self, token: Rc<Token>, ip: usize) -> Result<(), ()> {
+    fn write_loop(&mut self, token: &Rc<Token>, ip: usize) -> Result<(), ()> {
         self.chunk
-            .write_loop(Rc::clone(&token), ip)
-            .map_err(|_| self.err(CompileError::HugeJump(token)))
+            .write_loop(token, ip)
+            .map_err(|_| self.err(CompileError::HugeJump(Rc::clone(token))))
     }
 
-    fn write_list_unpack(&mut self, token: Rc<Token>, to: usize) -> Result<(), ()> {
+    fn write_list_unpack(&mut self, token: &Rc<Token>, to: usize, rest: bool) -> Result<(), ()> {
         self.chunk
-            .write_list_unpack(Rc::clone(&token), to)
-            .map_err(|_| self.err(CompileError::HugeSize(token)))
+            .write_list_unpack(token, to, rest)
+            .map_err(|_| self.err(CompileError::HugeSize(Rc::clone(token))))
     }
 
-    fn write_hash_map_unpack(&mut self, token: Rc<Token>, defaults: Vec<bool>) -> Result<(), ()> {
+    fn write_hash_map_unpack(&mut self, token: &Rc<Token>, defaults: Vec<bool>) -> Result<(), ()> {
         self.chunk
-            .write_hash_map_unpack(Rc::clone(&token), defaults)
-            .map_err(|_| self.err(CompileError::HugeSize(token)))
+            .write_hash_map_unpack(token, defaults)
+            .map_err(|_| self.err(CompileError::HugeSize(Rc::clone(token))))
     }
 
     fn write_closure(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         function: value::Function,
         upvalues: Vec<(bool, usize)>,
     ) -> Result<(), ()> {
         self.chunk
-            .write_closure(Rc::clone(&token), function, upvalues)
-            .map_err(|_| self.err(CompileError::TooManyConsts(token)))
+            .write_closure(token, function, upvalues)
+            .map_err(|_| self.err(CompileError::TooManyConsts(Rc::clone(token))))
     }
 
-    fn write_call(&mut self, token: Rc<Token>, argc: usize) -> Result<(), ()> {
+    fn write_call(&mut self, token: &Rc<Token>, argc: usize) -> Result<(), ()> {
         self.chunk
-            .write_call(Rc::clone(&token), argc)
-            .map_err(|_| self.err(CompileError::TooManyArgs(token)))
+            .write_call(token, argc)
+            .map_err(|_| self.err(CompileError::TooManyArgs(Rc::clone(token))))
     }
 
     #[allow(unused_must_use)]
-    fn write_call_unchecked(&mut self, token: Rc<Token>, argc: usize) {
+    fn write_call_unchecked(&mut self, token: &Rc<Token>, argc: usize) {
         self.chunk.write_call(token, argc);
     }
 
-    fn push(&mut self, token: Rc<Token>) -> Result<(), ()> {
+    fn push(&mut self, token: &Rc<Token>) -> Result<(), ()> {
         let mut locals = self.locals.borrow_mut();
-        let res = locals.push(Rc::clone(&token));
+        let res = locals.push(Rc::clone(token));
+        let slot = locals.len() - 1;
         drop(locals);
         match res {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                if self.debug_info {
+                    let named = token.typ() == TokenType::Identifier && token.lexeme() != "_";
+                    let idx = named.then(|| self.chunk.start_local(token, slot));
+                    self.debug_locals.push(idx)
+                }
+                Ok(())
+            }
             Err(_) => {
-                self.err(CompileError::TooManyLocals(token));
+                self.err(CompileError::TooManyLocals(Rc::clone(token)));
                 Err(())
             }
         }
     }
 
-    fn quoted_string(&mut self, token: Rc<Token>) -> Result<String, ()> {
-        let mut content = String::new();
-        let mut iter = token.lexeme().chars().skip(1);
-        while let Some(ch) = iter.next() {
-            if ch == '\\' {
-                if let Some(ch) = iter.next() {
-                    match ch {
-                        'n' => content.push('\n'),
-                        'r' => content.push('\r'),
-                        't' => content.push('\t'),
-                        '\\' => content.push('\\'),
-                        '"' => content.push('"'),
-                        _ => {
-                            self.err(CompileError::BackSlashMisuse(token));
-                            return Err(());
-                        }
-                    }
-                } else {
-                    self.err(CompileError::BackSlashMisuse(token));
-                    return Err(());
-                }
-            } else if ch == '"' {
-                break;
-            } else {
-                content.push(ch);
-            }
-        }
-        Ok(content)
+    fn quoted_string(&mut self, token: &Rc<Token>) -> Result<String, ()> {
+        unquote(Rc::clone(token)).map_err(|err| self.err(err))
     }
 
     /// Parses quoted strings and unquoted ones.
-    fn string(&mut self, token: Rc<Token>) -> Result<String, ()> {
+    fn string(&mut self, token: &Rc<Token>) -> Result<String, ()> {
         if token.lexeme().starts_with("\"") {
             self.quoted_string(token)
         } else {
@@ -356,7 +483,7 @@ impl<'a> Compiler<'a> {
         }
     }
 
-    fn unary(&mut self, op: Rc<Token>, expr: &Expr) -> Result<(), ()> {
+    fn unary(&mut self, op: &Rc<Token>, expr: &Expr) -> Result<(), ()> {
         self.expr(expr)?;
         match op.typ() {
             TokenType::Minus => {
@@ -370,12 +497,13 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
-    fn binary(&mut self, lhs: &Expr, op: Rc<Token>, rhs: &Expr) -> Result<(), ()> {
+    fn binary(&mut self, lhs: &Expr, op: &Rc<Token>, rhs: &Expr) -> Result<(), ()> {
         match op.typ() {
             TokenType::Equal => {
                 self.expr(rhs)?;
                 self.chunk.write_instr_no_operands(DUP, op);
-                self.settable(lhs)?;
+                let source = is_pattern(lhs).then(|| rhs.span());
+                self.settable(lhs, source.as_ref())?;
                 return Ok(());
             }
             TokenType::PlusEqual
@@ -384,7 +512,10 @@ impl<'a> Compiler<'a> {
             | TokenType::SlashEqual
             | TokenType::PercentEqual => match lhs {
                 Expr::Variable(..) | Expr::Member(..) => {
+                    // The read and the write aren't written by the user, so their errors point at the operator.
+                    let synthetic = Rc::new(op.synthetic());
                     self.get(lhs)?;
+                    self.chunk.retoken_last(&synthetic);
                     self.expr(rhs)?;
                     self.chunk.write_instr_no_operands(
                         match op.typ() {
@@ -398,6 +529,7 @@ impl<'a> Compiler<'a> {
                         op,
                     );
                     self.set(lhs, false)?;
+                    self.chunk.retoken_last(&synthetic);
                     return Ok(());
                 }
                 _ => unreachable!(),
@@ -446,20 +578,17 @@ impl<'a> Compiler<'a> {
         variadic: &Option<(Rc<Token>, Box<Expr>)>,
         body: &Stml,
     ) -> Result<(), ()> {
-        self.function(body, required, optional, variadic, None, Rc::clone(token))
+        self.function(body, required, optional, variadic, None, token)
     }
 
     fn literal(&mut self, literal: &Literal) -> Result<(), ()> {
         match literal {
             Literal::Number(token) => {
-                self.write_const(
-                    Rc::clone(token),
-                    Value::Number(token.lexeme().clone().parse().unwrap()),
-                )?;
+                self.write_const(token, Value::Number(token.lexeme().parse().unwrap()))?;
             }
             Literal::Bool(token) => {
                 self.bool(
-                    Rc::clone(token),
+                    token,
                     match token.typ() {
                         TokenType::True => true,
                         TokenType::False => false,
@@ -468,11 +597,11 @@ impl<'a> Compiler<'a> {
                 );
             }
             Literal::String(token) => {
-                let value = Value::from(self.string(Rc::clone(token))?);
-                self.write_const(Rc::clone(token), value)?;
+                let value = Value::from(self.string(token)?);
+                self.write_const(token, value)?;
             }
             Literal::Nil(token) => {
-                self.nil(Rc::clone(token));
+                self.nil(token);
             }
             // TODO report HugeSize with better tokens
             Literal::List(token, exprs) => {
@@ -481,15 +610,27 @@ impl<'a> Compiler<'a> {
                     self.expr(expr)?;
                     size += 1;
                 }
-                self.write_build(BUILD_LIST, Rc::clone(token), size)?
+                self.write_build(BUILD_LIST, token, size)?
+            }
+            Literal::Comprehension(token, element, for_token, definable, _, iterable, guard) => {
+                self.comprehension(
+                    token,
+                    element,
+                    for_token,
+                    definable,
+                    iterable,
+                    guard
+                        .as_ref()
+                        .map(|(when, condition)| (when, condition.as_ref())),
+                )?
             }
             Literal::Object(token, props) => {
                 let mut size = 0;
                 for (key, value, default) in props {
-                    self.write_const(Rc::clone(key), Value::from(key.lexeme().clone()))?;
+                    self.write_const(key, Value::from(key.lexeme()))?;
                     match value {
                         Some(lhs) => match default {
-                            Some((op, rhs)) => self.binary(lhs, Rc::clone(op), rhs)?,
+                            Some((op, rhs)) => self.binary(lhs, op, rhs)?,
                             None => self.expr(lhs)?,
                         },
                         None => match default {
@@ -499,7 +640,7 @@ impl<'a> Compiler<'a> {
                     }
                     size += 1;
                 }
-                self.write_build(BUILD_HASH_MAP, Rc::clone(token), size)?
+                self.write_build(BUILD_HASH_MAP, token, size)?
             }
             Literal::Lambda(token, required, optional, variadic, body) => {
                 self.lambda(token, required, optional, variadic, body)?
@@ -508,11 +649,11 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
-    fn resolve_local(&self, token: Rc<Token>) -> Option<usize> {
+    fn resolve_local(&self, token: &Rc<Token>) -> Option<usize> {
         self.locals.borrow().resolve_local(token)
     }
 
-    fn resolve_upvalue(&self, token: Rc<Token>) -> Result<Option<usize>, ()> {
+    fn resolve_upvalue(&self, token: &Rc<Token>) -> Result<Option<usize>, ()> {
         self.locals.borrow_mut().resolve_upvalue(token)
     }
 
@@ -520,19 +661,19 @@ impl<'a> Compiler<'a> {
     fn get(&mut self, expr: &Expr) -> Result<(), ()> {
         match expr {
             Expr::Variable(token) => {
-                if let Some(idx) = self.resolve_local(Rc::clone(token)) {
-                    self.write_instr_idx(GET_LOCAL, Rc::clone(token), idx);
+                if let Some(idx) = self.resolve_local(token) {
+                    self.write_instr_idx(GET_LOCAL, token, idx);
                 } else {
-                    match self.resolve_upvalue(Rc::clone(token)) {
+                    match self.resolve_upvalue(token) {
                         Ok(idx) => match idx {
                             Some(idx) => {
-                                self.write_instr_idx(GET_UPVALUE, Rc::clone(token), idx);
+                                self.write_instr_idx(GET_UPVALUE, token, idx);
                             }
                             None => {
                                 self.write_instr_const(
                                     (GET_GLOBAL8, GET_GLOBAL16),
-                                    Rc::clone(token),
-                                    Value::from(token.lexeme().clone()),
+                                    token,
+                                    Value::from(token.lexeme()),
                                 )?;
                             }
                         },
@@ -542,15 +683,23 @@ impl<'a> Compiler<'a> {
             }
             Expr::Member(expr, op, key) => {
                 self.expr(expr)?;
-                self.expr(key)?;
-                self.chunk.write_instr_no_operands(GET, Rc::clone(op));
+                match key.as_ref() {
+                    Expr::Literal(Literal::String(key)) => {
+                        let key = Value::from(self.string(key)?);
+                        self.write_instr_const((GET_PROP8, GET_PROP16), op, key)?
+                    }
+                    key => {
+                        self.expr(key)?;
+                        self.chunk.write_instr_no_operands(GET, op);
+                    }
+                }
             }
             _ => unreachable!(),
         }
         Ok(())
     }
 
-    fn call(&mut self, callee: &Expr, op: Rc<Token>, exprs: &Vec<Expr>) -> Result<(), ()> {
+    fn call(&mut self, callee: &Expr, op: &Rc<Token>, exprs: &Vec<Expr>) -> Result<(), ()> {
         self.expr(callee)?;
         for arg in exprs {
             self.expr(arg)?
@@ -563,30 +712,30 @@ impl<'a> Compiler<'a> {
         match expr {
             Expr::Variable(..) | Expr::Member(..) => self.get(expr),
             Expr::Literal(literal) => self.literal(literal),
-            Expr::Unary(op, expr) => self.unary(Rc::clone(op), expr),
-            Expr::Binary(lhs, op, rhs) => self.binary(lhs, Rc::clone(op), rhs),
-            Expr::Call(callee, op, exprs) => self.call(callee, Rc::clone(op), exprs),
+            Expr::Unary(op, expr) => self.unary(op, expr),
+            Expr::Binary(lhs, op, rhs) => self.binary(lhs, op, rhs),
+            Expr::Call(callee, op, exprs) => self.call(callee, op, exprs),
         }
     }
 
-    fn define(&mut self, token: Rc<Token>) -> Result<(), ()> {
+    fn define(&mut self, token: &Rc<Token>) -> Result<(), ()> {
         if self.in_global() {
             self.write_instr_const(
                 (DEF_GLOBAL8, DEF_GLOBAL16),
-                Rc::clone(&token),
-                Value::from(token.lexeme().clone()),
+                token,
+                Value::from(token.lexeme()),
             )?
         } else {
             if token.lexeme() != "_" {
-                if let Some(idx) = self.resolve_local(Rc::clone(&token)) {
+                if let Some(idx) = self.resolve_local(token) {
                     if self.locals.borrow().get(idx).depth == self.locals.borrow().depth {
-                        self.err(CompileError::SameVarInScope(token));
+                        self.err(CompileError::SameVarInScope(Rc::clone(token)));
                         return Err(());
                     }
                 }
             }
 
-            self.push(Rc::clone(&token))?;
+            self.push(token)?;
             self.chunk.write_instr_no_operands(DEF_LOCAL, token)
         }
         Ok(())
@@ -596,12 +745,12 @@ impl<'a> Compiler<'a> {
         self.typ != CompilerType::Function && self.locals.borrow().depth == 0
     }
 
-    fn export(&mut self, token: Rc<Token>) -> Result<(), ()> {
+    fn export(&mut self, token: &Rc<Token>) -> Result<(), ()> {
         if !self.can_export() {
             return Err(());
         }
         if !self.in_global() {
-            self.define(Rc::clone(&token))?;
+            self.define(token)?;
             self.locals.borrow_mut().last_mut().export();
         }
         Ok(())
@@ -609,12 +758,12 @@ impl<'a> Compiler<'a> {
 
     fn unpack_hash_map(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         props: &Vec<(Rc<Token>, Option<Expr>, Option<(Rc<Token>, Expr)>)>,
     ) -> Result<(), ()> {
         let mut defaults = vec![];
         for (key, _, default) in props {
-            self.write_string_of_ident(Rc::clone(key))?;
+            self.write_string_of_ident(key)?;
             match default {
                 Some((_, expr)) => {
                     self.expr(expr)?;
@@ -630,49 +779,57 @@ impl<'a> Compiler<'a> {
     fn set(&mut self, expr: &Expr, pop: bool) -> Result<(), ()> {
         match expr {
             Expr::Variable(token) => {
-                if let Some(idx) = self.resolve_local(Rc::clone(token)) {
-                    self.chunk
-                        .write_instr_idx(SET_LOCAL, Rc::clone(token), idx)?
-                } else if let Some(idx) = self.resolve_upvalue(Rc::clone(token))? {
-                    self.chunk
-                        .write_instr_idx(SET_UPVALUE, Rc::clone(token), idx)?
+                if let Some(idx) = self.resolve_local(token) {
+                    self.chunk.write_instr_idx(SET_LOCAL, token, idx)?
+                } else if let Some(idx) = self.resolve_upvalue(token)? {
+                    self.chunk.write_instr_idx(SET_UPVALUE, token, idx)?
                 } else {
                     self.write_instr_const(
                         (SET_GLOBAL8, SET_GLOBAL16),
-                        Rc::clone(token),
-                        Value::from(token.lexeme().clone()),
+                        token,
+                        Value::from(token.lexeme()),
                     )?
                 }
             }
             Expr::Member(expr, op, key) => {
                 self.expr(expr)?;
-                self.expr(key)?;
-                self.chunk.write_instr_no_operands(SET, Rc::clone(op))
+                match key.as_ref() {
+                    Expr::Literal(Literal::String(key)) => {
+                        let key = Value::from(self.string(key)?);
+                        self.write_instr_const((SET_PROP8, SET_PROP16), op, key)?
+                    }
+                    key => {
+                        self.expr(key)?;
+                        self.chunk.write_instr_no_operands(SET, op)
+                    }
+                }
             }
             _ => unreachable!(),
         }
         if pop {
-            self.chunk.write_instr_no_operands(POP, expr.token());
+            self.chunk.write_instr_no_operands(POP, &expr.token());
         }
         Ok(())
     }
 
-    fn settable(&mut self, settable: &Expr) -> Result<(), ()> {
+    /// `source` is the token of the expression the value comes from, the unpacking of a pattern points at it when given
+    /// and at the pattern otherwise.
+    fn settable(&mut self, settable: &Expr, source: Option<&Rc<Token>>) -> Result<(), ()> {
         match settable {
             Expr::Variable(..) | Expr::Member(..) => self.set(settable, true)?,
             Expr::Literal(Literal::List(token, exprs)) => {
-                self.write_list_unpack(Rc::clone(token), exprs.len())?;
+                self.write_list_unpack(source.unwrap_or(token), exprs.len(), false)?;
                 for settable in exprs.iter().rev() {
-                    self.settable(settable)?
+                    self.settable(settable, None)?
                 }
             }
             Expr::Literal(Literal::Object(token, props)) => {
                 // 1. Unpacking
-                self.unpack_hash_map(Rc::clone(token), props)?;
+                self.unpack_hash_map(source.unwrap_or(token), props)?;
                 // 2. Destructuring
                 for (key, value, _) in props {
                     match value {
-                        Some(expr) => self.settable(expr)?,
+                        Some(expr) => self.settable(expr, None)?,
                         None => self.set(&Expr::Variable(Rc::clone(key)), true)?,
                     }
                 }
@@ -685,13 +842,19 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
-    fn definable(&mut self, definable: &Expr, export: bool) -> Result<(), ()> {
+    /// Like `settable`.
+    fn definable(
+        &mut self,
+        definable: &Expr,
+        export: bool,
+        source: Option<&Rc<Token>>,
+    ) -> Result<(), ()> {
         macro_rules! oper {
             ($token:ident) => {
                 if export {
-                    self.export(Rc::clone($token))?
+                    self.export($token)?
                 } else {
-                    self.define(Rc::clone($token))?
+                    self.define($token)?
                 }
             };
         }
@@ -699,18 +862,18 @@ impl<'a> Compiler<'a> {
         match definable {
             Expr::Variable(token) => oper!(token),
             Expr::Literal(Literal::List(token, exprs)) => {
-                self.write_list_unpack(Rc::clone(token), exprs.len())?;
+                self.write_list_unpack(source.unwrap_or(token), exprs.len(), false)?;
                 for definable in exprs.iter().rev() {
-                    self.definable(definable, export)?
+                    self.definable(definable, export, None)?
                 }
             }
             Expr::Literal(Literal::Object(token, props)) => {
                 // 1. Unpacking
-                self.unpack_hash_map(Rc::clone(token), props)?;
+                self.unpack_hash_map(source.unwrap_or(token), props)?;
                 // 2. Destructuring
                 for (key, value, _) in props {
                     match value {
-                        Some(expr) => self.definable(expr, export)?,
+                        Some(expr) => self.definable(expr, export, None)?,
                         None => {
                             oper!(key)
                         }
@@ -728,15 +891,21 @@ impl<'a> Compiler<'a> {
     fn var_decl(
         &mut self,
         export_token: &Option<Rc<Token>>,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         decls: &Vec<(Expr, Option<Expr>)>,
     ) -> Result<(), ()> {
         for (definable, init) in decls {
-            match init {
-                Some(expr) => self.expr(expr)?,
-                None => self.nil(Rc::clone(&token)),
-            }
-            self.definable(definable, export_token.is_some())?
+            let source = match init {
+                Some(expr) => {
+                    self.expr(expr)?;
+                    is_pattern(definable).then(|| expr.span())
+                }
+                None => {
+                    self.nil(token);
+                    None
+                }
+            };
+            self.definable(definable, export_token.is_some(), source.as_ref())?
         }
         Ok(())
     }
@@ -745,12 +914,14 @@ impl<'a> Compiler<'a> {
         self.locals.borrow_mut().start_scope();
     }
 
-    fn end_scope(&mut self, token: Rc<Token>) {
+    fn end_scope(&mut self, token: &Rc<Token>) {
+        let token = Rc::new(token.synthetic());
         for captured in self.locals.borrow_mut().end_scope() {
-            self.chunk.write_instr_no_operands(
-                if captured { CLOSE_UPVALUE } else { POP_LOCAL },
-                Rc::clone(&token),
-            )
+            if let Some(idx) = self.debug_locals.pop().flatten() {
+                self.chunk.end_local(idx)
+            }
+            self.chunk
+                .write_instr_no_operands(if captured { CLOSE_UPVALUE } else { POP_LOCAL }, &token)
         }
     }
 
@@ -763,15 +934,15 @@ impl<'a> Compiler<'a> {
         else_: &Option<(Rc<Token>, Box<Stml>)>,
     ) -> Result<(), ()> {
         self.expr(condition)?;
-        let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, Rc::clone(token));
+        let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, token);
         self.stml(body)?;
-        let mut end = vec![self.chunk.write_jump(JUMP, body.token())];
+        let mut end = vec![self.chunk.write_jump(JUMP, &body.token())];
         self.settle_jump(falsy_condition)?;
         for (token, condition, body) in elseifs {
             self.expr(condition)?;
-            let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, Rc::clone(token));
+            let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, token);
             self.stml(body)?;
-            end.push(self.chunk.write_jump(JUMP, Rc::clone(token)));
+            end.push(self.chunk.write_jump(JUMP, token));
             self.settle_jump(falsy_condition)?;
         }
         match else_ {
@@ -786,6 +957,9 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
+    /// The callee is below the arguments when the function starts, and no local is reserved for it in any frame (the
+    /// root one included), so the params take the first slots (the last param first since it's on top) and the callee
+    /// is defined after them if the function is named or popped otherwise.
     fn params(
         &mut self,
         required: &Vec<Expr>,
@@ -799,15 +973,14 @@ impl<'a> Compiler<'a> {
         }
         let body = self.ip();
         if let Some((token, definable)) = variadic {
-            self.chunk
-                .write_instr_no_operands(BUILD_VARIADIC, Rc::clone(token));
-            self.definable(definable, false)?
+            self.chunk.write_instr_no_operands(BUILD_VARIADIC, token);
+            self.definable(definable, false, None)?
         }
         for (definable, _) in optional.iter().rev() {
-            self.definable(definable, false)?
+            self.definable(definable, false, None)?
         }
         for definable in required.iter().rev() {
-            self.definable(definable, false)?
+            self.definable(definable, false, None)?
         }
         Ok((
             Arity::new(
@@ -831,18 +1004,35 @@ impl<'a> Compiler<'a> {
         optional: &Vec<(Expr, Expr)>,
         variadic: &Option<(Rc<Token>, Box<Expr>)>,
         name: Option<Rc<Token>>,
-        token: Rc<Token>,
+        token: &Rc<Token>,
     ) -> Result<(), ()> {
-        let mut compiler = Compiler::new_function(Rc::clone(&token), body, Rc::clone(&self.locals));
-        let (arity, defaults, body) = compiler.params(required, optional, variadic)?;
-        if let Some(token) = &name {
-            compiler.define(Rc::clone(token))?
-        } else {
-            compiler
-                .chunk
-                .write_instr_no_operands(POP, Rc::clone(&token))
+        let mut compiler = Compiler::new_function(Rc::clone(token), body, Rc::clone(&self.locals))
+            .debug_info(self.debug_info);
+        compiler.enclosing_loop =
+            self.loops.iter().any(|enclosing| !enclosing.hidden) || self.enclosing_loop;
+        let params = compiler.params(required, optional, variadic);
+        let params = params.and_then(|params| {
+            if let Some(token) = &name {
+                compiler.define(token)?
+            } else {
+                compiler
+                    .chunk
+                    .write_instr_no_operands(POP, &Rc::new(token.synthetic()))
+            };
+            Ok(params)
+        });
+        // The errors of the defaults (and of defining the name) are the function's like the ones of its body.
+        let (arity, defaults, body) = match params {
+            Ok(params) => params,
+            Err(()) => {
+                self.warnings.append(&mut compiler.warnings);
+                self.errors.append(&mut compiler.errors);
+                return Err(());
+            }
         };
-        let chunk = match compiler.compile() {
+        let chunk = compiler.compile();
+        self.warnings.append(&mut compiler.warnings);
+        let chunk = match chunk {
             Ok(chunk) => chunk,
             Err(errors) => {
                 for err in errors {
@@ -852,6 +1042,7 @@ impl<'a> Compiler<'a> {
             }
         };
         let upvalues = compiler.locals.borrow().upvalues.clone();
+        let generator = token.typ() == TokenType::Generator;
         self.write_closure(
             token,
             value::Function::new(
@@ -860,6 +1051,8 @@ impl<'a> Compiler<'a> {
                 arity,
                 defaults,
                 body,
+                generator,
+                upvalues.len(),
             ),
             upvalues,
         )?;
@@ -869,8 +1062,8 @@ impl<'a> Compiler<'a> {
     fn function_decl(
         &mut self,
         export_token: &Option<Rc<Token>>,
-        token: Rc<Token>,
-        name: Rc<Token>,
+        token: &Rc<Token>,
+        name: &Rc<Token>,
         required: &Vec<Expr>,
         optional: &Vec<(Expr, Expr)>,
         variadic: &Option<(Rc<Token>, Box<Expr>)>,
@@ -881,7 +1074,7 @@ impl<'a> Compiler<'a> {
             required,
             optional,
             variadic,
-            Some(Rc::clone(&name)),
+            Some(Rc::clone(name)),
             token,
         )?;
         match export_token {
@@ -891,172 +1084,593 @@ impl<'a> Compiler<'a> {
         Ok(())
     }
 
-    fn return_stml(&mut self, token: Rc<Token>, value: &Option<Expr>) -> Result<(), ()> {
+    fn return_stml(&mut self, token: &Rc<Token>, value: &Option<Expr>) -> Result<(), ()> {
         if self.typ != CompilerType::Function {
-            self.err(CompileError::ReturnOutsideFunction(Rc::clone(&token)));
+            self.err(CompileError::ReturnOutsideFunction(Rc::clone(token)));
             return Err(());
         }
         match value {
             Some(expr) => self.expr(expr)?,
-            None => self.nil(Rc::clone(&token)),
+            None => self.nil(token),
         };
+        if let Some(&(_, handlers)) = self.resources.first() {
+            self.unwind_handlers(handlers, &Rc::new(token.synthetic()))?
+        }
         self.chunk.write_instr_no_operands(RET, token);
         Ok(())
     }
 
-    fn throw_stml(&mut self, token: Rc<Token>, value: &Option<Expr>) -> Result<(), ()> {
+    fn yield_stml(&mut self, token: &Rc<Token>, value: &Expr) -> Result<(), ()> {
+        if self.token.typ() != TokenType::Generator {
+            self.err(CompileError::YieldOutsideGenerator(Rc::clone(token)));
+            return Err(());
+        }
+        self.expr(value)?;
+        self.chunk.write_instr_no_operands(YIELD, token);
+        Ok(())
+    }
+
+    fn throw_stml(&mut self, token: &Rc<Token>, value: &Option<Expr>) -> Result<(), ()> {
         match value {
             Some(expr) => self.expr(expr)?,
-            None => self.nil(Rc::clone(&token)),
+            None => self.nil(token),
         };
         self.chunk.write_instr_no_operands(THROW, token);
         Ok(())
     }
 
-    fn settle_breaks(&mut self) -> Result<(), ()> {
-        while let Some(ip) = self.breaks.pop() {
+    fn rethrow_stml(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        match self.catches.last() {
+            Some(&idx) => self.chunk.write_instr_idx(RETHROW, token, idx),
+            None => {
+                self.err(CompileError::RethrowOutsideCatch(Rc::clone(token)));
+                Err(())
+            }
+        }
+    }
+
+    fn start_loop(&mut self, start: usize, hidden: bool) {
+        let locals = self.locals.borrow().len();
+        self.loops
+            .push(Loop::new(start, locals, self.handlers, hidden))
+    }
+
+    fn end_loop(&mut self) -> Result<(), ()> {
+        for ip in self.loops.pop().unwrap().breaks {
             self.settle_jump(ip)?
         }
         Ok(())
     }
 
-    fn loop_stml(&mut self, token: Rc<Token>, body: &Stml) -> Result<(), ()> {
+    fn loop_stml(&mut self, token: &Rc<Token>, body: &Stml) -> Result<(), ()> {
         let start = self.ip();
-        self.loops.push(start);
+        self.start_loop(start, false);
         self.stml(body)?;
-        self.write_loop(token, start)?;
-        self.settle_breaks()
+        self.write_loop(&Rc::new(token.synthetic()), start)?;
+        self.end_loop()
     }
-    fn while_stml(&mut self, token: Rc<Token>, condition: &Expr, body: &Stml) -> Result<(), ()> {
+    fn while_stml(&mut self, token: &Rc<Token>, condition: &Expr, body: &Stml) -> Result<(), ()> {
         let start = self.ip();
-        self.loops.push(start);
+        self.start_loop(start, false);
         self.expr(condition)?;
-        let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, Rc::clone(&token));
+        let falsy_condition = self.chunk.write_jump(POP_JUMP_IF_FALSY, token);
         self.stml(body)?;
-        self.write_loop(token, start)?;
+        self.write_loop(&Rc::new(token.synthetic()), start)?;
         self.settle_jump(falsy_condition)?;
-        self.settle_breaks()
+        self.end_loop()
     }
 
     fn for_in_stml(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         definable: &Expr,
-        _: Rc<Token>,
+        _: &Rc<Token>,
         iterable: &Expr,
+        guard: &Option<(Rc<Token>, Expr)>,
         body: &Stml,
     ) -> Result<(), ()> {
-        self.expr(iterable)?;
-        self.chunk.write_instr_no_operands(ITER, Rc::clone(&token));
-        let start = self.ip();
-        self.loops.push(start);
-        let iterator_stopped = self.chunk.write_jump(FOR_ITER, token);
         match body {
-            Stml::Block(token, stmls) => {
-                self.start_scope();
-                self.definable(definable, false)?;
-                self.stmls(stmls);
-                self.end_scope(Rc::clone(token));
-                self.write_loop(Rc::clone(token), start)?
+            Stml::Block(block_token, stmls) => {
+                self.for_in(token, definable, iterable, block_token, false, |compiler| {
+                    compiler.guard(guard.as_ref().map(|(when, condition)| (when, condition)))?;
+                    compiler.stmls(stmls);
+                    Ok(())
+                })
             }
             _ => unreachable!(),
         }
+    }
+
+    /// Loops over `iterable` defining each element in a scope (ended with `scope_token`) where `body` is compiled. A
+    /// `hidden` loop doesn't count as enclosing the functions defined in it.
+    fn for_in(
+        &mut self,
+        token: &Rc<Token>,
+        definable: &Expr,
+        iterable: &Expr,
+        scope_token: &Rc<Token>,
+        hidden: bool,
+        body: impl FnOnce(&mut Self) -> Result<(), ()>,
+    ) -> Result<(), ()> {
+        self.expr(iterable)?;
+        self.chunk
+            .write_instr_no_operands(ITER, &Rc::new(iterable.span().synthetic()));
+        let start = self.ip();
+        self.start_loop(start, hidden);
+        let iterator_stopped = self.chunk.write_jump(FOR_ITER, &Rc::new(token.synthetic()));
+        self.start_scope();
+        self.definable(definable, false, None)?;
+        body(self)?;
+        self.end_scope(scope_token);
+        self.write_loop(&Rc::new(scope_token.synthetic()), start)?;
         self.settle_jump(iterator_stopped)?;
-        self.settle_breaks()
+        self.end_loop()?;
+        // `ITER` keeps the iterable under its iterator.
+        let synthetic = Rc::new(token.synthetic());
+        self.chunk.write_instr_no_operands(POP, &synthetic);
+        self.chunk.write_instr_no_operands(POP, &synthetic);
+        Ok(())
     }
 
-    fn break_stml(&mut self, token: Rc<Token>) -> Result<(), ()> {
-        if !self.in_loop() {
-            self.err(CompileError::OutsideLoopBreak(token));
-            return Err(());
+    /// Skips the elements `guard` rejects as if the body of the loop started with a conditional `واصل`.
+    fn guard(&mut self, guard: Option<(&Rc<Token>, &Expr)>) -> Result<(), ()> {
+        if let Some((when, condition)) = guard {
+            self.expr(condition)?;
+            let passed = self.chunk.write_jump(POP_JUMP_IF_TRUTHY, when);
+            self.continue_stml(when)?;
+            self.settle_jump(passed)?
         }
-        self.breaks.push(self.ip());
         Ok(())
     }
 
-    fn continue_stml(&mut self, token: Rc<Token>) -> Result<(), ()> {
-        if !self.in_loop() {
-            self.err(CompileError::OutsideLoopContinue(token));
-            return Err(());
+    /// Builds the list by appending to it in a for-in loop, it's kept in a hidden local meanwhile as the iterator is
+    /// above it on tmps.
+    fn comprehension(
+        &mut self,
+        token: &Rc<Token>,
+        element: &Expr,
+        for_token: &Rc<Token>,
+        definable: &Expr,
+        iterable: &Expr,
+        guard: Option<(&Rc<Token>, &Expr)>,
+    ) -> Result<(), ()> {
+        self.write_build(BUILD_LIST, token, 0)?;
+        self.start_scope();
+        self.push(token)?;
+        self.chunk.write_instr_no_operands(DEF_LOCAL, token);
+        let list = self.locals.borrow().len() - 1;
+        self.for_in(for_token, definable, iterable, token, true, |compiler| {
+            compiler.guard(guard)?;
+            compiler.write_path(list, &[])?;
+            compiler.expr(element)?;
+            let token = Rc::new(element.span().synthetic());
+            compiler.chunk.write_instr_no_operands(LIST_APPEND, &token);
+            compiler.chunk.write_instr_no_operands(POP, &token);
+            Ok(())
+        })?;
+        self.write_path(list, &[])?;
+        self.end_scope(token);
+        Ok(())
+    }
+
+    /// Checks that `token` (`إكسر` or `واصل`) is in a loop of this function.
+    fn check_in_loop(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        if self.in_loop() {
+            return Ok(());
+        }
+        self.err(if self.enclosing_loop {
+            CompileError::LoopAcrossFunction(Rc::clone(token))
+        } else if token.typ() == TokenType::Break {
+            CompileError::OutsideLoopBreak(Rc::clone(token))
+        } else {
+            CompileError::OutsideLoopContinue(Rc::clone(token))
+        });
+        Err(())
+    }
+
+    /// Pops the locals and the handlers of the innermost loop's body without ending their scopes, as the code after the
+    /// jump is still in them.
+    fn unwind_loop(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        let token = Rc::new(token.synthetic());
+        let (locals, handlers) = {
+            let innermost = self.loops.last().unwrap();
+            (innermost.locals, innermost.handlers)
+        };
+        // The handlers are popped first, as they point into the locals.
+        self.unwind_handlers(handlers, &token)?;
+        self.pop_locals_since(locals, &token);
+        Ok(())
+    }
+
+    /// Pops the handlers after the first `handlers` ones, closing the resources of the `مع` blocks being left once their
+    /// handlers are popped (so that failing to close them isn't caught by their own blocks).
+    fn unwind_handlers(&mut self, handlers: usize, token: &Rc<Token>) -> Result<(), ()> {
+        let resources = self
+            .resources
+            .iter()
+            .rev()
+            .take_while(|(_, before)| *before >= handlers)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut current = self.handlers;
+        for (resource, before) in resources {
+            for _ in before..current {
+                self.chunk.write_instr_no_operands(POP_HANDLER, token)
+            }
+            current = before;
+            self.close_resource(resource)?
+        }
+        for _ in handlers..current {
+            self.chunk.write_instr_no_operands(POP_HANDLER, token)
+        }
+        Ok(())
+    }
+
+    fn close_resource(&mut self, resource: usize) -> Result<(), ()> {
+        let token = Rc::clone(&self.locals.borrow().get(resource).token);
+        self.write_path(resource, &[])?;
+        self.chunk.write_instr_no_operands(CLOSE_RESOURCE, &token);
+        Ok(())
+    }
+
+    /// Pops the locals after the first `locals` ones without ending their scopes, for the code that jumps out of them.
+    fn pop_locals_since(&mut self, locals: usize, token: &Rc<Token>) {
+        let captured = {
+            let inner = self.locals.borrow();
+            (locals..inner.len())
+                .rev()
+                .map(|idx| inner.get(idx).captured)
+                .collect::<Vec<_>>()
+        };
+        for captured in captured {
+            self.chunk
+                .write_instr_no_operands(if captured { CLOSE_UPVALUE } else { POP_LOCAL }, token)
         }
-        self.write_loop(token, *self.loops.last().unwrap())
+    }
+
+    fn break_stml(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        self.check_in_loop(token)?;
+        self.unwind_loop(token)?;
+        let jump = self.chunk.write_jump(JUMP, token);
+        self.loops.last_mut().unwrap().breaks.push(jump);
+        Ok(())
+    }
+
+    fn continue_stml(&mut self, token: &Rc<Token>) -> Result<(), ()> {
+        self.check_in_loop(token)?;
+        self.unwind_loop(token)?;
+        let start = self.loops.last().unwrap().start;
+        self.write_loop(token, start)
     }
 
     fn try_catch_stml(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         body: &Stml,
-        _: Rc<Token>,
-        err: Rc<Token>,
+        catch_token: &Rc<Token>,
+        err: &Expr,
         catch_body: &Stml,
     ) -> Result<(), ()> {
         let caught = self.chunk.write_jump(APPEND_HANDLER, token);
-        self.stml(body)?;
+        self.handlers += 1;
+        let result = self.stml(body);
+        self.handlers -= 1;
+        result?;
+        self.chunk.write_instr_no_operands(POP_HANDLER, catch_token);
+        let succeeded = self.chunk.write_jump(JUMP, catch_token);
         self.settle_jump(caught)?;
         match catch_body {
             Stml::Block(token, stmls) => {
+                // The handler is popped before the catch block runs, so failing to destructure the error propagates outward.
                 self.start_scope();
-                self.define(err)?;
-                self.stmls(stmls);
-                self.end_scope(Rc::clone(token))
+                // The caught error itself is on top of its value, it's kept in a local named after the catch token
+                // (which can't be a variable name) for `ألقي_مجدداً`.
+                self.push(catch_token)?;
+                self.chunk.write_instr_no_operands(DEF_LOCAL, catch_token);
+                self.catches.push(self.locals.borrow().len() - 1);
+                let result = self.definable(err, false, None);
+                if result.is_ok() {
+                    self.stmls(stmls);
+                }
+                self.catches.pop();
+                result?;
+                self.end_scope(token)
             }
             _ => unreachable!(),
         }
+        self.settle_jump(succeeded)
+    }
+
+    /// Closes the resource however the block is left: after it ends, when it throws (rethrowing the error afterwards),
+    /// and before `إكسر`, `واصل`, and `أرجع` jump out of it.
+    fn with_stml(
+        &mut self,
+        token: &Rc<Token>,
+        name: &Rc<Token>,
+        value: &Expr,
+        body: &Stml,
+    ) -> Result<(), ()> {
+        self.expr(value)?;
+        self.start_scope();
+        self.define(name)?;
+        let resource = self.locals.borrow().len() - 1;
+        let caught = self.chunk.write_jump(APPEND_HANDLER, token);
+        self.resources.push((resource, self.handlers));
+        self.handlers += 1;
+        let result = self.stml(body);
+        self.handlers -= 1;
+        self.resources.pop();
+        result?;
+        let synthetic = Rc::new(token.synthetic());
+        self.chunk.write_instr_no_operands(POP_HANDLER, &synthetic);
+        self.close_resource(resource)?;
+        let succeeded = self.chunk.write_jump(JUMP, &synthetic);
+        self.settle_jump(caught)?;
+        // The caught error is on top of its value, it's kept in a local named after the token to be rethrown.
+        self.start_scope();
+        self.push(token)?;
+        self.chunk.write_instr_no_operands(DEF_LOCAL, token);
+        self.chunk.write_instr_no_operands(POP, &synthetic);
+        // Failing to close the resource here discards that error, so the one the block threw is still rethrown.
+        let failed = self.chunk.write_jump(APPEND_HANDLER, &synthetic);
+        self.close_resource(resource)?;
+        self.chunk.write_instr_no_operands(POP_HANDLER, &synthetic);
+        let closed = self.chunk.write_jump(JUMP, &synthetic);
+        self.settle_jump(failed)?;
+        self.chunk.write_instr_no_operands(POP, &synthetic);
+        self.chunk.write_instr_no_operands(POP, &synthetic);
+        self.settle_jump(closed)?;
+        let err = self.locals.borrow().len() - 1;
+        self.chunk.write_instr_idx(RETHROW, &synthetic, err)?;
+        self.end_scope(token);
+        self.settle_jump(succeeded)?;
+        self.end_scope(token);
+        Ok(())
+    }
+
+    /// Pushes the part of the local `subject` that `path` leads to.
+    fn write_path(&mut self, subject: usize, path: &[Step]) -> Result<(), ()> {
+        let token = Rc::new(self.locals.borrow().get(subject).token.synthetic());
+        self.write_instr_idx(GET_LOCAL, &token, subject);
+        for step in path {
+            match step {
+                Step::Index(token, idx) => {
+                    let token = Rc::new(token.synthetic());
+                    self.write_const(&token, Value::Number(*idx as f64))?;
+                    self.chunk.write_instr_no_operands(GET, &token)
+                }
+                Step::Key(key) => {
+                    let key = Rc::new(key.synthetic());
+                    self.write_instr_const(
+                        (GET_PROP8, GET_PROP16),
+                        &key,
+                        Value::from(key.lexeme()),
+                    )?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the part of the local `subject` that `path` leads to against `pattern`, the jumps taken when a check fails
+    /// are added to `fails` and leave nothing on the stacks. Lists and hash maps are checked before their elements, so
+    /// getting the elements can't fail.
+    fn pattern_checks(
+        &mut self,
+        pattern: &Pattern,
+        subject: usize,
+        path: &mut Vec<Step>,
+        fails: &mut Vec<usize>,
+    ) -> Result<(), ()> {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::Binding(_) => {}
+            Pattern::Literal(literal) => {
+                let token = literal.token();
+                self.write_path(subject, path)?;
+                self.literal(literal)?;
+                self.chunk.write_instr_no_operands(EQ, &token);
+                fails.push(self.chunk.write_jump(POP_JUMP_IF_FALSY, &token))
+            }
+            Pattern::List(token, patterns, rest) => {
+                self.write_path(subject, path)?;
+                let op_code = if rest.is_some() {
+                    MATCH_LIST_REST
+                } else {
+                    MATCH_LIST
+                };
+                self.write_build(op_code, token, patterns.len())?;
+                fails.push(self.chunk.write_jump(POP_JUMP_IF_FALSY, token));
+                for (idx, pattern) in patterns.iter().enumerate() {
+                    path.push(Step::Index(Rc::clone(token), idx));
+                    self.pattern_checks(pattern, subject, path, fails)?;
+                    path.pop();
+                }
+            }
+            Pattern::Object(token, props) => {
+                self.write_path(subject, path)?;
+                for (key, _) in props {
+                    self.write_string_of_ident(key)?
+                }
+                self.write_build(MATCH_HASH_MAP, token, props.len())?;
+                fails.push(self.chunk.write_jump(POP_JUMP_IF_FALSY, token));
+                for (key, pattern) in props {
+                    if let Some(pattern) = pattern {
+                        path.push(Step::Key(Rc::clone(key)));
+                        self.pattern_checks(pattern, subject, path, fails)?;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Defines the variables of `pattern` from TOT, which is known to match it.
+    fn pattern_bindings(&mut self, pattern: &Pattern) -> Result<(), ()> {
+        match pattern {
+            Pattern::Binding(token) => self.define(token)?,
+            Pattern::Wildcard(_) | Pattern::Literal(_) => self
+                .chunk
+                .write_instr_no_operands(POP, &Rc::new(pattern.token().synthetic())),
+            Pattern::List(token, patterns, rest) => {
+                self.write_list_unpack(token, patterns.len(), rest.is_some())?;
+                if let Some((_, name)) = rest {
+                    self.define(name)?
+                }
+                for pattern in patterns.iter().rev() {
+                    self.pattern_bindings(pattern)?
+                }
+            }
+            Pattern::Object(token, props) => {
+                for (key, _) in props {
+                    self.write_string_of_ident(key)?
+                }
+                self.write_hash_map_unpack(token, vec![false; props.len()])?;
+                for (key, pattern) in props {
+                    match pattern {
+                        Some(pattern) => self.pattern_bindings(pattern)?,
+                        None => self.define(key)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The value is kept in a local named after the `طابق` token (which can't be a variable name). Every arm checks it
+    /// against its pattern jumping to the next arm at the first failed check, then defines the pattern's variables in a
+    /// scope of their own that the guard and the body are in, a failed guard pops them before jumping.
+    fn match_stml(&mut self, token: &Rc<Token>, value: &Expr, arms: &[Arm]) -> Result<(), ()> {
+        if !arms
+            .iter()
+            .any(|(pattern, guard, ..)| pattern.is_irrefutable() && guard.is_none())
+        {
+            self.warn(CompileWarning::NonExhaustiveMatch(Rc::clone(token)))
+        }
+        self.expr(value)?;
+        self.start_scope();
+        self.push(token)?;
+        self.chunk.write_instr_no_operands(DEF_LOCAL, token);
+        let subject = self.locals.borrow().len() - 1;
+        let mut end = vec![];
+        for (pattern, guard, arrow, body) in arms {
+            let mut fails = vec![];
+            self.pattern_checks(pattern, subject, &mut vec![], &mut fails)?;
+            let locals = self.locals.borrow().len();
+            self.start_scope();
+            if has_bindings(pattern) {
+                self.write_path(subject, &[])?;
+                self.pattern_bindings(pattern)?
+            }
+            if let Some((token, condition)) = guard {
+                self.expr(condition)?;
+                let passed = self.chunk.write_jump(POP_JUMP_IF_TRUTHY, token);
+                let token = Rc::new(token.synthetic());
+                self.pop_locals_since(locals, &token);
+                fails.push(self.chunk.write_jump(JUMP, &token));
+                self.settle_jump(passed)?
+            }
+            self.stml(body)?;
+            self.end_scope(arrow);
+            end.push(self.chunk.write_jump(JUMP, &Rc::new(arrow.synthetic())));
+            for fail in fails {
+                self.settle_jump(fail)?
+            }
+        }
+        for jump in end {
+            self.settle_jump(jump)?
+        }
+        self.end_scope(token);
         Ok(())
     }
 
     fn import_stml(
         &mut self,
-        token: Rc<Token>,
+        token: &Rc<Token>,
         definable: &Expr,
-        _: Rc<Token>,
-        path: Rc<Token>,
+        _: &Rc<Token>,
+        path: &Rc<Token>,
     ) -> Result<(), ()> {
         if !self.can_export() {
-            self.err(CompileError::InvalidImportUsage(token));
+            self.err(CompileError::InvalidImportUsage(Rc::clone(token)));
             return Err(());
         }
-        let path = {
-            let tmp = self.quoted_string(path)?;
-            match token.path() {
-                Some(path) => path.parent().unwrap_or(&Path::new("")).join(tmp),
-                None => PathBuf::from(tmp),
-            }
-        };
+        let specifier = unquote(Rc::clone(path)).map_err(|err| self.err(err))?;
+        if stdlib::is_reserved(&specifier) {
+            let chunk = stdlib::module(&specifier, self.debug_info)
+                .ok_or_else(|| self.err(CompileError::UnknownStdModule(Rc::clone(path))))?;
+            self.write_closure(
+                token,
+                value::Function::new(None, chunk, Arity::default(), vec![], 0, false, 0),
+                vec![],
+            )?;
+            self.write_call_unchecked(token, 0);
+            return self.definable(definable, false, None);
+        }
+        let path = import_path(Rc::clone(path)).map_err(|err| self.err(err))?;
         let source = fs::read_to_string(&path)
-            .map_err(|err| self.err(CompileError::Io(Rc::clone(&token), Rc::new(err))))?;
+            .map_err(|err| self.err(CompileError::Io(Rc::clone(token), Rc::new(err), vec![])))?;
+        let import = token;
         let tokens = Lexer::new(source, Some(&path)).lex();
         let token = Rc::clone(tokens.last().unwrap());
-        let ast = Parser::new(tokens)
-            .parse()
-            .map_err(|errors| self.err(CompileError::ModuleParser(Rc::clone(&token), errors)))?;
-        let chunk = Compiler::new(CompilerType::Module, &ast, Rc::clone(&token))
-            .compile()
-            .map_err(|errors| {
-                for err in errors {
-                    self.err(err)
-                }
-            })?;
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().map_err(|errors| {
+            self.err(CompileError::ModuleParser(
+                Rc::clone(&token),
+                errors,
+                vec![Rc::clone(import)],
+            ))
+        })?;
+        self.warnings
+            .extend(parser.warnings().iter().cloned().map(CompileWarning::Parse));
+        let mut compiler = Compiler::new(CompilerType::Module, &ast, Rc::clone(&token))
+            .debug_info(self.debug_info);
+        let chunk = compiler.compile();
+        self.warnings.append(&mut compiler.warnings);
+        let chunk = chunk.map_err(|errors| {
+            for err in errors {
+                self.err(err.imported_from(Rc::clone(import)))
+            }
+        })?;
         self.write_closure(
-            Rc::clone(&token),
-            value::Function::new(None, chunk, Arity::default(), vec![], 0),
+            &token,
+            value::Function::new(None, chunk, Arity::default(), vec![], 0, false, 0),
             vec![],
         )?;
-        self.write_call_unchecked(token, 0);
-        self.definable(definable, false)?;
+        self.write_call_unchecked(&token, 0);
+        self.definable(definable, false, None)?;
+        Ok(())
+    }
+
+    /// Defines `path::FILE_VAR` and `path::DIRECTORY_VAR` as locals of a module, which shadow the globals of the script so
+    /// that it sees its own path.
+    fn define_location(&mut self) -> Result<(), ()> {
+        let path = self.token.path().cloned();
+        for (name, value) in path::location(path.as_deref()) {
+            let token = Token::new(
+                TokenType::Identifier,
+                Rc::new(name.to_owned()),
+                path.clone().map(Rc::new),
+                0,
+                name.len(),
+            );
+            let token = Rc::new(token.synthetic());
+            self.write_const(&token, value)?;
+            self.define(&token)?
+        }
         Ok(())
     }
 
     fn stml(&mut self, stml: &Stml) -> Result<(), ()> {
         match stml {
             Stml::VarDecl(export_token, token, decls) => {
-                self.var_decl(export_token, Rc::clone(token), decls)?
+                self.var_decl(export_token, token, decls)?
             }
             Stml::FunctionDecl(export_token, token, name, required, optional, variadic, body) => {
                 self.function_decl(
                     export_token,
-                    Rc::clone(token),
-                    Rc::clone(name),
+                    token,
+                    name,
                     required,
                     optional,
                     variadic,
@@ -1064,45 +1678,40 @@ impl<'a> Compiler<'a> {
                 )?
             }
             Stml::Expr(expr) => {
+                if self.typ != CompilerType::Repl && expr.is_pure() {
+                    self.warn(CompileWarning::UnusedValue(expr.span()))
+                }
                 self.expr(expr)?;
-                self.chunk.write_instr_no_operands(POP, expr.token())
+                self.chunk
+                    .write_instr_no_operands(POP, &Rc::new(expr.token().synthetic()))
             }
             Stml::Block(token, stmls) => {
                 self.start_scope();
                 self.stmls(stmls);
-                self.end_scope(Rc::clone(token));
+                self.end_scope(token);
             }
             Stml::If(token, condition, body, elseifs, else_) => {
                 self.if_stml(token, condition, body, elseifs, else_)?
             }
-            Stml::Return(token, value) => self.return_stml(Rc::clone(token), value)?,
-            Stml::Throw(token, value) => self.throw_stml(Rc::clone(token), value)?,
-            Stml::While(token, condition, body) => {
-                self.while_stml(Rc::clone(token), condition, body)?
+            Stml::Return(token, value) => self.return_stml(token, value)?,
+            Stml::Throw(token, value) => self.throw_stml(token, value)?,
+            Stml::Rethrow(token) => self.rethrow_stml(token)?,
+            Stml::Yield(token, value) => self.yield_stml(token, value)?,
+            Stml::While(token, condition, body) => self.while_stml(token, condition, body)?,
+            Stml::Loop(token, body) => self.loop_stml(token, body)?,
+            Stml::ForIn(token, definable, in_token, iterable, guard, body) => {
+                self.for_in_stml(token, definable, in_token, iterable, guard, body)?
             }
-            Stml::Loop(token, body) => self.loop_stml(Rc::clone(token), body)?,
-            Stml::ForIn(token, definable, in_token, iterable, body) => self.for_in_stml(
-                Rc::clone(token),
-                definable,
-                Rc::clone(in_token),
-                iterable,
-                body,
-            )?,
-            Stml::Break(token) => self.break_stml(Rc::clone(token))?,
-            Stml::Continue(token) => self.continue_stml(Rc::clone(token))?,
-            Stml::TryCatch(token, body, catch_token, err, catch_body) => self.try_catch_stml(
-                Rc::clone(token),
-                body,
-                Rc::clone(catch_token),
-                Rc::clone(err),
-                catch_body,
-            )?,
-            Stml::Import(token, definable, from_token, path) => self.import_stml(
-                Rc::clone(token),
-                definable,
-                Rc::clone(from_token),
-                Rc::clone(path),
-            )?,
+            Stml::Break(token) => self.break_stml(token)?,
+            Stml::Continue(token) => self.continue_stml(token)?,
+            Stml::TryCatch(token, body, catch_token, err, catch_body) => {
+                self.try_catch_stml(token, body, catch_token, err, catch_body)?
+            }
+            Stml::Import(token, definable, from_token, path) => {
+                self.import_stml(token, definable, from_token, path)?
+            }
+            Stml::With(token, name, _, value, body) => self.with_stml(token, name, value, body)?,
+            Stml::Match(token, value, arms) => self.match_stml(token, value, arms)?,
         }
         Ok(())
     }
@@ -1116,45 +1725,50 @@ impl<'a> Compiler<'a> {
 
     #[allow(unused_must_use)]
     pub fn compile(&mut self) -> Result<Chunk, Vec<CompileError>> {
-        if cfg!(feature = "verbose") && self.typ == CompilerType::Script {
+        if cfg!(feature = "verbose") && self.is_script() {
             println!("[COMPILER] started")
         }
+        self.chunk.set_path(self.token.path().cloned());
+        if self.typ == CompilerType::Module {
+            self.define_location();
+        }
         self.stmls(self.ast);
         match self.typ {
-            CompilerType::Script => {}
+            CompilerType::Script | CompilerType::Repl => {}
             CompilerType::Function => {
-                self.write_const(Rc::clone(&self.token), Value::Nil);
-                self.chunk
-                    .write_instr_no_operands(RET, Rc::clone(&self.token));
+                let token = Rc::new(self.token.synthetic());
+                self.write_const(&token, Value::Nil);
+                self.chunk.write_instr_no_operands(RET, &token);
             }
             CompilerType::Module => {
+                let token = Rc::new(self.token.synthetic());
                 let locals = self.locals.borrow().clone();
                 let mut size = 0;
                 for idx in 0..locals.len() {
                     let local = locals.get(idx);
                     if local.exported {
-                        self.write_const(
-                            Rc::clone(&local.token),
-                            Value::from(local.token.lexeme().clone()),
-                        );
-                        self.write_instr_idx(GET_LOCAL, Rc::clone(&local.token), idx);
+                        let token = Rc::new(local.token.synthetic());
+                        self.write_const(&token, Value::from(local.token.lexeme()));
+                        self.write_instr_idx(GET_LOCAL, &token, idx);
                         size += 1;
                     }
                 }
                 self.chunk
-                    .write_build(BUILD_HASH_MAP, Rc::clone(&self.token), size)
+                    .write_build(BUILD_HASH_MAP, &token, size)
                     .map_err(|_| CompileError::TooManyExports(Rc::clone(&self.token))); // ?
-                self.chunk
-                    .write_instr_no_operands(RET, Rc::clone(&self.token))
+                self.chunk.write_instr_no_operands(RET, &token)
             }
         }
+        for idx in self.debug_locals.drain(..).flatten() {
+            self.chunk.end_local(idx)
+        }
         if self.errors.len() > 0 {
-            if cfg!(feature = "verbose") && self.typ == CompilerType::Script {
+            if cfg!(feature = "verbose") && self.is_script() {
                 println!("[COMPILER] failed")
             }
             Err(self.errors.clone())
         } else {
-            if cfg!(feature = "verbose") && self.typ == CompilerType::Script {
+            if cfg!(feature = "verbose") && self.is_script() {
                 println!("[COMPILER] succeeded");
                 println!("{:?}", self.chunk)
             }
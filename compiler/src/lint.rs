@@ -0,0 +1,435 @@
+//! Flagging code that's valid but likely a mistake, which the compiler accepts silently.
+//!
+//! The scopes mirror the compiler's: the params of a function are in a scope of their own that encloses its body, and
+//! the definables of `لكل` and `أمسك` are in the scope of their block.
+
+use super::error::CompileWarning;
+use lexer::token::{Token, TokenInside, TokenType};
+use parser::ast::visit::{self, Visitor};
+use parser::ast::{Expr, Literal, Pattern, Stml};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// Local variables declared with `متغير` or by `لكل` that are never read, names starting with "_" are exempt.
+    UnusedVariable,
+    /// Statements after `أرجع`, `إكسر`, `واصل`, `ألقي`, or `ألقي_مجدداً` in the same block.
+    UnreachableCode,
+    /// Conditions that are assignments, unless they're wrapped in parentheses of their own.
+    AssignmentInCondition,
+    /// Declarations whose name is already declared in an enclosing scope.
+    Shadowing,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [
+        Self::UnusedVariable,
+        Self::UnreachableCode,
+        Self::AssignmentInCondition,
+        Self::Shadowing,
+    ];
+
+    /// What it's called in `--اسمح=<name>`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UnusedVariable => "متغير-غير-مستخدم",
+            Self::UnreachableCode => "شيفرة-لا-تصل",
+            Self::AssignmentInCondition => "إسناد-في-شرط",
+            Self::Shadowing => "تظليل",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+struct Variable {
+    token: Rc<Token>,
+    used: bool,
+    /// Whether it's reported if it's never used.
+    checked: bool,
+}
+
+struct Linter<'a> {
+    tokens: &'a [Rc<Token>],
+    allowed: &'a [Lint],
+    /// The global scope comes first and is never checked for unused variables, since other modules can use them.
+    scopes: Vec<Vec<Variable>>,
+    warnings: Vec<CompileWarning>,
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, warning: CompileWarning) {
+        if warning
+            .lint()
+            .is_none_or(|lint| !self.allowed.contains(&lint))
+        {
+            self.warnings.push(warning)
+        }
+    }
+
+    fn start_scope(&mut self) {
+        self.scopes.push(vec![])
+    }
+
+    fn end_scope(&mut self) {
+        for variable in self.scopes.pop().unwrap() {
+            if variable.checked && !variable.used && !variable.token.lexeme().starts_with('_') {
+                self.warn(CompileWarning::UnusedVariable(variable.token))
+            }
+        }
+    }
+
+    fn declare(&mut self, token: &Rc<Token>, checked: bool) {
+        if token.lexeme() == "_" {
+            return;
+        }
+        let (scope, enclosing) = self.scopes.split_last().unwrap();
+        // Declaring the same name twice in a scope is a compile error, unless it's global.
+        if scope
+            .iter()
+            .all(|variable| variable.token.lexeme() != token.lexeme())
+        {
+            let shadowed = enclosing
+                .iter()
+                .rev()
+                .flatten()
+                .find(|variable| variable.token.lexeme() == token.lexeme());
+            if let Some(shadowed) = shadowed {
+                let warning =
+                    CompileWarning::Shadowing(Rc::clone(token), Rc::clone(&shadowed.token));
+                self.warn(warning)
+            }
+        }
+        self.scopes.last_mut().unwrap().push(Variable {
+            token: Rc::clone(token),
+            used: false,
+            checked,
+        })
+    }
+
+    /// Visits the defaults before declaring the names, since they're evaluated first.
+    fn definable(&mut self, definable: &Expr, checked: bool) {
+        match definable {
+            Expr::Variable(token) => self.declare(token, checked),
+            Expr::Literal(Literal::List(_, exprs)) => {
+                for definable in exprs {
+                    self.definable(definable, checked)
+                }
+            }
+            Expr::Literal(Literal::Object(_, props)) => {
+                for (key, value, default) in props {
+                    if let Some((_, default)) = default {
+                        self.visit_expr(default)
+                    }
+                    match value {
+                        Some(definable) => self.definable(definable, checked),
+                        None => self.declare(key, checked),
+                    }
+                }
+            }
+            // Invalid, the compiler reports it.
+            expr => self.visit_expr(expr),
+        }
+    }
+
+    /// Like `definable` but for the left-hand side of `=`, whose variables are written not read.
+    fn settable(&mut self, settable: &Expr) {
+        match settable {
+            Expr::Variable(_) => {}
+            Expr::Literal(Literal::List(_, exprs)) => {
+                for settable in exprs {
+                    self.settable(settable)
+                }
+            }
+            Expr::Literal(Literal::Object(_, props)) => {
+                for (_, value, default) in props {
+                    if let Some((_, default)) = default {
+                        self.visit_expr(default)
+                    }
+                    if let Some(settable) = value {
+                        self.settable(settable)
+                    }
+                }
+            }
+            expr => self.visit_expr(expr),
+        }
+    }
+
+    /// Declares the variables `pattern` binds.
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard(_) | Pattern::Literal(_) => {}
+            Pattern::Binding(token) => self.declare(token, true),
+            Pattern::List(_, patterns, rest) => {
+                for pattern in patterns {
+                    self.pattern(pattern)
+                }
+                if let Some((_, name)) = rest {
+                    self.declare(name, true)
+                }
+            }
+            Pattern::Object(_, props) => {
+                for (key, pattern) in props {
+                    match pattern {
+                        Some(pattern) => self.pattern(pattern),
+                        None => self.declare(key, true),
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve(&mut self, token: &Rc<Token>) {
+        let variable = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flatten()
+            .find(|variable| variable.token.lexeme() == token.lexeme());
+        if let Some(variable) = variable {
+            variable.used = true
+        }
+    }
+
+    fn function(
+        &mut self,
+        required: &[Expr],
+        optional: &[(Expr, Expr)],
+        variadic: &Option<(Rc<Token>, Box<Expr>)>,
+        body: &Stml,
+    ) {
+        self.start_scope();
+        for param in required {
+            self.definable(param, false)
+        }
+        for (param, default) in optional {
+            self.visit_expr(default);
+            self.definable(param, false)
+        }
+        if let Some((_, param)) = variadic {
+            self.definable(param, false)
+        }
+        self.visit_stml(body);
+        self.end_scope()
+    }
+
+    /// Declares `definable` in the scope of `block` before visiting its statements.
+    fn block_with(&mut self, definable: &Expr, checked: bool, block: &Stml) {
+        match block {
+            Stml::Block(_, stmls) => {
+                self.start_scope();
+                self.definable(definable, checked);
+                self.stmls(stmls);
+                self.end_scope()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn stmls(&mut self, stmls: &[Stml]) {
+        // Only the first unreachable statement of a block is reported.
+        let mut terminator: Option<Rc<Token>> = None;
+        let mut reported = false;
+        for stml in stmls {
+            if let (Some(token), false) = (&terminator, reported) {
+                self.warn(CompileWarning::UnreachableCode(
+                    stml.span(),
+                    Rc::clone(token),
+                ));
+                reported = true
+            }
+            self.visit_stml(stml);
+            if let (
+                None,
+                Stml::Return(token, _)
+                | Stml::Break(token)
+                | Stml::Continue(token)
+                | Stml::Throw(token, _)
+                | Stml::Rethrow(token),
+            ) = (&terminator, stml)
+            {
+                terminator = Some(Rc::clone(token))
+            }
+        }
+    }
+
+    /// The tokens are searched for the parentheses since the AST doesn't keep them, the ones of the statement itself
+    /// don't count.
+    fn condition(&mut self, condition: &Expr) {
+        let Expr::Binary(_, op, _) = condition else {
+            return;
+        };
+        if !matches!(
+            op.typ(),
+            TokenType::Equal
+                | TokenType::PlusEqual
+                | TokenType::MinusEqual
+                | TokenType::StarEqual
+                | TokenType::SlashEqual
+                | TokenType::PercentEqual
+        ) {
+            return;
+        }
+        let span = condition.span();
+        let idx = self
+            .tokens
+            .partition_point(|token| token.start() < span.start());
+        let parens = self.tokens[..idx]
+            .iter()
+            .rev()
+            .filter(|token| token.typ() != TokenType::NewLine)
+            .take_while(|token| token.typ() == TokenType::OParen)
+            .count();
+        if parens < 2 {
+            self.warn(CompileWarning::AssignmentInCondition(span))
+        }
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_stml(&mut self, stml: &Stml) {
+        match stml {
+            Stml::Block(_, stmls) => {
+                self.start_scope();
+                self.stmls(stmls);
+                self.end_scope()
+            }
+            Stml::FunctionDecl(_, _, name, required, optional, variadic, body) => {
+                self.declare(name, false);
+                self.function(required, optional, variadic, body)
+            }
+            Stml::VarDecl(_, _, decls) => {
+                for (definable, init) in decls {
+                    if let Some(init) = init {
+                        self.visit_expr(init)
+                    }
+                    self.definable(definable, true)
+                }
+            }
+            Stml::TryCatch(_, body, _, err, catch_body) => {
+                self.visit_stml(body);
+                self.block_with(err, false, catch_body)
+            }
+            Stml::If(_, condition, body, elseifs, else_) => {
+                self.condition(condition);
+                self.visit_expr(condition);
+                self.visit_stml(body);
+                for (_, condition, body) in elseifs {
+                    self.condition(condition);
+                    self.visit_expr(condition);
+                    self.visit_stml(body);
+                }
+                if let Some((_, body)) = else_ {
+                    self.visit_stml(body)
+                }
+            }
+            Stml::While(_, condition, body) => {
+                self.condition(condition);
+                self.visit_expr(condition);
+                self.visit_stml(body)
+            }
+            Stml::Import(_, definable, ..) => self.definable(definable, false),
+            Stml::ForIn(_, definable, _, iterable, guard, body) => {
+                self.visit_expr(iterable);
+                match (guard, body.as_ref()) {
+                    (Some((_, condition)), Stml::Block(_, stmls)) => {
+                        self.start_scope();
+                        self.definable(definable, true);
+                        self.condition(condition);
+                        self.visit_expr(condition);
+                        self.stmls(stmls);
+                        self.end_scope()
+                    }
+                    _ => self.block_with(definable, true, body),
+                }
+            }
+            // The resource is used by closing it, so it's not reported when the block doesn't use it.
+            Stml::With(_, name, _, value, body) => {
+                self.visit_expr(value);
+                self.start_scope();
+                self.declare(name, false);
+                self.visit_stml(body);
+                self.end_scope()
+            }
+            Stml::Match(_, value, arms) => {
+                self.visit_expr(value);
+                for (pattern, guard, _, body) in arms {
+                    self.start_scope();
+                    self.pattern(pattern);
+                    if let Some((_, condition)) = guard {
+                        self.visit_expr(condition)
+                    }
+                    self.visit_stml(body);
+                    self.end_scope()
+                }
+            }
+            stml => visit::walk_stml(self, stml),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(token) => self.resolve(token),
+            Expr::Binary(lhs, op, rhs) if op.typ() == TokenType::Equal => {
+                self.visit_expr(rhs);
+                self.settable(lhs)
+            }
+            expr => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Lambda(_, required, optional, variadic, body) => {
+                self.function(required, optional, variadic, body)
+            }
+            Literal::Comprehension(_, element, _, definable, _, iterable, guard) => {
+                self.visit_expr(iterable);
+                self.start_scope();
+                self.definable(definable, true);
+                if let Some((_, condition)) = guard {
+                    self.condition(condition);
+                    self.visit_expr(condition)
+                }
+                self.visit_expr(element);
+                self.end_scope()
+            }
+            // `{ أ }` reads the variable `أ`.
+            Literal::Object(_, props) => {
+                for (key, value, default) in props {
+                    match (value, default) {
+                        (None, None) => self.resolve(key),
+                        (value, default) => {
+                            if let Some(value) = value {
+                                self.visit_expr(value)
+                            }
+                            if let Some((_, default)) = default {
+                                self.visit_expr(default)
+                            }
+                        }
+                    }
+                }
+            }
+            literal => visit::walk_literal(self, literal),
+        }
+    }
+}
+
+/// Returns the warnings of the lints that aren't allowed in the order of their positions, `tokens` are the ones `ast` was
+/// parsed from.
+pub fn lint(ast: &[Stml], tokens: &[Rc<Token>], allowed: &[Lint]) -> Vec<CompileWarning> {
+    let mut linter = Linter {
+        tokens,
+        allowed,
+        scopes: vec![vec![]],
+        warnings: vec![],
+    };
+    linter.stmls(ast);
+    // Unused variables are only known at the end of their scopes.
+    linter
+        .warnings
+        .sort_by_key(|warning| warning.token().start());
+    linter.warnings
+}
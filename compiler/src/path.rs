@@ -0,0 +1,264 @@
+//! Resolving the paths programs refer to (imports and the files natives access) relative to the file they're written
+//! in. The base is the path of that file, `None` (e.g. in the REPL) resolves relative to the working directory like
+//! `STDIN_PATH` and `INLINE_PATH` do.
+
+use super::{chunk::value::Value, INLINE_PATH, STDIN_PATH};
+use lexer::token::display_path;
+use std::{
+    env, fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The globals the VM defines for the script, and the locals every module defines for itself, that hold the path of the
+/// file and of its directory.
+pub const FILE_VAR: &str = "هذا_الملف";
+pub const DIRECTORY_VAR: &str = "هذا_المجلد";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathError {
+    Empty,
+    /// The specifier starts with `~` but the home directory isn't known.
+    UnknownHome,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "المسار فارغ"),
+            Self::UnknownHome => write!(f, "تعذر معرفة مجلد المستخدم الذي يشير إليه \"~\""),
+        }
+    }
+}
+
+/// Like `resolve_runtime` but fails instead of falling back, as a wrong module is worse than a missing one.
+pub fn resolve_import(base: Option<&Path>, spec: &str) -> Result<PathBuf, PathError> {
+    if spec.is_empty() {
+        return Err(PathError::Empty);
+    }
+    let spec = expand_home(spec).ok_or(PathError::UnknownHome)?;
+    Ok(normalize(&join(base, &spec)))
+}
+
+/// Resolves `spec` relative to the directory of `base` unless it's absolute, `~` is left as is when the home directory
+/// isn't known.
+pub fn resolve_runtime(base: Option<&Path>, spec: &str) -> PathBuf {
+    let spec = expand_home(spec).unwrap_or_else(|| PathBuf::from(spec));
+    normalize(&join(base, &spec))
+}
+
+fn join(base: Option<&Path>, spec: &Path) -> PathBuf {
+    base.and_then(Path::parent)
+        .unwrap_or(Path::new(""))
+        .join(spec)
+}
+
+/// Replaces a leading `~` component with the home directory, `None` if it isn't known.
+fn expand_home(spec: &str) -> Option<PathBuf> {
+    let path = Path::new(spec);
+    match path.strip_prefix("~") {
+        Ok(rest) => {
+            let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+            Some(PathBuf::from(home).join(rest))
+        }
+        Err(_) => Some(path.to_owned()),
+    }
+}
+
+/// Removes `.` components and the directories `..` components leave without looking at the file system, so symbolic
+/// links aren't followed. Leading `..`s of relative paths are kept, and the ones after the root are dropped.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
+}
+
+/// The values of `FILE_VAR` and `DIRECTORY_VAR` for a file at `path`, both `Nil` when it isn't a file (e.g. in the REPL
+/// or when reading the standard input).
+pub fn location(path: Option<&Path>) -> [(&'static str, Value); 2] {
+    match path {
+        Some(path) if path != Path::new(STDIN_PATH) && path != Path::new(INLINE_PATH) => {
+            let directory = normalize(path.parent().unwrap_or(Path::new("")));
+            [
+                (FILE_VAR, Value::from(display_path(path))),
+                (DIRECTORY_VAR, Value::from(display_path(&directory))),
+            ]
+        }
+        _ => [(FILE_VAR, Value::Nil), (DIRECTORY_VAR, Value::Nil)],
+    }
+}
+
+/// Identifies files by their canonical paths so that different ways of referring to a file are the same, paths that
+/// can't be canonicalized (e.g. missing files) are only normalized.
+pub fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| normalize(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASES: [Option<&str>; 5] = [
+        None,
+        Some("رئيسي.قتام"),
+        Some("مجلد/رئيسي.قتام"),
+        Some("/جذر/مجلد/رئيسي.قتام"),
+        Some("../أعلى/رئيسي.قتام"),
+    ];
+
+    /// The specifiers along with what each of them resolves to relative to each of `BASES`.
+    const MATRIX: [(&str, [&str; 5]); 7] = [
+        (
+            "وحدة.قتام",
+            [
+                "وحدة.قتام",
+                "وحدة.قتام",
+                "مجلد/وحدة.قتام",
+                "/جذر/مجلد/وحدة.قتام",
+                "../أعلى/وحدة.قتام",
+            ],
+        ),
+        (
+            "./وحدة.قتام",
+            [
+                "وحدة.قتام",
+                "وحدة.قتام",
+                "مجلد/وحدة.قتام",
+                "/جذر/مجلد/وحدة.قتام",
+                "../أعلى/وحدة.قتام",
+            ],
+        ),
+        (
+            "../وحدة.قتام",
+            [
+                "../وحدة.قتام",
+                "../وحدة.قتام",
+                "وحدة.قتام",
+                "/جذر/وحدة.قتام",
+                "../وحدة.قتام",
+            ],
+        ),
+        (
+            "../../../وحدة.قتام",
+            [
+                "../../../وحدة.قتام",
+                "../../../وحدة.قتام",
+                "../../وحدة.قتام",
+                "/وحدة.قتام",
+                "../../../وحدة.قتام",
+            ],
+        ),
+        (
+            "فرعي/./../وحدة.قتام",
+            [
+                "وحدة.قتام",
+                "وحدة.قتام",
+                "مجلد/وحدة.قتام",
+                "/جذر/مجلد/وحدة.قتام",
+                "../أعلى/وحدة.قتام",
+            ],
+        ),
+        (
+            "/مكتبة/وحدة.قتام",
+            [
+                "/مكتبة/وحدة.قتام",
+                "/مكتبة/وحدة.قتام",
+                "/مكتبة/وحدة.قتام",
+                "/مكتبة/وحدة.قتام",
+                "/مكتبة/وحدة.قتام",
+            ],
+        ),
+        // The extension isn't added, a file without one can be imported.
+        (
+            "وحدة",
+            [
+                "وحدة",
+                "وحدة",
+                "مجلد/وحدة",
+                "/جذر/مجلد/وحدة",
+                "../أعلى/وحدة",
+            ],
+        ),
+    ];
+
+    #[test]
+    fn resolves_every_specifier_relative_to_every_base() {
+        for (spec, expected) in MATRIX {
+            for (base, expected) in BASES.into_iter().zip(expected) {
+                let base = base.map(Path::new);
+                assert_eq!(
+                    resolve_import(base, spec),
+                    Ok(PathBuf::from(expected)),
+                    "{spec} from {base:?}"
+                );
+                assert_eq!(
+                    resolve_runtime(base, spec),
+                    PathBuf::from(expected),
+                    "{spec} from {base:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn expands_the_home_directory() {
+        let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) else {
+            return;
+        };
+        let expected = normalize(&PathBuf::from(home).join("وحدة.قتام"));
+        for base in BASES {
+            let base = base.map(Path::new);
+            assert_eq!(resolve_import(base, "~/وحدة.قتام"), Ok(expected.clone()));
+            assert_eq!(resolve_runtime(base, "~/وحدة.قتام"), expected);
+        }
+        // Only as a whole component.
+        assert_eq!(
+            resolve_import(None, "~وحدة.قتام"),
+            Ok(PathBuf::from("~وحدة.قتام"))
+        );
+    }
+
+    #[test]
+    fn refuses_empty_imports() {
+        for base in BASES {
+            assert_eq!(
+                resolve_import(base.map(Path::new), ""),
+                Err(PathError::Empty)
+            );
+        }
+    }
+
+    #[test]
+    fn normalizes_to_the_current_directory_at_least() {
+        assert_eq!(normalize(Path::new("")), PathBuf::from("."));
+        assert_eq!(normalize(Path::new("مجلد/..")), PathBuf::from("."));
+        assert_eq!(normalize(Path::new("/..")), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn locates_only_files() {
+        for path in [None, Some(STDIN_PATH), Some(INLINE_PATH)] {
+            let [(_, file), (_, directory)] = location(path.map(Path::new));
+            assert!(matches!((file, directory), (Value::Nil, Value::Nil)));
+        }
+        let [(file_var, file), (directory_var, directory)] =
+            location(Some(Path::new("مجلد/./رئيسي.قتام")));
+        assert_eq!((file_var, directory_var), (FILE_VAR, DIRECTORY_VAR));
+        assert_eq!(file.to_string(), "مجلد/./رئيسي.قتام");
+        assert_eq!(directory.to_string(), "مجلد");
+    }
+}
@@ -0,0 +1,87 @@
+//! The modules of the standard library that are written in Qatam, which are embedded in the binary and imported by
+//! their names (e.g. `"قياسي/قوائم"`) instead of paths.
+//!
+//! Every import starting with `PREFIX` is one of them, so files (e.g. a `قياسي` directory next to the program) can't
+//! shadow them.
+
+use super::chunk::Chunk;
+use super::{Compiler, CompilerType};
+use lexer::Lexer;
+use parser::Parser;
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, rc::Rc};
+
+pub const PREFIX: &str = "قياسي/";
+
+const MODULES: [(&str, &str); 3] = [
+    ("قياسي/قوائم", include_str!("../stdlib/قوائم.قتام")),
+    ("قياسي/نصوص", include_str!("../stdlib/نصوص.قتام")),
+    ("قياسي/رياضيات", include_str!("../stdlib/رياضيات.قتام")),
+];
+
+thread_local! {
+    /// The compiled modules by their names and whether they have debug info, since they can't change.
+    static CACHE: RefCell<HashMap<(&'static str, bool), Chunk>> = RefCell::new(HashMap::new());
+}
+
+pub fn is_reserved(specifier: &str) -> bool {
+    specifier.starts_with(PREFIX)
+}
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    MODULES.iter().map(|(name, _)| *name)
+}
+
+/// Compiles the module the first time it's imported, `None` if there's no module called `name`.
+///
+/// Panics if it doesn't compile, as that's a bug in the interpreter not in the program importing it.
+pub fn module(name: &str, debug_info: bool) -> Option<Chunk> {
+    let (name, source) = MODULES.iter().find(|(other, _)| *other == name)?;
+    let cached = CACHE.with(|cache| cache.borrow().get(&(*name, debug_info)).cloned());
+    if cached.is_some() {
+        return cached;
+    }
+    let tokens = Lexer::new(source.to_string(), Some(&PathBuf::from(name))).lex();
+    let token = Rc::clone(tokens.last().unwrap());
+    let ast = Parser::new(tokens).parse().unwrap_or_else(|errors| {
+        let errors = errors.iter().map(|err| err.to_string()).collect::<String>();
+        panic!("خطأ داخلي: تعذر تحليل الوحدة القياسية \"{name}\"\n{errors}")
+    });
+    let chunk = Compiler::new(CompilerType::Module, &ast, token)
+        .debug_info(debug_info)
+        .compile()
+        .unwrap_or_else(|errors| {
+            let errors = errors
+                .iter()
+                .map(|err| format!("{err}\n"))
+                .collect::<String>();
+            panic!("خطأ داخلي: تعذرت ترجمة الوحدة القياسية \"{name}\"\n{errors}")
+        });
+    CACHE.with(|cache| {
+        cache
+            .borrow_mut()
+            .insert((*name, debug_info), chunk.clone())
+    });
+    Some(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_module_compiles() {
+        for name in names() {
+            assert!(is_reserved(name));
+            for debug_info in [false, true] {
+                assert!(module(name, debug_info).is_some(), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_embedded_modules_exist() {
+        assert!(module("قياسي/غير-موجودة", false).is_none());
+        assert!(module("قوائم", false).is_none());
+        assert!(!is_reserved("./قياسي/قوائم"));
+    }
+}
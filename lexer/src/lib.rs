@@ -1,6 +1,6 @@
 pub mod token;
 
-use std::{iter::Peekable, path::PathBuf, rc::Rc, str::CharIndices};
+use std::{iter::Peekable, mem, path::PathBuf, rc::Rc, str::CharIndices};
 use token::*;
 
 fn is_whitespace(c: char) -> bool {
@@ -14,27 +14,104 @@ fn is_newline(c: char) -> bool {
     ['\u{000a}', '\u{000d}', '\u{2028}', '\u{2029}'].contains(&c)
 }
 
+/// The Arabic diacritics (e.g. the shadda in "صدّر") along with the general combining marks, they can be anywhere in a
+/// word after its first letter.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036f}'
+            | '\u{0610}'..='\u{061a}'
+            | '\u{064b}'..='\u{065f}'
+            | '\u{0670}'
+            | '\u{06d6}'..='\u{06dc}'
+            | '\u{06df}'..='\u{06e4}'
+            | '\u{06e7}'..='\u{06e8}'
+            | '\u{06ea}'..='\u{06ed}'
+    )
+}
+
+/// The type of the keyword spelled by `word`, if it's one.
+fn keyword(word: &str) -> Option<TokenType> {
+    use TokenType::*;
+
+    Some(match word {
+        "إن" => If,
+        "وإن" => ElseIf,
+        "إلا" => Else,
+        "دالة" => Function,
+        "متغير" => Var,
+        "كرر" => Loop,
+        "طالما" => While,
+        "إكسر" => Break,
+        "واصل" => Continue,
+        "أرجع" => Return,
+        "ألقي" => Throw,
+        "ألقي_مجدداً" => Rethrow,
+        "حاول" => Try,
+        "أمسك" => Catch,
+        "عدم" => Nil,
+        "صحيح" => True,
+        "خطأ" => False,
+        "و" => And,
+        "أو" => Or,
+        "استورد" => Import,
+        "من" => From,
+        "صدّر" => Export,
+        "لكل" => For,
+        "في" => In,
+        "مولّد" => Generator,
+        "أنتج" => Yield,
+        "طابق" => Match,
+        "إذا" => When,
+        "مع" => With,
+        _ => return None,
+    })
+}
+
+/// Like `is_newline` but a `\r` right before a `\n` doesn't end the line by itself, so Windows line endings count once.
+fn ends_line(source: &str, offset: usize, c: char) -> bool {
+    is_newline(c) && !(c == '\r' && source[offset + c.len_utf8()..].starts_with('\n'))
+}
+
+/// What the lexer is in the middle of at the end of the text fed to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexState {
+    Code,
+    String,
+    InlineComment,
+    BlockComment,
+}
+
+/// Lexes a whole source at once with `lex`, or incrementally (e.g. as the user types) with `feed` and `finish`.
 pub struct Lexer {
+    /// All of the text fed so far, tokens share it until more text is fed.
     source: Rc<String>,
-    path: Option<PathBuf>,
+    path: Option<Rc<PathBuf>>,
+    /// The offset of the first character that isn't part of a returned token.
+    offset: usize,
 }
 
 impl Lexer {
     pub fn new(source: String, path: Option<&PathBuf>) -> Self {
         Self {
             source: Rc::new(source),
-            path: path.cloned(),
+            path: path.cloned().map(Rc::new),
+            offset: 0,
         }
     }
 
+    pub fn new_incremental(path: Option<&PathBuf>) -> Self {
+        Self::new(String::new(), path)
+    }
+
     /// Creates a new token returning it.
     ///
-    /// `first` represents the offset of the first character, while `last` represents the offset of the last.
+    /// `first` represents the offset of the first character, while `length` is in bytes.
     fn pop_token(&mut self, typ: TokenType, first: usize, length: usize) -> Rc<Token> {
         Rc::new(Token::new(
             typ,
             Rc::clone(&self.source),
-            self.path.as_ref(),
+            self.path.clone(),
             first,
             length,
         ))
@@ -60,7 +137,7 @@ impl Lexer {
     }
 
     fn at_end(char_indices: &mut Peekable<CharIndices>) -> bool {
-        Self::next(char_indices).is_none()
+        Self::peek(char_indices).is_none()
     }
 
     /// If the next character matches `pred`, Advances the iterator returning the next element.
@@ -80,184 +157,313 @@ impl Lexer {
         Box::new(move |c| c == expected)
     }
 
-    /// Advances the iterator ignoring whitespaces.
-    fn next_no_whitespace(char_indices: &mut Peekable<CharIndices>) -> Option<(usize, char)> {
-        loop {
-            if Self::check_next(char_indices, Box::new(is_whitespace)).is_none() {
-                return Self::next(char_indices);
-            }
-        }
-    }
-
-    pub fn lex(mut self) -> Vec<Rc<Token>> {
+    /// Scans the lexeme at the start of `rest` returning its type and length.
+    ///
+    /// Returns `None` if the lexeme reaches the end of `rest` and more text might change it, unless `eof` is set, in
+    /// which case the lexeme is complete (or unterminated).
+    fn scan(rest: &str, eof: bool) -> Option<(TokenType, usize)> {
         use TokenType::*;
 
-        let source = Rc::clone(&self.source);
-        let mut char_indices = source.char_indices().peekable();
-        let mut tokens = vec![];
-        while let Some((first, c)) = Self::next_no_whitespace(&mut char_indices) {
-            macro_rules! single {
-                ($typ:ident) => {
-                    tokens.push(self.pop_token($typ, first, 1))
-                };
-            }
-            macro_rules! optional_equal {
-                ($without:ident, $with:ident) => {
-                    if Self::check_next(&mut char_indices, Self::is('=')).is_some() {
-                        tokens.push(self.pop_token($with, first, 2))
-                    } else {
-                        tokens.push(self.pop_token($without, first, 1))
-                    }
-                };
-            }
+        let mut char_indices = rest.char_indices().peekable();
+        let (_, c) = Self::next(&mut char_indices)?;
+        let len = c.len_utf8();
+        // The length up to the next character, or `None` if there's none yet.
+        macro_rules! until_next {
+            () => {
+                match Self::peek(&mut char_indices) {
+                    Some((offset, _)) => offset,
+                    None if eof => rest.len(),
+                    None => return None,
+                }
+            };
+        }
+        macro_rules! optional_equal {
+            ($without:ident, $with:ident) => {
+                if Self::check_next(&mut char_indices, Self::is('=')).is_some() {
+                    Some(($with, 2))
+                } else if Self::at_end(&mut char_indices) && !eof {
+                    None
+                } else {
+                    Some(($without, 1))
+                }
+            };
+        }
 
-            match c {
-                x if is_newline(x) => single!(NewLine),
-                '(' => single!(OParen),
-                ')' => single!(CParen),
-                '{' => single!(OBrace),
-                '}' => single!(CBrace),
-                '[' => single!(OBracket),
-                ']' => single!(CBracket),
-                '،' => single!(Comma),
-                '؟' => single!(QuestionMark),
-                ':' => single!(Colon),
-                '|' => single!(Pipe),
-                '+' => optional_equal!(Plus, PlusEqual),
-                '-' => optional_equal!(Minus, MinusEqual),
-                '*' => optional_equal!(Star, StarEqual),
-                '/' => {
-                    if Self::check_next(&mut char_indices, Self::is('=')).is_some() {
-                        tokens.push(self.pop_token(SlashEqual, first, 2))
-                    } else if Self::check_next(&mut char_indices, Self::is('/')).is_some() {
-                        loop {
+        match c {
+            x if is_newline(x) => Some((NewLine, len)),
+            '(' => Some((OParen, len)),
+            ')' => Some((CParen, len)),
+            '{' => Some((OBrace, len)),
+            '}' => Some((CBrace, len)),
+            '[' => Some((OBracket, len)),
+            ']' => Some((CBracket, len)),
+            '،' => Some((Comma, len)),
+            '؟' => Some((QuestionMark, len)),
+            '؛' => Some((Semicolon, len)),
+            ':' => Some((Colon, len)),
+            '|' => Some((Pipe, len)),
+            '+' => optional_equal!(Plus, PlusEqual),
+            '-' => optional_equal!(Minus, MinusEqual),
+            '*' => optional_equal!(Star, StarEqual),
+            '/' => {
+                if Self::check_next(&mut char_indices, Self::is('=')).is_some() {
+                    Some((SlashEqual, 2))
+                } else if Self::check_next(&mut char_indices, Self::is('/')).is_some() {
+                    while !Self::at_end(&mut char_indices)
+                        && !Self::check(&mut char_indices, Box::new(is_newline))
+                    {
+                        Self::next(&mut char_indices);
+                    }
+                    Some((InlineComment, until_next!()))
+                } else if Self::check_next(&mut char_indices, Self::is('*')).is_some() {
+                    loop {
+                        if Self::check_next(&mut char_indices, Self::is('*')).is_some() {
                             if let Some((last, _)) =
-                                Self::check_next(&mut char_indices, Box::new(is_newline))
+                                Self::check_next(&mut char_indices, Self::is('/'))
                             {
-                                tokens.push(self.pop_token(InlineComment, first, first - last + 1));
-                                break;
-                            } else if Self::at_end(&mut char_indices) {
-                                // TODO test
-                                tokens.push(self.pop_token(
-                                    InlineComment,
-                                    first,
-                                    self.source.len() - first,
-                                ));
-                                break;
-                            } else {
-                                Self::next(&mut char_indices);
+                                break Some((BlockComment, last + 1));
                             }
-                        }
-                    } else if let Some((_, _)) = Self::check_next(&mut char_indices, Self::is('*'))
-                    {
-                        loop {
-                            if Self::check_next(&mut char_indices, Self::is('*')).is_some() {
-                                if let Some((last, _)) =
-                                    Self::check_next(&mut char_indices, Self::is('/'))
-                                {
-                                    tokens.push(self.pop_token(
-                                        BlockComment,
-                                        first,
-                                        first - last + 1,
-                                    ));
-                                    break;
-                                }
-                            } else if Self::at_end(&mut char_indices) {
-                                tokens.push(self.pop_token(
-                                    UnterminatedBlockComment,
-                                    first,
-                                    source.len() - first,
-                                ));
-                                break;
+                        } else if Self::at_end(&mut char_indices) {
+                            break if eof {
+                                Some((UnterminatedBlockComment, rest.len()))
                             } else {
-                                Self::next(&mut char_indices);
-                            }
-                        }
-                    } else {
-                        tokens.push(self.pop_token(Slash, first, 1))
-                    }
-                }
-                '%' => optional_equal!(Percent, PercentEqual),
-                '!' => optional_equal!(Bang, BangEqual),
-                '=' => optional_equal!(Equal, DEqual),
-                '>' => optional_equal!(Greater, GreaterEqual),
-                '<' => optional_equal!(Less, LessEqual),
-                '.' => {
-                    if let Some((second_first, _)) =
-                        Self::check_next(&mut char_indices, Self::is('.'))
-                    {
-                        if Self::check_next(&mut char_indices, Self::is('.')).is_some() {
-                            tokens.push(self.pop_token(TPeriod, first, 3))
+                                None
+                            };
                         } else {
-                            tokens.push(self.pop_token(Period, first, 1));
-                            tokens.push(self.pop_token(Period, second_first, 1))
+                            Self::next(&mut char_indices);
                         }
-                    } else {
-                        tokens.push(self.pop_token(Period, first, 1))
                     }
+                } else if Self::at_end(&mut char_indices) && !eof {
+                    None
+                } else {
+                    Some((Slash, 1))
                 }
-                '"' => loop {
-                    if let Some((last, _)) = Self::check_next(&mut char_indices, Self::is('"')) {
-                        tokens.push(self.pop_token(String, first, first - last + 1));
-                        break;
-                    } else if Self::check_next(&mut char_indices, Self::is('\\')).is_some() {
-                        Self::check_next(&mut char_indices, Box::new(|c| c == '"'));
-                    } else if let Some((last, _)) =
-                        Self::check_next(&mut char_indices, Box::new(is_newline))
-                    {
-                        tokens.push(self.pop_token(UnterminatedString, first, first - last + 1));
-                        break;
-                    } else if Self::at_end(&mut char_indices) {
-                        tokens.push(self.pop_token(
-                            UnterminatedString,
-                            first,
-                            source.len() - first,
-                        ));
-                        break;
+            }
+            '%' => optional_equal!(Percent, PercentEqual),
+            '!' => optional_equal!(Bang, BangEqual),
+            '=' => {
+                if Self::check_next(&mut char_indices, Self::is('>')).is_some() {
+                    Some((FatArrow, 2))
+                } else {
+                    optional_equal!(Equal, DEqual)
+                }
+            }
+            '>' => optional_equal!(Greater, GreaterEqual),
+            '<' => optional_equal!(Less, LessEqual),
+            '.' => {
+                if Self::check_next(&mut char_indices, Self::is('.')).is_some() {
+                    if Self::check_next(&mut char_indices, Self::is('.')).is_some() {
+                        Some((TPeriod, 3))
+                    } else if Self::at_end(&mut char_indices) && !eof {
+                        None
                     } else {
-                        Self::next(&mut char_indices);
+                        // The second period gets scanned on its own.
+                        Some((Period, 1))
                     }
-                },
-                x if x.is_alphabetic() || x == '_' => {
-                    let mut last = first;
-                    while let Some((offset, _)) = Self::check_next(
-                        &mut char_indices,
-                        Box::new(|c| c.is_alphanumeric() || c == '_'),
-                    ) {
-                        last = offset;
-                    }
-                    tokens.push(self.pop_token(Identifier, first, last))
+                } else if Self::at_end(&mut char_indices) && !eof {
+                    None
+                } else {
+                    Some((Period, 1))
                 }
-                x if x.is_ascii_digit() => {
-                    let mut int_last = first;
-                    while let Some((offset, _)) =
-                        Self::check_next(&mut char_indices, Box::new(|c| c.is_ascii_digit()))
-                    {
-                        int_last = offset;
+            }
+            '"' => loop {
+                if let Some((last, _)) = Self::check_next(&mut char_indices, Self::is('"')) {
+                    break Some((String, last + 1));
+                } else if Self::check_next(&mut char_indices, Self::is('\\')).is_some() {
+                    if Self::at_end(&mut char_indices) && !eof {
+                        break None;
                     }
-                    if let Some((offset, _)) = Self::check_next(&mut char_indices, Self::is('.')) {
-                        if Self::check_next(&mut char_indices, Box::new(|c| c.is_ascii_digit()))
+                    Self::check_next(&mut char_indices, Self::is('"'));
+                } else if Self::check(&mut char_indices, Box::new(is_newline)) {
+                    break Some((UnterminatedString, until_next!()));
+                } else if Self::at_end(&mut char_indices) {
+                    break if eof {
+                        Some((UnterminatedString, rest.len()))
+                    } else {
+                        None
+                    };
+                } else {
+                    Self::next(&mut char_indices);
+                }
+            },
+            x if x.is_alphabetic() || x == '_' => {
+                while Self::check_next(
+                    &mut char_indices,
+                    Box::new(|c| c.is_alphanumeric() || c == '_' || is_combining_mark(c)),
+                )
+                .is_some()
+                {}
+                let len = until_next!();
+                Some((keyword(&rest[..len]).unwrap_or(Identifier), len))
+            }
+            x if x.is_ascii_digit() => {
+                while Self::check_next(&mut char_indices, Box::new(|c| c.is_ascii_digit()))
+                    .is_some()
+                {}
+                let int_len = until_next!();
+                if Self::check_next(&mut char_indices, Self::is('.')).is_some() {
+                    if Self::check(&mut char_indices, Box::new(|c| c.is_ascii_digit())) {
+                        while Self::check_next(&mut char_indices, Box::new(|c| c.is_ascii_digit()))
                             .is_some()
-                        {
-                            todo!("Floats")
-                        } else {
-                            tokens.push(self.pop_token(Number, first, int_last));
-                            tokens.push(self.pop_token(Period, offset, offset));
-                        }
+                        {}
+                        Some((Number, until_next!()))
+                    } else if Self::at_end(&mut char_indices) && !eof {
+                        None
                     } else {
-                        tokens.push(self.pop_token(Number, first, int_last));
+                        // The period gets scanned on its own.
+                        Some((Number, int_len))
                     }
+                } else {
+                    Some((Number, int_len))
+                }
+            }
+            _ => Some((Unknown, len)),
+        }
+    }
+
+    /// Returns the tokens of the complete lexemes after `self.offset`, leaving the incomplete one for later unless
+    /// `eof` is set.
+    fn lex_available(&mut self, eof: bool) -> Vec<Rc<Token>> {
+        let source = Rc::clone(&self.source);
+        let mut tokens = vec![];
+        loop {
+            let rest = &source[self.offset..];
+            let trimmed = rest.trim_start_matches(is_whitespace);
+            self.offset += rest.len() - trimmed.len();
+            match Self::scan(trimmed, eof) {
+                Some((typ, length)) => {
+                    tokens.push(self.pop_token(typ, self.offset, length));
+                    self.offset += length;
                 }
-                _ => single!(Unknown),
+                None => break,
             }
         }
-        tokens.push(Rc::new(Token::new(
-            EOF,
-            Rc::clone(&source),
-            self.path.as_ref(),
-            source.len() - 1,
-            0,
-        )));
         tokens
     }
+
+    /// Appends `text` to the source returning the tokens that got completed, a lexeme that reaches the end of `text`
+    /// is buffered until the next call as more text might change it (e.g. an identifier or an unterminated string).
+    pub fn feed(&mut self, text: &str) -> Vec<Rc<Token>> {
+        // Copies the source if it's still shared with previously returned tokens, which is fine for interactive input
+        // but makes feeding a big source in small pieces quadratic.
+        Rc::make_mut(&mut self.source).push_str(text);
+        self.lex_available(false)
+    }
+
+    /// What the buffered lexeme is, i.e. whether the end of the fed text is inside a string or a comment.
+    pub fn state(&self) -> LexState {
+        let rest = self.source[self.offset..].trim_start_matches(is_whitespace);
+        if rest.starts_with('"') {
+            LexState::String
+        } else if rest.starts_with("//") {
+            LexState::InlineComment
+        } else if rest.starts_with("/*") {
+            LexState::BlockComment
+        } else {
+            LexState::Code
+        }
+    }
+
+    /// Returns the buffered tokens (if any) followed by the `EOF` token.
+    pub fn finish(mut self) -> Vec<Rc<Token>> {
+        let mut tokens = self.lex_available(true);
+        let last = self
+            .source
+            .char_indices()
+            .last()
+            .map_or(0, |(offset, _)| offset);
+        tokens.push(self.pop_token(TokenType::EOF, last, 0));
+        tokens
+    }
+
+    pub fn lex(self) -> Vec<Rc<Token>> {
+        let source = Rc::clone(&self.source);
+        let tokens = self.finish();
+        if cfg!(feature = "verbose") {
+            // Every token takes a slot in the vector, and an allocation holding the reference counts along with it.
+            let token_size =
+                mem::size_of::<Rc<Token>>() + 2 * mem::size_of::<usize>() + mem::size_of::<Token>();
+            println!(
+                "[LEXER] {} tokens taking {} bytes for a {} bytes source",
+                tokens.len(),
+                tokens.len() * token_size,
+                source.len()
+            )
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "دالة مجموع(أ، ب = 1.5، ...ج) {\r\n    // تعليق\r\n    أرجع أ + ب * 10 != \"نص \\\"مقتبس\\\"\"\n}\n/* تعليق\nطويل */ س += [1، 2.، .3]؛ ص >= 4 و ع == عدم ؟ 0 : \"غير منته";
+
+    fn describe(tokens: Vec<Rc<Token>>) -> Vec<(TokenType, usize, String)> {
+        tokens
+            .iter()
+            .map(|token| (token.typ(), token.start(), token.lexeme().to_owned()))
+            .collect()
+    }
+
+    fn lex_in(chunks: &[&str]) -> Vec<(TokenType, usize, String)> {
+        let mut lexer = Lexer::new_incremental(None);
+        let mut tokens = vec![];
+        for chunk in chunks {
+            tokens.extend(lexer.feed(chunk));
+        }
+        tokens.extend(lexer.finish());
+        describe(tokens)
+    }
+
+    #[test]
+    fn lexing_in_two_pieces_is_lexing_at_once() {
+        let expected = describe(Lexer::new(SOURCE.to_owned(), None).lex());
+        for (offset, _) in SOURCE.char_indices() {
+            let (first, second) = SOURCE.split_at(offset);
+            assert_eq!(lex_in(&[first, second]), expected, "split at {offset}");
+        }
+    }
+
+    #[test]
+    fn lexing_in_arbitrary_pieces_is_lexing_at_once() {
+        let expected = describe(Lexer::new(SOURCE.to_owned(), None).lex());
+        let offsets = SOURCE
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain([SOURCE.len()])
+            .collect::<Vec<_>>();
+        // xorshift64, enough to pick the sizes of the pieces.
+        let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+        for _ in 0..500 {
+            let mut chunks = vec![];
+            let mut idx = 0;
+            while idx < offsets.len() - 1 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                // Empty pieces are fed too.
+                let next = (idx + (state % 6) as usize).min(offsets.len() - 1);
+                chunks.push(&SOURCE[offsets[idx]..offsets[next]]);
+                idx = next;
+            }
+            assert_eq!(lex_in(&chunks), expected, "{chunks:?}");
+        }
+    }
+
+    #[test]
+    fn state_tells_what_the_end_is_in() {
+        let mut lexer = Lexer::new_incremental(None);
+        for (text, state) in [
+            ("س = ", LexState::Code),
+            ("\"نص", LexState::String),
+            ("\" // تعليق", LexState::InlineComment),
+            ("\n/* تعليق", LexState::BlockComment),
+            (" */ ص", LexState::Code),
+        ] {
+            lexer.feed(text);
+            assert_eq!(lexer.state(), state, "after {text:?}");
+        }
+    }
 }
@@ -1,10 +1,17 @@
 extern crate variant_count;
 
 use colored::Colorize;
-use std::{cmp::PartialEq, convert::Into, fmt, path::PathBuf, rc::Rc, string};
+use std::{
+    cmp::PartialEq,
+    convert::Into,
+    fmt,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    rc::Rc,
+    string,
+};
 use variant_count::VariantCount;
 
-use super::is_newline;
+use super::{ends_line, is_newline};
 
 #[derive(Debug, Clone, Copy, PartialEq, VariantCount)]
 pub enum TokenType {
@@ -56,6 +63,7 @@ pub enum TokenType {
     Continue,                 // 43
     Return,                   // 44
     Throw,                    // 45
+    Rethrow,                  // 46
     Try,                      // 46
     Catch,                    // 47
     Nil,                      // 48
@@ -68,9 +76,16 @@ pub enum TokenType {
     Pipe,                     // 55
     For,                      // 56
     In,                       // 57
-    Unknown,                  // 58
-    NewLine,                  // 59
-    EOF,                      // 60
+    Generator,                // 58
+    Yield,                    // 59
+    Semicolon,                // 60
+    Match,                    // 61
+    FatArrow,                 // 62
+    When,                     // 63
+    With,                     // 64
+    Unknown,                  // 65
+    NewLine,                  // 66
+    EOF,                      // 67
 }
 
 impl Into<&'static str> for TokenType {
@@ -108,8 +123,8 @@ impl Into<&'static str> for TokenType {
             Self::GreaterEqual => ">=",
             Self::Less => "<",
             Self::LessEqual => "<=",
-            Self::And => "&&",
-            Self::Or => "||",
+            Self::And => "و",
+            Self::Or => "أو",
 
             Self::String => "نص",
             Self::UnterminatedString => "نص غير مغلق",
@@ -129,6 +144,7 @@ impl Into<&'static str> for TokenType {
             Self::Continue => "واصل",
             Self::Return => "أرجع",
             Self::Throw => "ألقي",
+            Self::Rethrow => "ألقي_مجدداً",
             Self::Try => "حاول",
             Self::Catch => "أمسك",
             Self::Nil => "عدم",
@@ -142,6 +158,13 @@ impl Into<&'static str> for TokenType {
             Self::Pipe => "|",
             Self::For => "لكل",
             Self::In => "في",
+            Self::Generator => "مولّد",
+            Self::Yield => "أنتج",
+            Self::Semicolon => "؛",
+            Self::Match => "طابق",
+            Self::FatArrow => "=>",
+            Self::When => "إذا",
+            Self::With => "مع",
             Self::Unknown => "حرف غير معروف",
             Self::EOF => "النهاية",
         }
@@ -149,46 +172,79 @@ impl Into<&'static str> for TokenType {
 }
 
 impl Token {
+    /// `path` is shared between all of the tokens of the same file, and sources bigger than 4GB aren't supported.
     pub fn new(
         typ: TokenType,
         source: Rc<string::String>,
-        path: Option<&PathBuf>,
+        path: Option<Rc<PathBuf>>,
         start: usize,
         length: usize,
     ) -> Self {
         Self {
             typ,
             source,
-            path: match path {
-                Some(path) => Some(path.clone()),
-                None => None,
-            },
-            start,
-            length,
+            path,
+            start: start as u32,
+            length: length as u32,
+            synthetic: false,
         }
     }
 
+    /// The offset of the token in its source in bytes.
+    pub fn start(&self) -> usize {
+        self.start as usize
+    }
+
+    fn end(&self) -> usize {
+        (self.start + self.length) as usize
+    }
+
     pub fn typ(&self) -> TokenType {
         self.typ
     }
 
     pub fn path(&self) -> Option<&PathBuf> {
-        self.path.as_ref()
+        self.path.as_deref()
     }
 
     pub fn lexeme(&self) -> &str {
-        self.source
-            .get(self.start..self.start + self.length)
-            .unwrap()
+        self.source.get(self.start()..self.end()).unwrap()
+    }
+
+    /// Returns a token spanning both `self` and `other` (which must be from the same source), it takes the type of
+    /// `self`.
+    pub fn to(&self, other: &Token) -> Self {
+        let start = self.start.min(other.start);
+        let end = self.end().max(other.end()) as u32;
+        Self {
+            typ: self.typ,
+            source: Rc::clone(&self.source),
+            path: self.path.clone(),
+            start,
+            length: end - start,
+            synthetic: self.synthetic,
+        }
+    }
+
+    /// Returns a copy of the token marked as synthetic.
+    pub fn synthetic(&self) -> Self {
+        Self {
+            synthetic: true,
+            ..self.clone()
+        }
+    }
+
+    pub fn is_synthetic(&self) -> bool {
+        self.synthetic
     }
 
     pub fn line(&self) -> usize {
         let mut line = 1;
         for (offset, c) in self.source.char_indices() {
-            if is_newline(c) {
+            if ends_line(&self.source, offset, c) {
                 line += 1;
             }
-            if offset == self.start {
+            if offset == self.start() {
                 break;
             }
         }
@@ -196,13 +252,27 @@ impl Token {
     }
 }
 
+/// Displays `path` with forward slashes on every platform, so that messages read the same wherever they're printed.
+pub fn display_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if MAIN_SEPARATOR == '/' {
+        path.into_owned()
+    } else {
+        path.replace(MAIN_SEPARATOR, "/")
+    }
+}
+
+/// Kept small as a token is created for every lexeme.
 #[derive(Clone)]
 pub struct Token {
     typ: TokenType,
     source: Rc<String>,
-    path: Option<PathBuf>,
-    start: usize,
-    length: usize,
+    path: Option<Rc<PathBuf>>,
+    start: u32,
+    length: u32,
+    /// Set on the tokens of instructions the compiler writes on its own (e.g. the read and write of `+=`), they still
+    /// point at the source the user wrote so errors are reported there.
+    synthetic: bool,
 }
 
 impl fmt::Debug for Token {
@@ -222,12 +292,14 @@ impl fmt::Display for Token {
         let mut line_start_offset = 0;
         while let Some((offset, c)) = char_indices.next() {
             if is_newline(c) {
+                line_start_offset = offset + c.len_utf8();
+            }
+            if ends_line(&self.source, offset, c) {
                 line_idx += 1;
-                line_start_offset = offset;
             }
-            if offset == self.start {
+            if offset == self.start() {
                 while let Some((offset, _)) = char_indices.peek() {
-                    if *offset == self.start + self.length {
+                    if *offset == self.end() {
                         break;
                     } else {
                         char_indices.next();
@@ -244,7 +316,7 @@ impl fmt::Display for Token {
                 "{:indent$}{} {}",
                 "",
                 "-->".bright_cyan(),
-                path.display().to_string().bright_cyan()
+                display_path(path).bright_cyan()
             )?
         }
         writeln!(f, "{:indent$} {}", "", "|".bright_cyan())?;
@@ -257,7 +329,10 @@ impl fmt::Display for Token {
         write!(
             f,
             "{}{}",
-            self.source.get(line_start_offset..self.start).unwrap(),
+            // Empty for the `EOF` token after a trailing newline, which starts before the line it's counted on.
+            self.source
+                .get(line_start_offset..self.start())
+                .unwrap_or_default(),
             self.lexeme().underline().bold()
         )?;
         while let Some((_, c)) = char_indices.next() {
@@ -281,6 +356,7 @@ impl Default for Token {
             path: None,
             start: 0,
             length: 0,
+            synthetic: false,
         }
     }
 }
@@ -303,3 +379,59 @@ pub const BINARY_SET: [TokenType; 6] = [
     TokenType::SlashEqual,
     TokenType::PercentEqual,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_paths_with_forward_slashes() {
+        assert_eq!(display_path(Path::new("مجلد/ملف.قتام")), "مجلد/ملف.قتام");
+        assert_eq!(display_path(Path::new("/مجلد/ملف.قتام")), "/مجلد/ملف.قتام");
+        let joined = Path::new("مجلد").join("فرعي").join("ملف.قتام");
+        assert_eq!(display_path(&joined), "مجلد/فرعي/ملف.قتام");
+        #[cfg(windows)]
+        assert_eq!(
+            display_path(Path::new(r"C:\مجلد\ملف.قتام")),
+            "C:/مجلد/ملف.قتام"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn displays_paths_that_are_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let path = Path::new(OsStr::from_bytes(b"/\xff/\xd9\x85"));
+        assert_eq!(display_path(path), "/\u{fffd}/م");
+    }
+
+    fn token_at(source: &str, lexeme: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            Rc::new(source.to_owned()),
+            None,
+            source.find(lexeme).unwrap(),
+            lexeme.len(),
+        )
+    }
+
+    #[test]
+    fn counts_windows_line_endings_once() {
+        for (source, line) in [
+            ("أ\nب\nج", 3),
+            ("أ\r\nب\r\nج", 3),
+            ("أ\rب\rج", 3),
+            ("أ\u{2028}ب\u{2029}ج", 3),
+            ("أ\r\n\r\nب\r\n\nج", 5),
+        ] {
+            assert_eq!(token_at(source, "ج").line(), line, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn prints_the_line_without_its_ending() {
+        colored::control::set_override(false);
+        let token = token_at("أ\r\nمتغير ب = ج\r\nد", "ج");
+        assert_eq!(token.to_string(), "  |\n2 | متغير ب = ج\n  |\n");
+    }
+}
@@ -0,0 +1,274 @@
+pub mod visit;
+
+use lexer::token::{Token, TokenInside, TokenType};
+use std::rc::Rc;
+use visit::Visitor;
+
+#[derive(Debug, Clone)]
+pub enum Literal {
+    /// token
+    Number(Rc<Token>),
+    /// token
+    String(Rc<Token>),
+    /// token
+    Bool(Rc<Token>),
+    /// token
+    Nil(Rc<Token>),
+    /// token, exprs
+    List(Rc<Token>, Vec<Expr>),
+    /// token, element, for_token, definable, in_token, iterable, guard: (token, condition)
+    Comprehension(
+        Rc<Token>,
+        Box<Expr>,
+        Rc<Token>,
+        Box<Expr>,
+        Rc<Token>,
+        Box<Expr>,
+        Option<(Rc<Token>, Box<Expr>)>,
+    ),
+    /// token, props: \[(key, value, default)\]
+    Object(
+        Rc<Token>,
+        Vec<(Rc<Token>, Option<Expr>, Option<(Rc<Token>, Expr)>)>,
+    ),
+    /// token, required, optional, variadic: (token, name), body
+    Lambda(
+        Rc<Token>,
+        Vec<Expr>,
+        Vec<(Expr, Expr)>,
+        Option<(Rc<Token>, Box<Expr>)>,
+        Box<Stml>,
+    ),
+}
+
+impl TokenInside for Literal {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::Number(token)
+            | Self::String(token)
+            | Self::Bool(token)
+            | Self::Nil(token)
+            | Self::List(token, ..)
+            | Self::Comprehension(token, ..)
+            | Self::Object(token, ..)
+            | Self::Lambda(token, ..) => Rc::clone(token),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// token
+    Variable(Rc<Token>),
+    /// literal
+    Literal(Literal),
+    /// op, expr
+    Unary(Rc<Token>, Box<Expr>),
+    /// lhs, op, rhs
+    Binary(Box<Expr>, Rc<Token>, Box<Expr>),
+    /// expr, op, exprs
+    Call(Box<Expr>, Rc<Token>, Vec<Expr>),
+    /// expr, op, key
+    Member(Box<Expr>, Rc<Token>, Box<Expr>),
+}
+
+/// What the arms of `طابق` match their value against.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// token (`_`), matches anything
+    Wildcard(Rc<Token>),
+    /// literal (a number, string, boolean, or `عدم`), matches the values equal to it
+    Literal(Literal),
+    /// token, matches anything binding it to the variable
+    Binding(Rc<Token>),
+    /// token, patterns, rest: (token, name), matches the lists of as many elements as the patterns (or more if there's
+    /// a rest, which binds the remaining ones)
+    List(Rc<Token>, Vec<Pattern>, Option<(Rc<Token>, Rc<Token>)>),
+    /// token, props: \[(key, pattern)\], matches the hash maps having all of the keys (even if they have more), a key
+    /// without a pattern is bound to a variable of its name
+    Object(Rc<Token>, Vec<(Rc<Token>, Option<Pattern>)>),
+}
+
+impl TokenInside for Pattern {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::Wildcard(token)
+            | Self::Binding(token)
+            | Self::List(token, ..)
+            | Self::Object(token, ..) => Rc::clone(token),
+            Self::Literal(literal) => literal.token(),
+        }
+    }
+}
+
+impl Pattern {
+    /// Whether the pattern matches any value.
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Self::Wildcard(_) | Self::Binding(_))
+    }
+}
+
+/// pattern, guard: (token, condition), arrow, body
+pub type Arm = (Pattern, Option<(Rc<Token>, Expr)>, Rc<Token>, Stml);
+
+/// Joins the tokens it visits into one.
+struct Span(Option<Token>);
+
+impl Visitor for Span {
+    fn visit_token(&mut self, token: &Rc<Token>) {
+        self.0 = Some(match self.0.take() {
+            Some(span) => span.to(token),
+            None => Token::clone(token),
+        })
+    }
+}
+
+impl From<Literal> for Expr {
+    fn from(literal: Literal) -> Self {
+        Expr::Literal(literal)
+    }
+}
+
+impl TokenInside for Expr {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::Variable(token) => Rc::clone(token),
+            Self::Unary(op, ..)
+            | Self::Binary(_, op, ..)
+            | Self::Call(_, op, ..)
+            | Self::Member(_, op, ..) => Rc::clone(op),
+            Self::Literal(literal) => literal.token(),
+        }
+    }
+}
+
+impl Expr {
+    /// A token spanning all of the tokens of the expression.
+    pub fn span(&self) -> Rc<Token> {
+        let mut span = Span(None);
+        span.visit_expr(self);
+        Rc::new(span.0.unwrap())
+    }
+
+    /// Whether evaluating the expression has no effects other than producing a value, calls and assignments are never
+    /// pure.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Self::Variable(_) => true,
+            Self::Literal(Literal::List(_, exprs)) => exprs.iter().all(Self::is_pure),
+            // Iterating can call the `التالي` of an object.
+            Self::Literal(Literal::Comprehension(..)) => false,
+            Self::Literal(Literal::Object(_, props)) => props
+                .iter()
+                .all(|(_, value, _)| value.as_ref().is_none_or(Self::is_pure)),
+            Self::Literal(_) => true,
+            Self::Unary(_, expr) => expr.is_pure(),
+            Self::Binary(lhs, op, rhs) => match op.typ() {
+                TokenType::Equal
+                | TokenType::PlusEqual
+                | TokenType::MinusEqual
+                | TokenType::StarEqual
+                | TokenType::SlashEqual
+                | TokenType::PercentEqual => false,
+                _ => lhs.is_pure() && rhs.is_pure(),
+            },
+            Self::Call(..) => false,
+            Self::Member(expr, _, key) => expr.is_pure() && key.is_pure(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Stml {
+    /// token, stmls
+    Block(Rc<Token>, Vec<Stml>),
+    /// export_token, token (`دالة` or `مولّد`), name, required: \[definable\], optional: \[(definable, default)\], : (token, name): (token, name), body
+    FunctionDecl(
+        Option<Rc<Token>>,
+        Rc<Token>,
+        Rc<Token>,
+        Vec<Expr>,
+        Vec<(Expr, Expr)>,
+        Option<(Rc<Token>, Box<Expr>)>,
+        Box<Stml>,
+    ),
+    /// export_token, token, decls: \[(definable, init)\]
+    VarDecl(Option<Rc<Token>>, Rc<Token>, Vec<(Expr, Option<Expr>)>),
+    /// token, expr
+    Return(Rc<Token>, Option<Expr>),
+    /// token, expr
+    Throw(Rc<Token>, Option<Expr>),
+    /// token
+    Rethrow(Rc<Token>),
+    /// token, expr
+    Yield(Rc<Token>, Expr),
+    /// token, body, catch_token, err: definable, catch_body
+    TryCatch(Rc<Token>, Box<Stml>, Rc<Token>, Expr, Box<Stml>),
+    /// token, condition, body, elseifs: \[(token, condition, body)\], else_: (token, body)
+    If(
+        Rc<Token>,
+        Expr,
+        Box<Stml>,
+        Vec<(Rc<Token>, Expr, Stml)>,
+        Option<(Rc<Token>, Box<Stml>)>,
+    ),
+    /// token, condition, body
+    While(Rc<Token>, Expr, Box<Stml>),
+    /// token, body
+    Loop(Rc<Token>, Box<Stml>),
+    /// token
+    Break(Rc<Token>),
+    /// token
+    Continue(Rc<Token>),
+    /// token, definable, from_token, path
+    Import(Rc<Token>, Expr, Rc<Token>, Rc<Token>),
+    /// token, definable, in_token, iterable, guard: (token, condition), body
+    ForIn(
+        Rc<Token>,
+        Expr,
+        Rc<Token>,
+        Expr,
+        Option<(Rc<Token>, Expr)>,
+        Box<Stml>,
+    ),
+    /// token, value, arms
+    Match(Rc<Token>, Expr, Vec<Arm>),
+    /// token, name, equal, value, body
+    With(Rc<Token>, Rc<Token>, Rc<Token>, Expr, Box<Stml>),
+    /// expr
+    Expr(Expr),
+}
+
+impl TokenInside for Stml {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::Block(token, ..)
+            | Self::FunctionDecl(_, token, ..)
+            | Self::VarDecl(_, token, ..)
+            | Self::Return(token, ..)
+            | Self::Throw(token, ..)
+            | Self::Rethrow(token)
+            | Self::Yield(token, ..)
+            | Self::TryCatch(token, ..)
+            | Self::If(token, ..)
+            | Self::While(token, ..)
+            | Self::Loop(token, ..)
+            | Self::Break(token)
+            | Self::Continue(token)
+            | Self::Import(token, ..)
+            | Self::ForIn(token, ..)
+            | Self::Match(token, ..)
+            | Self::With(token, ..) => Rc::clone(token),
+            Self::Expr(expr) => expr.token(),
+        }
+    }
+}
+
+impl Stml {
+    /// A token spanning all of the tokens of the statement.
+    pub fn span(&self) -> Rc<Token> {
+        let mut span = Span(None);
+        span.visit_stml(self);
+        Rc::new(span.0.unwrap())
+    }
+}
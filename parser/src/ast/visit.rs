@@ -0,0 +1,824 @@
+//! Traversal of the AST.
+//!
+//! Every `visit_*` method defaults to its `walk_*` counterpart, which visits the node's children in the order they
+//! appear in the source. To hook into a node kind override its `visit_*` method, do what should happen before the
+//! children, call the `walk_*` function (or don't to skip them), and then do what should happen after them.
+//!
+//! Every child edge is walked exactly once, including the ones hidden inside tuples (optional params' defaults,
+//! object props' defaults, elseifs, catch bodies, etc.), and so is every token.
+
+use super::*;
+
+pub trait Visitor: Sized {
+    fn visit_stml(&mut self, stml: &Stml) {
+        walk_stml(self, stml)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        walk_literal(self, literal)
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_token(&mut self, _token: &Rc<Token>) {}
+}
+
+fn walk_params<V: Visitor>(
+    visitor: &mut V,
+    required: &[Expr],
+    optional: &[(Expr, Expr)],
+    variadic: &Option<(Rc<Token>, Box<Expr>)>,
+) {
+    for param in required {
+        visitor.visit_expr(param);
+    }
+    for (param, default) in optional {
+        visitor.visit_expr(param);
+        visitor.visit_expr(default);
+    }
+    if let Some((token, param)) = variadic {
+        visitor.visit_token(token);
+        visitor.visit_expr(param);
+    }
+}
+
+pub fn walk_literal<V: Visitor>(visitor: &mut V, literal: &Literal) {
+    match literal {
+        Literal::Number(token)
+        | Literal::String(token)
+        | Literal::Bool(token)
+        | Literal::Nil(token) => visitor.visit_token(token),
+        Literal::List(token, exprs) => {
+            visitor.visit_token(token);
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+        Literal::Comprehension(token, element, for_token, definable, in_token, iterable, guard) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(element);
+            visitor.visit_token(for_token);
+            visitor.visit_expr(definable);
+            visitor.visit_token(in_token);
+            visitor.visit_expr(iterable);
+            if let Some((when, condition)) = guard {
+                visitor.visit_token(when);
+                visitor.visit_expr(condition);
+            }
+        }
+        Literal::Object(token, props) => {
+            visitor.visit_token(token);
+            for (key, value, default) in props {
+                visitor.visit_token(key);
+                if let Some(value) = value {
+                    visitor.visit_expr(value);
+                }
+                if let Some((token, default)) = default {
+                    visitor.visit_token(token);
+                    visitor.visit_expr(default);
+                }
+            }
+        }
+        Literal::Lambda(token, required, optional, variadic, body) => {
+            visitor.visit_token(token);
+            walk_params(visitor, required, optional, variadic);
+            visitor.visit_stml(body);
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Variable(token) => visitor.visit_token(token),
+        Expr::Literal(literal) => visitor.visit_literal(literal),
+        Expr::Unary(op, expr) => {
+            visitor.visit_token(op);
+            visitor.visit_expr(expr);
+        }
+        Expr::Binary(lhs, op, rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_token(op);
+            visitor.visit_expr(rhs);
+        }
+        Expr::Call(expr, op, args) => {
+            visitor.visit_expr(expr);
+            visitor.visit_token(op);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        Expr::Member(expr, op, key) => {
+            visitor.visit_expr(expr);
+            visitor.visit_token(op);
+            visitor.visit_expr(key);
+        }
+    }
+}
+
+pub fn walk_pattern<V: Visitor>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Wildcard(token) | Pattern::Binding(token) => visitor.visit_token(token),
+        Pattern::Literal(literal) => visitor.visit_literal(literal),
+        Pattern::List(token, patterns, rest) => {
+            visitor.visit_token(token);
+            for pattern in patterns {
+                visitor.visit_pattern(pattern);
+            }
+            if let Some((token, name)) = rest {
+                visitor.visit_token(token);
+                visitor.visit_token(name);
+            }
+        }
+        Pattern::Object(token, props) => {
+            visitor.visit_token(token);
+            for (key, pattern) in props {
+                visitor.visit_token(key);
+                if let Some(pattern) = pattern {
+                    visitor.visit_pattern(pattern);
+                }
+            }
+        }
+    }
+}
+
+pub fn walk_stml<V: Visitor>(visitor: &mut V, stml: &Stml) {
+    match stml {
+        Stml::Block(token, stmls) => {
+            visitor.visit_token(token);
+            for stml in stmls {
+                visitor.visit_stml(stml);
+            }
+        }
+        Stml::FunctionDecl(export_token, token, name, required, optional, variadic, body) => {
+            if let Some(export_token) = export_token {
+                visitor.visit_token(export_token);
+            }
+            visitor.visit_token(token);
+            visitor.visit_token(name);
+            walk_params(visitor, required, optional, variadic);
+            visitor.visit_stml(body);
+        }
+        Stml::VarDecl(export_token, token, decls) => {
+            if let Some(export_token) = export_token {
+                visitor.visit_token(export_token);
+            }
+            visitor.visit_token(token);
+            for (definable, init) in decls {
+                visitor.visit_expr(definable);
+                if let Some(init) = init {
+                    visitor.visit_expr(init);
+                }
+            }
+        }
+        Stml::Return(token, expr) | Stml::Throw(token, expr) => {
+            visitor.visit_token(token);
+            if let Some(expr) = expr {
+                visitor.visit_expr(expr);
+            }
+        }
+        Stml::Yield(token, expr) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(expr);
+        }
+        Stml::TryCatch(token, body, catch_token, err, catch_body) => {
+            visitor.visit_token(token);
+            visitor.visit_stml(body);
+            visitor.visit_token(catch_token);
+            visitor.visit_expr(err);
+            visitor.visit_stml(catch_body);
+        }
+        Stml::If(token, condition, body, elseifs, else_) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(condition);
+            visitor.visit_stml(body);
+            for (token, condition, body) in elseifs {
+                visitor.visit_token(token);
+                visitor.visit_expr(condition);
+                visitor.visit_stml(body);
+            }
+            if let Some((token, body)) = else_ {
+                visitor.visit_token(token);
+                visitor.visit_stml(body);
+            }
+        }
+        Stml::While(token, condition, body) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(condition);
+            visitor.visit_stml(body);
+        }
+        Stml::Loop(token, body) => {
+            visitor.visit_token(token);
+            visitor.visit_stml(body);
+        }
+        Stml::Break(token) | Stml::Continue(token) | Stml::Rethrow(token) => {
+            visitor.visit_token(token)
+        }
+        Stml::Import(token, definable, from_token, path) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(definable);
+            visitor.visit_token(from_token);
+            visitor.visit_token(path);
+        }
+        Stml::ForIn(token, definable, in_token, iterable, guard, body) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(definable);
+            visitor.visit_token(in_token);
+            visitor.visit_expr(iterable);
+            if let Some((when, condition)) = guard {
+                visitor.visit_token(when);
+                visitor.visit_expr(condition);
+            }
+            visitor.visit_stml(body);
+        }
+        Stml::With(token, name, equal, value, body) => {
+            visitor.visit_token(token);
+            visitor.visit_token(name);
+            visitor.visit_token(equal);
+            visitor.visit_expr(value);
+            visitor.visit_stml(body);
+        }
+        Stml::Match(token, value, arms) => {
+            visitor.visit_token(token);
+            visitor.visit_expr(value);
+            for (pattern, guard, arrow, body) in arms {
+                visitor.visit_pattern(pattern);
+                if let Some((token, condition)) = guard {
+                    visitor.visit_token(token);
+                    visitor.visit_expr(condition);
+                }
+                visitor.visit_token(arrow);
+                visitor.visit_stml(body);
+            }
+        }
+        Stml::Expr(expr) => visitor.visit_expr(expr),
+    }
+}
+
+/// Like `Visitor` but can modify the nodes in place, which is what transformations (e.g. constant folding) need.
+pub trait VisitorMut: Sized {
+    fn visit_stml_mut(&mut self, stml: &mut Stml) {
+        walk_stml_mut(self, stml)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_literal_mut(&mut self, literal: &mut Literal) {
+        walk_literal_mut(self, literal)
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern)
+    }
+
+    fn visit_token_mut(&mut self, _token: &mut Rc<Token>) {}
+}
+
+fn walk_params_mut<V: VisitorMut>(
+    visitor: &mut V,
+    required: &mut [Expr],
+    optional: &mut [(Expr, Expr)],
+    variadic: &mut Option<(Rc<Token>, Box<Expr>)>,
+) {
+    for param in required {
+        visitor.visit_expr_mut(param);
+    }
+    for (param, default) in optional {
+        visitor.visit_expr_mut(param);
+        visitor.visit_expr_mut(default);
+    }
+    if let Some((token, param)) = variadic {
+        visitor.visit_token_mut(token);
+        visitor.visit_expr_mut(param);
+    }
+}
+
+pub fn walk_literal_mut<V: VisitorMut>(visitor: &mut V, literal: &mut Literal) {
+    match literal {
+        Literal::Number(token)
+        | Literal::String(token)
+        | Literal::Bool(token)
+        | Literal::Nil(token) => visitor.visit_token_mut(token),
+        Literal::List(token, exprs) => {
+            visitor.visit_token_mut(token);
+            for expr in exprs {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        Literal::Comprehension(token, element, for_token, definable, in_token, iterable, guard) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(element);
+            visitor.visit_token_mut(for_token);
+            visitor.visit_expr_mut(definable);
+            visitor.visit_token_mut(in_token);
+            visitor.visit_expr_mut(iterable);
+            if let Some((when, condition)) = guard {
+                visitor.visit_token_mut(when);
+                visitor.visit_expr_mut(condition);
+            }
+        }
+        Literal::Object(token, props) => {
+            visitor.visit_token_mut(token);
+            for (key, value, default) in props {
+                visitor.visit_token_mut(key);
+                if let Some(value) = value {
+                    visitor.visit_expr_mut(value);
+                }
+                if let Some((token, default)) = default {
+                    visitor.visit_token_mut(token);
+                    visitor.visit_expr_mut(default);
+                }
+            }
+        }
+        Literal::Lambda(token, required, optional, variadic, body) => {
+            visitor.visit_token_mut(token);
+            walk_params_mut(visitor, required, optional, variadic);
+            visitor.visit_stml_mut(body);
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: &mut Expr) {
+    match expr {
+        Expr::Variable(token) => visitor.visit_token_mut(token),
+        Expr::Literal(literal) => visitor.visit_literal_mut(literal),
+        Expr::Unary(op, expr) => {
+            visitor.visit_token_mut(op);
+            visitor.visit_expr_mut(expr);
+        }
+        Expr::Binary(lhs, op, rhs) => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_token_mut(op);
+            visitor.visit_expr_mut(rhs);
+        }
+        Expr::Call(expr, op, args) => {
+            visitor.visit_expr_mut(expr);
+            visitor.visit_token_mut(op);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        Expr::Member(expr, op, key) => {
+            visitor.visit_expr_mut(expr);
+            visitor.visit_token_mut(op);
+            visitor.visit_expr_mut(key);
+        }
+    }
+}
+
+pub fn walk_pattern_mut<V: VisitorMut>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Wildcard(token) | Pattern::Binding(token) => visitor.visit_token_mut(token),
+        Pattern::Literal(literal) => visitor.visit_literal_mut(literal),
+        Pattern::List(token, patterns, rest) => {
+            visitor.visit_token_mut(token);
+            for pattern in patterns {
+                visitor.visit_pattern_mut(pattern);
+            }
+            if let Some((token, name)) = rest {
+                visitor.visit_token_mut(token);
+                visitor.visit_token_mut(name);
+            }
+        }
+        Pattern::Object(token, props) => {
+            visitor.visit_token_mut(token);
+            for (key, pattern) in props {
+                visitor.visit_token_mut(key);
+                if let Some(pattern) = pattern {
+                    visitor.visit_pattern_mut(pattern);
+                }
+            }
+        }
+    }
+}
+
+pub fn walk_stml_mut<V: VisitorMut>(visitor: &mut V, stml: &mut Stml) {
+    match stml {
+        Stml::Block(token, stmls) => {
+            visitor.visit_token_mut(token);
+            for stml in stmls {
+                visitor.visit_stml_mut(stml);
+            }
+        }
+        Stml::FunctionDecl(export_token, token, name, required, optional, variadic, body) => {
+            if let Some(export_token) = export_token {
+                visitor.visit_token_mut(export_token);
+            }
+            visitor.visit_token_mut(token);
+            visitor.visit_token_mut(name);
+            walk_params_mut(visitor, required, optional, variadic);
+            visitor.visit_stml_mut(body);
+        }
+        Stml::VarDecl(export_token, token, decls) => {
+            if let Some(export_token) = export_token {
+                visitor.visit_token_mut(export_token);
+            }
+            visitor.visit_token_mut(token);
+            for (definable, init) in decls {
+                visitor.visit_expr_mut(definable);
+                if let Some(init) = init {
+                    visitor.visit_expr_mut(init);
+                }
+            }
+        }
+        Stml::Return(token, expr) | Stml::Throw(token, expr) => {
+            visitor.visit_token_mut(token);
+            if let Some(expr) = expr {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        Stml::Yield(token, expr) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(expr);
+        }
+        Stml::TryCatch(token, body, catch_token, err, catch_body) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_stml_mut(body);
+            visitor.visit_token_mut(catch_token);
+            visitor.visit_expr_mut(err);
+            visitor.visit_stml_mut(catch_body);
+        }
+        Stml::If(token, condition, body, elseifs, else_) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stml_mut(body);
+            for (token, condition, body) in elseifs {
+                visitor.visit_token_mut(token);
+                visitor.visit_expr_mut(condition);
+                visitor.visit_stml_mut(body);
+            }
+            if let Some((token, body)) = else_ {
+                visitor.visit_token_mut(token);
+                visitor.visit_stml_mut(body);
+            }
+        }
+        Stml::While(token, condition, body) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stml_mut(body);
+        }
+        Stml::Loop(token, body) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_stml_mut(body);
+        }
+        Stml::Break(token) | Stml::Continue(token) | Stml::Rethrow(token) => {
+            visitor.visit_token_mut(token)
+        }
+        Stml::Import(token, definable, from_token, path) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(definable);
+            visitor.visit_token_mut(from_token);
+            visitor.visit_token_mut(path);
+        }
+        Stml::ForIn(token, definable, in_token, iterable, guard, body) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(definable);
+            visitor.visit_token_mut(in_token);
+            visitor.visit_expr_mut(iterable);
+            if let Some((when, condition)) = guard {
+                visitor.visit_token_mut(when);
+                visitor.visit_expr_mut(condition);
+            }
+            visitor.visit_stml_mut(body);
+        }
+        Stml::With(token, name, equal, value, body) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_token_mut(name);
+            visitor.visit_token_mut(equal);
+            visitor.visit_expr_mut(value);
+            visitor.visit_stml_mut(body);
+        }
+        Stml::Match(token, value, arms) => {
+            visitor.visit_token_mut(token);
+            visitor.visit_expr_mut(value);
+            for (pattern, guard, arrow, body) in arms {
+                visitor.visit_pattern_mut(pattern);
+                if let Some((token, condition)) = guard {
+                    visitor.visit_token_mut(token);
+                    visitor.visit_expr_mut(condition);
+                }
+                visitor.visit_token_mut(arrow);
+                visitor.visit_stml_mut(body);
+            }
+        }
+        Stml::Expr(expr) => visitor.visit_expr_mut(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Hands out distinct tokens, remembering every one of them.
+    #[derive(Default)]
+    struct Tokens(Vec<Rc<Token>>);
+
+    impl Tokens {
+        fn next(&mut self) -> Rc<Token> {
+            let token = Rc::new(Token::new(
+                TokenType::Identifier,
+                Rc::new(String::from("س")),
+                None,
+                0,
+                "س".len(),
+            ));
+            self.0.push(Rc::clone(&token));
+            token
+        }
+
+        fn var(&mut self) -> Expr {
+            Expr::Variable(self.next())
+        }
+
+        fn block(&mut self) -> Stml {
+            let var = self.var();
+            Stml::Block(self.next(), vec![Stml::Expr(var)])
+        }
+    }
+
+    /// Matched exhaustively so that a new variant doesn't compile before it's covered here.
+    fn stml_kind(stml: &Stml) -> usize {
+        match stml {
+            Stml::Block(..) => 0,
+            Stml::FunctionDecl(..) => 1,
+            Stml::VarDecl(..) => 2,
+            Stml::Return(..) => 3,
+            Stml::Throw(..) => 4,
+            Stml::Rethrow(..) => 5,
+            Stml::Yield(..) => 6,
+            Stml::TryCatch(..) => 7,
+            Stml::If(..) => 8,
+            Stml::While(..) => 9,
+            Stml::Loop(..) => 10,
+            Stml::Break(..) => 11,
+            Stml::Continue(..) => 12,
+            Stml::Import(..) => 13,
+            Stml::ForIn(..) => 14,
+            Stml::Match(..) => 15,
+            Stml::With(..) => 16,
+            Stml::Expr(..) => 17,
+        }
+    }
+    const STML_KINDS: usize = 18;
+
+    fn expr_kind(expr: &Expr) -> usize {
+        match expr {
+            Expr::Variable(..) => 0,
+            Expr::Literal(..) => 1,
+            Expr::Unary(..) => 2,
+            Expr::Binary(..) => 3,
+            Expr::Call(..) => 4,
+            Expr::Member(..) => 5,
+        }
+    }
+    const EXPR_KINDS: usize = 6;
+
+    fn literal_kind(literal: &Literal) -> usize {
+        match literal {
+            Literal::Number(..) => 0,
+            Literal::String(..) => 1,
+            Literal::Bool(..) => 2,
+            Literal::Nil(..) => 3,
+            Literal::List(..) => 4,
+            Literal::Comprehension(..) => 5,
+            Literal::Object(..) => 6,
+            Literal::Lambda(..) => 7,
+        }
+    }
+    const LITERAL_KINDS: usize = 8;
+
+    fn pattern_kind(pattern: &Pattern) -> usize {
+        match pattern {
+            Pattern::Wildcard(..) => 0,
+            Pattern::Literal(..) => 1,
+            Pattern::Binding(..) => 2,
+            Pattern::List(..) => 3,
+            Pattern::Object(..) => 4,
+        }
+    }
+    const PATTERN_KINDS: usize = 5;
+
+    /// A program having every kind of node, with all of their optional parts present.
+    fn program(t: &mut Tokens) -> Stml {
+        let literals = vec![
+            Expr::Literal(Literal::Number(t.next())),
+            Expr::Literal(Literal::String(t.next())),
+            Expr::Literal(Literal::Bool(t.next())),
+            Expr::Literal(Literal::Nil(t.next())),
+            Expr::Literal(Literal::List(t.next(), vec![t.var(), t.var()])),
+            Expr::Literal(Literal::Comprehension(
+                t.next(),
+                Box::new(t.var()),
+                t.next(),
+                Box::new(t.var()),
+                t.next(),
+                Box::new(t.var()),
+                Some((t.next(), Box::new(t.var()))),
+            )),
+            Expr::Literal(Literal::Object(
+                t.next(),
+                vec![
+                    (t.next(), Some(t.var()), Some((t.next(), t.var()))),
+                    (t.next(), None, None),
+                ],
+            )),
+            Expr::Literal(Literal::Lambda(
+                t.next(),
+                vec![t.var()],
+                vec![(t.var(), t.var())],
+                Some((t.next(), Box::new(t.var()))),
+                Box::new(t.block()),
+            )),
+        ];
+        let exprs = vec![
+            Expr::Unary(t.next(), Box::new(t.var())),
+            Expr::Binary(Box::new(t.var()), t.next(), Box::new(t.var())),
+            Expr::Call(Box::new(t.var()), t.next(), vec![t.var(), t.var()]),
+            Expr::Member(Box::new(t.var()), t.next(), Box::new(t.var())),
+        ];
+        let arms = vec![
+            (Pattern::Wildcard(t.next()), None, t.next(), t.block()),
+            (
+                Pattern::Literal(Literal::Number(t.next())),
+                Some((t.next(), t.var())),
+                t.next(),
+                t.block(),
+            ),
+            (Pattern::Binding(t.next()), None, t.next(), t.block()),
+            (
+                Pattern::List(
+                    t.next(),
+                    vec![Pattern::Binding(t.next())],
+                    Some((t.next(), t.next())),
+                ),
+                None,
+                t.next(),
+                t.block(),
+            ),
+            (
+                Pattern::Object(
+                    t.next(),
+                    vec![
+                        (t.next(), Some(Pattern::Wildcard(t.next()))),
+                        (t.next(), None),
+                    ],
+                ),
+                None,
+                t.next(),
+                t.block(),
+            ),
+        ];
+        let mut stmls: Vec<Stml> = literals.into_iter().chain(exprs).map(Stml::Expr).collect();
+        stmls.extend([
+            Stml::FunctionDecl(
+                Some(t.next()),
+                t.next(),
+                t.next(),
+                vec![t.var()],
+                vec![(t.var(), t.var())],
+                Some((t.next(), Box::new(t.var()))),
+                Box::new(t.block()),
+            ),
+            Stml::VarDecl(
+                Some(t.next()),
+                t.next(),
+                vec![(t.var(), Some(t.var())), (t.var(), None)],
+            ),
+            Stml::Return(t.next(), Some(t.var())),
+            Stml::Throw(t.next(), Some(t.var())),
+            Stml::Rethrow(t.next()),
+            Stml::Yield(t.next(), t.var()),
+            Stml::TryCatch(
+                t.next(),
+                Box::new(t.block()),
+                t.next(),
+                t.var(),
+                Box::new(t.block()),
+            ),
+            Stml::If(
+                t.next(),
+                t.var(),
+                Box::new(t.block()),
+                vec![(t.next(), t.var(), t.block())],
+                Some((t.next(), Box::new(t.block()))),
+            ),
+            Stml::While(t.next(), t.var(), Box::new(t.block())),
+            Stml::Loop(t.next(), Box::new(t.block())),
+            Stml::Break(t.next()),
+            Stml::Continue(t.next()),
+            Stml::Import(t.next(), t.var(), t.next(), t.next()),
+            Stml::ForIn(
+                t.next(),
+                t.var(),
+                t.next(),
+                t.var(),
+                Some((t.next(), t.var())),
+                Box::new(t.block()),
+            ),
+            Stml::Match(t.next(), t.var(), arms),
+            Stml::With(t.next(), t.next(), t.next(), t.var(), Box::new(t.block())),
+        ]);
+        Stml::Block(t.next(), stmls)
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        tokens: Vec<*const Token>,
+        stmls: HashSet<usize>,
+        exprs: HashSet<usize>,
+        literals: HashSet<usize>,
+        patterns: HashSet<usize>,
+    }
+
+    impl Recorder {
+        fn assert_complete(mut self, tokens: &Tokens) {
+            assert_eq!(self.stmls.len(), STML_KINDS);
+            assert_eq!(self.exprs.len(), EXPR_KINDS);
+            assert_eq!(self.literals.len(), LITERAL_KINDS);
+            assert_eq!(self.patterns.len(), PATTERN_KINDS);
+            let mut expected: Vec<_> = tokens.0.iter().map(Rc::as_ptr).collect();
+            expected.sort();
+            self.tokens.sort();
+            // Each token is reached, and only once.
+            assert_eq!(self.tokens, expected);
+        }
+    }
+
+    impl Visitor for Recorder {
+        fn visit_stml(&mut self, stml: &Stml) {
+            self.stmls.insert(stml_kind(stml));
+            walk_stml(self, stml)
+        }
+
+        fn visit_expr(&mut self, expr: &Expr) {
+            self.exprs.insert(expr_kind(expr));
+            walk_expr(self, expr)
+        }
+
+        fn visit_literal(&mut self, literal: &Literal) {
+            self.literals.insert(literal_kind(literal));
+            walk_literal(self, literal)
+        }
+
+        fn visit_pattern(&mut self, pattern: &Pattern) {
+            self.patterns.insert(pattern_kind(pattern));
+            walk_pattern(self, pattern)
+        }
+
+        fn visit_token(&mut self, token: &Rc<Token>) {
+            self.tokens.push(Rc::as_ptr(token));
+        }
+    }
+
+    impl VisitorMut for Recorder {
+        fn visit_stml_mut(&mut self, stml: &mut Stml) {
+            self.stmls.insert(stml_kind(stml));
+            walk_stml_mut(self, stml)
+        }
+
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            self.exprs.insert(expr_kind(expr));
+            walk_expr_mut(self, expr)
+        }
+
+        fn visit_literal_mut(&mut self, literal: &mut Literal) {
+            self.literals.insert(literal_kind(literal));
+            walk_literal_mut(self, literal)
+        }
+
+        fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+            self.patterns.insert(pattern_kind(pattern));
+            walk_pattern_mut(self, pattern)
+        }
+
+        fn visit_token_mut(&mut self, token: &mut Rc<Token>) {
+            self.tokens.push(Rc::as_ptr(token));
+        }
+    }
+
+    #[test]
+    fn visits_every_node_and_token() {
+        let mut tokens = Tokens::default();
+        let program = program(&mut tokens);
+        let mut recorder = Recorder::default();
+        recorder.visit_stml(&program);
+        recorder.assert_complete(&tokens);
+    }
+
+    #[test]
+    fn visits_every_node_and_token_mutably() {
+        let mut tokens = Tokens::default();
+        let mut program = program(&mut tokens);
+        let mut recorder = Recorder::default();
+        recorder.visit_stml_mut(&mut program);
+        recorder.assert_complete(&tokens);
+    }
+}
@@ -1,5 +1,5 @@
 use colored::Colorize;
-use lexer::token::{Token, TokenType};
+use lexer::token::{Token, TokenInside, TokenType};
 use std::{fmt, rc::Rc};
 
 #[derive(Debug, Clone)]
@@ -8,6 +8,8 @@ pub enum ParseError {
     ExpectedExpr(Rc<Token>),
     InvalidRhs(Rc<Token>),
     ExpectedOptional(Rc<Token>),
+    /// The token after `صدّر`, which doesn't start a declaration.
+    InvalidExport(Rc<Token>),
 }
 
 impl fmt::Display for ParseError {
@@ -39,6 +41,44 @@ impl fmt::Display for ParseError {
             Self::ExpectedOptional(token) => {
                 write!(f, "لا يمكن وضع مدخل إجباري بعد مدخل إختياري\n{token}")
             }
+            Self::InvalidExport(token) => {
+                write!(f, "يمكن تصدير التعريفات فقط\n{token}")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseWarning {
+    /// A line starting with `(` or `[` right after an expression statement, it starts a new statement instead of
+    /// calling or indexing the previous one.
+    AmbiguousLineStart(Rc<Token>),
+}
+
+impl TokenInside for ParseWarning {
+    fn token(&self) -> Rc<Token> {
+        match self {
+            Self::AmbiguousLineStart(token) => Rc::clone(token),
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", "تحذير تحليلي: ".bright_yellow())?;
+        match self {
+            Self::AmbiguousLineStart(token) => {
+                let bracket = token.lexeme();
+                writeln!(
+                    f,
+                    "يبدأ هذا السطر بـ\"{bracket}\" بعد عبارة مكتملة فيعامل كعبارة جديدة"
+                )?;
+                writeln!(f, "{token}")?;
+                write!(
+                    f,
+                    "إقتراح: ضع \"؛\" في نهاية السطر السابق إن كان هذا مقصوداً، أو انقل \"{bracket}\" إليه لإكمال العبارة"
+                )
+            }
         }
     }
 }
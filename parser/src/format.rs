@@ -0,0 +1,601 @@
+//! Re-emitting parsed source in the canonical style: every statement on its own line indented by two spaces for every
+//! block it's in, a space around binary operators and after commas, and at most one empty line between statements.
+//!
+//! Comments aren't part of the AST, the parser skips them, so they're taken from the tokens instead. A comment is
+//! written before the statement that follows it, or at the end of the line it was at the end of (which moves the ones
+//! inside multiline expressions to the end of the expression).
+
+use super::ast::{visit::Visitor, *};
+use super::operators::{Associativity, OPERATORS};
+use lexer::token::{Token, TokenInside, TokenType};
+use std::{collections::HashMap, rc::Rc};
+
+const INDENT: &str = "  ";
+
+/// `tokens` must be the ones `ast` was parsed from, comments included.
+pub fn format(ast: &[Stml], tokens: &[Rc<Token>]) -> String {
+    let mut formatter = Formatter::new(tokens);
+    formatter.stmls(ast);
+    formatter.comments_before(usize::MAX);
+    formatter.out
+}
+
+struct Formatter<'a> {
+    tokens: &'a [Rc<Token>],
+    /// The offsets of the closing braces by the offsets of their opening braces, as blocks don't keep the former.
+    closing: HashMap<usize, usize>,
+    /// The index of the first token whose comments weren't written yet.
+    next: usize,
+    out: String,
+    depth: usize,
+    /// Whether the next write starts a line.
+    line_start: bool,
+    /// Whether nothing was written in the current block yet, empty lines aren't kept there.
+    block_start: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(tokens: &'a [Rc<Token>]) -> Self {
+        let mut closing = HashMap::new();
+        let mut open = vec![];
+        for token in tokens {
+            match token.typ() {
+                TokenType::OBrace => open.push(token.start()),
+                TokenType::CBrace => {
+                    if let Some(start) = open.pop() {
+                        closing.insert(start, token.start());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            tokens,
+            closing,
+            next: 0,
+            out: String::new(),
+            depth: 0,
+            line_start: true,
+            block_start: true,
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            self.out.push_str(&INDENT.repeat(self.depth));
+            self.line_start = false;
+        }
+        self.block_start = false;
+        self.out.push_str(text)
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.line_start = true;
+    }
+
+    /// The index of the first token at or after `offset`.
+    fn index(&self, offset: usize) -> usize {
+        self.tokens.partition_point(|token| token.start() < offset)
+    }
+
+    fn is_comment(token: &Token) -> bool {
+        matches!(
+            token.typ(),
+            TokenType::InlineComment | TokenType::BlockComment
+        )
+    }
+
+    /// Whether an empty line separates the token at `idx` from the code before it, `\r\n` counts as one line break.
+    fn empty_line_before(&self, mut idx: usize) -> bool {
+        // Grouping parentheses aren't in the AST, so the statement might start before its first token.
+        while idx > 0 && self.tokens[idx - 1].typ() == TokenType::OParen {
+            idx -= 1
+        }
+        let mut breaks = 0;
+        while idx > 0 && self.tokens[idx - 1].typ() == TokenType::NewLine {
+            let token = &self.tokens[idx - 1];
+            let crlf = token.lexeme() == "\r"
+                && self.tokens.get(idx).is_some_and(|next| {
+                    next.typ() == TokenType::NewLine && next.start() == token.start() + 1
+                });
+            if !crlf {
+                breaks += 1
+            }
+            idx -= 1
+        }
+        breaks > 1
+    }
+
+    fn empty_line(&mut self, idx: usize) {
+        if !self.block_start && self.empty_line_before(idx) {
+            self.out.push('\n')
+        }
+    }
+
+    /// Writes the comments before `offset` that weren't written yet.
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(token) = self.tokens.get(self.next) {
+            if token.start() >= offset {
+                break;
+            }
+            if Self::is_comment(token) {
+                let trailing =
+                    self.next > 0 && self.tokens[self.next - 1].typ() != TokenType::NewLine;
+                if trailing && self.line_start && !self.out.is_empty() {
+                    self.out.pop();
+                    self.out.push(' ');
+                    self.out.push_str(token.lexeme());
+                    self.out.push('\n');
+                } else {
+                    self.empty_line(self.next);
+                    self.write(token.lexeme());
+                    self.newline();
+                }
+            }
+            self.next += 1;
+        }
+    }
+
+    fn has_comments_before(&self, offset: usize) -> bool {
+        self.tokens[self.next..]
+            .iter()
+            .take_while(|token| token.start() < offset)
+            .any(|token| Self::is_comment(token))
+    }
+
+    fn stmls(&mut self, stmls: &[Stml]) {
+        let mut iter = stmls.iter().peekable();
+        while let Some(stml) = iter.next() {
+            let start = start(stml);
+            self.comments_before(start);
+            self.empty_line(self.index(start));
+            self.stml(stml);
+            // Otherwise the next line would be read as a call or an index of this one.
+            if let (Stml::Expr(_), Some(Stml::Expr(next))) = (stml, iter.peek()) {
+                if starts_with_bracket(next) {
+                    self.write("؛")
+                }
+            }
+            self.newline();
+        }
+    }
+
+    fn block(&mut self, token: &Rc<Token>, stmls: &[Stml]) {
+        let close = self
+            .closing
+            .get(&token.start())
+            .copied()
+            .unwrap_or(usize::MAX);
+        self.write("{");
+        if stmls.is_empty() && !self.has_comments_before(close) {
+            self.write("}");
+            return;
+        }
+        self.newline();
+        self.depth += 1;
+        self.block_start = true;
+        self.stmls(stmls);
+        self.comments_before(close);
+        self.depth -= 1;
+        self.write("}");
+    }
+
+    /// Bodies are always blocks when parsed, but the AST allows any statement.
+    fn body(&mut self, stml: &Stml) {
+        match stml {
+            Stml::Block(token, stmls) => self.block(token, stmls),
+            stml => self.stml(stml),
+        }
+    }
+
+    fn keyword(&mut self, token: &Rc<Token>) {
+        self.write(token.lexeme());
+        self.write(" ");
+    }
+
+    fn params(
+        &mut self,
+        required: &[Expr],
+        optional: &[(Expr, Expr)],
+        variadic: &Option<(Rc<Token>, Box<Expr>)>,
+    ) {
+        let mut first = true;
+        let mut separate = |formatter: &mut Self| {
+            if !first {
+                formatter.write("، ")
+            }
+            first = false;
+        };
+        for param in required {
+            separate(self);
+            self.expr(param);
+        }
+        for (param, default) in optional {
+            separate(self);
+            self.expr(param);
+            self.write(" = ");
+            self.expr(default);
+        }
+        if let Some((token, param)) = variadic {
+            separate(self);
+            self.write(token.lexeme());
+            self.expr(param);
+        }
+    }
+
+    fn stml(&mut self, stml: &Stml) {
+        match stml {
+            Stml::Block(token, stmls) => self.block(token, stmls),
+            Stml::FunctionDecl(export_token, token, name, required, optional, variadic, body) => {
+                if let Some(export_token) = export_token {
+                    self.keyword(export_token)
+                }
+                self.keyword(token);
+                self.write(name.lexeme());
+                self.write("(");
+                self.params(required, optional, variadic);
+                self.write(") ");
+                self.body(body);
+            }
+            Stml::VarDecl(export_token, token, decls) => {
+                if let Some(export_token) = export_token {
+                    self.keyword(export_token)
+                }
+                self.keyword(token);
+                for (idx, (definable, init)) in decls.iter().enumerate() {
+                    if idx > 0 {
+                        self.write("، ")
+                    }
+                    self.expr(definable);
+                    if let Some(init) = init {
+                        self.write(" = ");
+                        self.expr(init);
+                    }
+                }
+            }
+            Stml::Return(token, expr) | Stml::Throw(token, expr) => {
+                self.write(token.lexeme());
+                if let Some(expr) = expr {
+                    self.write(" ");
+                    self.expr(expr);
+                }
+            }
+            Stml::Rethrow(token) | Stml::Break(token) | Stml::Continue(token) => {
+                self.write(token.lexeme())
+            }
+            Stml::Yield(token, expr) => {
+                self.keyword(token);
+                self.expr(expr);
+            }
+            Stml::TryCatch(token, body, catch_token, err, catch_body) => {
+                self.keyword(token);
+                self.body(body);
+                self.write(" ");
+                self.keyword(catch_token);
+                self.expr(err);
+                self.write(" ");
+                self.body(catch_body);
+            }
+            Stml::If(token, condition, body, elseifs, else_) => {
+                self.keyword(token);
+                self.expr(condition);
+                self.write(" ");
+                self.body(body);
+                for (token, condition, body) in elseifs {
+                    self.write(" ");
+                    self.keyword(token);
+                    self.expr(condition);
+                    self.write(" ");
+                    self.body(body);
+                }
+                if let Some((token, body)) = else_ {
+                    self.write(" ");
+                    self.keyword(token);
+                    self.body(body);
+                }
+            }
+            Stml::While(token, condition, body) => {
+                self.keyword(token);
+                self.expr(condition);
+                self.write(" ");
+                self.body(body);
+            }
+            Stml::Loop(token, body) => {
+                self.keyword(token);
+                self.body(body);
+            }
+            Stml::Import(token, definable, from_token, path) => {
+                self.keyword(token);
+                self.expr(definable);
+                self.write(" ");
+                self.keyword(from_token);
+                self.write(path.lexeme());
+            }
+            Stml::ForIn(token, definable, in_token, iterable, guard, body) => {
+                self.keyword(token);
+                self.expr(definable);
+                self.write(" ");
+                self.keyword(in_token);
+                self.expr(iterable);
+                self.write(" ");
+                if let Some((when, condition)) = guard {
+                    self.keyword(when);
+                    self.expr(condition);
+                    self.write(" ");
+                }
+                self.body(body);
+            }
+            Stml::With(token, name, _, value, body) => {
+                self.keyword(token);
+                self.write(name.lexeme());
+                self.write(" = ");
+                self.expr(value);
+                self.write(" ");
+                self.body(body);
+            }
+            Stml::Match(token, value, arms) => {
+                self.keyword(token);
+                self.expr(value);
+                self.write(" ");
+                self.arms(value, arms);
+            }
+            Stml::Expr(expr) => self.expr(expr),
+        }
+    }
+
+    /// Written like a block with an arm on every line, the AST doesn't keep the braces so they're searched for after
+    /// the value.
+    fn arms(&mut self, value: &Expr, arms: &[Arm]) {
+        let span = value.span();
+        let after = self.index(span.start() + span.lexeme().len());
+        let close = self.tokens[after..]
+            .iter()
+            .find(|token| token.typ() == TokenType::OBrace)
+            .and_then(|token| self.closing.get(&token.start()))
+            .copied()
+            .unwrap_or(usize::MAX);
+        self.write("{");
+        if arms.is_empty() && !self.has_comments_before(close) {
+            self.write("}");
+            return;
+        }
+        self.newline();
+        self.depth += 1;
+        self.block_start = true;
+        for (pattern, guard, arrow, body) in arms {
+            let start = pattern.token().start();
+            self.comments_before(start);
+            self.empty_line(self.index(start));
+            self.pattern(pattern);
+            if let Some((token, condition)) = guard {
+                self.write(" ");
+                self.keyword(token);
+                self.expr(condition);
+            }
+            self.write(" ");
+            self.keyword(arrow);
+            self.body(body);
+            self.newline();
+        }
+        self.comments_before(close);
+        self.depth -= 1;
+        self.write("}");
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard(token) | Pattern::Binding(token) => self.write(token.lexeme()),
+            Pattern::Literal(literal) => self.literal(literal),
+            Pattern::List(_, patterns, rest) => {
+                self.write("[");
+                for (idx, pattern) in patterns.iter().enumerate() {
+                    if idx > 0 {
+                        self.write("، ")
+                    }
+                    self.pattern(pattern);
+                }
+                if let Some((token, name)) = rest {
+                    if !patterns.is_empty() {
+                        self.write("، ")
+                    }
+                    self.write(token.lexeme());
+                    self.write(name.lexeme());
+                }
+                self.write("]");
+            }
+            Pattern::Object(_, props) if props.is_empty() => self.write("{}"),
+            Pattern::Object(_, props) => {
+                self.write("{ ");
+                for (idx, (key, pattern)) in props.iter().enumerate() {
+                    if idx > 0 {
+                        self.write("، ")
+                    }
+                    self.write(key.lexeme());
+                    if let Some(pattern) = pattern {
+                        self.write(": ");
+                        self.pattern(pattern);
+                    }
+                }
+                self.write(" }");
+            }
+        }
+    }
+
+    fn grouped(&mut self, expr: &Expr, group: bool) {
+        if group {
+            self.write("(");
+            self.expr(expr);
+            self.write(")");
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for (idx, expr) in exprs.iter().enumerate() {
+            if idx > 0 {
+                self.write("، ")
+            }
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable(token) => self.write(token.lexeme()),
+            Expr::Literal(literal) => self.literal(literal),
+            Expr::Unary(op, expr) => {
+                self.write(op.lexeme());
+                self.grouped(expr, !is_atom(expr));
+            }
+            Expr::Binary(lhs, op, rhs) => {
+                self.grouped(lhs, lhs_needs_group(lhs, op));
+                self.write(" ");
+                self.write(op.lexeme());
+                self.write(" ");
+                self.grouped(rhs, rhs_needs_group(rhs, op));
+            }
+            Expr::Call(callee, _, args) => {
+                self.grouped(callee, !is_atom(callee));
+                self.write("(");
+                self.exprs(args);
+                self.write(")");
+            }
+            Expr::Member(expr, op, key) => {
+                self.grouped(expr, !is_atom(expr));
+                match (op.typ(), key.as_ref()) {
+                    (TokenType::Period, Expr::Literal(Literal::String(key))) => {
+                        self.write(".");
+                        self.write(key.lexeme());
+                    }
+                    (_, key) => {
+                        self.write("[");
+                        self.expr(key);
+                        self.write("]");
+                    }
+                }
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Number(token)
+            | Literal::String(token)
+            | Literal::Bool(token)
+            | Literal::Nil(token) => self.write(token.lexeme()),
+            Literal::List(_, exprs) => {
+                self.write("[");
+                self.exprs(exprs);
+                self.write("]");
+            }
+            Literal::Comprehension(_, element, for_token, definable, in_token, iterable, guard) => {
+                self.write("[");
+                self.expr(element);
+                self.write(" ");
+                self.keyword(for_token);
+                self.expr(definable);
+                self.write(" ");
+                self.keyword(in_token);
+                self.expr(iterable);
+                if let Some((when, condition)) = guard {
+                    self.write(" ");
+                    self.keyword(when);
+                    self.expr(condition);
+                }
+                self.write("]");
+            }
+            Literal::Object(_, props) if props.is_empty() => self.write("{}"),
+            Literal::Object(_, props) => {
+                self.write("{ ");
+                for (idx, (key, value, default)) in props.iter().enumerate() {
+                    if idx > 0 {
+                        self.write("، ")
+                    }
+                    self.write(key.lexeme());
+                    if let Some(value) = value {
+                        self.write(": ");
+                        self.expr(value);
+                    }
+                    if let Some((_, default)) = default {
+                        self.write(" = ");
+                        self.expr(default);
+                    }
+                }
+                self.write(" }");
+            }
+            Literal::Lambda(_, required, optional, variadic, body) => {
+                self.write("|");
+                self.params(required, optional, variadic);
+                self.write("| ");
+                self.body(body);
+            }
+        }
+    }
+}
+
+/// The offset of the first token of `stml`.
+fn start(stml: &Stml) -> usize {
+    struct Start(Option<usize>);
+
+    impl Visitor for Start {
+        fn visit_token(&mut self, token: &Rc<Token>) {
+            self.0.get_or_insert(token.start());
+        }
+    }
+
+    let mut start = Start(None);
+    start.visit_stml(stml);
+    start.0.unwrap_or(0)
+}
+
+/// Whether nothing can be mistaken as part of the expression when it's followed by a call or an operator.
+fn is_atom(expr: &Expr) -> bool {
+    match expr {
+        Expr::Variable(_) | Expr::Call(..) | Expr::Member(..) => true,
+        Expr::Literal(literal) => !matches!(literal, Literal::Lambda(..)),
+        Expr::Unary(..) | Expr::Binary(..) => false,
+    }
+}
+
+/// Smaller precedences bind tighter, prefix operators bind tighter than the infix ones and the infix operators the
+/// parser doesn't know are always grouped.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary(_, op, _) => infix_precedence(op),
+        _ => 0,
+    }
+}
+
+fn infix_precedence(op: &Token) -> u8 {
+    OPERATORS[op.typ() as usize].1.unwrap_or(u8::MAX)
+}
+
+fn is_right_associative(op: &Token) -> bool {
+    matches!(OPERATORS[op.typ() as usize].3, Some(Associativity::Right))
+}
+
+fn lhs_needs_group(lhs: &Expr, op: &Token) -> bool {
+    let op_precedence = infix_precedence(op);
+    matches!(lhs, Expr::Literal(Literal::Lambda(..)))
+        || precedence(lhs) > op_precedence
+        || precedence(lhs) == op_precedence && is_right_associative(op)
+}
+
+fn rhs_needs_group(rhs: &Expr, op: &Token) -> bool {
+    let op_precedence = infix_precedence(op);
+    precedence(rhs) > op_precedence || precedence(rhs) == op_precedence && !is_right_associative(op)
+}
+
+/// Whether the written form of the expression starts with `(` or `[`.
+fn starts_with_bracket(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(Literal::List(..) | Literal::Comprehension(..)) => true,
+        Expr::Binary(lhs, op, _) => lhs_needs_group(lhs, op) || starts_with_bracket(lhs),
+        Expr::Call(expr, ..) | Expr::Member(expr, ..) => {
+            !is_atom(expr) || starts_with_bracket(expr)
+        }
+        _ => false,
+    }
+}
@@ -1,5 +1,6 @@
 pub mod ast;
 pub mod error;
+pub mod format;
 mod operators;
 
 use ast::*;
@@ -8,6 +9,9 @@ use lexer::token::*;
 use operators::*;
 use std::rc::Rc;
 
+/// required: \[definable\], optional: \[(definable, default)\], variadic: (token, definable)
+type Params = (Vec<Expr>, Vec<(Expr, Expr)>, Option<(Rc<Token>, Box<Expr>)>);
+
 #[derive(PartialEq, Clone, Copy)]
 enum AssignAbility {
     AnyOp,
@@ -15,19 +19,36 @@ enum AssignAbility {
     None,
 }
 
+/// A statement ends at a new line or "؛", unless the line ends with an operator or inside brackets (`()`, `[]` or the
+/// braces of an object), where new lines are skipped. Blocks start counting brackets again, so their statements are
+/// still separated by new lines.
 pub struct Parser {
     tokens: Vec<Rc<Token>>,
     /// The token at current represents the next token and it should always be a valid one.
     current: usize,
     errors: Vec<Error>,
+    warnings: Vec<ParseWarning>,
+    /// The number of brackets the current token is inside of.
+    depth: usize,
+    /// Whether the previous statement was an expression statement that ended at a new line.
+    after_expr_stml: bool,
 }
 
 impl Parser {
+    /// Comments are skipped, `format` takes them from the tokens instead.
     pub fn new(tokens: Vec<Rc<Token>>) -> Self {
         Self {
-            tokens,
+            tokens: tokens
+                .into_iter()
+                .filter(|token| {
+                    ![TokenType::InlineComment, TokenType::BlockComment].contains(&token.typ())
+                })
+                .collect(),
             current: 0,
             errors: vec![],
+            warnings: vec![],
+            depth: 0,
+            after_expr_stml: false,
         }
     }
 
@@ -51,6 +72,32 @@ impl Parser {
         self.err(Error::Parse(err))
     }
 
+    fn warn(&mut self, warning: ParseWarning) {
+        self.warnings.push(warning)
+    }
+
+    /// The warnings of the parser, available even if the parsing failed.
+    pub fn warnings(&self) -> &Vec<ParseWarning> {
+        &self.warnings
+    }
+
+    /// Parses inside brackets, where new lines don't end the expression.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, ()>) -> Result<T, ()> {
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Checks if the expression ends at the next token.
+    fn at_expr_end(&mut self) -> Result<bool, ()> {
+        if self.depth > 0 {
+            self.at_end()
+        } else {
+            self.check(&[TokenType::NewLine, TokenType::Semicolon, TokenType::EOF])
+        }
+    }
+
     /// Skips new lines until it finds a valid or error token.
     fn peek_no_lines(&mut self) -> Result<Rc<Token>, ()> {
         while self.check(&[TokenType::NewLine])? {
@@ -96,7 +143,10 @@ impl Parser {
 
     /// Advance `self.current`.
     fn advance(&mut self) -> Result<(), ()> {
-        self.current += 1;
+        // Everything after the last token (`EOF`) is still the end.
+        if self.current + 1 < self.tokens.len() {
+            self.current += 1;
+        }
         self.validate_current()
     }
 
@@ -126,6 +176,8 @@ impl Parser {
     }
 
     fn expr(&mut self, min_precedence: u8, mut assign_abililty: AssignAbility) -> Result<Expr, ()> {
+        // An expression is expected, so the line can't end here (e.g. after an operator).
+        self.peek_no_lines()?;
         let token = self.next()?;
         let mut expr;
 
@@ -146,20 +198,25 @@ impl Parser {
             }
             TokenType::Minus | TokenType::Bang => {
                 assign_abililty = AssignAbility::None;
-                self.literal()?
+                let prefix_precedence = OPERATORS[token.typ() as usize].0.unwrap();
+                Expr::Unary(
+                    Rc::clone(&token),
+                    Box::new(self.expr(prefix_precedence, AssignAbility::None)?),
+                )
             }
             TokenType::OParen => {
                 assign_abililty = AssignAbility::None;
-                self.literal()?
+                let expr = self.nested(Self::parse_expr)?;
+                self.consume(&[TokenType::CParen])?;
+                expr
             }
-            TokenType::EOF => return Err(()),
             _ => {
                 self.parse_err(ParseError::ExpectedExpr(token));
                 return Err(());
             }
         };
 
-        while !self.check(&[TokenType::NewLine])? && !self.at_end()? {
+        while !self.at_expr_end()? {
             let op = self.peek();
             let row: usize = op.typ() as usize;
             if let Some(infix_precedence) = OPERATORS[row].1 {
@@ -198,7 +255,8 @@ impl Parser {
                 match op.typ() {
                     TokenType::OParen => {
                         assign_abililty = AssignAbility::None;
-                        expr = Expr::Call(Box::new(expr), op, self.exprs(TokenType::CParen)?);
+                        let args = self.nested(|parser| parser.exprs(TokenType::CParen))?;
+                        expr = Expr::Call(Box::new(expr), op, args);
                     }
                     TokenType::Period | TokenType::OBracket => {
                         match expr {
@@ -209,14 +267,15 @@ impl Parser {
                         }
                         let key = match op.typ() {
                             TokenType::Period => {
+                                self.peek_no_lines()?;
                                 self.consume(&[TokenType::Identifier])?;
                                 Expr::Literal(Literal::String(self.previous()))
                             }
-                            TokenType::OBracket => {
-                                let tmp = self.parse_expr()?;
-                                self.consume(&[TokenType::CBracket])?;
-                                tmp
-                            }
+                            TokenType::OBracket => self.nested(|parser| {
+                                let tmp = parser.parse_expr()?;
+                                parser.consume(&[TokenType::CBracket])?;
+                                Ok(tmp)
+                            })?,
                             _ => unreachable!(),
                         };
 
@@ -238,31 +297,66 @@ impl Parser {
             TokenType::String => Ok(Expr::Literal(Literal::String(token))),
             TokenType::True | TokenType::False => Ok(Expr::Literal(Literal::Bool(token))),
             TokenType::Nil => Ok(Expr::Literal(Literal::Nil(token))),
-            TokenType::OBracket => Ok(self.list()?.into()),
-            TokenType::OBrace => Ok(self.object()?.into()),
+            TokenType::OBracket => Ok(self.nested(Self::list)?.into()),
+            TokenType::OBrace => Ok(self.nested(Self::object)?.into()),
             TokenType::Pipe => Ok(self.lambda()?.into()),
             _ => unreachable!(),
         }
     }
 
     fn exprs(&mut self, closing_token: TokenType) -> Result<Vec<Expr>, ()> {
-        let mut exprs = vec![];
-        if !self.check(&[closing_token])? {
-            exprs.push(self.parse_expr()?);
-            while self.check_consume(&[TokenType::Comma])? {
-                if self.check(&[closing_token])? {
-                    break;
-                }
-                exprs.push(self.parse_expr()?)
+        if self.check_consume(&[closing_token])? {
+            return Ok(vec![]);
+        }
+        let first = self.parse_expr()?;
+        self.exprs_after(first, closing_token)
+    }
+
+    /// The rest of `exprs` after the first expression was parsed.
+    fn exprs_after(&mut self, first: Expr, closing_token: TokenType) -> Result<Vec<Expr>, ()> {
+        let mut exprs = vec![first];
+        while self.check_consume(&[TokenType::Comma])? {
+            if self.check(&[closing_token])? {
+                break;
             }
+            exprs.push(self.parse_expr()?)
         }
         self.consume(&[closing_token])?;
         Ok(exprs)
     }
 
+    /// A list whose first element is followed by `لكل` is a comprehension.
     fn list(&mut self) -> Result<Literal, ()> {
         let token = self.previous();
-        Ok(Literal::List(token, self.exprs(TokenType::CBracket)?))
+        if self.check_consume(&[TokenType::CBracket])? {
+            return Ok(Literal::List(token, vec![]));
+        }
+        let first = self.parse_expr()?;
+        if !self.check_consume(&[TokenType::For])? {
+            return Ok(Literal::List(
+                token,
+                self.exprs_after(first, TokenType::CBracket)?,
+            ));
+        }
+        let for_token = self.previous();
+        let definable = self.definable()?;
+        let in_token = self.consume(&[TokenType::In])?;
+        let iterable = self.parse_expr()?;
+        let guard = if self.check_consume(&[TokenType::When])? {
+            Some((self.previous(), Box::new(self.parse_expr()?)))
+        } else {
+            None
+        };
+        self.consume(&[TokenType::CBracket])?;
+        Ok(Literal::Comprehension(
+            token,
+            Box::new(first),
+            for_token,
+            Box::new(definable),
+            in_token,
+            Box::new(iterable),
+            guard,
+        ))
     }
 
     fn prop(&mut self) -> Result<(Rc<Token>, Option<Expr>, Option<(Rc<Token>, Expr)>), ()> {
@@ -310,16 +404,164 @@ impl Parser {
         Ok(Literal::Object(token, self.props()?))
     }
 
+    /// `|مدخلات| { ... }`, the parameters are like those of a function but between pipes.
     fn lambda(&mut self) -> Result<Literal, ()> {
-        todo!()
+        let token = self.previous();
+        let (required, optional, variadic) =
+            self.nested(|parser| parser.params(TokenType::Pipe))?;
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Literal::Lambda(
+            token,
+            required,
+            optional,
+            variadic,
+            Box::new(body),
+        ))
     }
 
     fn parse_expr(&mut self) -> Result<Expr, ()> {
         self.expr(9, AssignAbility::AnyOp)
     }
 
+    /// Parses a variable name or a list or object destructuring pattern.
     fn definable(&mut self) -> Result<Expr, ()> {
-        todo!()
+        let token = self.next()?;
+        match token.typ() {
+            TokenType::Identifier => Ok(Expr::Variable(token)),
+            TokenType::OBracket => {
+                let mut definables = vec![];
+                if !self.check(&[TokenType::CBracket])? {
+                    definables.push(self.definable()?);
+                    while self.check_consume(&[TokenType::Comma])? {
+                        if self.check(&[TokenType::CBracket])? {
+                            break;
+                        }
+                        definables.push(self.definable()?)
+                    }
+                }
+                self.consume(&[TokenType::CBracket])?;
+                Ok(Literal::List(token, definables).into())
+            }
+            TokenType::OBrace => Ok(self.object()?.into()),
+            _ => {
+                self.parse_err(ParseError::ExpectedInstead(
+                    vec![
+                        TokenType::Identifier,
+                        TokenType::OBracket,
+                        TokenType::OBrace,
+                    ],
+                    token,
+                ));
+                Err(())
+            }
+        }
+    }
+
+    /// Parses `_`, a literal, a variable name, or a list or object pattern.
+    fn pattern(&mut self) -> Result<Pattern, ()> {
+        let token = self.next()?;
+        match token.typ() {
+            TokenType::Identifier if token.lexeme() == "_" => Ok(Pattern::Wildcard(token)),
+            TokenType::Identifier => Ok(Pattern::Binding(token)),
+            TokenType::Number
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil => match self.literal()? {
+                Expr::Literal(literal) => Ok(Pattern::Literal(literal)),
+                _ => unreachable!(),
+            },
+            TokenType::OBracket => self.nested(Self::list_pattern),
+            TokenType::OBrace => self.nested(Self::object_pattern),
+            _ => {
+                self.parse_err(ParseError::ExpectedInstead(
+                    vec![
+                        TokenType::Identifier,
+                        TokenType::Number,
+                        TokenType::String,
+                        TokenType::OBracket,
+                        TokenType::OBrace,
+                    ],
+                    token,
+                ));
+                Err(())
+            }
+        }
+    }
+
+    /// The rest (`...name`) can only be the last element.
+    fn list_pattern(&mut self) -> Result<Pattern, ()> {
+        let token = self.previous();
+        let mut patterns = vec![];
+        let mut rest = None;
+        while !self.check(&[TokenType::CBracket])? {
+            if self.check_consume(&[TokenType::TPeriod])? {
+                rest = Some((self.previous(), self.consume(&[TokenType::Identifier])?));
+                self.check_consume(&[TokenType::Comma])?;
+                break;
+            }
+            patterns.push(self.pattern()?);
+            if !self.check_consume(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        self.consume(&[TokenType::CBracket])?;
+        Ok(Pattern::List(token, patterns, rest))
+    }
+
+    fn object_pattern(&mut self) -> Result<Pattern, ()> {
+        let token = self.previous();
+        let mut props = vec![];
+        while !self.check(&[TokenType::CBrace])? {
+            let key = self.consume(&[TokenType::Identifier])?;
+            let pattern = if self.check_consume(&[TokenType::Colon])? {
+                Some(self.pattern()?)
+            } else {
+                None
+            };
+            props.push((key, pattern));
+            if !self.check_consume(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        self.consume(&[TokenType::CBrace])?;
+        Ok(Pattern::Object(token, props))
+    }
+
+    /// `مع اسم = قيمة { ... }`, the resource is bound to a name (rather than any definable) as it's what gets closed.
+    fn with_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let name = self.consume(&[TokenType::Identifier])?;
+        let equal = self.consume(&[TokenType::Equal])?;
+        let value = self.parse_expr()?;
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Stml::With(token, name, equal, value, Box::new(body)))
+    }
+
+    /// Every arm is a pattern, optionally followed by `إذا` and a condition, then `=>` and a statement. The arms are
+    /// separated by new lines like the statements of a block.
+    fn match_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let value = self.parse_expr()?;
+        self.consume(&[TokenType::OBrace])?;
+        let depth = std::mem::take(&mut self.depth);
+        let mut arms = vec![];
+        while !self.check(&[TokenType::CBrace, TokenType::EOF])? {
+            let pattern = self.pattern()?;
+            let guard = if self.check_consume(&[TokenType::When])? {
+                Some((self.previous(), self.parse_expr()?))
+            } else {
+                None
+            };
+            let arrow = self.consume(&[TokenType::FatArrow])?;
+            arms.push((pattern, guard, arrow, self.stml()?));
+        }
+        self.consume(&[TokenType::CBrace])?;
+        self.depth = depth;
+        self.after_expr_stml = false;
+        Ok(Stml::Match(token, value, arms))
     }
 
     fn import_stml(&mut self) -> Result<Stml, ()> {
@@ -330,50 +572,300 @@ impl Parser {
         Ok(Stml::Import(token, definable, from_token, path))
     }
 
-    fn expr_stml(&mut self) -> Result<Stml, ()> {
-        Ok(Stml::Expr(self.parse_expr()?))
+    /// Parses the parameters up to `closing`, the optional ones (which have defaults) can't be followed by required ones
+    /// and the variadic one (`...name`) can only be the last.
+    fn params(&mut self, closing: TokenType) -> Result<Params, ()> {
+        let (mut required, mut optional, mut variadic) = (vec![], vec![], None);
+        while !self.check(&[closing])? {
+            if self.check_consume(&[TokenType::TPeriod])? {
+                variadic = Some((self.previous(), Box::new(self.definable()?)));
+                self.check_consume(&[TokenType::Comma])?;
+                break;
+            }
+            let definable = self.definable()?;
+            if self.check_consume(&[TokenType::Equal])? {
+                optional.push((definable, self.parse_expr()?));
+            } else {
+                if !optional.is_empty() {
+                    self.parse_err(ParseError::ExpectedOptional(definable.token()));
+                }
+                required.push(definable);
+            }
+            if !self.check_consume(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        self.consume(&[closing])?;
+        Ok((required, optional, variadic))
+    }
+
+    /// Expects the opening brace to be consumed.
+    fn block(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let depth = std::mem::take(&mut self.depth);
+        let mut stmls = vec![];
+        while !self.check(&[TokenType::CBrace, TokenType::EOF])? {
+            stmls.push(self.stml()?);
+        }
+        self.consume(&[TokenType::CBrace])?;
+        self.depth = depth;
+        self.after_expr_stml = false;
+        Ok(Stml::Block(token, stmls))
+    }
+
+    fn function_decl(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let name = self.consume(&[TokenType::Identifier])?;
+        self.consume(&[TokenType::OParen])?;
+        let (required, optional, variadic) =
+            self.nested(|parser| parser.params(TokenType::CParen))?;
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Stml::FunctionDecl(
+            None,
+            token,
+            name,
+            required,
+            optional,
+            variadic,
+            Box::new(body),
+        ))
+    }
+
+    /// Declares one or more comma separated definables, each with an optional initializer.
+    fn var_decl(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let mut decls = vec![];
+        loop {
+            let definable = self.definable()?;
+            let init = if self.check_consume(&[TokenType::Equal])? {
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            decls.push((definable, init));
+            if !self.check_consume(&[TokenType::Comma])? {
+                break;
+            }
+        }
+        Ok(Stml::VarDecl(None, token, decls))
+    }
+
+    /// The error can be bound to any definable, optionally wrapped in parentheses.
+    fn try_catch_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        let catch_token = self.consume(&[TokenType::Catch])?;
+        let err = if self.check_consume(&[TokenType::OParen])? {
+            let err = self.definable()?;
+            self.consume(&[TokenType::CParen])?;
+            err
+        } else {
+            self.definable()?
+        };
+        self.consume(&[TokenType::OBrace])?;
+        let catch_body = self.block()?;
+        Ok(Stml::TryCatch(
+            token,
+            Box::new(body),
+            catch_token,
+            err,
+            Box::new(catch_body),
+        ))
+    }
+
+    /// The condition of `إن` and every `وإن` is followed by a block, `إلا` can only come last.
+    fn if_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let condition = self.parse_expr()?;
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        let mut elseifs = vec![];
+        while self.check_consume(&[TokenType::ElseIf])? {
+            let elseif_token = self.previous();
+            let condition = self.parse_expr()?;
+            self.consume(&[TokenType::OBrace])?;
+            elseifs.push((elseif_token, condition, self.block()?));
+        }
+        let else_ = if self.check_consume(&[TokenType::Else])? {
+            let else_token = self.previous();
+            self.consume(&[TokenType::OBrace])?;
+            Some((else_token, Box::new(self.block()?)))
+        } else {
+            None
+        };
+        Ok(Stml::If(token, condition, Box::new(body), elseifs, else_))
+    }
+
+    fn while_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let condition = self.parse_expr()?;
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Stml::While(token, condition, Box::new(body)))
+    }
+
+    fn loop_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Stml::Loop(token, Box::new(body)))
+    }
+
+    /// The value of `أرجع` and `ألقي` is optional, they have none if the statement ends right after them.
+    fn optional_value(&mut self) -> Result<Option<Expr>, ()> {
+        if self.check(&[
+            TokenType::NewLine,
+            TokenType::Semicolon,
+            TokenType::CBrace,
+            TokenType::EOF,
+        ])? {
+            Ok(None)
+        } else {
+            Ok(Some(self.parse_expr()?))
+        }
+    }
+
+    /// `لكل عنصر في متسلسلة { ... }`, the definable and the iterable can be wrapped in parentheses, and `إذا` followed by
+    /// a condition skips the elements it doesn't hold for.
+    fn for_in_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let header = |parser: &mut Self| {
+            let definable = parser.definable()?;
+            let in_token = parser.consume(&[TokenType::In])?;
+            Ok((definable, in_token, parser.parse_expr()?))
+        };
+        let (definable, in_token, iterable) = if self.check_consume(&[TokenType::OParen])? {
+            let header = self.nested(header)?;
+            self.consume(&[TokenType::CParen])?;
+            header
+        } else {
+            header(self)?
+        };
+        let guard = if self.check_consume(&[TokenType::When])? {
+            Some((self.previous(), self.parse_expr()?))
+        } else {
+            None
+        };
+        self.consume(&[TokenType::OBrace])?;
+        let body = self.block()?;
+        Ok(Stml::ForIn(
+            token,
+            definable,
+            in_token,
+            iterable,
+            guard,
+            Box::new(body),
+        ))
+    }
+
+    /// `صدّر` prefixes a function or variable declaration, destructuring ones included, and marks it as exported.
+    fn export_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let next = self.peek_no_lines()?;
+        if ![TokenType::Function, TokenType::Generator, TokenType::Var].contains(&next.typ()) {
+            self.parse_err(ParseError::InvalidExport(next));
+            return Err(());
+        }
+        match self.unterminated_stml()? {
+            Stml::FunctionDecl(None, decl_token, name, required, optional, variadic, body) => {
+                Ok(Stml::FunctionDecl(
+                    Some(token),
+                    decl_token,
+                    name,
+                    required,
+                    optional,
+                    variadic,
+                    body,
+                ))
+            }
+            Stml::VarDecl(None, decl_token, decls) => {
+                Ok(Stml::VarDecl(Some(token), decl_token, decls))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn yield_stml(&mut self) -> Result<Stml, ()> {
+        let token = self.previous();
+        let value = self.parse_expr()?;
+        Ok(Stml::Yield(token, value))
+    }
+
+    fn expr_stml(&mut self, after_expr_stml: bool) -> Result<Stml, ()> {
+        let token = self.peek_no_lines()?;
+        if after_expr_stml && [TokenType::OParen, TokenType::OBracket].contains(&token.typ()) {
+            self.warn(ParseWarning::AmbiguousLineStart(token))
+        }
+        let expr = self.parse_expr()?;
+        self.after_expr_stml = self.check(&[TokenType::NewLine])?;
+        Ok(Stml::Expr(expr))
     }
 
     fn stml(&mut self) -> Result<Stml, ()> {
+        let stml = self.unterminated_stml()?;
+        if self.peek().typ() == TokenType::Semicolon {
+            self.advance()?;
+        }
+        Ok(stml)
+    }
+
+    fn unterminated_stml(&mut self) -> Result<Stml, ()> {
+        let after_expr_stml = std::mem::take(&mut self.after_expr_stml);
         if self.check_consume(&[TokenType::Import])? {
             self.import_stml()
-        } else if self.check_consume(&[TokenType::Function])? {
-            todo!()
+        } else if self.check_consume(&[TokenType::Function, TokenType::Generator])? {
+            self.function_decl()
         } else if self.check_consume(&[TokenType::Var])? {
-            todo!()
+            self.var_decl()
         } else if self.check_consume(&[TokenType::While])? {
-            todo!()
+            self.while_stml()
         } else if self.check_consume(&[TokenType::Loop])? {
-            todo!()
+            self.loop_stml()
         } else if self.check_consume(&[TokenType::If])? {
-            todo!()
+            self.if_stml()
         } else if self.check_consume(&[TokenType::Try])? {
-            todo!()
+            self.try_catch_stml()
         } else if self.check_consume(&[TokenType::OBrace])? {
-            todo!()
+            self.block()
         } else if self.check_consume(&[TokenType::Break])? {
-            todo!()
+            Ok(Stml::Break(self.previous()))
         } else if self.check_consume(&[TokenType::Continue])? {
-            todo!()
+            Ok(Stml::Continue(self.previous()))
         } else if self.check_consume(&[TokenType::Return])? {
-            todo!()
+            let token = self.previous();
+            Ok(Stml::Return(token, self.optional_value()?))
         } else if self.check_consume(&[TokenType::Throw])? {
-            todo!()
+            let token = self.previous();
+            Ok(Stml::Throw(token, self.optional_value()?))
+        } else if self.check_consume(&[TokenType::Rethrow])? {
+            Ok(Stml::Rethrow(self.previous()))
+        } else if self.check_consume(&[TokenType::Yield])? {
+            self.yield_stml()
         } else if self.check_consume(&[TokenType::Export])? {
-            todo!()
+            self.export_stml()
         } else if self.check_consume(&[TokenType::For])? {
-            todo!()
+            self.for_in_stml()
+        } else if self.check_consume(&[TokenType::Match])? {
+            self.match_stml()
+        } else if self.check_consume(&[TokenType::With])? {
+            self.with_stml()
         } else {
-            self.expr_stml()
+            self.expr_stml(after_expr_stml)
         }
     }
 
     #[allow(unused_must_use)]
     fn sync(&mut self) {
+        // Errors are only recovered from at the top level.
+        self.depth = 0;
+        self.after_expr_stml = false;
         while !self.at_end().unwrap_or(false)
             && !self
                 .check(&[
                     TokenType::Function,
+                    TokenType::Generator,
                     TokenType::Var,
                     TokenType::While,
                     TokenType::Loop,
@@ -384,9 +876,13 @@ impl Parser {
                     TokenType::Continue,
                     TokenType::Return,
                     TokenType::Throw,
+                    TokenType::Rethrow,
+                    TokenType::Yield,
                     TokenType::Import,
                     TokenType::Export,
                     TokenType::For,
+                    TokenType::Match,
+                    TokenType::With,
                 ])
                 .unwrap_or(false)
         {
@@ -395,9 +891,14 @@ impl Parser {
     }
 
     #[allow(unused_must_use)]
-    pub fn parse(mut self) -> Result<Vec<Stml>, Vec<Error>> {
-        println!("[PARSER] started");
-        self.validate_current();
+    pub fn parse(&mut self) -> Result<Vec<Stml>, Vec<Error>> {
+        if cfg!(feature = "verbose") {
+            println!("[PARSER] started")
+        }
+        // The first token is never advanced to, so it's validated here.
+        if self.validate_current().is_err() {
+            self.sync()
+        }
         let mut ast = vec![];
         while !self.at_end().unwrap_or(false) {
             match self.stml() {
@@ -415,7 +916,7 @@ impl Parser {
             if cfg!(feature = "verbose") {
                 println!("[PARSER] failed")
             }
-            Err(self.errors)
+            Err(self.errors.clone())
         }
     }
 }
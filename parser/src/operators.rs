@@ -30,17 +30,17 @@ pub const OPERATORS: [(Option<u8>, Option<u8>, Option<u8>, Option<Associativity>
     (None, Some(9), None, Some(Associativity::Right)),   // 19
     (None, Some(9), None, Some(Associativity::Right)),   // 20
     (None, Some(9), None, Some(Associativity::Right)),   // 21
-    (None, None, None, None),                            // 22
-    (None, None, None, None),                            // 23
+    (None, Some(6), None, Some(Associativity::Left)),    // 22
+    (Some(2), None, None, None),                         // 23
     (None, Some(6), None, Some(Associativity::Left)),    // 24
-    (Some(2), None, None, None),                         // 25
-    (None, Some(6), None, Some(Associativity::Left)),    // 26
+    (None, Some(5), None, Some(Associativity::Left)),    // 25
+    (None, Some(5), None, Some(Associativity::Left)),    // 26
     (None, Some(5), None, Some(Associativity::Left)),    // 27
     (None, Some(5), None, Some(Associativity::Left)),    // 28
-    (None, Some(5), None, Some(Associativity::Left)),    // 29
-    (None, Some(5), None, Some(Associativity::Left)),    // 30
-    (None, Some(7), None, Some(Associativity::Left)),    // 31
-    (None, Some(8), None, Some(Associativity::Left)),    // 32
+    (None, Some(7), None, Some(Associativity::Left)),    // 29
+    (None, Some(8), None, Some(Associativity::Left)),    // 30
+    (None, None, None, None),                            // 31
+    (None, None, None, None),                            // 32
     (None, None, None, None),                            // 33
     (None, None, None, None),                            // 34
     (None, None, None, None),                            // 35
@@ -71,4 +71,12 @@ pub const OPERATORS: [(Option<u8>, Option<u8>, Option<u8>, Option<Associativity>
     (None, None, None, None),                            // 60
     (None, None, None, None),                            // 61
     (None, None, None, None),                            // 62
+    (None, None, None, None),                            // 63
+    (None, None, None, None),                            // 64
+    (None, None, None, None),                            // 65
+    (None, None, None, None),                            // 66
+    (None, None, None, None),                            // 67
+    (None, None, None, None),                            // 68
+    (None, None, None, None),                            // 69
+    (None, None, None, None),                            // 70
 ];
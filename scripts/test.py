@@ -1,28 +1,53 @@
 import sys
-from os import listdir
+from os import environ, listdir
 from shutil import rmtree
 from pathlib import Path
 import subprocess
 from difflib import Differ
 from datetime import datetime
-from typing import Tuple
+from typing import Dict, List, Tuple
 
 DEFAULT_DIR = Path("tests")
 BIN_DIR = Path("target/release")
 
 
-def build():
-    subprocess.run(["cargo", "build", "--release"])
+def build(features: List[str] = []):
+    subprocess.run(["cargo", "build", "--release",
+                   *(["--features", ",".join(features)] if features else [])])
 
 
 def serialize(returncode, stdout, stderr):
     return f"returncode: {returncode}\nstdout:\n{stdout}stderr:\n{stderr}"
 
 
+def get_settings(path: Path) -> List[str]:
+    """Reads the settings the file should be run with from `<name>.إعدادات` (one per line) if it exists."""
+    settings_path = path.with_name(f"{path.stem}.إعدادات")
+    if not settings_path.exists():
+        return []
+    return open(settings_path, "r", encoding="utf-8").read().split()
+
+
+def get_env(path: Path) -> Dict[str, str]:
+    """Extends the current environment with the variables defined in `<name>.بيئة` (`NAME=VALUE` per line) if it exists."""
+    env = dict(environ)
+    env_path = path.with_name(f"{path.stem}.بيئة")
+    if env_path.exists():
+        for line in open(env_path, "r", encoding="utf-8").read().splitlines():
+            if line:
+                (name, value) = line.split("=", 1)
+                env[name] = value
+    return env
+
+
 def execute(path: Path) -> Tuple[str, str]:
+    settings = get_settings(path)
+    # a "-" setting means that the file should be piped through the standard input instead
+    (args, input) = (settings, open(path, "r", encoding="utf-8").read()
+                     ) if "-" in settings else ([*settings, path], None)
     start = datetime.now()
     process = subprocess.run(
-        [BIN_DIR.joinpath("قتام.exe"), path], stdout=subprocess.PIPE, stderr=subprocess.PIPE, text=True, encoding="utf-8")
+        [BIN_DIR.joinpath("قتام.exe"), *args], input=input, stdout=subprocess.PIPE, stderr=subprocess.PIPE, text=True, encoding="utf-8", env=get_env(path))
     bench = {"path": path.as_posix(), "ms": round(
         (datetime.now() - start).total_seconds() * 1000)}
     return (serialize(process.returncode, process.stdout, process.stderr), bench)
@@ -43,7 +68,7 @@ def sync(dir: Path, should_build: bool = True):
     clean(dir)
     benches = []
     try:
-        for name in listdir(dir):
+        for name in sorted(listdir(dir)):
             path = dir.joinpath(name)
             if path.is_dir():
                 sync(path, False)
@@ -70,7 +95,7 @@ def run(dir: Path, should_build: bool = True):
         build()
     benches = []
     try:
-        for name in listdir(dir):
+        for name in sorted(listdir(dir)):
             path = dir.joinpath(name)
             if path.is_dir() and name != "النتائج":
                 run(path, False)
@@ -91,9 +116,15 @@ def run(dir: Path, should_build: bool = True):
             bench["path"], to_symbol(bench["succeeded"]), bench["ms"]))
 
 
+def check(dir: Path):
+    """Like `run` but with the VM verifying its stacks after every instruction, which panics on bugs that don't change the output."""
+    build(["invariant-checks"])
+    run(dir, False)
+
+
 def clean(dir: Path):
     try:
-        for name in listdir(dir):
+        for name in sorted(listdir(dir)):
             path = dir.joinpath(name)
             if path.is_dir() and name == "النتائج":
                 rmtree(path)
@@ -106,6 +137,7 @@ def clean(dir: Path):
 subcommands = {
     "sync": {"func": sync, "desc": f"Runs all of the files inside the dir speceified as an argument (or the default one which is {DEFAULT_DIR}) and creates snapshots."},
     "run": {"func": run, "desc": "Runs all of the files inside the dir and compares the result with the snapshots."},
+    "check": {"func": check, "desc": "Like run but with the VM's internal invariant checks enabled."},
     "clean": {"func": clean, "desc": "Cleans all of the snapshots in the passed dir"}
 }
 
@@ -0,0 +1,94 @@
+//! Converting rows of fields to and from CSV.
+//!
+//! Fields are separated by commas and rows by "\n", "\r\n", or "\r". A field can be quoted to hold commas, newlines,
+//! and quotes (which are doubled inside it). Rows are kept as they are, so they don't need to have the same number of
+//! fields.
+
+use std::fmt;
+
+/// Positions are counted in characters from the start of the input.
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// The position of the opening quote.
+    UnterminatedQuote(usize),
+    CharAfterQuote(char, usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnterminatedQuote(pos) => {
+                write!(f, "لم تغلق علامة التنصيص المفتوحة في الموضع {pos}")
+            }
+            Self::CharAfterQuote(c, pos) => write!(
+                f,
+                "حرف غير متوقع '{c}' بعد علامة التنصيص المغلقة في الموضع {pos}، توقعت فاصلة أو سطراً جديداً"
+            ),
+        }
+    }
+}
+
+/// A trailing newline ends the last row instead of starting an empty one, so an empty text has no rows.
+pub fn parse(csv: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut chars = csv.chars().enumerate().peekable();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) if chars.peek().map(|&(_, c)| c) == Some('"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        Some((_, '"')) => break,
+                        Some((_, c)) => field.push(c),
+                        None => return Err(Error::UnterminatedQuote(pos)),
+                    }
+                }
+                match chars.peek() {
+                    None | Some((_, ',' | '\n' | '\r')) => {}
+                    Some(&(pos, c)) => return Err(Error::CharAfterQuote(c, pos)),
+                }
+            }
+            ',' => row.push(std::mem::take(&mut field)),
+            '\n' | '\r' => {
+                if c == '\r' && chars.peek().map(|&(_, c)| c) == Some('\n') {
+                    chars.next();
+                }
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    // Whether the text ended in the middle of a row, which an empty first field of it doesn't tell.
+    if !row.is_empty() || !field.is_empty() || csv.ends_with(',') || csv.ends_with('"') {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Fields with commas, quotes, or newlines are quoted, every row ends with "\n".
+pub fn serialize(rows: &[Vec<String>]) -> String {
+    let mut buf = String::new();
+    for row in rows {
+        for (idx, field) in row.iter().enumerate() {
+            if idx > 0 {
+                buf.push(',');
+            }
+            if field.contains([',', '"', '\n', '\r']) {
+                buf.push('"');
+                buf.push_str(&field.replace('"', "\"\""));
+                buf.push('"');
+            } else {
+                buf.push_str(field);
+            }
+        }
+        buf.push('\n');
+    }
+    buf
+}
@@ -0,0 +1,154 @@
+//! Describing the natives for generating their documentation.
+
+use super::json;
+use compiler::chunk::value::{Arity, ArityType};
+use std::fmt::{self, Write};
+
+/// The sections of the catalogue, in the order they're rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Output,
+    Logging,
+    Testing,
+    Errors,
+    Files,
+    Json,
+    Csv,
+    Values,
+    Sets,
+    Lists,
+    Streams,
+    Tasks,
+    Strings,
+    Integers,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Output => "الإخراج",
+                Self::Logging => "التسجيل",
+                Self::Testing => "الاختبار",
+                Self::Errors => "الأخطاء",
+                Self::Files => "الملفات",
+                Self::Json => "جسون",
+                Self::Csv => "CSV",
+                Self::Values => "القيم",
+                Self::Sets => "المجموعات",
+                Self::Lists => "القوائم",
+                Self::Streams => "التيارات",
+                Self::Tasks => "المهام",
+                Self::Strings => "النصوص",
+                Self::Integers => "الأعداد الصحيحة",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamDoc {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeDoc {
+    pub name: &'static str,
+    pub category: Category,
+    pub arity: Arity,
+    /// A single line.
+    pub description: &'static str,
+    /// The required parameters come first, then the optional ones.
+    pub params: Vec<ParamDoc>,
+    /// Snippets of code, each can span multiple lines.
+    pub examples: Vec<&'static str>,
+}
+
+impl NativeDoc {
+    /// Like `إكتب(مسار، محتوى، [خيارات])`.
+    pub fn signature(&self) -> String {
+        let required = self.arity.required();
+        let params = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(idx, param)| match self.arity.typ() {
+                _ if idx < required => param.name.to_owned(),
+                ArityType::Fixed => format!("[{}]", param.name),
+                ArityType::Variadic => format!("...{}", param.name),
+            })
+            .collect::<Vec<_>>();
+        format!("{}({})", self.name, params.join("، "))
+    }
+}
+
+/// Has a section for every category, in which every native has a heading with its signature followed by its
+/// description, parameters, and examples.
+pub fn markdown(docs: &[NativeDoc]) -> String {
+    let mut buf = String::from("# الدوال المدمجة\n");
+    let mut category = None;
+    for doc in docs {
+        if category != Some(doc.category) {
+            category = Some(doc.category);
+            write!(buf, "\n## {}\n", doc.category).unwrap();
+        }
+        write!(buf, "\n### `{}`\n\n{}\n", doc.signature(), doc.description).unwrap();
+        if !doc.params.is_empty() {
+            buf.push('\n');
+            for param in &doc.params {
+                writeln!(buf, "- `{}`: {}", param.name, param.description).unwrap();
+            }
+        }
+        for example in &doc.examples {
+            write!(buf, "\n```قتام\n{example}\n```\n").unwrap();
+        }
+    }
+    buf
+}
+
+fn json_string(string: &str) -> String {
+    let mut buf = String::new();
+    json::write_string(&mut buf, string);
+    buf
+}
+
+/// Puts every element on its own line, indented one level deeper than `indent`.
+fn json_array(elements: impl Iterator<Item = String>, indent: &str) -> String {
+    let elements = elements
+        .map(|element| format!("{indent}  {element}"))
+        .collect::<Vec<_>>();
+    if elements.is_empty() {
+        "[]".to_owned()
+    } else {
+        format!("[\n{}\n{indent}]", elements.join(",\n"))
+    }
+}
+
+/// An array with an object for every native, indented so that it can be read and diffed.
+pub fn json(docs: &[NativeDoc]) -> String {
+    let natives = docs.iter().map(|doc| {
+        let params = doc.params.iter().map(|param| {
+            format!(
+                "{{ \"الاسم\": {}, \"الوصف\": {} }}",
+                json_string(param.name),
+                json_string(param.description)
+            )
+        });
+        let examples = doc.examples.iter().map(|example| json_string(example));
+        let fields = [
+            format!("\"الاسم\": {}", json_string(doc.name)),
+            format!("\"الفئة\": {}", json_string(&doc.category.to_string())),
+            format!("\"المطلوب\": {}", doc.arity.required()),
+            format!("\"الاختياري\": {}", doc.arity.optional()),
+            format!("\"متغير\": {}", doc.arity.typ() == ArityType::Variadic),
+            format!("\"الوصف\": {}", json_string(doc.description)),
+            format!("\"المعاملات\": {}", json_array(params, "    ")),
+            format!("\"الأمثلة\": {}", json_array(examples, "    ")),
+        ];
+        format!("{{\n    {}\n  }}", fields.join(",\n    "))
+    });
+    format!("{}\n", json_array(natives, ""))
+}
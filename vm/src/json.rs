@@ -0,0 +1,308 @@
+//! Converting values to and from JSON.
+//!
+//! Numbers are `f64`s so integers beyond ±(2^53 - 1) can't be represented exactly, instead of silently rounding them
+//! parsing fails unless the caller asks for them to be kept as strings.
+
+use compiler::chunk::value::{DataType, Object, TooLarge, Traversal, TraversalLimits, Value};
+use std::{collections::HashMap, fmt, fmt::Write};
+
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Positions are counted in characters from the start of the input.
+#[derive(Debug, Clone)]
+pub enum Error {
+    UnexpectedChar(char, usize),
+    UnexpectedEnd,
+    InsufficientPrecision(String, usize),
+    TrailingChars(usize),
+    Unserializable(DataType),
+    TooLarge(TooLarge),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(c, pos) => write!(f, "حرف غير متوقع '{c}' في الموضع {pos}"),
+            Self::UnexpectedEnd => write!(f, "انتهى النص قبل اكتمال القيمة"),
+            Self::InsufficientPrecision(number, pos) => {
+                write!(f, "دقة غير كافية لتمثيل العدد {number} في الموضع {pos}")
+            }
+            Self::TrailingChars(pos) => write!(f, "توجد حروف زائدة بعد القيمة في الموضع {pos}"),
+            Self::Unserializable(typ) => write!(f, "لا يمكن تحويل {typ} إلى جسون"),
+            Self::TooLarge(..) => write!(f, "بنية كبيرة جداً للمقارنة"),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Returns integers that don't fit in a `f64` as strings instead of failing.
+    preserve_precision: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<char, Error> {
+        let c = self.peek().ok_or(Error::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(Error::UnexpectedChar(c, self.pos - 1)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, Error> {
+        for c in keyword.chars() {
+            self.expect(c)?
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+        let value = match self.peek().ok_or(Error::UnexpectedEnd)? {
+            'n' => self.keyword("null", Value::Nil)?,
+            't' => self.keyword("true", Value::from(true))?,
+            'f' => self.keyword("false", Value::from(false))?,
+            '"' => Value::from(self.string()?),
+            '[' => self.list()?,
+            '{' => self.hash_map()?,
+            '-' | '0'..='9' => self.number()?,
+            c => return Err(Error::UnexpectedChar(c, self.pos)),
+        };
+        self.skip_whitespace();
+        Ok(value)
+    }
+
+    fn digits(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            '0'..='9' => {}
+            c => return Err(Error::UnexpectedChar(c, self.pos - 1)),
+        }
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        let mut integer = true;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        self.digits()?;
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            integer = false;
+            self.digits()?;
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.pos += 1;
+            integer = false;
+            if let Some('+' | '-') = self.peek() {
+                self.pos += 1;
+            }
+            self.digits()?;
+        }
+        let lexeme = self.chars[start..self.pos].iter().collect::<String>();
+        let number = lexeme.parse::<f64>().unwrap();
+        if integer && number.abs() > MAX_SAFE_INTEGER {
+            if self.preserve_precision {
+                return Ok(Value::from(lexeme));
+            }
+            return Err(Error::InsufficientPrecision(lexeme, start));
+        }
+        Ok(Value::from(number))
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let c = self.next()?;
+            code = code * 16
+                + c.to_digit(16)
+                    .ok_or(Error::UnexpectedChar(c, self.pos - 1))?;
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut buf = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(buf),
+                '\\' => match self.next()? {
+                    '"' => buf.push('"'),
+                    '\\' => buf.push('\\'),
+                    '/' => buf.push('/'),
+                    'b' => buf.push('\u{8}'),
+                    'f' => buf.push('\u{c}'),
+                    'n' => buf.push('\n'),
+                    'r' => buf.push('\r'),
+                    't' => buf.push('\t'),
+                    'u' => {
+                        let start = self.pos - 2;
+                        let mut code = self.hex()?;
+                        // A high surrogate must be followed by an escaped low one.
+                        if (0xD800..0xDC00).contains(&code) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.hex()?;
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return Err(Error::UnexpectedChar('\\', self.pos - 6));
+                            }
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        buf.push(char::from_u32(code).ok_or(Error::UnexpectedChar('\\', start))?)
+                    }
+                    c => return Err(Error::UnexpectedChar(c, self.pos - 1)),
+                },
+                c if c < ' ' => return Err(Error::UnexpectedChar(c, self.pos - 1)),
+                c => buf.push(c),
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, Error> {
+        self.expect('[')?;
+        let mut list = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::from(list));
+        }
+        loop {
+            list.push(self.value()?);
+            match self.next()? {
+                ',' => {}
+                ']' => return Ok(Value::from(list)),
+                c => return Err(Error::UnexpectedChar(c, self.pos - 1)),
+            }
+        }
+    }
+
+    /// Duplicate keys are allowed, the last one wins like in object literals.
+    fn hash_map(&mut self) -> Result<Value, Error> {
+        self.expect('{')?;
+        let mut hash_map = HashMap::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::from(hash_map));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            hash_map.insert(key, self.value()?);
+            match self.next()? {
+                ',' => {}
+                '}' => return Ok(Value::from(hash_map)),
+                c => return Err(Error::UnexpectedChar(c, self.pos - 1)),
+            }
+        }
+    }
+}
+
+/// Fails if anything other than whitespace follows the top-level value.
+pub fn parse(json: &str, preserve_precision: bool) -> Result<Value, Error> {
+    let mut parser = Parser {
+        chars: json.chars().collect(),
+        pos: 0,
+        preserve_precision,
+    };
+    let value = parser.value()?;
+    match parser.peek() {
+        Some(_) => Err(Error::TrailingChars(parser.pos)),
+        None => Ok(value),
+    }
+}
+
+pub(crate) fn write_string(buf: &mut String, string: &str) {
+    buf.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if c < ' ' => write!(buf, "\\u{:04x}", c as u32).unwrap(),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+/// Containers are written as they're reached and closed once their values are written.
+enum Piece {
+    /// Written after the text before it (e.g. a separator or a key).
+    Value(String, Value),
+    Close(char),
+}
+
+pub fn serialize(value: &Value, limits: TraversalLimits) -> Result<String, Error> {
+    let mut buf = String::new();
+    let mut traversal = Traversal::new(limits);
+    traversal.push(Piece::Value(String::new(), value.clone()));
+    while let Some(piece) = traversal.next().map_err(Error::TooLarge)? {
+        let value = match piece {
+            Piece::Value(before, value) => {
+                buf.push_str(&before);
+                value
+            }
+            Piece::Close(c) => {
+                buf.push(c);
+                continue;
+            }
+        };
+        match value {
+            Value::Nil => buf.push_str("null"),
+            Value::Bool(bool) => write!(buf, "{bool}").unwrap(),
+            Value::Number(number) if number.is_finite() => write!(buf, "{value}").unwrap(),
+            Value::Number(_) => buf.push_str("null"),
+            Value::String(string) => write_string(&mut buf, &string),
+            Value::Object(Object::List(list)) => {
+                buf.push('[');
+                traversal.push(Piece::Close(']'));
+                for (idx, value) in list.borrow().iter().enumerate().rev() {
+                    let before = if idx == 0 { "" } else { "," };
+                    traversal.push(Piece::Value(before.to_owned(), value.clone()))
+                }
+            }
+            Value::Object(Object::HashMap(hash_map)) => {
+                let hash_map = hash_map.borrow();
+                // Sorted so that the output is the same between runs.
+                let mut keys = hash_map.keys().collect::<Vec<_>>();
+                keys.sort();
+                buf.push('{');
+                traversal.push(Piece::Close('}'));
+                for (idx, key) in keys.into_iter().enumerate().rev() {
+                    let mut before = if idx == 0 { "" } else { "," }.to_owned();
+                    write_string(&mut before, key);
+                    before.push(':');
+                    traversal.push(Piece::Value(before, hash_map[key].clone()))
+                }
+            }
+            value => return Err(Error::Unserializable(value.typ())),
+        }
+    }
+    Ok(buf)
+}
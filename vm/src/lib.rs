@@ -1,38 +1,282 @@
+mod csv;
+pub mod docs;
+mod json;
+pub mod memory;
+mod natives;
+
 use compiler::chunk::value::{
-    self, Arity, ArityType, Closure, DataType, Function, Iterable, Native, Object, Upvalue, Value,
+    self, Arity, ArityType, Awaitable, Closure, DataType, Function, Generator, Interpreter,
+    Iterable, Object, TraversalLimits, Upvalue, Value,
 };
-use compiler::chunk::{Chunk, Instruction, OpCode::*};
-use compiler::error::{Backtrace, RuntimeError};
+use compiler::chunk::{Chunk, Instruction, LocalInfo, OpCode, OpCode::*};
+use compiler::error::{self, Backtrace, NativeError, Operand, RuntimeError};
+use compiler::path;
+use docs::NativeDoc;
 use lexer::token::Token;
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, LinkedList, VecDeque};
 use std::ops::{Deref, DerefMut, Div, Mul, Rem, Sub};
-use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+use std::path::PathBuf;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    mem,
+    rc::{Rc, Weak},
+};
+
+/// The method objects must have to be iterated over.
+const NEXT_METHOD: &str = "التالي";
+/// The method objects must have to be closed by `مع`.
+const CLOSE_METHOD: &str = "أغلق";
+
+/// The functions hash maps can have to overload operators. They're called with the operands in order, the left
+/// operand's function is used if both have one.
+const ADD_HOOK: &str = "__جمع__";
+const SUB_HOOK: &str = "__طرح__";
+const MUL_HOOK: &str = "__ضرب__";
+const DIV_HOOK: &str = "__قسمة__";
+const REM_HOOK: &str = "__باقي__";
+/// Called with the only operand of unary `-`.
+const NEG_HOOK: &str = "__سالب__";
+/// Returns whether they're equal, `!=` negates it.
+const EQ_HOOK: &str = "__يساوي__";
+/// Returns a negative number, zero, or a positive number if the left operand is less than, equal to, or greater than
+/// the right one, which the comparison operators check.
+const CMP_HOOK: &str = "__قارن__";
+
+/// What running a chunk took, see `Vm::stats`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub instructions: usize,
+    /// The highest number of temporaries on the stack at once.
+    pub peak_tmps: usize,
+    /// The heap allocations made while running, which include the lists, hash maps, and strings the program creates
+    /// but also the VM's own bookkeeping, `None` if the embedder doesn't count them (see `memory`).
+    pub allocations: Option<usize>,
+    pub elapsed: Duration,
+}
 
 pub struct Vm {
     tmps: Vec<Value>,
     locals: Vec<Value>,
     globals: HashMap<String, Value>,
+    /// The tokens the globals were defined at, natives don't have any.
+    global_tokens: HashMap<String, Rc<Token>>,
+    open_upvalues: LinkedList<Rc<RefCell<Upvalue>>>,
+    /// Makes reading an undefined global evaluate to `Nil` instead of failing, so that code can check whether an optional native exists.
+    undefined_as_nil: bool,
+    /// Makes the natives that touch the file system fail.
+    untrusted: bool,
+    passed_assertions: usize,
+    /// Set by natives, `None` displays numbers with as many digits as needed.
+    display_precision: Option<usize>,
+    traversal_limits: TraversalLimits,
+    /// Makes frames verify the stacks after every instruction, see `Frame::check_invariants`.
+    invariant_checks: bool,
+    /// `None` means unlimited.
+    max_call_depth: Option<usize>,
+    max_elements: Option<usize>,
+    max_output_bytes: Option<usize>,
+    max_list_len: usize,
+    /// The number of list and hash map elements created so far.
+    elements: usize,
+    /// The number of bytes printed so far.
+    output_bytes: usize,
+    /// Of the last run.
+    stats: Stats,
+    /// Called before every instruction, see `Vm::on_step`.
+    on_step: Option<StepHook>,
+    /// The instructions left to run before suspending, `None` when not running with `Vm::run_budgeted`.
+    budget: Option<u64>,
+    /// Set from outside (e.g. by a Ctrl-C handler) to stop the program, see `Vm::interrupt`.
+    interrupt: Option<Arc<AtomicBool>>,
+    /// The files the program opened, closed when the VM is dropped in case they're still referenced (e.g. by a cycle).
+    files: Vec<Weak<RefCell<value::File>>>,
+    /// How many natives and iterator advances are running, which can't be suspended in the middle.
+    atomic: usize,
+    /// The frames that suspended, the innermost first, filled while they return one after another.
+    suspended: Vec<SuspendedFrame>,
+    /// The tasks waiting for their turn, see `Interpreter::run_tasks`.
+    tasks: VecDeque<Task>,
+    /// The value of `atomic` the frames of the tasks run with, `None` when they aren't running.
+    tasks_atomic: Option<usize>,
+    /// Set by `سلّم` for the frame that called it to suspend once it returns.
+    yielding: bool,
+    /// What the task that's yielding waits for, see `Interpreter::wait`.
+    waiting: Option<Awaitable>,
+    /// The names of the functions that are waiting for others to return and the tokens of their calls, the outermost
+    /// first.
+    calls: Vec<(Option<String>, Rc<Token>)>,
+}
+
+/// What running with a budget ended with, see `Vm::run_budgeted`.
+pub enum BudgetedResult {
+    /// Holds the exports of a module, `Nil` for scripts.
+    Completed(Value),
+    Errored(RuntimeError),
+    /// The budget ran out before the program did.
+    Suspended(ResumeHandle),
+}
+
+/// Everything a suspended program needs to continue, see `Vm::resume`.
+pub struct ResumeHandle {
+    /// The outermost first.
+    frames: Vec<SuspendedFrame>,
+    tmps: Vec<Value>,
+    locals: Vec<Value>,
     open_upvalues: LinkedList<Rc<RefCell<Upvalue>>>,
 }
 
 impl Vm {
     pub fn new() -> Self {
-        let qatam_print = Native::new(
-            |args: Vec<Value>| {
-                println!("{}", args[1]);
-                Ok(Value::Nil)
-            },
-            Arity::new(ArityType::Fixed, 1, 0),
-        );
-
         Self {
             tmps: vec![],
             locals: vec![],
-            globals: HashMap::from([("إطبع".to_owned(), Value::from(qatam_print))]),
+            globals: natives::natives()
+                .into_iter()
+                .map(|registration| {
+                    (
+                        registration.doc.name.to_owned(),
+                        Value::from(registration.native),
+                    )
+                })
+                .collect(),
+            global_tokens: HashMap::new(),
             open_upvalues: LinkedList::new(),
+            undefined_as_nil: false,
+            untrusted: false,
+            passed_assertions: 0,
+            display_precision: None,
+            traversal_limits: TraversalLimits::default(),
+            invariant_checks: cfg!(debug_assertions) || cfg!(feature = "invariant-checks"),
+            max_call_depth: None,
+            max_elements: None,
+            max_output_bytes: None,
+            max_list_len: value::MAX_LIST_LEN,
+            elements: 0,
+            output_bytes: 0,
+            stats: Stats::default(),
+            on_step: None,
+            budget: None,
+            interrupt: None,
+            files: vec![],
+            atomic: 0,
+            suspended: vec![],
+            tasks: VecDeque::new(),
+            tasks_atomic: None,
+            yielding: false,
+            waiting: None,
+            calls: vec![],
         }
     }
 
+    /// The documentation of the natives every new VM defines, sorted by category.
+    pub fn natives_docs() -> Vec<NativeDoc> {
+        let mut docs = natives::natives()
+            .into_iter()
+            .map(|registration| registration.doc)
+            .collect::<Vec<_>>();
+        docs.sort_by_key(|doc| doc.category);
+        docs
+    }
+
+    pub fn undefined_as_nil(mut self, flag: bool) -> Self {
+        self.undefined_as_nil = flag;
+        self
+    }
+
+    pub fn untrusted(mut self, flag: bool) -> Self {
+        self.untrusted = flag;
+        self
+    }
+
+    /// Bounds deep equality, deep cloning, displaying, and JSON serialization of nested lists and hash maps.
+    pub fn traversal_limits(mut self, limits: TraversalLimits) -> Self {
+        self.traversal_limits = limits;
+        self
+    }
+
+    /// Makes it panic as soon as an instruction leaves the stacks inconsistent, which is on by default in debug builds
+    /// and in the ones with the `invariant-checks` feature.
+    pub fn enable_invariant_checks(mut self) -> Self {
+        self.invariant_checks = true;
+        self
+    }
+
+    /// Limits how many function calls can be nested (the top level isn't counted).
+    pub fn max_call_depth(mut self, limit: usize) -> Self {
+        self.max_call_depth = Some(limit);
+        self
+    }
+
+    /// Limits the total number of elements of the lists and hash maps the program creates, whether or not they're
+    /// still alive.
+    pub fn max_elements(mut self, limit: usize) -> Self {
+        self.max_elements = Some(limit);
+        self
+    }
+
+    /// Limits the number of bytes the program prints.
+    pub fn max_output_bytes(mut self, limit: usize) -> Self {
+        self.max_output_bytes = Some(limit);
+        self
+    }
+
+    /// Limits the length of every list the program creates at once (e.g. with `مصفوفة`), and of the strings `كرّر_نص`
+    /// creates, `value::MAX_LIST_LEN` by default.
+    pub fn max_list_len(mut self, limit: usize) -> Self {
+        self.max_list_len = limit;
+        self
+    }
+
+    /// Calls `hook` before every instruction runs, which is how debuggers follow the program. The chunks must be compiled
+    /// with `Compiler::debug_info` for `Step::locals` to find anything.
+    pub fn on_step(mut self, hook: impl FnMut(&Step<'_, '_>) + 'static) -> Self {
+        self.on_step = Some(Box::new(hook));
+        self
+    }
+
+    /// Stops the program with `RuntimeError::Interrupted`, which can't be caught, once `flag` is set. It's checked
+    /// between instructions like the budget, and cleared when it stops the program so that the VM can run again.
+    pub fn interrupt(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(flag);
+        self
+    }
+
+    /// Returns how many times an assertion native succeeded.
+    pub fn passed_assertions(&self) -> usize {
+        self.passed_assertions
+    }
+
+    /// The value of the global named `name` (natives included), `None` if it isn't defined.
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(name)
+    }
+
+    /// The token the global named `name` was defined at, `None` for natives and undefined globals.
+    pub fn global_token(&self, name: &str) -> Option<Rc<Token>> {
+        self.global_tokens.get(name).cloned()
+    }
+
+    /// Returns the statistics of the last run, whether it succeeded or not.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Returns the defined global closest to `name` if it's at most 2 edits away (and not just as different).
+    fn suggest(&self, name: &str) -> Option<String> {
+        let len = name.chars().count();
+        self.globals
+            .keys()
+            .map(|global| (edit_distance(name, global), global))
+            .filter(|(distance, _)| *distance <= 2 && *distance < len)
+            // Ties are broken alphabetically, as the order of the globals isn't deterministic.
+            .min()
+            .map(|(_, global)| global.clone())
+    }
+
     fn add_upvalue(&mut self, idx: usize) -> Rc<RefCell<Upvalue>> {
         macro_rules! create_upvalue {
             () => {
@@ -80,11 +324,42 @@ impl Vm {
         }
     }
 
+    /// So that Ctrl-C pressed as the last run was ending doesn't stop the next one.
+    fn clear_interrupt(&self) {
+        if let Some(flag) = &self.interrupt {
+            flag.store(false, atomic::Ordering::Relaxed)
+        }
+    }
+
+    /// Drops what a failed run left on the stacks (e.g. the files it opened) instead of keeping it until the VM is
+    /// dropped, and so that the next run (of a REPL line) starts with empty stacks like the first.
+    fn clear_stacks(&mut self) {
+        self.close_upvalues(0);
+        self.locals.clear();
+        self.tmps.clear();
+    }
+
+    /// Defines `path::FILE_VAR` and `path::DIRECTORY_VAR` for the file of the root chunk, modules define their own.
+    fn define_location(&mut self, chunk: &Chunk) {
+        for (name, value) in path::location(chunk.path().map(PathBuf::as_path)) {
+            self.globals.insert(name.to_owned(), value);
+        }
+    }
+
     pub fn run(&mut self, chunk: Chunk) -> Result<(), RuntimeError> {
         if cfg!(feature = "verbose") {
             println!("[VM] started")
         }
-        match Frame::new(self, Rc::new(chunk.into())).run() {
+        self.stats = Stats::default();
+        self.clear_interrupt();
+        self.define_location(&chunk);
+        let (start, allocations) = (Instant::now(), memory::allocations());
+        let result = Frame::new(self, Rc::new(chunk.into())).run();
+        self.stats.elapsed = start.elapsed();
+        self.stats.allocations = allocations
+            .zip(memory::allocations())
+            .map(|(before, after)| after - before);
+        match result {
             Ok(_) => {
                 if cfg!(feature = "verbose") {
                     println!("[VM] succeeded")
@@ -95,10 +370,135 @@ impl Vm {
                 if cfg!(feature = "verbose") {
                     println!("[VM] failed")
                 }
+                self.clear_stacks();
                 Err(err)
             }
         }
     }
+
+    /// Like `run` but suspends once `budget` instructions have run, so that the program can be continued later with
+    /// `resume`. The budget is only checked between instructions, natives (and the functions they call) always run to
+    /// the end.
+    pub fn run_budgeted(&mut self, chunk: Chunk, budget: u64) -> BudgetedResult {
+        self.stats = Stats::default();
+        self.clear_interrupt();
+        self.define_location(&chunk);
+        self.budgeted(budget, |vm| Frame::new(vm, Rc::new(chunk.into())).run())
+    }
+
+    /// Continues a suspended program with a fresh `budget`, its stats are added to the ones of the runs before it.
+    pub fn resume(&mut self, handle: ResumeHandle, budget: u64) -> BudgetedResult {
+        self.tmps = handle.tmps;
+        self.locals = handle.locals;
+        self.open_upvalues = handle.open_upvalues;
+        let mut frames = handle.frames.into_iter();
+        let root = frames.next().unwrap();
+        self.budgeted(budget, |vm| root.resume(vm, frames))
+    }
+
+    fn budgeted(
+        &mut self,
+        budget: u64,
+        run: impl FnOnce(&mut Self) -> Result<Option<Value>, RuntimeError>,
+    ) -> BudgetedResult {
+        let (start, allocations) = (Instant::now(), memory::allocations());
+        self.budget = Some(budget);
+        let result = run(self);
+        self.budget = None;
+        self.stats.elapsed += start.elapsed();
+        if let (Some(before), Some(after)) = (allocations, memory::allocations()) {
+            *self.stats.allocations.get_or_insert(0) += after - before
+        }
+        match result {
+            Err(err) => {
+                self.clear_stacks();
+                BudgetedResult::Errored(err)
+            }
+            Ok(_) if !self.suspended.is_empty() => {
+                let mut frames = mem::take(&mut self.suspended);
+                frames.reverse();
+                BudgetedResult::Suspended(ResumeHandle {
+                    frames,
+                    tmps: mem::take(&mut self.tmps),
+                    locals: mem::take(&mut self.locals),
+                    open_upvalues: mem::take(&mut self.open_upvalues),
+                })
+            }
+            Ok(value) => BudgetedResult::Completed(value.unwrap_or(Value::Nil)),
+        }
+    }
+}
+
+impl Drop for Vm {
+    /// Nothing can report the errors of closing the files anymore, so they're ignored like the ones of dropping them.
+    fn drop(&mut self) {
+        for file in self.files.iter().filter_map(Weak::upgrade) {
+            file.borrow_mut().close().ok();
+        }
+    }
+}
+
+/// The number of insertions, deletions, and substitutions of characters needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + (a != *b) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+type StepHook = Box<dyn FnMut(&Step<'_, '_>)>;
+
+/// The frame that is about to run an instruction, as seen by the hook of `Vm::on_step`.
+pub struct Step<'a, 'b> {
+    frame: &'a Frame<'b>,
+}
+
+impl Step<'_, '_> {
+    /// The number of function calls the frame is nested in, 0 for the top level.
+    pub fn depth(&self) -> usize {
+        self.frame.idx
+    }
+
+    /// The name of the function the frame is running, `None` for the top level and anonymous functions.
+    pub fn function(&self) -> Option<String> {
+        self.frame.closure.name()
+    }
+
+    /// The token of the instruction about to run, synthetic if the compiler added it on its own.
+    pub fn token(&self) -> Rc<Token> {
+        self.frame.token()
+    }
+
+    /// The names and values of the frame's locals that are visible, in the order they're written in the source.
+    pub fn locals(&self) -> Vec<(String, Value)> {
+        let mut locals = self.frame.chunk().visible_locals(self.frame.ip);
+        locals.sort_by_key(|local| local.token.start());
+        locals
+            .into_iter()
+            .map(|local| (local.token.lexeme().to_owned(), self.value(local)))
+            .collect()
+    }
+
+    /// The value of the visible local named `name`, `None` if there isn't one (e.g. when it's a global).
+    pub fn local(&self, name: &str) -> Option<Value> {
+        self.frame
+            .chunk()
+            .visible_locals(self.frame.ip)
+            .into_iter()
+            .find(|local| local.token.lexeme() == name)
+            .map(|local| self.value(local))
+    }
+
+    fn value(&self, local: &LocalInfo) -> Value {
+        self.frame.local(self.frame.slots + local.slot).clone()
+    }
 }
 
 struct Frame<'a> {
@@ -106,8 +506,13 @@ struct Frame<'a> {
     closure: Rc<Closure>,
     ip: usize,
     slots: usize,
+    /// The length of tmps when the frame started (excluding the callee and the arguments it consumes), it never pops below
+    /// it.
+    tmps_start: usize,
     idx: usize,
     handlers: Vec<Handler>,
+    /// Set when the frame of a generator stops because of `YIELD` rather than returning.
+    yielded: bool,
 }
 
 impl<'a> Frame<'a> {
@@ -117,19 +522,25 @@ impl<'a> Frame<'a> {
             closure,
             ip: 0,
             slots: 0,
+            tmps_start: 0,
             idx: 0,
             handlers: vec![],
+            yielded: false,
         }
     }
 
+    /// The callee and the arguments stay on the tmps until the function defines them, so its slots start empty like the
+    /// root frame's (see `Compiler::params`).
     fn new_function(state: &'a mut Vm, closure: Rc<Closure>, argc: usize, idx: usize) -> Self {
         Self {
             ip: closure.start_ip(argc),
             slots: state.locals.len(),
+            tmps_start: state.tmps.len() - argc - 1,
             state,
             closure,
             idx,
             handlers: vec![],
+            yielded: false,
         }
     }
 
@@ -147,6 +558,26 @@ impl<'a> Frame<'a> {
         }
     }
 
+    fn check_operand(
+        &self,
+        operand: Operand,
+        value: &Value,
+        expected: &[DataType],
+    ) -> Result<(), RuntimeError> {
+        let received = value.typ();
+        if expected.contains(&received) {
+            Ok(())
+        } else {
+            Err(RuntimeError::OperandType(
+                operand,
+                expected.to_owned(),
+                received,
+                self.token(),
+                Backtrace::default(),
+            ))
+        }
+    }
+
     fn check_arity(&self, arity: &Arity, argc: usize) -> Result<(), RuntimeError> {
         match argc {
             x if x >= arity.required() && x <= arity.required() + arity.optional() => Ok(()),
@@ -196,6 +627,21 @@ impl<'a> Frame<'a> {
         Ok(value)
     }
 
+    /// Like `pop_typed` but with a dedicated error for `عدم`.
+    fn pop_unpacked(&mut self, expected: DataType) -> Result<Value, RuntimeError> {
+        match self.pop() {
+            Value::Nil => Err(RuntimeError::NilUnpack(
+                expected,
+                self.token(),
+                Backtrace::default(),
+            )),
+            value => {
+                self.check_type(&value, &[expected])?;
+                Ok(value)
+            }
+        }
+    }
+
     fn last(&self) -> &Value {
         self.state.tmps.last().unwrap()
     }
@@ -214,25 +660,47 @@ impl<'a> Frame<'a> {
         let mut returned = None;
         let mut advance = true;
         macro_rules! numeric_arith_op {
-            ($method:ident) => {{
-                let b = self.pop_typed(&[DataType::Number])?;
-                let a = self.pop_typed(&[DataType::Number])?;
-                self.push(Value::$method(a, b))
+            ($method:ident, $hook:expr) => {{
+                let b = self.pop();
+                let a = self.pop();
+                match self.call_hook($hook, &[&a, &b])? {
+                    Some(value) => self.push(value),
+                    None => {
+                        self.check_operand(Operand::Left, &a, &[DataType::Number])?;
+                        self.check_operand(Operand::Right, &b, &[DataType::Number])?;
+                        self.push(Value::$method(a, b))
+                    }
+                }
             }};
         }
         macro_rules! eq_op {
-            ($method:ident) => {{
+            ($method:ident, $equal:expr) => {{
                 let b = self.pop();
                 let a = self.pop();
-                self.push(Value::from(Value::$method(&a, &b)))
+                let res = match self.call_hook(EQ_HOOK, &[&a, &b])? {
+                    Some(value) => value.truthy() == $equal,
+                    None => Value::$method(&a, &b),
+                };
+                self.push(Value::from(res))
             }};
         }
         macro_rules! numeric_cmp_op {
             ($($ordering:expr),+) => {{
-                let b = self.pop_typed(&[DataType::Number])?;
-                let a = self.pop_typed(&[DataType::Number])?;
-                let res = Value::partial_cmp(&a, &b).unwrap();
-                self.push(Value::from($(res == $ordering)||+))
+                let b = self.pop();
+                let a = self.pop();
+                let res = match self.call_hook(CMP_HOOK, &[&a, &b])? {
+                    // Compared to zero, NaN makes all of them false.
+                    Some(value) => {
+                        self.check_type(&value, &[DataType::Number])?;
+                        Value::partial_cmp(&value, &Value::Number(0.0))
+                    }
+                    None => {
+                        self.check_operand(Operand::Left, &a, &[DataType::Number])?;
+                        self.check_operand(Operand::Right, &b, &[DataType::Number])?;
+                        Value::partial_cmp(&a, &b)
+                    }
+                };
+                self.push(Value::from($(res == Some($ordering))||+))
             }};
         }
         macro_rules! jump_if_x_or_pop {
@@ -257,8 +725,14 @@ impl<'a> Frame<'a> {
         }
         match instr.op_code() {
             NEG => {
-                let value = self.pop_typed(&[DataType::Number])?;
-                self.push(-value)
+                let value = self.pop();
+                match self.call_hook(NEG_HOOK, &[&value])? {
+                    Some(value) => self.push(value),
+                    None => {
+                        self.check_type(&value, &[DataType::Number])?;
+                        self.push(-value)
+                    }
+                }
             }
             NOT => {
                 let value = self.pop();
@@ -266,16 +740,30 @@ impl<'a> Frame<'a> {
             }
             ADD => {
                 let b = self.pop();
-                let a = self.pop_typed(&[DataType::Number, DataType::String, DataType::List])?;
-                self.check_type(&b, &[a.typ()])?;
-                self.push(a + b)
-            }
-            SUB => numeric_arith_op!(sub),
-            MUL => numeric_arith_op!(mul),
-            DIV => numeric_arith_op!(div),
-            REM => numeric_arith_op!(rem),
-            EQ => eq_op!(eq),
-            NOT_EQ => eq_op!(ne),
+                let a = self.pop();
+                if let Some(value) = self.call_hook(ADD_HOOK, &[&a, &b])? {
+                    self.push(value)
+                } else {
+                    self.check_operand(
+                        Operand::Left,
+                        &a,
+                        &[DataType::Number, DataType::String, DataType::List],
+                    )?;
+                    self.check_operand(Operand::Right, &b, &[a.typ()])?;
+                    if let (Value::Object(Object::List(a)), Value::Object(Object::List(b))) =
+                        (&a, &b)
+                    {
+                        self.allocate(a.borrow().len().saturating_add(b.borrow().len()))?
+                    }
+                    self.push(a + b)
+                }
+            }
+            SUB => numeric_arith_op!(sub, SUB_HOOK),
+            MUL => numeric_arith_op!(mul, MUL_HOOK),
+            DIV => numeric_arith_op!(div, DIV_HOOK),
+            REM => numeric_arith_op!(rem, REM_HOOK),
+            EQ => eq_op!(eq, true),
+            NOT_EQ => eq_op!(ne, false),
             GREATER => numeric_cmp_op!(Ordering::Greater),
             GREATER_EQ => numeric_cmp_op!(Ordering::Greater, Ordering::Equal),
             LESS => numeric_cmp_op!(Ordering::Less),
@@ -300,8 +788,11 @@ impl<'a> Frame<'a> {
                     .clone()
                     .try_into()
                     .unwrap();
-                let mut iterator = iterator.borrow_mut();
-                match iterator.next() {
+                // The frames of generators and `التالي` methods can't be suspended.
+                self.state.atomic += 1;
+                let next = self.advance(&iterator);
+                self.state.atomic -= 1;
+                match next? {
                     Some(value) => self.push(value),
                     None => {
                         self.ip += offset;
@@ -361,8 +852,15 @@ impl<'a> Frame<'a> {
                 let name: String = self.chunk().constant(idx).try_into().unwrap();
                 let value = match self.state.globals.get(&name) {
                     Some(value) => value.clone(),
+                    None if self.state.undefined_as_nil => Value::Nil,
                     None => {
-                        return Err(RuntimeError::Name(name, self.token(), Backtrace::default()))
+                        let suggestion = self.state.suggest(&name);
+                        return Err(RuntimeError::Name(
+                            name,
+                            suggestion,
+                            self.token(),
+                            Backtrace::default(),
+                        ));
                     }
                 };
                 self.push(value)
@@ -374,7 +872,13 @@ impl<'a> Frame<'a> {
                 match self.state.globals.get_mut(&name) {
                     Some(value) => *value = new_value,
                     None => {
-                        return Err(RuntimeError::Name(name, self.token(), Backtrace::default()))
+                        let suggestion = self.state.suggest(&name);
+                        return Err(RuntimeError::Name(
+                            name,
+                            suggestion,
+                            self.token(),
+                            Backtrace::default(),
+                        ));
                     }
                 }
             }
@@ -383,10 +887,12 @@ impl<'a> Frame<'a> {
                 let name: String = self.chunk().constant(idx).try_into().unwrap();
                 let value = self.pop();
                 if !self.state.globals.contains_key(&name) || name == "_" {
+                    self.state.global_tokens.insert(name.clone(), self.token());
                     self.state.globals.insert(name, value);
                 } else {
                     return Err(RuntimeError::AlreadyDefined(
-                        name,
+                        name.clone(),
+                        self.state.global_tokens.get(&name).cloned(),
                         self.token(),
                         Backtrace::default(),
                     ));
@@ -415,55 +921,59 @@ impl<'a> Frame<'a> {
                 self.push(Value::from(Closure::new(function, upvalues)))
             }
             CALL => {
-                // TODO add stack overflowing
                 let argc = instr.read_byte_oper(0);
-                let tmps_len = self.state.tmps.len();
-                let idx = tmps_len - argc - 1;
-                match self.state.tmps[idx].clone() {
-                    Value::Object(Object::Closure(closure)) => {
-                        self.check_arity(closure.arity(), argc)?;
-                        let value = Frame::new_function(self.state, closure, argc, self.idx + 1)
-                            .run()?
-                            .unwrap();
-                        self.push(value);
-                        if cfg!(feature = "verbose") {
-                            println!(
-                                "[VM] {}'s chunk",
-                                Value::Object(Object::Closure(Rc::clone(&self.closure)))
-                            )
-                        }
-                    }
-                    Value::Object(Object::Native(native)) => {
-                        self.check_arity(native.arity(), argc)?;
-                        let args = self.state.tmps.drain(idx..).collect::<Vec<_>>();
-                        self.push(native.call(args)?)
-                    }
-                    _ => todo!("Add Uncallable error type"),
-                }
+                self.call(argc)?
             }
             BUILD_VARIADIC => {
                 let arity = self.closure.arity();
+                // The arguments are above the callee, which is at the start of the frame's tmps.
                 let additional = self
                     .state
                     .tmps
-                    .drain(arity.required() + arity.optional() + 1..) // https://share.sketchpad.app/22/36b-20f7-cd4981.png
+                    .drain(self.tmps_start + 1 + arity.required() + arity.optional()..) // https://share.sketchpad.app/22/36b-20f7-cd4981.png
                     .collect::<Vec<_>>();
-                self.push(Value::from(additional))
+                let additional = Value::list(self, additional)?;
+                self.push(additional)
             }
             RET => {
                 self.state.close_upvalues(self.slots);
                 self.state.locals.drain(self.slots..);
+                returned = Some(self.pop());
+                // The iterators of the loops it returned from (or finished) are still there.
+                self.state.tmps.truncate(self.tmps_start)
+            }
+            YIELD => {
+                self.ip += instr.size();
+                self.yielded = true;
                 returned = Some(self.pop())
             }
             BUILD_LIST => {
                 let size = instr.read_two_bytes_oper(0);
-                let list = self
-                    .state
-                    .tmps
-                    .drain(self.state.tmps.len() - size..)
-                    .collect::<Vec<_>>();
+                // The size fits in two bytes, it's checked in case the limit is lower.
+                let mut list = value::checked_list_alloc(self, size)?;
+                let start = self.state.tmps.len() - size;
+                list.extend(self.state.tmps.drain(start..));
                 self.push(Value::from(list))
             }
+            LIST_APPEND => {
+                let value = self.pop();
+                let list: Rc<RefCell<Vec<Value>>> = self
+                    .last_typed(&[DataType::List])?
+                    .clone()
+                    .try_into()
+                    .unwrap();
+                let len = list.borrow().len().saturating_add(1);
+                if len > self.max_list_len() {
+                    return Err(RuntimeError::ListTooLong(
+                        len as u64,
+                        Some(self.max_list_len()),
+                        self.token(),
+                        Backtrace::default(),
+                    ));
+                }
+                self.allocate(1)?;
+                list.borrow_mut().push(value);
+            }
             BUILD_HASH_MAP => {
                 let size = instr.read_two_bytes_oper(0);
                 let mut hash_map = HashMap::new();
@@ -472,119 +982,130 @@ impl<'a> Frame<'a> {
                     let key = self.pop_typed(&[DataType::String])?.try_into().unwrap();
                     hash_map.insert(key, value);
                 }
-                self.push(Value::from(hash_map))
+                let hash_map = Value::hash_map(self, hash_map)?;
+                self.push(hash_map)
             }
             GET => {
                 let key = self.pop();
                 let popped =
                     self.pop_typed(&[DataType::String, DataType::List, DataType::HashMap])?;
-                let value = match &popped {
-                    Value::String(..) | Value::Object(Object::List(..)) => {
-                        let idx: usize = key.try_into().map_err(|_| {
-                            RuntimeError::InvalidIdx(self.token(), Backtrace::default())
-                        })?;
-                        match popped {
-                            Value::String(string) => match string.chars().nth(idx) {
-                                Some(c) => Value::from(c),
-                                None => {
-                                    return Err(RuntimeError::OutOfRange(
-                                        idx,
-                                        string.chars().count(),
-                                        self.token(),
-                                        Backtrace::default(),
-                                    ))
-                                }
-                            },
-                            Value::Object(Object::List(list)) => match list.borrow().get(idx) {
-                                Some(value) => value.clone(),
-                                None => {
-                                    return Err(RuntimeError::OutOfRange(
-                                        idx,
-                                        list.borrow().len(),
-                                        self.token(),
-                                        Backtrace::default(),
-                                    ))
-                                }
-                            },
-                            _ => unreachable!(),
-                        }
-                    }
-                    Value::Object(Object::HashMap(hash_map)) => {
-                        self.check_type(&key, &[DataType::String])?;
-                        let key: String = key.try_into().unwrap();
-                        match hash_map.borrow().get(&key).cloned() {
-                            Some(value) => value,
+                let value = self.get(popped, key)?;
+                self.push(value)
+            }
+            SET => {
+                let key = self.pop();
+                let popped = self.pop_typed(&[DataType::List, DataType::HashMap])?;
+                self.set(popped, key)?
+            }
+            GET_PROP8 | GET_PROP16 => {
+                let idx = match instr.op_code() {
+                    GET_PROP8 => instr.read_byte_oper(0),
+                    GET_PROP16 => instr.read_two_bytes_oper(0),
+                    _ => unreachable!(),
+                };
+                let popped =
+                    self.pop_typed(&[DataType::String, DataType::List, DataType::HashMap])?;
+                let value = match (&popped, self.chunk().constant_ref(idx)) {
+                    (Value::Object(Object::HashMap(hash_map)), Value::String(key)) => {
+                        match hash_map.borrow().get(key) {
+                            Some(value) => value.clone(),
                             None => {
                                 return Err(RuntimeError::UndefinedKey(
-                                    key,
+                                    key.clone(),
                                     self.token(),
                                     Backtrace::default(),
                                 ))
                             }
                         }
                     }
-                    _ => unreachable!(),
+                    _ => self.get(popped, self.chunk().constant(idx))?,
                 };
                 self.push(value)
             }
-            SET => {
-                let key = self.pop();
+            SET_PROP8 | SET_PROP16 => {
+                let idx = match instr.op_code() {
+                    SET_PROP8 => instr.read_byte_oper(0),
+                    SET_PROP16 => instr.read_two_bytes_oper(0),
+                    _ => unreachable!(),
+                };
                 let popped = self.pop_typed(&[DataType::List, DataType::HashMap])?;
-                let new_value = self.last().clone();
-                match popped {
-                    Value::Object(Object::List(list)) => {
-                        let idx: usize = key.try_into().map_err(|_| {
-                            RuntimeError::InvalidIdx(self.token(), Backtrace::default())
-                        })?;
-                        match list.borrow_mut().get_mut(idx) {
-                            Some(value) => {
-                                *value = new_value;
-                            }
+                match (&popped, self.chunk().constant_ref(idx)) {
+                    (Value::Object(Object::HashMap(hash_map)), Value::String(key)) => {
+                        let new_value = self.last().clone();
+                        let mut hash_map = hash_map.borrow_mut();
+                        match hash_map.get_mut(key) {
+                            Some(value) => *value = new_value,
                             None => {
-                                return Err(RuntimeError::OutOfRange(
-                                    idx,
-                                    list.borrow().len(),
-                                    self.token(),
-                                    Backtrace::default(),
-                                ));
+                                let key = key.clone();
+                                self.allocate(1)?;
+                                hash_map.insert(key, new_value);
                             }
                         }
                     }
-                    Value::Object(Object::HashMap(hash_map)) => {
-                        self.check_type(&key, &[DataType::String])?;
-                        let key: String = key.try_into().unwrap();
-                        hash_map.borrow_mut().insert(key, new_value);
-                    }
-                    _ => unreachable!(),
+                    _ => self.set(popped, self.chunk().constant(idx))?,
                 }
             }
             APPEND_HANDLER => {
                 let offset = instr.read_two_bytes_oper(0);
-                self.handlers
-                    .push(Handler::new(self.ip + offset, self.state.locals.len()))
+                self.handlers.push(Handler::new(
+                    self.ip + offset,
+                    self.state.locals.len(),
+                    self.state.tmps.len(),
+                ))
             }
             POP_HANDLER => {
                 self.handlers.pop();
             }
             THROW => {
                 let value = self.pop();
+                error::stamp_error(&value, &self.token());
                 return Err(RuntimeError::User(
                     value,
                     self.token(),
                     Backtrace::default(),
                 ));
             }
+            RETHROW => {
+                let idx = instr.read_byte_oper(0);
+                match &self.state.locals[self.slots + idx] {
+                    Value::Object(Object::Caught(err)) => return Err(err.deref().clone()),
+                    _ => unreachable!(),
+                }
+            }
             ITER => {
-                let iterable: Iterable = self
-                    .last_typed(&[DataType::String, DataType::List])?
-                    .clone()
-                    .try_into()
-                    .unwrap();
-                self.push(Value::from(iterable))
+                let value = self
+                    .last_typed(&[
+                        DataType::String,
+                        DataType::List,
+                        DataType::HashMap,
+                        DataType::Set,
+                        DataType::Iterator,
+                    ])?
+                    .clone();
+                match value {
+                    // Iterators (e.g. the ones returned from natives) iterate themselves.
+                    Value::Object(Object::Iterator(..)) => self.push(value),
+                    Value::Object(Object::HashMap(hash_map)) => {
+                        let method =
+                            hash_map.borrow().get(NEXT_METHOD).cloned().ok_or_else(|| {
+                                RuntimeError::UndefinedKey(
+                                    NEXT_METHOD.to_owned(),
+                                    self.token(),
+                                    Backtrace::default(),
+                                )
+                            })?;
+                        self.check_type(&method, &[DataType::Closure, DataType::Native])?;
+                        self.push(Value::from(Iterable::Custom(method)))
+                    }
+                    value => {
+                        let iterable: Iterable = value.try_into().unwrap();
+                        self.push(Value::from(iterable))
+                    }
+                }
             }
             UNPACK_LIST => {
                 let to = instr.read_two_bytes_oper(0);
-                let popped = self.pop_typed(&[DataType::List])?;
+                let popped = self.pop_unpacked(DataType::List)?;
                 let list: Rc<RefCell<Vec<Value>>> = popped.try_into().unwrap();
                 let list = list.borrow();
                 if list.len() != to {
@@ -615,7 +1136,7 @@ impl<'a> Frame<'a> {
                     }
                     tmp
                 };
-                let popped = self.pop_typed(&[DataType::HashMap])?;
+                let popped = self.pop_unpacked(DataType::HashMap)?;
                 let hash_map: Rc<RefCell<HashMap<String, Value>>> = popped.try_into().unwrap();
                 let hash_map = hash_map.borrow();
                 for (key, default) in keys {
@@ -635,6 +1156,85 @@ impl<'a> Frame<'a> {
                     self.push(value)
                 }
             }
+            UNPACK_LIST_REST => {
+                let to = instr.read_two_bytes_oper(0);
+                let popped = self.pop_unpacked(DataType::List)?;
+                let list: Rc<RefCell<Vec<Value>>> = popped.try_into().unwrap();
+                let list = list.borrow();
+                if list.len() < to {
+                    return Err(RuntimeError::ListUnpack(
+                        to,
+                        list.len(),
+                        self.token(),
+                        Backtrace::default(),
+                    ));
+                }
+                let mut rest = value::checked_list_alloc(self, list.len() - to)?;
+                rest.extend(list[to..].iter().cloned());
+                for value in list[..to].iter() {
+                    self.push(value.clone())
+                }
+                self.push(Value::from(rest))
+            }
+            MATCH_LIST | MATCH_LIST_REST => {
+                let len = instr.read_two_bytes_oper(0);
+                let matched = match self.pop() {
+                    Value::Object(Object::List(list)) => match instr.op_code() {
+                        MATCH_LIST => list.borrow().len() == len,
+                        _ => list.borrow().len() >= len,
+                    },
+                    _ => false,
+                };
+                self.push(Value::from(matched))
+            }
+            MATCH_HASH_MAP => {
+                let keyc = instr.read_two_bytes_oper(0);
+                let keys = self.state.tmps.split_off(self.state.tmps.len() - keyc);
+                let matched = match self.pop() {
+                    Value::Object(Object::HashMap(hash_map)) => {
+                        let hash_map = hash_map.borrow();
+                        keys.into_iter().all(|key| {
+                            let key: String = key.try_into().unwrap();
+                            hash_map.contains_key(&key)
+                        })
+                    }
+                    _ => false,
+                };
+                self.push(Value::from(matched))
+            }
+            CLOSE_RESOURCE => {
+                match self.pop_typed(&[DataType::File, DataType::Channel, DataType::HashMap])? {
+                    Value::Object(Object::File(file)) => {
+                        let mut file = file.borrow_mut();
+                        if let Err(err) = file.close() {
+                            return Err(NativeError::io(&err, &PathBuf::from(file.name()))
+                                .into_runtime_error(self.token()));
+                        }
+                    }
+                    Value::Object(Object::Channel(channel)) => channel.borrow_mut().close(),
+                    Value::Object(Object::HashMap(hash_map)) => {
+                        let method =
+                            hash_map
+                                .borrow()
+                                .get(CLOSE_METHOD)
+                                .cloned()
+                                .ok_or_else(|| {
+                                    RuntimeError::UndefinedKey(
+                                        CLOSE_METHOD.to_owned(),
+                                        self.token(),
+                                        Backtrace::default(),
+                                    )
+                                })?;
+                        self.check_type(&method, &[DataType::Closure, DataType::Native])?;
+                        // Like `التالي`, its frame can't be suspended.
+                        self.state.atomic += 1;
+                        let closed = self.invoke(method, vec![]);
+                        self.state.atomic -= 1;
+                        closed?;
+                    }
+                    _ => unreachable!(),
+                }
+            }
             POP => {
                 self.pop();
             }
@@ -647,6 +1247,472 @@ impl<'a> Frame<'a> {
         Ok((returned, advance))
     }
 
+    /// Implements `popped[key]`, expects `popped` to be a string, list, or hash map.
+    /// Calls the value below the `argc` arguments on the stack replacing them with the returned value.
+    fn call(&mut self, argc: usize) -> Result<(), RuntimeError> {
+        // TODO add stack overflowing
+        let tmps_len = self.state.tmps.len();
+        let idx = tmps_len - argc - 1;
+        match self.state.tmps[idx].clone() {
+            // The body runs when the returned iterator is resumed, the arguments are kept till then.
+            Value::Object(Object::Closure(closure)) if closure.is_generator() => {
+                self.check_arity(closure.arity(), argc)?;
+                let tmps = self.state.tmps.drain(idx..).collect::<Vec<_>>();
+                self.push(Value::from(Iterable::Generator(Generator::Suspended {
+                    ip: closure.start_ip(argc),
+                    closure,
+                    locals: vec![],
+                    tmps,
+                    handlers: vec![],
+                })))
+            }
+            Value::Object(Object::Closure(closure)) => {
+                self.check_arity(closure.arity(), argc)?;
+                self.check_depth()?;
+                self.state.calls.push((self.closure.name(), self.token()));
+                let result = Frame::new_function(self.state, closure, argc, self.idx + 1).run();
+                self.state.calls.pop();
+                // Nothing is returned when it suspends, `run` suspends this frame too.
+                match result? {
+                    Some(value) => self.push(value),
+                    None => return Ok(()),
+                }
+                if cfg!(feature = "verbose") {
+                    println!(
+                        "[VM] {}'s chunk",
+                        Value::Object(Object::Closure(Rc::clone(&self.closure)))
+                    )
+                }
+            }
+            Value::Object(Object::Native(native)) => {
+                self.check_arity(native.arity(), argc)?;
+                let args = self.state.tmps.drain(idx..).collect::<Vec<_>>();
+                self.state.atomic += 1;
+                let value = native.call(self, args);
+                self.state.atomic -= 1;
+                self.push(value?)
+            }
+            _ => todo!("Add Uncallable error type"),
+        }
+        Ok(())
+    }
+
+    /// Fails if a frame called from this one would be nested deeper than the limit.
+    fn check_depth(&self) -> Result<(), RuntimeError> {
+        match self.state.max_call_depth {
+            Some(limit) if self.idx >= limit => Err(RuntimeError::CallDepth(
+                limit,
+                self.token(),
+                Backtrace::default(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Implements `Interpreter::advance`.
+    fn advance_iterator(
+        &mut self,
+        iterator: &Rc<RefCell<value::Iterator>>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let method = iterator.borrow().method().cloned();
+        if let Some(method) = method {
+            return self.call_next(method);
+        }
+        if iterator.borrow_mut().generator_mut().is_some() {
+            return self.resume(iterator);
+        }
+        // Only the stages are cloned, as cloning the rest can be expensive (e.g. generators).
+        let stage = match iterator.borrow().iterable() {
+            stage @ (Iterable::Map(..) | Iterable::Filter(..)) => Some(stage.clone()),
+            _ => None,
+        };
+        match stage {
+            Some(Iterable::Map(source, function)) => match self.advance_iterator(&source)? {
+                Some(value) => Ok(Some(self.invoke(function, vec![value])?)),
+                None => Ok(None),
+            },
+            Some(Iterable::Filter(source, predicate)) => loop {
+                match self.advance_iterator(&source)? {
+                    Some(value) => {
+                        if self
+                            .invoke(predicate.clone(), vec![value.clone()])?
+                            .truthy()
+                        {
+                            return Ok(Some(value));
+                        }
+                    }
+                    None => return Ok(None),
+                }
+            },
+            _ => {
+                let mut iterator = iterator.borrow_mut();
+                match iterator.next() {
+                    Some(Ok(value)) => Ok(Some(value)),
+                    Some(Err(err)) => Err(NativeError::io(&err, iterator.path().unwrap())
+                        .into_runtime_error(self.token())),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Calls the `التالي` method of a user-defined iterator, which returns `[value, done]`, or `Nil` when done.
+    fn call_next(&mut self, method: Value) -> Result<Option<Value>, RuntimeError> {
+        self.push(method);
+        self.call(0)?;
+        let returned = self.pop_typed(&[DataType::List, DataType::Nil])?;
+        let list: Rc<RefCell<Vec<Value>>> = match returned {
+            Value::Nil => return Ok(None),
+            returned => returned.try_into().unwrap(),
+        };
+        let list = list.borrow();
+        match list.as_slice() {
+            [_, done] if done.truthy() => Ok(None),
+            [value, _] => Ok(Some(value.clone())),
+            _ => Err(RuntimeError::ListUnpack(
+                2,
+                list.len(),
+                self.token(),
+                Backtrace::default(),
+            )),
+        }
+    }
+
+    /// Calls the function that the first hash map of `operands` has under `hook` with them, returns `None` if none has
+    /// one. It runs to completion even in budgeted mode, like the natives.
+    ///
+    /// The operands are only cloned into arguments once a hook is found, so operators on other values don't pay for it.
+    fn call_hook(
+        &mut self,
+        hook: &str,
+        operands: &[&Value],
+    ) -> Result<Option<Value>, RuntimeError> {
+        let function = operands.iter().find_map(|operand| match operand {
+            Value::Object(Object::HashMap(hash_map)) => hash_map.borrow().get(hook).cloned(),
+            _ => None,
+        });
+        let Some(function) = function else {
+            return Ok(None);
+        };
+        self.check_type(&function, &[DataType::Closure, DataType::Native])?;
+        let args = operands.iter().map(|&operand| operand.clone()).collect();
+        self.state.atomic += 1;
+        let value = self.invoke(function, args);
+        self.state.atomic -= 1;
+        value.map(Some)
+    }
+
+    /// Runs the generator that `iterator` iterates until it yields a value, which is returned, or finishes.
+    ///
+    /// Its locals and tmps are moved back to the stacks while it runs, and out of them when it yields. Upvalues that
+    /// point to its locals get closed when it yields, so closures created inside it don't see the changes it makes
+    /// after that.
+    fn resume(
+        &mut self,
+        iterator: &Rc<RefCell<value::Iterator>>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        self.check_depth()?;
+        let (closure, ip, locals, tmps, handlers) = {
+            let mut iterator = iterator.borrow_mut();
+            let generator = iterator.generator_mut().unwrap();
+            match mem::replace(generator, Generator::Running) {
+                Generator::Suspended {
+                    closure,
+                    ip,
+                    locals,
+                    tmps,
+                    handlers,
+                } => (closure, ip, locals, tmps, handlers),
+                Generator::Running => {
+                    return Err(RuntimeError::GeneratorRunning(
+                        self.token(),
+                        Backtrace::default(),
+                    ))
+                }
+                Generator::Done => {
+                    *generator = Generator::Done;
+                    return Ok(None);
+                }
+            }
+        };
+        let slots = self.state.locals.len();
+        let tmps_start = self.state.tmps.len();
+        let call = (self.closure.name(), self.token());
+        self.state.locals.extend(locals);
+        self.state.tmps.extend(tmps);
+        let mut frame = Frame {
+            state: &mut *self.state,
+            closure,
+            ip,
+            slots,
+            tmps_start,
+            idx: self.idx + 1,
+            handlers: handlers
+                .into_iter()
+                .map(|(ip, handler_slots, handler_tmps)| {
+                    Handler::new(ip, slots + handler_slots, tmps_start + handler_tmps)
+                })
+                .collect(),
+            yielded: false,
+        };
+        frame.state.calls.push(call);
+        let result = frame.run();
+        frame.state.calls.pop();
+        let (state, next) = match result {
+            Ok(Some(value)) if frame.yielded => {
+                frame.state.close_upvalues(slots);
+                let state = Generator::Suspended {
+                    closure: Rc::clone(&frame.closure),
+                    ip: frame.ip,
+                    locals: frame.state.locals.drain(slots..).collect(),
+                    tmps: frame.state.tmps.drain(tmps_start..).collect(),
+                    handlers: frame
+                        .handlers
+                        .iter()
+                        .map(|handler| {
+                            (
+                                handler.ip(),
+                                handler.slots() - slots,
+                                handler.tmps() - tmps_start,
+                            )
+                        })
+                        .collect(),
+                };
+                (state, Some(value))
+            }
+            Ok(_) => (Generator::Done, None),
+            Err(err) => {
+                self.state.close_upvalues(slots);
+                self.state.locals.truncate(slots);
+                self.state.tmps.truncate(tmps_start);
+                *iterator.borrow_mut().generator_mut().unwrap() = Generator::Done;
+                return Err(err);
+            }
+        };
+        *iterator.borrow_mut().generator_mut().unwrap() = state;
+        Ok(next)
+    }
+
+    /// Implements `Interpreter::run_tasks`, a task that yields is suspended like a program that runs out of budget and
+    /// is put back at the end of the queue, where it's skipped until what it waits for (if anything) is ready. Upvalues
+    /// that point to its locals get closed when it yields, like the ones of generators.
+    fn run_task_queue(&mut self) -> Result<(), RuntimeError> {
+        let slots = self.state.locals.len();
+        let tmps_start = self.state.tmps.len();
+        // The number of tasks in a row that are waiting, none of them will ever continue once it's all of them.
+        let mut waiting = 0;
+        while let Some(task) = self.state.tasks.pop_front() {
+            let (returned, future) = match task {
+                task if task.is_waiting() => {
+                    self.state.tasks.push_back(task);
+                    waiting += 1;
+                    if waiting == self.state.tasks.len() {
+                        return Err(RuntimeError::Deadlock(self.token(), Backtrace::default()));
+                    }
+                    continue;
+                }
+                Task::New(callee, future) => {
+                    self.push(callee);
+                    self.call(0)?;
+                    let returned = match self.state.suspended.is_empty() {
+                        true => {
+                            // A native has no frame to suspend when it's `سلّم` itself.
+                            self.state.yielding = false;
+                            self.state.waiting = None;
+                            Some(self.pop())
+                        }
+                        false => None,
+                    };
+                    (returned, future)
+                }
+                Task::Suspended {
+                    frames,
+                    locals,
+                    mut tmps,
+                    waiting,
+                    future,
+                } => {
+                    if let Some(awaitable) = waiting {
+                        *tmps.last_mut().unwrap() = awaitable.take();
+                    }
+                    self.state.locals.extend(locals);
+                    self.state.tmps.extend(tmps);
+                    let mut frames = frames.into_iter();
+                    let root = frames.next().unwrap();
+                    self.state.calls.push((self.closure.name(), self.token()));
+                    let result = root.resume(self.state, frames);
+                    self.state.calls.pop();
+                    (result?, future)
+                }
+            };
+            waiting = 0;
+            match returned {
+                Some(value) => {
+                    if let Some(future) = future {
+                        future.borrow_mut().resolve(value)
+                    }
+                }
+                None => {
+                    self.state.close_upvalues(slots);
+                    let mut frames = mem::take(&mut self.state.suspended);
+                    frames.reverse();
+                    let task = Task::Suspended {
+                        frames,
+                        locals: self.state.locals.drain(slots..).collect(),
+                        tmps: self.state.tmps.drain(tmps_start..).collect(),
+                        waiting: self.state.waiting.take(),
+                        future,
+                    };
+                    self.state.tasks.push_back(task)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, popped: Value, key: Value) -> Result<Value, RuntimeError> {
+        Ok(match &popped {
+            Value::String(..) | Value::Object(Object::List(..)) => {
+                let idx: usize = key
+                    .try_into()
+                    .map_err(|_| RuntimeError::InvalidIdx(self.token(), Backtrace::default()))?;
+                match popped {
+                    // Strings are indexed by characters, which makes this O(n).
+                    Value::String(string) => match string.chars().nth(idx) {
+                        Some(c) => Value::from(c),
+                        None => {
+                            return Err(RuntimeError::OutOfRange(
+                                idx as i64,
+                                string.chars().count(),
+                                self.token(),
+                                Backtrace::default(),
+                            ))
+                        }
+                    },
+                    Value::Object(Object::List(list)) => match list.borrow().get(idx) {
+                        Some(value) => value.clone(),
+                        None => {
+                            return Err(RuntimeError::OutOfRange(
+                                idx as i64,
+                                list.borrow().len(),
+                                self.token(),
+                                Backtrace::default(),
+                            ))
+                        }
+                    },
+                    _ => unreachable!(),
+                }
+            }
+            Value::Object(Object::HashMap(hash_map)) => {
+                self.check_type(&key, &[DataType::String])?;
+                let key: String = key.try_into().unwrap();
+                match hash_map.borrow().get(&key).cloned() {
+                    Some(value) => value,
+                    None => {
+                        return Err(RuntimeError::UndefinedKey(
+                            key,
+                            self.token(),
+                            Backtrace::default(),
+                        ))
+                    }
+                }
+            }
+            _ => unreachable!(),
+        })
+    }
+
+    /// Implements `popped[key] = TOT`, expects `popped` to be a list or hash map.
+    fn set(&mut self, popped: Value, key: Value) -> Result<(), RuntimeError> {
+        let new_value = self.last().clone();
+        match popped {
+            Value::Object(Object::List(list)) => {
+                let idx: usize = key
+                    .try_into()
+                    .map_err(|_| RuntimeError::InvalidIdx(self.token(), Backtrace::default()))?;
+                match list.borrow_mut().get_mut(idx) {
+                    Some(value) => {
+                        *value = new_value;
+                    }
+                    None => {
+                        return Err(RuntimeError::OutOfRange(
+                            idx as i64,
+                            list.borrow().len(),
+                            self.token(),
+                            Backtrace::default(),
+                        ));
+                    }
+                }
+            }
+            Value::Object(Object::HashMap(hash_map)) => {
+                self.check_type(&key, &[DataType::String])?;
+                let key: String = key.try_into().unwrap();
+                let mut hash_map = hash_map.borrow_mut();
+                if !hash_map.contains_key(&key) {
+                    self.allocate(1)?
+                }
+                hash_map.insert(key, new_value);
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Panics if the stacks are inconsistent after running the instruction at `ip`, which can only be caused by a bug in
+    /// the compiler or the VM.
+    fn check_invariants(&self, op_code: OpCode, ip: usize) {
+        let locals = self.state.locals.len();
+        let open_upvalues = self
+            .state
+            .open_upvalues
+            .iter()
+            .map(|upvalue| match upvalue.borrow().deref() {
+                Upvalue::Open(idx) => Some(*idx),
+                Upvalue::Closed(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let handlers = self.handlers.iter().map(Handler::slots).collect::<Vec<_>>();
+        let violation = if self.state.tmps.len() < self.tmps_start {
+            "tmps are shorter than where the frame's tmps start"
+        } else if locals < self.slots {
+            "locals are shorter than where the frame's slots start"
+        } else if open_upvalues
+            .iter()
+            .any(|idx| !matches!(idx, Some(idx) if *idx < locals))
+        {
+            "an open upvalue is closed or points past the locals"
+        } else if handlers.windows(2).any(|pair| pair[0] > pair[1])
+            || handlers
+                .iter()
+                .any(|slots| *slots < self.slots || *slots > locals)
+        {
+            "the handlers' slots aren't non-decreasing within the frame's locals"
+        } else if self.handlers.iter().any(|handler| {
+            handler.tmps() < self.tmps_start || handler.tmps() > self.state.tmps.len()
+        }) {
+            "a handler's tmps are outside the frame's tmps"
+        } else {
+            return;
+        };
+        let display = |values: &[Value]| {
+            values
+                .iter()
+                .map(|value| format!("{value}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        panic!(
+            "invariant violated after {op_code:?} at {ip}: {violation}\n\
+             tmps (starting at {}): [{}]\n\
+             locals (slots starting at {}): [{}]\n\
+             open upvalues: {open_upvalues:?}\n\
+             handlers' slots: {handlers:?}",
+            self.tmps_start,
+            display(&self.state.tmps),
+            self.slots,
+            display(&self.state.locals),
+        )
+    }
+
     fn run(&mut self) -> Result<Option<Value>, RuntimeError> {
         if cfg!(feature = "verbose") {
             println!(
@@ -659,44 +1725,328 @@ impl<'a> Frame<'a> {
             if cfg!(feature = "verbose") {
                 println!("{}", self.ip)
             }
-            let size = instr.size();
-            match self.run_instr(instr) {
+            if let Some(flag) = &self.state.interrupt {
+                if flag.swap(false, atomic::Ordering::Relaxed) {
+                    let mut err = RuntimeError::Interrupted(self.token(), Backtrace::default());
+                    err.backtrace_mut().push(self.closure.name(), self.token());
+                    return Err(err);
+                }
+            }
+            match &mut self.state.budget {
+                Some(0) if self.state.atomic == 0 => {
+                    self.suspend();
+                    return Ok(None);
+                }
+                Some(budget) => *budget = budget.saturating_sub(1),
+                None => {}
+            }
+            let (ip, op_code, size) = (self.ip, instr.op_code(), instr.size());
+            if let Some(mut hook) = self.state.on_step.take() {
+                hook(&Step { frame: self });
+                self.state.on_step = Some(hook);
+            }
+            let result = self.run_instr(instr);
+            self.state.stats.instructions += 1;
+            self.state.stats.peak_tmps = self.state.stats.peak_tmps.max(self.state.tmps.len());
+            if self.state.invariant_checks {
+                self.check_invariants(op_code, ip)
+            }
+            match result {
                 Ok((returned, advance)) => {
                     match returned {
                         Some(returned) => return Ok(Some(returned)),
                         None => {}
                     }
-                    if advance {
+                    // The task continues after the call to `سلّم`.
+                    if self.state.yielding {
+                        self.state.yielding = false;
                         self.ip += size;
+                        self.suspend();
+                        return Ok(None);
                     }
-                }
-                Err(mut err) => match self.handlers.pop() {
-                    Some(handler) => {
-                        self.state.close_upvalues(handler.slots());
-                        self.state.locals.drain(handler.slots()..);
-                        self.push(err.into());
-                        self.ip = handler.ip();
+                    // A function it called suspended, it's continued from the call.
+                    if !self.state.suspended.is_empty() {
+                        self.suspend();
+                        return Ok(None);
                     }
-                    None => {
-                        err.backtrace_mut().push(self.closure.name(), self.token());
-                        return Err(err);
+                    if advance {
+                        self.ip += size;
                     }
-                },
+                }
+                Err(err) => self.catch(err, op_code, ip)?,
             }
         }
         Ok(None)
     }
+
+    /// Jumps to the innermost handler, or fails if there isn't one (or the program was interrupted).
+    fn catch(
+        &mut self,
+        mut err: RuntimeError,
+        op_code: OpCode,
+        ip: usize,
+    ) -> Result<(), RuntimeError> {
+        let handler = match err {
+            RuntimeError::Interrupted(..) => None,
+            _ => self.handlers.pop(),
+        };
+        match handler {
+            Some(handler) => {
+                self.state.close_upvalues(handler.slots());
+                self.state.locals.drain(handler.slots()..);
+                // Whatever the throwing expression was building (e.g. the elements of a list literal) is discarded.
+                self.state.tmps.truncate(handler.tmps());
+                self.push(err.clone().into());
+                self.push(Value::Object(Object::Caught(Rc::new(err))));
+                self.ip = handler.ip();
+                if self.state.invariant_checks {
+                    self.check_invariants(op_code, ip)
+                }
+                Ok(())
+            }
+            None => {
+                err.backtrace_mut().push(self.closure.name(), self.token());
+                Err(err)
+            }
+        }
+    }
+
+    /// Saves the frame to be resumed, its locals and tmps stay on the stacks.
+    fn suspend(&mut self) {
+        let frame = SuspendedFrame {
+            closure: Rc::clone(&self.closure),
+            ip: self.ip,
+            slots: self.slots,
+            tmps_start: self.tmps_start,
+            idx: self.idx,
+            handlers: mem::take(&mut self.handlers),
+        };
+        self.state.suspended.push(frame)
+    }
+}
+
+/// A frame that ran out of budget, or that is waiting for a function it called that did.
+struct SuspendedFrame {
+    closure: Rc<Closure>,
+    /// At the call when it's waiting for a function.
+    ip: usize,
+    slots: usize,
+    tmps_start: usize,
+    idx: usize,
+    handlers: Vec<Handler>,
+}
+
+impl SuspendedFrame {
+    /// Resumes the functions it's waiting for, `callees`, before continuing where it stopped. Returns like `Frame::run`.
+    fn resume(
+        self,
+        state: &mut Vm,
+        mut callees: impl Iterator<Item = SuspendedFrame>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let mut frame = Frame {
+            state,
+            closure: self.closure,
+            ip: self.ip,
+            slots: self.slots,
+            tmps_start: self.tmps_start,
+            idx: self.idx,
+            handlers: self.handlers,
+            yielded: false,
+        };
+        if let Some(callee) = callees.next() {
+            // It finishes the call the way `Frame::call` would have.
+            frame
+                .state
+                .calls
+                .push((frame.closure.name(), frame.token()));
+            let result = callee.resume(frame.state, callees);
+            frame.state.calls.pop();
+            match result {
+                Ok(Some(value)) => {
+                    frame.push(value);
+                    frame.ip += frame.chunk().read(frame.ip).unwrap().size();
+                }
+                Ok(None) => {
+                    frame.suspend();
+                    return Ok(None);
+                }
+                Err(err) => frame.catch(err, CALL, frame.ip)?,
+            }
+        }
+        frame.run()
+    }
+}
+
+/// A function scheduled with `مهمة`.
+enum Task {
+    /// The function, and the future what it returns resolves.
+    New(Value, Option<Rc<RefCell<value::Future>>>),
+    /// Holds its frames, the outermost first, and the locals and tmps they had above the ones of the frame that runs the
+    /// tasks, which are at the same place every time it resumes one of them.
+    Suspended {
+        frames: Vec<SuspendedFrame>,
+        locals: Vec<Value>,
+        tmps: Vec<Value>,
+        /// What it can't continue before, the last of its tmps is replaced with what's taken from it.
+        waiting: Option<Awaitable>,
+        future: Option<Rc<RefCell<value::Future>>>,
+    },
+}
+
+impl Task {
+    fn is_waiting(&self) -> bool {
+        matches!(self, Self::Suspended { waiting: Some(awaitable), .. } if !awaitable.is_ready())
+    }
+}
+
+impl<'a> Interpreter for Frame<'a> {
+    fn token(&self) -> Rc<Token> {
+        self.chunk().token(self.ip)
+    }
+
+    fn untrusted(&self) -> bool {
+        self.state.untrusted
+    }
+
+    fn assertion_passed(&mut self) {
+        self.state.passed_assertions += 1
+    }
+
+    fn display_precision(&self) -> Option<usize> {
+        self.state.display_precision
+    }
+
+    fn set_display_precision(&mut self, digits: Option<usize>) {
+        self.state.display_precision = digits
+    }
+
+    fn traversal_limits(&self) -> TraversalLimits {
+        self.state.traversal_limits
+    }
+
+    fn allocate(&mut self, count: usize) -> Result<(), RuntimeError> {
+        self.state.elements = self.state.elements.saturating_add(count);
+        match self.state.max_elements {
+            Some(limit) if self.state.elements > limit => Err(RuntimeError::Elements(
+                limit,
+                self.token(),
+                Backtrace::default(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn max_list_len(&self) -> usize {
+        self.state.max_list_len
+    }
+
+    fn output(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        self.state.output_bytes = self.state.output_bytes.saturating_add(bytes);
+        match self.state.max_output_bytes {
+            Some(limit) if self.state.output_bytes > limit => Err(RuntimeError::Output(
+                limit,
+                self.token(),
+                Backtrace::default(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn invoke(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let argc = args.len();
+        self.push(callee);
+        for arg in args {
+            self.push(arg)
+        }
+        self.call(argc)?;
+        Ok(self.pop())
+    }
+
+    fn advance(
+        &mut self,
+        iterator: &Rc<RefCell<value::Iterator>>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        self.advance_iterator(iterator)
+    }
+
+    fn backtrace(&self) -> Backtrace {
+        let mut backtrace = Backtrace::default();
+        backtrace.push(self.closure.name(), self.token());
+        for (name, token) in self.state.calls.iter().rev() {
+            backtrace.push(name.clone(), Rc::clone(token))
+        }
+        backtrace
+    }
+
+    fn open_file(&mut self, file: value::File) -> Value {
+        let file = Rc::new(RefCell::new(file));
+        let files = &mut self.state.files;
+        files.retain(|file| file.strong_count() > 0);
+        files.push(Rc::downgrade(&file));
+        Value::Object(Object::File(file))
+    }
+
+    fn spawn(&mut self, callee: Value, future: Option<Rc<RefCell<value::Future>>>) {
+        self.state.tasks.push_back(Task::New(callee, future))
+    }
+
+    fn tasks_running(&self) -> bool {
+        self.state.tasks_atomic.is_some()
+    }
+
+    /// A task that fails stops the others, which are dropped.
+    fn run_tasks(&mut self) -> Result<(), RuntimeError> {
+        if self.state.tasks_atomic.is_some() {
+            return Err(RuntimeError::TasksRunning(
+                self.token(),
+                Backtrace::default(),
+            ));
+        }
+        self.state.tasks_atomic = Some(self.state.atomic);
+        let result = self.run_task_queue();
+        self.state.tasks_atomic = None;
+        if result.is_err() {
+            self.state.tasks.clear()
+        }
+        result
+    }
+
+    /// Only the natives a task calls directly can yield, the frames of the natives and iterators in between couldn't be
+    /// suspended.
+    fn yield_task(&mut self) -> Result<(), RuntimeError> {
+        match self.state.tasks_atomic {
+            Some(atomic) if self.state.atomic == atomic + 1 => {
+                self.state.yielding = true;
+                Ok(())
+            }
+            _ => Err(RuntimeError::YieldOutsideTask(
+                self.token(),
+                Backtrace::default(),
+            )),
+        }
+    }
+
+    fn wait(&mut self, awaitable: Awaitable) -> Result<(), RuntimeError> {
+        self.yield_task().map_err(|err| match awaitable {
+            Awaitable::Channel(_) => RuntimeError::EmptyChannel(self.token(), Backtrace::default()),
+            Awaitable::Future(_) => err,
+        })?;
+        self.state.waiting = Some(awaitable);
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Handler {
     ip: usize,
     slots: usize,
+    /// The length of the tmps when the handler was appended.
+    tmps: usize,
 }
 
 impl Handler {
-    fn new(ip: usize, slots: usize) -> Self {
-        Self { ip, slots }
+    fn new(ip: usize, slots: usize, tmps: usize) -> Self {
+        Self { ip, slots, tmps }
     }
 
     fn ip(&self) -> usize {
@@ -706,4 +2056,83 @@ impl Handler {
     fn slots(&self) -> usize {
         self.slots
     }
+
+    fn tmps(&self) -> usize {
+        self.tmps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compiler::chunk::asm::assemble;
+
+    #[test]
+    fn list_append_keeps_the_list_on_tmps() {
+        let chunk = assemble(
+            "
+                BUILD_LIST 0
+                CONST8 3
+                LIST_APPEND
+                CONST8 4
+                LIST_APPEND
+                # Appending the list to itself appends a reference to it, not a copy.
+                DUP
+                LIST_APPEND
+                DEF_GLOBAL8 5
+            ",
+            vec![Value::from(1.0), Value::from("ب"), Value::from("ق")],
+        );
+        let mut vm = Vm::new();
+        vm.run(chunk).unwrap();
+        let list: Rc<RefCell<Vec<Value>>> = vm.global("ق").unwrap().clone().try_into().unwrap();
+        let list_ref = list.borrow();
+        assert_eq!(list_ref.len(), 3);
+        assert_eq!(list_ref[0].to_string(), "1");
+        assert_eq!(list_ref[1].to_string(), "ب");
+        let inner: Rc<RefCell<Vec<Value>>> = list_ref[2].clone().try_into().unwrap();
+        assert!(Rc::ptr_eq(&inner, &list));
+        drop(list_ref);
+        // Breaks the cycle.
+        list.borrow_mut().clear();
+    }
+
+    #[test]
+    fn list_append_expects_a_list() {
+        let chunk = assemble(
+            "
+                CONST8 3
+                CONST8 3
+                LIST_APPEND
+            ",
+            vec![Value::from(1.0)],
+        );
+        match Vm::new().run(chunk) {
+            Err(RuntimeError::Type(expected, received, token, _)) => {
+                assert_eq!(expected, [DataType::List]);
+                assert_eq!(received, DataType::Number);
+                assert_eq!(token.lexeme(), "LIST_APPEND");
+            }
+            result => panic!("expected a type error but got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn list_append_respects_the_list_length_limit() {
+        let chunk = assemble(
+            "
+                CONST8 3
+                CONST8 3
+                BUILD_LIST 2
+                CONST8 3
+                LIST_APPEND
+            ",
+            vec![Value::from(1.0)],
+        );
+        let result = Vm::new().max_list_len(2).run(chunk);
+        assert!(
+            matches!(result, Err(RuntimeError::ListTooLong(3, Some(2), ..))),
+            "{result:?}"
+        );
+    }
 }
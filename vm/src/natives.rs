@@ -0,0 +1,3082 @@
+use super::docs::{Category, NativeDoc, ParamDoc};
+use super::{csv, json, memory};
+use compiler::chunk::value::{
+    self, Arity, ArityType, Awaitable, DataType, FileMode, Interpreter, Iterable, Native, Object,
+    Set, SetKey, Value,
+};
+use compiler::error::{self, Backtrace, NativeError, RuntimeError};
+use compiler::path;
+use lexer::token::{display_path, Token};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    iter,
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+    sync::atomic::{self, AtomicU64},
+    time::SystemTime,
+};
+use unicode_normalization::UnicodeNormalization;
+
+fn check_type(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+    expected: &[DataType],
+) -> Result<(), RuntimeError> {
+    let received = value.typ();
+    if expected.contains(&received) {
+        Ok(())
+    } else {
+        Err(RuntimeError::Type(
+            expected.to_owned(),
+            received,
+            interpreter.token(),
+            Backtrace::default(),
+        ))
+    }
+}
+
+fn string(interpreter: &dyn Interpreter, value: Value) -> Result<String, RuntimeError> {
+    check_type(interpreter, &value, &[DataType::String])?;
+    Ok(value.try_into().unwrap())
+}
+
+/// Fails unless `value` is a string containing a single character.
+fn single_char(interpreter: &dyn Interpreter, value: Value) -> Result<char, RuntimeError> {
+    let string = string(interpreter, value)?;
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Ok(ch),
+        _ => Err(RuntimeError::ExpectedChar(
+            string,
+            interpreter.token(),
+            Backtrace::default(),
+        )),
+    }
+}
+
+fn too_large(interpreter: &dyn Interpreter, _: value::TooLarge) -> RuntimeError {
+    RuntimeError::TooLarge(interpreter.token(), Backtrace::default())
+}
+
+/// Displays `value` honoring the display precision.
+fn display(interpreter: &dyn Interpreter, value: &Value) -> Result<String, RuntimeError> {
+    value
+        .render(
+            interpreter.display_precision(),
+            interpreter.traversal_limits(),
+        )
+        .map_err(|err| too_large(interpreter, err))
+}
+
+/// Prints `text` on its own line if that doesn't exceed the output limit.
+fn print_line(interpreter: &mut dyn Interpreter, text: &str) -> Result<(), RuntimeError> {
+    interpreter.output(text.len() + 1)?;
+    println!("{text}");
+    Ok(())
+}
+
+fn print(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = display(interpreter, &args[1])?;
+    print_line(interpreter, &text)?;
+    Ok(Value::Nil)
+}
+
+/// Every line `إطبع` prints is written out right away already, this is for making sure nothing is left in the buffer
+/// before the program waits (e.g. for another process).
+fn flush_output(interpreter: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    io::stdout().flush().map_err(|err| {
+        NativeError::new(error::io_error_kind(err.kind()), err.to_string())
+            .into_runtime_error(interpreter.token())
+    })?;
+    Ok(Value::Nil)
+}
+
+/// Sets the number of significant digits numbers are displayed with, `عدم` restores the default.
+fn set_display_precision(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::Number, DataType::Nil])?;
+    let digits = match args[1] {
+        Value::Nil => None,
+        _ => match integer(interpreter, &args[1])? {
+            digits if digits > 0 => Some(digits as usize),
+            digits => {
+                return Err(RuntimeError::ExpectedInteger(
+                    digits as f64,
+                    interpreter.token(),
+                    Backtrace::default(),
+                ))
+            }
+        },
+    };
+    interpreter.set_display_precision(digits);
+    Ok(Value::Nil)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// The name of the environment variable that controls the minimum level of the messages logged, it defaults to "معلومة".
+const LOG_LEVEL_VAR: &str = "قتام_السجل";
+/// When this environment variable is defined the logged messages aren't prefixed by the time.
+const LOG_NO_TIME_VAR: &str = "قتام_السجل_بلا_وقت";
+
+impl LogLevel {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Info => "معلومة",
+            Self::Warning => "تحذير",
+            Self::Error => "خطأ",
+        }
+    }
+
+    /// Unknown levels are ignored.
+    fn min() -> Self {
+        match env::var(LOG_LEVEL_VAR) {
+            Ok(name) if name == Self::Warning.name() => Self::Warning,
+            Ok(name) if name == Self::Error.name() => Self::Error,
+            _ => Self::Info,
+        }
+    }
+}
+
+/// Formats the current UTC time as `YYYY-MM-DD HH:MM:SS`.
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn log(interpreter: &dyn Interpreter, level: LogLevel, value: &Value) -> Result<(), RuntimeError> {
+    if level < LogLevel::min() {
+        return Ok(());
+    }
+    let value = display(interpreter, value)?;
+    if env::var_os(LOG_NO_TIME_VAR).is_some() {
+        eprintln!("[{}] {value}", level.name())
+    } else {
+        eprintln!("{} [{}] {value}", timestamp(), level.name())
+    }
+    Ok(())
+}
+
+fn log_info(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    log(interpreter, LogLevel::Info, &args[1])?;
+    Ok(Value::Nil)
+}
+
+fn log_warning(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    log(interpreter, LogLevel::Warning, &args[1])?;
+    Ok(Value::Nil)
+}
+
+fn log_error(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    log(interpreter, LogLevel::Error, &args[1])?;
+    Ok(Value::Nil)
+}
+
+/// The key of the function's name in the entries `مكدس` returns.
+const FUNCTION_KEY: &str = "الدالة";
+
+/// The position of `token` with the keys of the positions of thrown error objects.
+fn position(token: &Token) -> HashMap<String, Value> {
+    let path = match token.path() {
+        Some(path) => Value::from(display_path(path)),
+        None => Value::Nil,
+    };
+    HashMap::from([
+        (error::ERROR_PATH.to_owned(), path),
+        (
+            error::ERROR_LINE.to_owned(),
+            Value::Number(token.line() as f64),
+        ),
+    ])
+}
+
+/// Returns where it's called from.
+fn location(interpreter: &mut dyn Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    // The token is of the call, since natives don't have frames of their own.
+    Ok(Value::from(position(&interpreter.token())))
+}
+
+/// Returns the position of every running frame and the name of its function (`Nil` for the top level and anonymous
+/// functions), starting with the caller's.
+fn call_stack(interpreter: &mut dyn Interpreter, _args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let backtrace = interpreter.backtrace();
+    // A hash map of 3 elements for every frame.
+    interpreter.allocate(backtrace.frames().len().saturating_mul(4))?;
+    let frames = backtrace
+        .frames()
+        .iter()
+        .map(|(name, token)| {
+            let mut frame = position(token);
+            let name = name.clone().map_or(Value::Nil, Value::from);
+            frame.insert(FUNCTION_KEY.to_owned(), name);
+            Value::from(frame)
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::from(frames))
+}
+
+/// Prints a list of hash maps as a table whose columns are the sorted union of their keys.
+fn print_table(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::List])?;
+    let list: Rc<RefCell<Vec<Value>>> = args[1].clone().try_into().unwrap();
+    let mut rows = vec![];
+    for value in list.borrow().iter() {
+        check_type(interpreter, value, &[DataType::HashMap])?;
+        let hash_map: Rc<RefCell<HashMap<String, Value>>> = value.clone().try_into().unwrap();
+        let row = hash_map
+            .borrow()
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), display(interpreter, value)?)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        rows.push(row);
+    }
+    let mut columns = rows.iter().flat_map(|row| row.keys()).collect::<Vec<_>>();
+    columns.sort();
+    columns.dedup();
+    let widths = columns
+        .iter()
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(*column))
+                .chain(iter::once(*column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_owned()
+    };
+    let mut lines = vec![
+        line(columns.iter().map(|column| column.as_str()).collect()),
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("-+-"),
+    ];
+    for row in &rows {
+        lines.push(line(
+            columns
+                .iter()
+                .map(|column| row.get(*column).map_or("", String::as_str))
+                .collect(),
+        ));
+    }
+    print_line(interpreter, &lines.join("\n"))?;
+    Ok(Value::Nil)
+}
+
+/// Fails with the message (or a default one) unless the condition is truthy.
+fn assert(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args[1].truthy() {
+        interpreter.assertion_passed();
+        return Ok(Value::Nil);
+    }
+    let msg = match args.get(2) {
+        Some(msg) => format!("{msg}"),
+        None => "لم يتحقق التوقع".to_owned(),
+    };
+    Err(RuntimeError::Assertion(
+        msg,
+        interpreter.token(),
+        Backtrace::default(),
+    ))
+}
+
+fn assert_eq(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    if args[1] == args[2] {
+        interpreter.assertion_passed();
+        return Ok(Value::Nil);
+    }
+    Err(RuntimeError::Assertion(
+        format!("توقعت {} ولكن حصلت على {}", args[2], args[1]),
+        interpreter.token(),
+        Backtrace::default(),
+    ))
+}
+
+/// Makes an error object of the kind "خطأ", its position is added when it's thrown.
+fn error(interpreter: &mut dyn Interpreter, mut args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let message = string(interpreter, args.pop().unwrap())?;
+    Ok(error::error_object("خطأ".to_owned(), message))
+}
+
+fn error_with_kind(
+    interpreter: &mut dyn Interpreter,
+    mut args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let message = string(interpreter, args.pop().unwrap())?;
+    let kind = string(interpreter, args.pop().unwrap())?;
+    Ok(error::error_object(kind, message))
+}
+
+fn check_trusted(interpreter: &dyn Interpreter) -> Result<(), RuntimeError> {
+    if interpreter.untrusted() {
+        return Err(RuntimeError::Untrusted(
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails in untrusted mode, otherwise resolves `value` relative to the directory of the file the calling function is
+/// written in (the one of the call's token), which isn't the script's when it's in a module. That directory is what
+/// `هذا_المجلد` holds.
+fn file_path(interpreter: &dyn Interpreter, value: Value) -> Result<PathBuf, RuntimeError> {
+    check_trusted(interpreter)?;
+    let path = string(interpreter, value)?;
+    Ok(path::resolve_runtime(
+        interpreter.token().path().map(PathBuf::as_path),
+        &path,
+    ))
+}
+
+/// Fails with an error object whose kind can be matched on, see `NativeError::io`.
+fn io_err(interpreter: &dyn Interpreter, err: io::Error, path: PathBuf) -> RuntimeError {
+    NativeError::io(&err, &path).into_runtime_error(interpreter.token())
+}
+
+/// Reads the whole file as a string.
+///
+/// The optional options object supports `وحد_الأسطر` (replace "\r\n" and "\r" with "\n").
+fn read(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let unify = match args.get(2) {
+        Some(options) => {
+            check_type(interpreter, options, &[DataType::HashMap])?;
+            let options: Rc<RefCell<HashMap<String, Value>>> = options.clone().try_into().unwrap();
+            let unify = options.borrow().get("وحد_الأسطر").is_some_and(Value::truthy);
+            unify
+        }
+        None => false,
+    };
+    let unify = |content: String| {
+        if unify {
+            unify_newlines(&content)
+        } else {
+            content
+        }
+    };
+    let file = File::open(&path).map_err(|err| io_err(interpreter, err, path.clone()))?;
+    match read_partially(file) {
+        Ok(content) => Ok(Value::from(unify(content))),
+        Err((err, partial)) => Err(NativeError::io(&err, &path)
+            .with(error::ERROR_PARTIAL, unify(partial))
+            .into_runtime_error(interpreter.token())),
+    }
+}
+
+/// Reads `reader` to its end as a string, failing with the valid text read before the error (if the reading itself
+/// failed or it isn't valid UTF-8).
+fn read_partially(mut reader: impl Read) -> Result<String, (io::Error, String)> {
+    let mut bytes = vec![];
+    let result = reader.read_to_end(&mut bytes);
+    match (result, String::from_utf8(bytes)) {
+        (Ok(_), Ok(content)) => Ok(content),
+        (Err(err), Ok(partial)) => Err((err, partial)),
+        (result, Err(invalid)) => {
+            let valid = invalid.utf8_error().valid_up_to();
+            let mut bytes = invalid.into_bytes();
+            bytes.truncate(valid);
+            let err = result.err().unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            });
+            Err((err, String::from_utf8(bytes).unwrap()))
+        }
+    }
+}
+
+/// Returns an iterator over the lines of a file that reads them as they're needed, so big files aren't loaded at once.
+fn read_lines(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    match File::open(&path) {
+        Ok(file) => Ok(Value::from(Iterable::Lines(
+            path,
+            Rc::new(RefCell::new(BufReader::new(file))),
+        ))),
+        Err(err) => Err(io_err(interpreter, err, path)),
+    }
+}
+
+/// Writes a string to a file, creating it if it doesn't exist.
+///
+/// The optional options object supports `الحق` (append instead of truncating) and `زامن` (wait for the content to
+/// reach the disk).
+fn write(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let content = string(interpreter, args[2].clone())?;
+    let (append, sync) = match args.get(3) {
+        Some(options) => {
+            check_type(interpreter, options, &[DataType::HashMap])?;
+            let options: Rc<RefCell<HashMap<String, Value>>> = options.clone().try_into().unwrap();
+            let options = options.borrow();
+            let flag = |key| options.get(key).is_some_and(Value::truthy);
+            (flag("الحق"), flag("زامن"))
+        }
+        None => (false, false),
+    };
+    let result = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            if sync {
+                file.sync_all()?;
+            }
+            Ok(())
+        });
+    match result {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, path)),
+    }
+}
+
+/// Writes to a temporary file in the same directory, syncs it, and then renames it over the target, so the target
+/// always has either its old or its new content.
+///
+/// The rename replaces the target atomically on Unix, but on Windows it isn't guaranteed to be. The directory itself
+/// isn't synced, so after a power loss the rename might not have persisted.
+fn write_atomically(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let content = string(interpreter, args[2].clone())?;
+    match replace_atomically(&path, &content) {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, path)),
+    }
+}
+
+/// See `write_atomically`.
+fn replace_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let tmp_path = {
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        name.push(format!(".{}.مؤقت", process::id()));
+        path.with_file_name(name)
+    };
+    let result = fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
+
+fn exists(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    Ok(Value::from(path.exists()))
+}
+
+/// Returns the sorted paths that match a glob pattern, a relative pattern is matched from the directory of the file
+/// the call is written in and so are the paths relative to it.
+fn glob_files(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_trusted(interpreter)?;
+    let pattern = string(interpreter, args[1].clone())?;
+    if let Err(err) = glob::Pattern::new(&pattern) {
+        return Err(RuntimeError::InvalidPattern(
+            pattern,
+            err.pos,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    let token = interpreter.token();
+    let dir = token
+        .path()
+        .and_then(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty() && Path::new(&pattern).is_relative());
+    let full = match dir {
+        // The directory's name can have characters with special meanings in patterns.
+        Some(dir) => format!(
+            "{}/{pattern}",
+            glob::Pattern::escape(&dir.to_string_lossy())
+        ),
+        None => pattern,
+    };
+    let mut paths = vec![];
+    for entry in glob::glob(&full).expect("the pattern was validated") {
+        match entry {
+            Ok(path) => {
+                let path = dir
+                    .and_then(|dir| path.strip_prefix(dir).ok())
+                    .unwrap_or(&path);
+                paths.push(display_path(path));
+            }
+            Err(err) => {
+                let path = err.path().to_owned();
+                return Err(io_err(interpreter, err.into(), path));
+            }
+        }
+    }
+    paths.sort();
+    interpreter.allocate(paths.len())?;
+    Ok(Value::from(
+        paths.into_iter().map(Value::from).collect::<Vec<_>>(),
+    ))
+}
+
+/// The keys of the hash maps `معلومات_ملف` returns.
+const SIZE_KEY: &str = "الحجم";
+const IS_DIR_KEY: &str = "مجلد";
+const MODIFIED_KEY: &str = "آخر_تعديل";
+
+/// Returns the size of what's at the path in bytes, whether it's a directory, and when it was last modified in seconds
+/// since the Unix epoch (`عدم` if the platform doesn't record it).
+fn metadata(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(err) => return Err(io_err(interpreter, err, path)),
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(Value::Nil, |duration| Value::from(duration.as_secs_f64()));
+    interpreter.allocate(3)?;
+    Ok(Value::from(HashMap::from([
+        (SIZE_KEY.to_owned(), Value::from(metadata.len() as f64)),
+        (IS_DIR_KEY.to_owned(), Value::from(metadata.is_dir())),
+        (MODIFIED_KEY.to_owned(), modified),
+    ])))
+}
+
+/// Tells apart the temporary paths created by the same process in the same nanosecond.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Creates a file or a directory with `create` at a new path in the system's temporary directory, retrying with another
+/// name if one already exists there.
+fn create_temp(
+    interpreter: &dyn Interpreter,
+    create: fn(&Path) -> io::Result<()>,
+) -> Result<Value, RuntimeError> {
+    check_trusted(interpreter)?;
+    let dir = env::temp_dir();
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_nanos());
+        let counter = TEMP_COUNTER.fetch_add(1, atomic::Ordering::Relaxed);
+        let path = dir.join(format!("قتام-{}-{nanos:x}-{counter}", process::id()));
+        match create(&path) {
+            Ok(()) => return Ok(Value::from(display_path(&path))),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(io_err(interpreter, err, path)),
+        }
+    }
+}
+
+/// Creates an empty file that no one else is using, it isn't deleted automatically.
+fn temp_file(interpreter: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    create_temp(interpreter, |path| {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map(|_| ())
+    })
+}
+
+/// Creates an empty directory that no one else is using, it isn't deleted automatically.
+fn temp_dir(interpreter: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    create_temp(interpreter, |path| fs::create_dir(path))
+}
+
+/// The kinds of the errors of using a closed file and of using a file in a mode it isn't open in.
+const CLOSED_FILE_KIND: &str = "ملف_مغلق";
+const FILE_MODE_KIND: &str = "وضع_غير_مناسب";
+
+/// Fails unless `value` is a file that's open in a mode `allowed` accepts, `action` describes what's being done to it
+/// in the error.
+fn open_file_arg(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+    allowed: fn(&FileMode) -> bool,
+    action: &str,
+) -> Result<Rc<RefCell<value::File>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::File])?;
+    let file: Rc<RefCell<value::File>> = value.clone().try_into().unwrap();
+    {
+        let file = file.borrow();
+        if file.is_closed() {
+            return Err(NativeError::new(
+                CLOSED_FILE_KIND,
+                format!("لا يمكن {action} الملف \"{}\" لأنه مغلق", file.name()),
+            )
+            .into_runtime_error(interpreter.token()));
+        }
+        if !allowed(&file.mode()) {
+            return Err(NativeError::new(
+                FILE_MODE_KIND,
+                format!(
+                    "لا يمكن {action} الملف \"{}\" لأنه مفتوح على وضع {}",
+                    file.name(),
+                    file.mode()
+                ),
+            )
+            .into_runtime_error(interpreter.token()));
+        }
+    }
+    Ok(file)
+}
+
+/// Opens a file to be read or written in parts, in the mode named by the optional second argument ("قراءة" by
+/// default).
+fn open_file(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let mode = match args.get(2) {
+        Some(mode) => {
+            let mode = string(interpreter, mode.clone())?;
+            FileMode::try_from(mode.clone()).map_err(|_| {
+                let modes = value::FILE_MODES
+                    .map(|mode| format!("\"{mode}\""))
+                    .join(" و");
+                NativeError::new(
+                    error::io_error_kind(io::ErrorKind::InvalidInput),
+                    format!("لا يوجد وضع باسم \"{mode}\"، الأوضاع المتاحة هي {modes}"),
+                )
+                .into_runtime_error(interpreter.token())
+            })?
+        }
+        None => FileMode::Read,
+    };
+    match value::File::open(&path, mode) {
+        Ok(file) => Ok(interpreter.open_file(file)),
+        Err(err) => Err(io_err(interpreter, err, path)),
+    }
+}
+
+/// Reads what's left of an open file as a string, failing with the valid text read before the error like `read`.
+fn read_from(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], FileMode::readable, "القراءة من")?;
+    let mut file = file.borrow_mut();
+    match read_partially(&mut *file) {
+        Ok(content) => Ok(Value::from(content)),
+        Err((err, partial)) => Err(NativeError::io(&err, Path::new(file.name()))
+            .with(error::ERROR_PARTIAL, partial)
+            .into_runtime_error(interpreter.token())),
+    }
+}
+
+fn write_to(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], FileMode::writable, "الكتابة في")?;
+    let content = string(interpreter, args[2].clone())?;
+    let mut file = file.borrow_mut();
+    match file.write_all(content.as_bytes()) {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// Reads up to a number of bytes from an open file as a list of integers, fewer are returned if it ends first.
+fn read_bytes(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], FileMode::readable, "القراءة من")?;
+    let count = integer(interpreter, &args[2])?;
+    if count < 0 {
+        return Err(RuntimeError::NegativeSize(
+            count as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    if count as u64 > interpreter.max_list_len() as u64 {
+        return Err(RuntimeError::ListTooLong(
+            count as u64,
+            Some(interpreter.max_list_len()),
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    let mut bytes = vec![];
+    let mut file = file.borrow_mut();
+    if let Err(err) = (&mut *file).take(count as u64).read_to_end(&mut bytes) {
+        return Err(io_err(interpreter, err, PathBuf::from(file.name())));
+    }
+    interpreter.allocate(bytes.len())?;
+    Ok(Value::from(
+        bytes
+            .into_iter()
+            .map(|byte| Value::from(byte as f64))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Writes a list of bytes to an open file, nothing is written if any of them isn't an integer from 0 to 255.
+fn write_bytes(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], FileMode::writable, "الكتابة في")?;
+    let bytes = list(interpreter, &args[2])?
+        .borrow()
+        .iter()
+        .map(|value| {
+            let byte = integer(interpreter, value)?;
+            u8::try_from(byte).map_err(|_| {
+                RuntimeError::InvalidByte(byte as f64, interpreter.token(), Backtrace::default())
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut file = file.borrow_mut();
+    match file.write_all(&bytes) {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// Makes sure what was written to a file reached the disk instead of waiting in its buffer.
+fn flush_file(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], FileMode::writable, "إفراغ")?;
+    let mut file = file.borrow_mut();
+    match file.flush() {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// Moves where a file is read from and written to, to a number of bytes from its start. Moving past its end is allowed,
+/// writing there fills the gap with zeros.
+fn seek_file(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], |_| true, "التنقل في")?;
+    let position = integer(interpreter, &args[2])?;
+    if position < 0 {
+        return Err(RuntimeError::NegativeSize(
+            position as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    let mut file = file.borrow_mut();
+    match file.seek(SeekFrom::Start(position as u64)) {
+        Ok(_) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// The number of bytes from the start of a file to where it's read from and written to.
+fn file_position(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let file = open_file_arg(interpreter, &args[1], |_| true, "معرفة الموضع في")?;
+    let mut file = file.borrow_mut();
+    match file.stream_position() {
+        Ok(position) => Ok(Value::from(position as f64)),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// Closes a file after flushing what was written to it, closing it again does nothing.
+fn close_file(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::File])?;
+    let file: Rc<RefCell<value::File>> = args[1].clone().try_into().unwrap();
+    let mut file = file.borrow_mut();
+    match file.close() {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, PathBuf::from(file.name()))),
+    }
+}
+
+/// The largest integer that `f64` can represent along with all the integers below it.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Fails unless `value` is an integer in the safe range.
+fn integer(interpreter: &dyn Interpreter, value: &Value) -> Result<i64, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Number])?;
+    let number = match value {
+        Value::Number(number) => *number,
+        _ => unreachable!(),
+    };
+    if number.fract() == 0.0 && number.abs() <= MAX_SAFE_INTEGER as f64 {
+        Ok(number as i64)
+    } else {
+        Err(RuntimeError::ExpectedInteger(
+            number,
+            interpreter.token(),
+            Backtrace::default(),
+        ))
+    }
+}
+
+/// Creates a native that applies `op` to two safe integers, failing if the result leaves the safe range instead of
+/// silently losing precision.
+macro_rules! checked_integer_op {
+    ($name:ident, $op:ident) => {
+        fn $name(
+            interpreter: &mut dyn Interpreter,
+            args: Vec<Value>,
+        ) -> Result<Value, RuntimeError> {
+            let (a, b) = (
+                integer(interpreter, &args[1])?,
+                integer(interpreter, &args[2])?,
+            );
+            match a.$op(b) {
+                Some(result) if result.abs() <= MAX_SAFE_INTEGER => Ok(Value::from(result as f64)),
+                _ => Err(RuntimeError::IntegerOverflow(
+                    interpreter.token(),
+                    Backtrace::default(),
+                )),
+            }
+        }
+    };
+}
+
+/// Creates a native that applies `op` to two integers truncated to 32 bits, wrapping around on overflow like two's
+/// complement arithmetic does.
+macro_rules! wrapping_integer_op {
+    ($name:ident, $op:ident) => {
+        fn $name(
+            interpreter: &mut dyn Interpreter,
+            args: Vec<Value>,
+        ) -> Result<Value, RuntimeError> {
+            let (a, b) = (
+                integer(interpreter, &args[1])?,
+                integer(interpreter, &args[2])?,
+            );
+            Ok(Value::from((a as i32).$op(b as i32) as f64))
+        }
+    };
+}
+
+checked_integer_op!(add_integers, checked_add);
+checked_integer_op!(mul_integers, checked_mul);
+wrapping_integer_op!(add_integers32, wrapping_add);
+wrapping_integer_op!(mul_integers32, wrapping_mul);
+
+/// Strings are measured in characters, not bytes.
+fn size(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_type(
+        interpreter,
+        &args[1],
+        &[
+            DataType::String,
+            DataType::List,
+            DataType::HashMap,
+            DataType::Set,
+        ],
+    )?;
+    Ok(Value::from(match &args[1] {
+        Value::String(string) => string.chars().count(),
+        Value::Object(Object::List(list)) => list.borrow().len(),
+        Value::Object(Object::HashMap(hash_map)) => hash_map.borrow().len(),
+        Value::Object(Object::Set(set)) => set.borrow().len(),
+        _ => unreachable!(),
+    }))
+}
+
+/// Returns the name of the value's type the way errors show it.
+fn type_of(_: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::from(args[1].typ().to_string()))
+}
+
+/// The elements of a list, the `[key, value]` pairs of a hash map, or the characters of a string, in a new list. The
+/// elements themselves aren't copied, so changing a list inside one changes the original too.
+fn elements(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_type(
+        interpreter,
+        &args[1],
+        &[DataType::String, DataType::List, DataType::HashMap],
+    )?;
+    match &args[1] {
+        Value::String(string) => {
+            Value::list(interpreter, string.chars().map(Value::from).collect())
+        }
+        Value::Object(Object::List(list)) => {
+            let list = list.borrow().clone();
+            Value::list(interpreter, list)
+        }
+        Value::Object(Object::HashMap(hash_map)) => {
+            // Each pair is a list of two elements.
+            interpreter.allocate(hash_map.borrow().len().saturating_mul(2))?;
+            let pairs = hash_map
+                .borrow()
+                .iter()
+                .map(|(key, value)| Value::from(vec![Value::from(key.as_str()), value.clone()]))
+                .collect();
+            Value::list(interpreter, pairs)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn is_callable(_: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::from(matches!(
+        args[1].typ(),
+        DataType::Closure | DataType::Native
+    )))
+}
+
+/// Calls the function with the rest of the arguments, then as long as what's returned is a function calls it with no
+/// arguments, and returns the first thing that isn't. Each step returns before the next one is called, so recursion
+/// written as returning the next step doesn't grow the stack.
+fn trampoline(
+    interpreter: &mut dyn Interpreter,
+    mut args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let function = callable(interpreter, &args[1])?;
+    let args = args.split_off(2);
+    let mut result = interpreter.invoke(function, args)?;
+    while matches!(result.typ(), DataType::Closure | DataType::Native) {
+        result = interpreter.invoke(result, vec![])?;
+    }
+    Ok(result)
+}
+
+/// How many times `iterate_until` calls the function by default before giving up.
+const DEFAULT_MAX_ITERATIONS: i64 = 10_000;
+
+/// The kind of the error `iterate_until` fails with when the condition doesn't hold after the maximum iterations.
+const MAX_ITERATIONS_KIND: &str = "تجاوز_حد_التكرار";
+
+/// Calls the function with the value, then with what it returns and so on, until the condition holds for the latest
+/// value. The condition is checked before the first call, so a value that already satisfies it is returned as it is.
+fn iterate_until(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let mut value = args[1].clone();
+    let function = callable(interpreter, &args[2])?;
+    let condition = callable(interpreter, &args[3])?;
+    let max = match args.get(4) {
+        Some(max) => integer(interpreter, max)?,
+        None => DEFAULT_MAX_ITERATIONS,
+    };
+    if max < 0 {
+        return Err(RuntimeError::NegativeSize(
+            max as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    for _ in 0..max {
+        if interpreter
+            .invoke(condition.clone(), vec![value.clone()])?
+            .truthy()
+        {
+            return Ok(value);
+        }
+        value = interpreter.invoke(function.clone(), vec![value])?;
+    }
+    if interpreter.invoke(condition, vec![value.clone()])?.truthy() {
+        return Ok(value);
+    }
+    Err(NativeError::new(
+        MAX_ITERATIONS_KIND,
+        format!("لم يتحقق شرط التوقف بعد بلوغ الحد الأقصى للاستدعاءات وهو {max}"),
+    )
+    .into_runtime_error(interpreter.token()))
+}
+
+/// Returns the value of the key, or when the hash map doesn't have it stores what the function returns (which is called
+/// without arguments only then) and returns that.
+fn get_or_insert(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::HashMap])?;
+    let hash_map: Rc<RefCell<HashMap<String, Value>>> = args[1].clone().try_into().unwrap();
+    let key = string(interpreter, args[2].clone())?;
+    let function = callable(interpreter, &args[3])?;
+    if let Some(value) = hash_map.borrow().get(&key) {
+        return Ok(value.clone());
+    }
+    let value = interpreter.invoke(function, vec![])?;
+    interpreter.allocate(1)?;
+    hash_map.borrow_mut().insert(key, value.clone());
+    Ok(value)
+}
+
+/// Fails unless `value` is a string or a whole number.
+fn set_key(interpreter: &dyn Interpreter, value: Value) -> Result<SetKey, RuntimeError> {
+    SetKey::try_from(value).map_err(|value| {
+        RuntimeError::InvalidSetElement(value, interpreter.token(), Backtrace::default())
+    })
+}
+
+fn set_arg(interpreter: &dyn Interpreter, value: &Value) -> Result<Rc<RefCell<Set>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Set])?;
+    Ok(value.clone().try_into().unwrap())
+}
+
+/// Creates a set of the elements of the optional list, the duplicates after the first are dropped.
+fn set(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let list = match args.get(1) {
+        Some(value) => {
+            check_type(interpreter, value, &[DataType::List, DataType::Nil])?;
+            match value {
+                Value::Object(Object::List(list)) => list.borrow().clone(),
+                _ => vec![],
+            }
+        }
+        None => vec![],
+    };
+    let set = list
+        .into_iter()
+        .map(|value| set_key(interpreter, value))
+        .collect::<Result<Set, _>>()?;
+    interpreter.allocate(set.len())?;
+    Ok(Value::from(set))
+}
+
+/// Returns whether the element wasn't in the set already.
+fn set_insert(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let set = set_arg(interpreter, &args[1])?;
+    let key = set_key(interpreter, args[2].clone())?;
+    if set.borrow().contains(&key) {
+        return Ok(Value::Bool(false));
+    }
+    interpreter.allocate(1)?;
+    let inserted = set.borrow_mut().insert(key);
+    Ok(Value::Bool(inserted))
+}
+
+/// Returns whether the element was in the set.
+fn set_remove(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let set = set_arg(interpreter, &args[1])?;
+    let key = set_key(interpreter, args[2].clone())?;
+    let removed = set.borrow_mut().remove(&key);
+    Ok(Value::Bool(removed))
+}
+
+fn set_contains(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let set = set_arg(interpreter, &args[1])?;
+    let key = set_key(interpreter, args[2].clone())?;
+    let contains = set.borrow().contains(&key);
+    Ok(Value::Bool(contains))
+}
+
+/// Returns the elements of the set in the order they were added.
+fn set_to_list(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let set = set_arg(interpreter, &args[1])?;
+    let set = set.borrow();
+    interpreter.allocate(set.len())?;
+    Ok(Value::from(
+        set.iter().cloned().map(Value::from).collect::<Vec<_>>(),
+    ))
+}
+
+/// Defines a native that creates a new set out of two others.
+macro_rules! set_operation {
+    ($name:ident, $method:ident) => {
+        fn $name(
+            interpreter: &mut dyn Interpreter,
+            args: Vec<Value>,
+        ) -> Result<Value, RuntimeError> {
+            let a = set_arg(interpreter, &args[1])?;
+            let b = set_arg(interpreter, &args[2])?;
+            let set = a.borrow().$method(&b.borrow());
+            interpreter.allocate(set.len())?;
+            Ok(Value::from(set))
+        }
+    };
+}
+
+set_operation!(set_union, union);
+set_operation!(set_intersection, intersection);
+set_operation!(set_difference, difference);
+
+/// Creates a list of `size` copies of `value` at once, which is faster than growing it when the size is known.
+fn array(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let size = integer(interpreter, &args[1])?;
+    if size < 0 {
+        return Err(RuntimeError::NegativeSize(
+            size as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    // It can be larger than the addressable memory on 32-bit targets.
+    let len = usize::try_from(size).map_err(|_| {
+        RuntimeError::ListTooLong(
+            size as u64,
+            Some(interpreter.max_list_len()),
+            interpreter.token(),
+            Backtrace::default(),
+        )
+    })?;
+    let mut list = value::checked_list_alloc(interpreter, len)?;
+    list.resize(len, args[2].clone());
+    Ok(Value::from(list))
+}
+
+fn list(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+) -> Result<Rc<RefCell<Vec<Value>>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::List])?;
+    match value {
+        Value::Object(Object::List(list)) => Ok(Rc::clone(list)),
+        _ => unreachable!(),
+    }
+}
+
+/// Negative indices count from the end, and `len` itself is only valid if `inclusive` (i.e. to insert at the end).
+fn list_idx(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+    len: usize,
+    inclusive: bool,
+) -> Result<usize, RuntimeError> {
+    let idx = integer(interpreter, value)?;
+    let resolved = if idx < 0 { idx + len as i64 } else { idx };
+    if resolved < 0 || resolved > len as i64 || (resolved == len as i64 && !inclusive) {
+        Err(RuntimeError::OutOfRange(
+            idx,
+            len,
+            interpreter.token(),
+            Backtrace::default(),
+        ))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+/// Removes the element at the index and returns it.
+fn remove_at(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let list = list(interpreter, &args[1])?;
+    let idx = list_idx(interpreter, &args[2], list.borrow().len(), false)?;
+    let removed = list.borrow_mut().remove(idx);
+    Ok(removed)
+}
+
+/// Inserts the value before the index, shifting the elements after it.
+fn insert_at(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let list = list(interpreter, &args[1])?;
+    let idx = list_idx(interpreter, &args[2], list.borrow().len(), true)?;
+    interpreter.allocate(1)?;
+    list.borrow_mut().insert(idx, args[3].clone());
+    Ok(Value::Nil)
+}
+
+/// Splits the list into lists of `size` elements, except for the last one which can be shorter.
+fn chunk(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let list = list(interpreter, &args[1])?;
+    let size = integer(interpreter, &args[2])?;
+    if size <= 0 {
+        return Err(RuntimeError::InvalidChunkSize(
+            size as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    // Sizes that don't fit (on 32-bit targets) are larger than any list anyway.
+    let size = usize::try_from(size).unwrap_or(usize::MAX);
+    let len = list.borrow().len();
+    interpreter.allocate(len.saturating_add(len.div_ceil(size)))?;
+    let chunks = list
+        .borrow()
+        .chunks(size)
+        .map(|chunk| Value::from(chunk.to_vec()))
+        .collect::<Vec<_>>();
+    Ok(Value::from(chunks))
+}
+
+/// Pairs the elements of the two lists, the extra elements of the longer one are ignored.
+fn zip(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (a, b) = (list(interpreter, &args[1])?, list(interpreter, &args[2])?);
+    // Each pair is a list of two elements.
+    interpreter.allocate(a.borrow().len().min(b.borrow().len()).saturating_mul(3))?;
+    let pairs = iter::zip(a.borrow().iter(), b.borrow().iter())
+        .map(|(a, b)| Value::from(vec![a.clone(), b.clone()]))
+        .collect::<Vec<_>>();
+    Ok(Value::from(pairs))
+}
+
+/// Groups the elements by the strings the function returns for them, each group keeps the order of its elements. The
+/// elements are the ones the list had when it was called, even if the function changes it.
+fn group_by(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let elements = list(interpreter, &args[1])?.borrow().clone();
+    let function = callable(interpreter, &args[2])?;
+    let len = elements.len();
+    let mut groups: HashMap<String, Vec<Value>> = HashMap::new();
+    for element in elements {
+        let key = interpreter.invoke(function.clone(), vec![element.clone()])?;
+        let key = string(interpreter, key)?;
+        groups.entry(key).or_default().push(element);
+    }
+    // Each group is an entry and a list.
+    interpreter.allocate(len.saturating_add(groups.len().saturating_mul(2)))?;
+    let groups = groups
+        .into_iter()
+        .map(|(key, group)| (key, Value::from(group)))
+        .collect::<HashMap<_, _>>();
+    Ok(Value::from(groups))
+}
+
+/// Splits the elements into the ones the function returns a truthy value for and the rest, calling it once for each.
+fn partition(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let elements = list(interpreter, &args[1])?.borrow().clone();
+    let predicate = callable(interpreter, &args[2])?;
+    // The pair and the two lists.
+    interpreter.allocate(elements.len().saturating_add(5))?;
+    let (mut matching, mut rest) = (vec![], vec![]);
+    for element in elements {
+        if interpreter
+            .invoke(predicate.clone(), vec![element.clone()])?
+            .truthy()
+        {
+            matching.push(element);
+        } else {
+            rest.push(element);
+        }
+    }
+    Ok(Value::from(vec![Value::from(matching), Value::from(rest)]))
+}
+
+/// Folds the list like `طو` but returns every accumulated value, starting with the initial one.
+fn scan(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let elements = list(interpreter, &args[1])?.borrow().clone();
+    let function = callable(interpreter, &args[2])?;
+    interpreter.allocate(elements.len().saturating_add(2))?;
+    let mut accumulated = Vec::with_capacity(elements.len() + 1);
+    accumulated.push(args[3].clone());
+    for element in elements {
+        let accumulator = accumulated.last().unwrap().clone();
+        accumulated.push(interpreter.invoke(function.clone(), vec![accumulator, element])?);
+    }
+    Ok(Value::from(accumulated))
+}
+
+/// The kind of the error `count` fails with when a number and its text are both in the list.
+const KEY_CONFLICT_KIND: &str = "مفتاح_متكرر";
+
+/// Counts the elements like sets compare them, then makes the whole numbers keys by their text. A number and its text
+/// aren't equal, so having both fails instead of adding their counts together.
+fn count(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let elements = list(interpreter, &args[1])?.borrow().clone();
+    let mut counts: HashMap<SetKey, usize> = HashMap::new();
+    for element in elements {
+        *counts.entry(set_key(interpreter, element)?).or_default() += 1;
+    }
+    interpreter.allocate(counts.len())?;
+    let mut hash_map = HashMap::with_capacity(counts.len());
+    for (key, count) in counts {
+        let key = match key {
+            SetKey::String(string) => string,
+            SetKey::Integer(integer) => integer.to_string(),
+        };
+        if hash_map.contains_key(&key) {
+            return Err(NativeError::new(
+                KEY_CONFLICT_KIND,
+                format!("لا يمكن عد العدد {key} والنص \"{key}\" معاً لأن لهما المفتاح نفسه"),
+            )
+            .into_runtime_error(interpreter.token()));
+        }
+        hash_map.insert(key, Value::Number(count as f64));
+    }
+    Ok(Value::from(hash_map))
+}
+
+/// Accepts strings, lists, sets, and iterators.
+fn iterator(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+) -> Result<Rc<RefCell<value::Iterator>>, RuntimeError> {
+    check_type(
+        interpreter,
+        value,
+        &[
+            DataType::String,
+            DataType::List,
+            DataType::Set,
+            DataType::Iterator,
+        ],
+    )?;
+    Ok(match value {
+        Value::Object(Object::Iterator(iterator)) => Rc::clone(iterator),
+        value => Value::from(Iterable::try_from(value.clone()).unwrap())
+            .try_into()
+            .unwrap(),
+    })
+}
+
+fn callable(interpreter: &dyn Interpreter, value: &Value) -> Result<Value, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Closure, DataType::Native])?;
+    Ok(value.clone())
+}
+
+/// Returns an iterator over the string, list, or iterator, which the stream natives compose.
+fn to_stream(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::Object(Object::Iterator(iterator(
+        interpreter,
+        &args[1],
+    )?)))
+}
+
+/// Returns an iterator that calls the function with each value only when it's reached, so chaining it with `صف` doesn't
+/// create a list for every stage.
+fn map(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let iterator = iterator(interpreter, &args[1])?;
+    let function = callable(interpreter, &args[2])?;
+    Ok(Value::from(Iterable::Map(iterator, function)))
+}
+
+/// Returns an iterator over the values the function returns a truthy value for, lazily like `حول_كل`.
+fn filter(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let iterator = iterator(interpreter, &args[1])?;
+    let predicate = callable(interpreter, &args[2])?;
+    Ok(Value::from(Iterable::Filter(iterator, predicate)))
+}
+
+/// Consumes the iterator into a list, when it fails the error object gets the values collected before it.
+fn collect(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let iterator = iterator(interpreter, &args[1])?;
+    let mut list = vec![];
+    loop {
+        let value = interpreter
+            .advance(&iterator)
+            .and_then(|value| interpreter.allocate(1).map(|_| value));
+        match value {
+            Ok(Some(value)) => list.push(value),
+            Ok(None) => return Ok(Value::from(list)),
+            Err(err) => {
+                error::add_partial(&err, list);
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Consumes the iterator calling the function with the accumulated value (starting with the third argument) and each
+/// value, and returns what it returns last.
+fn fold(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let iterator = iterator(interpreter, &args[1])?;
+    let function = callable(interpreter, &args[2])?;
+    let mut accumulator = args[3].clone();
+    while let Some(value) = interpreter.advance(&iterator)? {
+        accumulator = interpreter.invoke(function.clone(), vec![accumulator, value])?;
+    }
+    Ok(accumulator)
+}
+
+fn spawn(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let function = callable(interpreter, &args[1])?;
+    interpreter.spawn(function, None);
+    Ok(Value::Nil)
+}
+
+/// Schedules the function as a task like `مهمة` and returns the future of what it returns.
+fn deferred(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let function = callable(interpreter, &args[1])?;
+    let future = Rc::new(RefCell::new(value::Future::new()));
+    interpreter.spawn(function, Some(Rc::clone(&future)));
+    Ok(Value::Object(Object::Future(future)))
+}
+
+/// Outside the tasks it runs them, which resolves the future unless its task was dropped when another failed. Inside
+/// them the task waits for the future, and `Nil` is a placeholder.
+fn await_future(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::Future])?;
+    let future: Rc<RefCell<value::Future>> = args[1].clone().try_into().unwrap();
+    if future.borrow().value().is_none() {
+        if !interpreter.tasks_running() {
+            interpreter.run_tasks()?;
+        } else {
+            interpreter.wait(Awaitable::Future(future))?;
+            return Ok(Value::Nil);
+        }
+    }
+    let value = future.borrow().value().cloned();
+    value.ok_or_else(|| RuntimeError::AbandonedFuture(interpreter.token(), Backtrace::default()))
+}
+
+fn run_tasks(interpreter: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    interpreter.run_tasks()?;
+    Ok(Value::Nil)
+}
+
+fn yield_task(interpreter: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    interpreter.yield_task()?;
+    Ok(Value::Nil)
+}
+
+fn cell_arg(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+) -> Result<Rc<RefCell<Value>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Cell])?;
+    Ok(value.clone().try_into().unwrap())
+}
+
+fn cell(_: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::Object(Object::Cell(Rc::new(RefCell::new(
+        args[1].clone(),
+    )))))
+}
+
+fn read_cell(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = cell_arg(interpreter, &args[1])?.borrow().clone();
+    Ok(value)
+}
+
+fn write_cell(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    *cell_arg(interpreter, &args[1])?.borrow_mut() = args[2].clone();
+    Ok(Value::Nil)
+}
+
+fn channel_arg(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+) -> Result<Rc<RefCell<value::Channel>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Channel])?;
+    Ok(value.clone().try_into().unwrap())
+}
+
+fn channel(_: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(Value::from(value::Channel::new()))
+}
+
+fn send(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let channel = channel_arg(interpreter, &args[1])?;
+    if channel.borrow().is_closed() {
+        return Err(RuntimeError::ClosedChannel(
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    channel.borrow_mut().send(args[2].clone());
+    Ok(Value::Nil)
+}
+
+/// Returns the next value right away if there's one, otherwise the task waits for it and `Nil` is a placeholder.
+fn receive(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let channel = channel_arg(interpreter, &args[1])?;
+    let value = channel.borrow_mut().receive();
+    match value {
+        Some(value) => Ok(value),
+        None if channel.borrow().is_closed() => Ok(Value::Nil),
+        None => {
+            interpreter.wait(Awaitable::Channel(channel))?;
+            Ok(Value::Nil)
+        }
+    }
+}
+
+fn close_channel(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    channel_arg(interpreter, &args[1])?.borrow_mut().close();
+    Ok(Value::Nil)
+}
+
+fn json_err(interpreter: &dyn Interpreter, err: json::Error) -> RuntimeError {
+    match err {
+        json::Error::TooLarge(err) => too_large(interpreter, err),
+        err => RuntimeError::Json(err.to_string(), interpreter.token(), Backtrace::default()),
+    }
+}
+
+/// The optional options object supports `حافظ_على_الدقة` (return integers that can't be represented exactly as strings
+/// instead of failing).
+fn from_json(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let json = string(interpreter, args[1].clone())?;
+    let preserve_precision = match args.get(2) {
+        Some(options) => {
+            check_type(interpreter, options, &[DataType::HashMap])?;
+            let options: Rc<RefCell<HashMap<String, Value>>> = options.clone().try_into().unwrap();
+            let preserve_precision = options
+                .borrow()
+                .get("حافظ_على_الدقة")
+                .is_some_and(Value::truthy);
+            preserve_precision
+        }
+        None => false,
+    };
+    json::parse(&json, preserve_precision).map_err(|err| json_err(interpreter, err))
+}
+
+fn to_json(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match json::serialize(&args[1], interpreter.traversal_limits()) {
+        Ok(json) => Ok(Value::from(json)),
+        Err(err) => Err(json_err(interpreter, err)),
+    }
+}
+
+fn parse_csv(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let rows = csv::parse(&text).map_err(|err| {
+        RuntimeError::Csv(err.to_string(), interpreter.token(), Backtrace::default())
+    })?;
+    interpreter.allocate(rows.iter().map(|row| row.len() + 1).sum())?;
+    Ok(Value::from(
+        rows.into_iter()
+            .map(|row| Value::from(row.into_iter().map(Value::from).collect::<Vec<_>>()))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// The fields can be strings or numbers, which are displayed like `إطبع` displays them.
+fn to_csv(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let rows = list(interpreter, &args[1])?;
+    let rows = rows
+        .borrow()
+        .iter()
+        .map(|row| {
+            list(interpreter, row)?
+                .borrow()
+                .iter()
+                .map(|field| {
+                    check_type(interpreter, field, &[DataType::String, DataType::Number])?;
+                    match field {
+                        Value::String(string) => Ok(string.to_string()),
+                        _ => display(interpreter, field),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::from(csv::serialize(&rows)))
+}
+
+/// Parses `KEY=VALUE` lines, skipping blank ones and the ones starting with "#". The values can be wrapped in matching
+/// quotes to keep their surrounding spaces. Fails with the number of the first line that isn't like that.
+fn parse_dotenv(text: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut entries = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or(idx + 1)?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(idx + 1);
+        }
+        let value = value.trim();
+        let value = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        entries.push((key.to_owned(), value.to_owned()));
+    }
+    Ok(entries)
+}
+
+/// Reads a JSON object from files ending with ".json" and `KEY=VALUE` lines (see `parse_dotenv`) from the rest.
+///
+/// The optional options object supports `مطلوب` (fail if the file doesn't exist instead of returning an empty hash
+/// map) and `بادئة_البيئة` (add the environment variables starting with it, without it, for the keys the file doesn't
+/// have).
+fn load_config(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let (required, prefix) = match args.get(2) {
+        Some(options) => {
+            check_type(interpreter, options, &[DataType::HashMap])?;
+            let options: Rc<RefCell<HashMap<String, Value>>> = options.clone().try_into().unwrap();
+            let options = options.borrow();
+            let required = options.get("مطلوب").is_some_and(Value::truthy);
+            let prefix = match options.get("بادئة_البيئة") {
+                Some(prefix) => Some(string(interpreter, prefix.clone())?),
+                None => None,
+            };
+            (required, prefix)
+        }
+        None => (false, None),
+    };
+    let mut config = match fs::read_to_string(&path) {
+        Ok(text) if path.extension().is_some_and(|ext| ext == "json") => {
+            let value = json::parse(&text, false).map_err(|err| json_err(interpreter, err))?;
+            check_type(interpreter, &value, &[DataType::HashMap])?;
+            let config: Rc<RefCell<HashMap<String, Value>>> = value.try_into().unwrap();
+            config.take()
+        }
+        Ok(text) => match parse_dotenv(&text) {
+            Ok(entries) => entries
+                .into_iter()
+                .map(|(key, value)| (key, Value::from(value)))
+                .collect(),
+            Err(line) => {
+                return Err(NativeError::new(
+                    error::io_error_kind(io::ErrorKind::InvalidData),
+                    format!(
+                        "السطر رقم {line} في \"{}\" ليس على صورة مفتاح=قيمة",
+                        display_path(&path)
+                    ),
+                )
+                .into_runtime_error(interpreter.token()))
+            }
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !required => HashMap::new(),
+        Err(err) => return Err(io_err(interpreter, err, path)),
+    };
+    if let Some(prefix) = prefix {
+        for (name, value) in env::vars() {
+            if let Some(key) = name.strip_prefix(&prefix) {
+                config
+                    .entry(key.to_owned())
+                    .or_insert_with(|| Value::from(value));
+            }
+        }
+    }
+    interpreter.allocate(config.len())?;
+    Ok(Value::from(config))
+}
+
+fn store_arg(
+    interpreter: &dyn Interpreter,
+    value: &Value,
+) -> Result<Rc<RefCell<value::Store>>, RuntimeError> {
+    check_type(interpreter, value, &[DataType::Store])?;
+    Ok(value.clone().try_into().unwrap())
+}
+
+/// Reads the JSON object in the file into a store, which starts empty if the file doesn't exist.
+fn open_store(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let path = file_path(interpreter, args[1].clone())?;
+    let entries = match fs::read_to_string(&path) {
+        Ok(json) => {
+            let value = json::parse(&json, false).map_err(|err| json_err(interpreter, err))?;
+            check_type(interpreter, &value, &[DataType::HashMap])?;
+            let entries: Rc<RefCell<HashMap<String, Value>>> = value.try_into().unwrap();
+            entries.take()
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+        Err(err) => return Err(io_err(interpreter, err, path)),
+    };
+    Ok(Value::from(value::Store::new(path, entries)))
+}
+
+/// Returns the value of the key, or the optional default (`عدم` if it's not given) if the store doesn't have it.
+fn store_get(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let store = store_arg(interpreter, &args[1])?;
+    let key = string(interpreter, args[2].clone())?;
+    let value = store.borrow().entries().get(&key).cloned();
+    Ok(value.unwrap_or_else(|| args.get(3).cloned().unwrap_or(Value::Nil)))
+}
+
+fn store_set(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let store = store_arg(interpreter, &args[1])?;
+    let key = string(interpreter, args[2].clone())?;
+    store
+        .borrow_mut()
+        .entries_mut()
+        .insert(key, args[3].clone());
+    Ok(Value::Nil)
+}
+
+/// Returns whether the store had the key.
+fn store_delete(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let store = store_arg(interpreter, &args[1])?;
+    let key = string(interpreter, args[2].clone())?;
+    let removed = store.borrow_mut().entries_mut().remove(&key);
+    Ok(Value::from(removed.is_some()))
+}
+
+/// Writes the store to its file as JSON, replacing it atomically like `اكتب_ذرياً`.
+fn store_save(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    check_trusted(interpreter)?;
+    let store = store_arg(interpreter, &args[1])?;
+    let store = store.borrow();
+    let entries = Value::from(store.entries().clone());
+    let json = json::serialize(&entries, interpreter.traversal_limits())
+        .map_err(|err| json_err(interpreter, err))?;
+    match replace_atomically(store.path(), &json) {
+        Ok(()) => Ok(Value::Nil),
+        Err(err) => Err(io_err(interpreter, err, store.path().to_owned())),
+    }
+}
+
+/// Compares lists and hash maps by their contents instead of by reference.
+fn deep_eq(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    match args[1].deep_eq(&args[2], interpreter.traversal_limits()) {
+        Ok(equal) => Ok(Value::from(equal)),
+        Err(err) => Err(too_large(interpreter, err)),
+    }
+}
+
+fn deep_clone(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    args[1]
+        .deep_clone(interpreter.traversal_limits())
+        .map_err(|err| too_large(interpreter, err))
+}
+
+/// Copies only the list, hash map, or set itself, the copy shares its elements with the original. Other values are
+/// returned as they are.
+fn shallow_clone(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    match &args[1] {
+        Value::Object(Object::List(list)) => {
+            let list = list.borrow().clone();
+            Value::list(interpreter, list)
+        }
+        Value::Object(Object::HashMap(hash_map)) => {
+            let hash_map = hash_map.borrow().clone();
+            Value::hash_map(interpreter, hash_map)
+        }
+        Value::Object(Object::Set(set)) => {
+            let set = set.borrow().clone();
+            interpreter.allocate(set.len())?;
+            Ok(Value::from(set))
+        }
+        value => Ok(value.clone()),
+    }
+}
+
+/// Returns the number of bytes currently allocated on the heap, which includes the VM itself and the compiled code not
+/// only the values, `عدم` if the embedder doesn't count them.
+fn memory(_: &mut dyn Interpreter, _: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(memory::allocated().map_or(Value::Nil, Value::from))
+}
+
+fn code_point(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let ch = single_char(interpreter, args[1].clone())?;
+    Ok(Value::from(ch as u32 as f64))
+}
+
+fn from_code_point(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    check_type(interpreter, &args[1], &[DataType::Number])?;
+    let number = match args[1] {
+        Value::Number(number) => number,
+        _ => unreachable!(),
+    };
+    if number.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&number) {
+        if let Some(ch) = char::from_u32(number as u32) {
+            return Ok(Value::from(ch));
+        }
+    }
+    Err(RuntimeError::InvalidCodePoint(
+        number,
+        interpreter.token(),
+        Backtrace::default(),
+    ))
+}
+
+/// Returns the string and what has to be added to it to reach the length, the fill is repeated and cut to fit and
+/// defaults to a space.
+fn padding(
+    interpreter: &dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<(String, String), RuntimeError> {
+    let mut args = args.into_iter().skip(1);
+    let text = string(interpreter, args.next().unwrap())?;
+    let len = integer(interpreter, &args.next().unwrap())?;
+    let fill = match args.next() {
+        Some(fill) => string(interpreter, fill)?,
+        None => " ".to_owned(),
+    };
+    if fill.is_empty() {
+        return Err(RuntimeError::EmptyFill(
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    // A negative length is as short as an empty string.
+    let missing = usize::try_from(len)
+        .unwrap_or(0)
+        .saturating_sub(text.chars().count());
+    let padding = fill.chars().cycle().take(missing).collect();
+    Ok((text, padding))
+}
+
+fn pad_start(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (text, padding) = padding(interpreter, args)?;
+    Ok(Value::from(padding + &text))
+}
+
+fn pad_end(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (text, padding) = padding(interpreter, args)?;
+    Ok(Value::from(text + &padding))
+}
+
+/// Cuts the string to at most the limit, ending it with the suffix when it's cut. The suffix counts toward the limit, so
+/// as many characters as the limit minus the suffix's are kept, and if the suffix itself is longer than the limit only
+/// its start is.
+fn truncate(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let limit = integer(interpreter, &args[2])?;
+    let suffix = match args.get(3) {
+        Some(suffix) => string(interpreter, suffix.clone())?,
+        None => "…".to_owned(),
+    };
+    if limit < 0 {
+        return Err(RuntimeError::NegativeSize(
+            limit as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    let limit = limit as usize;
+    if text.chars().count() <= limit {
+        return Ok(Value::from(text));
+    }
+    let kept = limit.saturating_sub(suffix.chars().count());
+    Ok(Value::from(
+        text.chars()
+            .take(kept)
+            .chain(suffix.chars())
+            .take(limit)
+            .collect::<String>(),
+    ))
+}
+
+/// Fails if the result would be longer than `Interpreter::max_list_len` characters, so that a huge count can't exhaust
+/// the memory before anything is printed.
+fn repeat_string(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let count = integer(interpreter, &args[2])?;
+    if count < 0 {
+        return Err(RuntimeError::NegativeSize(
+            count as f64,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    let len = (text.chars().count() as u64).saturating_mul(count as u64);
+    let limit = interpreter.max_list_len();
+    if len > limit as u64 {
+        return Err(RuntimeError::StringTooLong(
+            len,
+            limit,
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    Ok(Value::from(text.repeat(count as usize)))
+}
+
+/// The form defaults to NFC, which composes what can be composed (e.g. "ا" followed by a madda becomes "آ"). The K
+/// forms also replace compatibility characters like "ﻻ" with what they stand for.
+fn normalize(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let form = match args.get(2) {
+        Some(form) => string(interpreter, form.clone())?,
+        None => "NFC".to_owned(),
+    };
+    let chars = text.chars();
+    Ok(Value::from(match form.as_str() {
+        "NFC" => chars.nfc().collect::<String>(),
+        "NFD" => chars.nfd().collect(),
+        "NFKC" => chars.nfkc().collect(),
+        "NFKD" => chars.nfkd().collect(),
+        _ => {
+            return Err(RuntimeError::UnknownNormalization(
+                form,
+                interpreter.token(),
+                Backtrace::default(),
+            ))
+        }
+    }))
+}
+
+/// Replaces every character of the string that's in `من` with the one at the same position in `إلى`, if a character is
+/// repeated in `من` its first position counts.
+fn translate(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let from = string(interpreter, args[2].clone())?
+        .chars()
+        .collect::<Vec<_>>();
+    let to = string(interpreter, args[3].clone())?
+        .chars()
+        .collect::<Vec<_>>();
+    if from.len() != to.len() {
+        return Err(RuntimeError::TranslationLengths(
+            from.len(),
+            to.len(),
+            interpreter.token(),
+            Backtrace::default(),
+        ));
+    }
+    Ok(Value::from(
+        text.chars()
+            .map(|c| match from.iter().position(|other| *other == c) {
+                Some(idx) => to[idx],
+                None => c,
+            })
+            .collect::<String>(),
+    ))
+}
+
+/// Splits on Unicode scalar values not graphemes, so a letter and its diacritics are separate characters.
+fn chars(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    Value::list(interpreter, text.chars().map(Value::from).collect())
+}
+
+/// Splits on runs of whitespace, ignoring the ones at the start and the end.
+fn words(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    Value::list(
+        interpreter,
+        text.split_whitespace().map(Value::from).collect(),
+    )
+}
+
+/// Splits after "\r\n", "\n", or a lone "\r", the last line ending doesn't add an empty line.
+fn lines(interpreter: &mut dyn Interpreter, args: Vec<Value>) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    let lines = unify_newlines(&text)
+        .lines()
+        .map(Value::from)
+        .collect::<Vec<_>>();
+    Value::list(interpreter, lines)
+}
+
+fn unify_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+fn unify_newlines_native(
+    interpreter: &mut dyn Interpreter,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let text = string(interpreter, args[1].clone())?;
+    Ok(Value::from(unify_newlines(&text)))
+}
+
+/// Creates a native that checks whether all of the characters of a non-empty string satisfy `pred`.
+macro_rules! classifier {
+    ($name:ident, $pred:expr) => {
+        fn $name(
+            interpreter: &mut dyn Interpreter,
+            args: Vec<Value>,
+        ) -> Result<Value, RuntimeError> {
+            let string = string(interpreter, args[1].clone())?;
+            Ok(Value::from(!string.is_empty() && string.chars().all($pred)))
+        }
+    };
+}
+
+classifier!(is_alphabetic, char::is_alphabetic);
+classifier!(is_numeric, char::is_numeric);
+classifier!(is_whitespace, char::is_whitespace);
+
+/// A native along with its documentation, which can't be registered without a description.
+pub struct Registration {
+    pub native: Native,
+    pub doc: NativeDoc,
+}
+
+impl Registration {
+    fn new(
+        category: Category,
+        name: &'static str,
+        function: fn(&mut dyn Interpreter, Vec<Value>) -> Result<Value, RuntimeError>,
+        arity: Arity,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            native: Native::new(function, arity.clone()),
+            doc: NativeDoc {
+                name,
+                category,
+                arity,
+                description,
+                params: vec![],
+                examples: vec![],
+            },
+        }
+    }
+
+    /// Must be called once for every parameter, in order.
+    fn param(mut self, name: &'static str, description: &'static str) -> Self {
+        self.doc.params.push(ParamDoc { name, description });
+        self
+    }
+
+    fn example(mut self, example: &'static str) -> Self {
+        self.doc.examples.push(example);
+        self
+    }
+}
+
+/// Returns the natives defined as globals in every new VM.
+pub fn natives() -> Vec<Registration> {
+    use Category::*;
+    let unary = || Arity::new(ArityType::Fixed, 1, 0);
+    let binary = || Arity::new(ArityType::Fixed, 2, 0);
+    let registrations =
+        vec![
+        Registration::new(Output, "إطبع", print, unary(), "يطبع القيمة في سطر مستقل.")
+            .param("قيمة", "أي قيمة، وتعرض الأعداد بدقة العرض الحالية.")
+            .example("إطبع(\"السلام عليكم\")"),
+        Registration::new(
+            Output,
+            "افرغ_المخرجات",
+            flush_output,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "يكتب ما بقي من المخرجات دون انتظار، مع أن \"إطبع\" يكتب كل سطر فور طباعته.",
+        )
+        .example("افرغ_المخرجات()"),
+        Registration::new(
+            Output,
+            "إطبع_جدول",
+            print_table,
+            unary(),
+            "يطبع قائمة من الكائنات كجدول أعمدته مفاتيحها مرتبة.",
+        )
+        .param("صفوف", "قائمة من الكائنات، كل كائن صف في الجدول.")
+        .example("إطبع_جدول([{ الاسم: \"أحمد\"، العمر: 30 }، { الاسم: \"سارة\" }])"),
+        Registration::new(
+            Output,
+            "عيّن_دقة_العرض",
+            set_display_precision,
+            unary(),
+            "يحدد عدد الأرقام المعنوية التي تعرض بها الأعداد.",
+        )
+        .param("دقة", "عدد صحيح موجب، أو عدم لاستعادة العرض الافتراضي.")
+        .example("عيّن_دقة_العرض(3)\nإطبع(3.14159)"),
+        Registration::new(
+            Logging,
+            "سجل_معلومة",
+            log_info,
+            unary(),
+            "يكتب رسالة بمستوى \"معلومة\" في المخرج القياسي للأخطاء.",
+        )
+        .param("رسالة", "القيمة المراد تسجيلها.")
+        .example("سجل_معلومة(\"بدأ التشغيل\")"),
+        Registration::new(
+            Logging,
+            "سجل_تحذير",
+            log_warning,
+            unary(),
+            "يكتب رسالة بمستوى \"تحذير\" في المخرج القياسي للأخطاء.",
+        )
+        .param("رسالة", "القيمة المراد تسجيلها.")
+        .example("سجل_تحذير(\"الملف فارغ\")"),
+        Registration::new(
+            Logging,
+            "سجل_خطأ",
+            log_error,
+            unary(),
+            "يكتب رسالة بمستوى \"خطأ\" في المخرج القياسي للأخطاء.",
+        )
+        .param("رسالة", "القيمة المراد تسجيلها.")
+        .example("سجل_خطأ(\"تعذر الاتصال\")"),
+        Registration::new(
+            Logging,
+            "موقعي",
+            location,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "يعيد كائناً فيه \"الملف\" و\"السطر\" اللذان استدعيت منهما، أي موضع الاستدعاء لا موضع تعريف الدالة.",
+        )
+        .example("سجل_تحذير(موقعي())"),
+        Registration::new(
+            Logging,
+            "مكدس",
+            call_stack,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "يعيد قائمة بالدوال التي تعمل حالياً بدءاً بالتي استدعته، لكل منها كائن فيه \"الدالة\" (أو عدم للمستوى الأعلى والدوال غير المسماة) و\"الملف\" و\"السطر\" الذي توقفت عنده.",
+        )
+        .example("دالة تحقق(قيمة) {\n  إن (قيمة < 0) {\n    سجل_خطأ(مكدس())\n  }\n}"),
+        Registration::new(
+            Testing,
+            "توقّع",
+            assert,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يلقي خطأً إن لم يكن الشرط صحيحاً.",
+        )
+        .param("شرط", "القيمة المتوقع أن تكون صحيحة.")
+        .param("رسالة", "رسالة الخطأ بدلاً من الرسالة الافتراضية.")
+        .example("توقّع(1 < 2، \"الواحد أصغر من الاثنين\")"),
+        Registration::new(
+            Testing,
+            "توقّع_يساوي",
+            assert_eq,
+            binary(),
+            "يلقي خطأً يعرض القيمتين إن لم تكونا متساويتين.",
+        )
+        .param("الفعلية", "القيمة التي حصلت عليها.")
+        .param("المتوقعة", "القيمة التي توقعتها.")
+        .example("توقّع_يساوي(1 + 1، 2)"),
+        Registration::new(
+            Errors,
+            "خطأ_برسالة",
+            error,
+            unary(),
+            "ينشئ كائن خطأ من نوع \"خطأ\" ويضاف إليه موضعه عند إلقائه.",
+        )
+        .param("رسالة", "نص يصف الخطأ.")
+        .example("ألقي خطأ_برسالة(\"القيمة سالبة\")"),
+        Registration::new(
+            Errors,
+            "خطأ_بنوع",
+            error_with_kind,
+            binary(),
+            "ينشئ كائن خطأ من النوع المعطى ويضاف إليه موضعه عند إلقائه.",
+        )
+        .param("نوع", "نص يصنف الخطأ ليمكن التمييز بينه وبين غيره عند إمساكه.")
+        .param("رسالة", "نص يصف الخطأ.")
+        .example("ألقي خطأ_بنوع(\"خطأ_مدخلات\"، \"العمر مفقود\")"),
+        Registration::new(
+            Files,
+            "إقرأ",
+            read,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يعيد محتوى الملف كاملاً كنص، وإن فشلت القراءة في منتصفها حمل كائن الخطأ ما قرئ قبلها في \"جزئي\".",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .param(
+            "خيارات",
+            "كائن يدعم \"وحد_الأسطر\" لاستبدال \"\\n\" بكل \"\\r\\n\" و\"\\r\" عند القراءة.",
+        )
+        .example("إطبع(إقرأ(\"ملاحظات.txt\"))")
+        .example("إقرأ(\"من-ويندوز.txt\"، { وحد_الأسطر: صحيح })"),
+        Registration::new(
+            Files,
+            "اقرأ_متدفق",
+            read_lines,
+            unary(),
+            "يعيد مكرراً على أسطر الملف يقرؤها عند الحاجة إليها فقط.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .example("اجمع(اقرأ_متدفق(\"سجل.txt\"))"),
+        Registration::new(
+            Files,
+            "إكتب",
+            write,
+            Arity::new(ArityType::Fixed, 2, 1),
+            "يكتب النص في الملف وينشئه إن لم يكن موجوداً.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .param("محتوى", "النص المراد كتابته.")
+        .param(
+            "خيارات",
+            "كائن يدعم \"الحق\" للإضافة إلى نهاية الملف و\"زامن\" لانتظار وصول المحتوى إلى القرص.",
+        )
+        .example("إكتب(\"سجل.txt\"، \"سطر جديد\\n\"، { الحق: صحيح })"),
+        Registration::new(
+            Files,
+            "اكتب_ذرياً",
+            write_atomically,
+            binary(),
+            "يكتب النص في ملف مؤقت ثم يستبدل به الملف فيبقى إما محتواه القديم أو الجديد.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .param("محتوى", "النص المراد كتابته.")
+        .example("اكتب_ذرياً(\"إعدادات.json\"، إلى_جسون(إعدادات))"),
+        Registration::new(
+            Files,
+            "افتح_ملف",
+            open_file,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يفتح الملف للقراءة منه أو الكتابة فيه على أجزاء، ويغلق عند انتهاء البرنامج إن لم يغلقه.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .param(
+            "وضع",
+            "\"قراءة\" (الافتراضي)، أو \"كتابة\" فيفرغه أو ينشئه، أو \"أي شئ\" للقراءة والكتابة فينشئه إن لم يكن موجوداً.",
+        )
+        .example("متغير ملف = افتح_ملف(\"سجل.txt\"، \"كتابة\")"),
+        Registration::new(
+            Files,
+            "اقرأ_من",
+            read_from,
+            unary(),
+            "يقرأ ما تبقى من الملف المفتوح كنص.",
+        )
+        .param("ملف", "ملف مفتوح على وضع يسمح بالقراءة.")
+        .example("اقرأ_من(افتح_ملف(\"سجل.txt\"))"),
+        Registration::new(
+            Files,
+            "اكتب_في",
+            write_to,
+            binary(),
+            "يكتب النص في الملف المفتوح بعد ما كتب فيه سابقاً، وقد لا يصل إلى القرص حتى يفرغ الملف أو يغلق.",
+        )
+        .param("ملف", "ملف مفتوح على وضع يسمح بالكتابة.")
+        .param("محتوى", "النص المراد كتابته.")
+        .example("اكتب_في(ملف، \"سطر جديد\\n\")"),
+        Registration::new(
+            Files,
+            "اقرأ_بايتات",
+            read_bytes,
+            binary(),
+            "يقرأ عدداً من البايتات من الملف المفتوح كقائمة أعداد من 0 إلى 255، وقد تكون أقل إن انتهى الملف قبلها.",
+        )
+        .param("ملف", "ملف مفتوح على وضع يسمح بالقراءة.")
+        .param("عدد", "أقصى عدد من البايتات يقرأ.")
+        .example("اقرأ_بايتات(افتح_ملف(\"صورة.png\")، 8)"),
+        Registration::new(
+            Files,
+            "اكتب_بايتات",
+            write_bytes,
+            binary(),
+            "يكتب قائمة من البايتات في الملف المفتوح، ولا يكتب شيئاً إن لم تكن كلها أعداداً صحيحة من 0 إلى 255.",
+        )
+        .param("ملف", "ملف مفتوح على وضع يسمح بالكتابة.")
+        .param("قائمة", "البايتات المراد كتابتها.")
+        .example("اكتب_بايتات(ملف، [137، 80، 78، 71])"),
+        Registration::new(
+            Files,
+            "افرغ",
+            flush_file,
+            unary(),
+            "يوصل ما كتب في الملف إلى القرص، ويحدث ذلك تلقائياً عند إغلاقه أو انتهاء البرنامج.",
+        )
+        .param("ملف", "ملف مفتوح على وضع يسمح بالكتابة.")
+        .example("افرغ(ملف)"),
+        Registration::new(
+            Files,
+            "انتقل",
+            seek_file,
+            binary(),
+            "ينقل موضع القراءة والكتابة في الملف إلى عدد من البايتات من بدايته، ويجوز تجاوز نهايته فتملأ الفجوة بأصفار إن كتب بعدها.",
+        )
+        .param("ملف", "ملف مفتوح.")
+        .param("موضع", "عدد البايتات من بداية الملف، عدد صحيح غير سالب.")
+        .example("انتقل(ملف، 0)"),
+        Registration::new(
+            Files,
+            "الموضع",
+            file_position,
+            unary(),
+            "يرجع موضع القراءة والكتابة في الملف بعدد البايتات من بدايته.",
+        )
+        .param("ملف", "ملف مفتوح.")
+        .example("الموضع(ملف)"),
+        Registration::new(
+            Files,
+            "أغلق_ملف",
+            close_file,
+            unary(),
+            "يغلق الملف بعد إيصال ما كتب فيه، ولا يفعل شيئاً إن كان مغلقاً.",
+        )
+        .param("ملف", "الملف المراد إغلاقه.")
+        .example("أغلق_ملف(ملف)"),
+        Registration::new(
+            Files,
+            "هل_موجود",
+            exists,
+            unary(),
+            "يتحقق إن كان هناك ملف أو مجلد في المسار.",
+        )
+        .param("مسار", "المسار نسبةً إلى مجلد البرنامج.")
+        .example("إن (هل_موجود(\"إعدادات.json\")) { إطبع(إقرأ(\"إعدادات.json\")) }"),
+        Registration::new(
+            Files,
+            "معلومات_ملف",
+            metadata,
+            unary(),
+            "يرجع كائناً فيه \"الحجم\" بالبايتات و\"مجلد\" و\"آخر_تعديل\" بالثواني منذ بداية 1970، ويرمي خطأً إن لم يكن المسار موجوداً.",
+        )
+        .param("مسار", "المسار نسبةً إلى مجلد البرنامج.")
+        .example("معلومات_ملف(\"سجل.txt\")[\"الحجم\"]"),
+        Registration::new(
+            Files,
+            "طابق_ملفات",
+            glob_files,
+            unary(),
+            "يرجع مسارات الملفات والمجلدات المطابقة للنمط مرتبة، حيث \"*\" أي عدد من الحروف و\"?\" حرف واحد و\"**\" أي عدد من المجلدات.",
+        )
+        .param("نمط", "النمط نسبةً إلى مجلد البرنامج، والمسارات المرجعة نسبةً إليه كذلك.")
+        .example("طابق_ملفات(\"**/*.قتام\")"),
+        Registration::new(
+            Files,
+            "ملف_مؤقت",
+            temp_file,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "ينشئ ملفاً فارغاً باسم فريد في المجلد المؤقت للنظام ويرجع مساره، ولا يحذف تلقائياً فاحذفه إن لم تعد تحتاجه.",
+        )
+        .example("إكتب(ملف_مؤقت()، \"بيانات مؤقتة\")"),
+        Registration::new(
+            Files,
+            "مجلد_مؤقت",
+            temp_dir,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "ينشئ مجلداً فارغاً باسم فريد في المجلد المؤقت للنظام ويرجع مساره، ولا يحذف تلقائياً فاحذفه إن لم تعد تحتاجه.",
+        )
+        .example("متغير مجلد = مجلد_مؤقت()"),
+        Registration::new(
+            Json,
+            "من_جسون",
+            from_json,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يحول نص جسون إلى القيمة التي يمثلها.",
+        )
+        .param("نص", "نص جسون صالح.")
+        .param(
+            "خيارات",
+            "كائن يدعم \"حافظ_على_الدقة\" لإعادة الأعداد الصحيحة التي لا يمكن تمثيلها بدقة كنصوص.",
+        )
+        .example("من_جسون(\"{\\\"أ\\\": [1, 2]}\")"),
+        Registration::new(
+            Json,
+            "إلى_جسون",
+            to_json,
+            unary(),
+            "يحول القيمة إلى نص جسون مفاتيحه مرتبة.",
+        )
+        .param("قيمة", "عدد أو نص أو قيمة منطقية أو عدم أو قائمة أو كائن منها.")
+        .example("إلى_جسون({ ب: 2، أ: [1، عدم] })"),
+        Registration::new(
+            Files,
+            "حمّل_إعدادات",
+            load_config,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يقرأ ملف إعدادات إلى كائن، من جسون إن انتهى اسمه بـ\".json\" وإلا من أسطر على صورة مفتاح=قيمة.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .param(
+            "خيارات",
+            "كائن يدعم \"مطلوب\" لرمي خطأ إن لم يكن الملف موجوداً بدلاً من إرجاع كائن فارغ، و\"بادئة_البيئة\" لإضافة متغيرات البيئة التي تبدأ بها (بعد حذفها) للمفاتيح التي ليست في الملف.",
+        )
+        .example("حمّل_إعدادات(\".env\"، { بادئة_البيئة: \"تطبيقي_\" })[\"المنفذ\"]"),
+        Registration::new(
+            Files,
+            "افتح_مخزن",
+            open_store,
+            unary(),
+            "يفتح مخزناً للمفاتيح والقيم محفوظاً في ملف جسون، ويبدأ فارغاً إن لم يكن الملف موجوداً. لا تحفظ التغييرات إلا باحفظ_مخزن، ولا يصلح لاستخدامه من أكثر من برنامج في نفس الوقت.",
+        )
+        .param("مسار", "مسار الملف نسبةً إلى مجلد البرنامج.")
+        .example("متغير مخزن = افتح_مخزن(\"بيانات.json\")"),
+        Registration::new(
+            Files,
+            "اقرأ_من_مخزن",
+            store_get,
+            Arity::new(ArityType::Fixed, 2, 1),
+            "يرجع قيمة المفتاح في المخزن، أو القيمة الافتراضية إن لم يكن فيه.",
+        )
+        .param("مخزن", "مخزن مفتوح.")
+        .param("مفتاح", "نص.")
+        .param("افتراضي", "ما يرجع إن لم يكن المفتاح موجوداً، عدم إن لم يعط.")
+        .example("اقرأ_من_مخزن(مخزن، \"عدد_الزيارات\"، 0)"),
+        Registration::new(
+            Files,
+            "خزّن",
+            store_set,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يضع القيمة للمفتاح في المخزن، ويجب أن تكون قابلة للتحويل إلى جسون عند الحفظ.",
+        )
+        .param("مخزن", "مخزن مفتوح.")
+        .param("مفتاح", "نص.")
+        .param("قيمة", "عدد أو نص أو قيمة منطقية أو عدم أو قائمة أو كائن منها.")
+        .example("خزّن(مخزن، \"عدد_الزيارات\"، 1)"),
+        Registration::new(
+            Files,
+            "احذف_من_مخزن",
+            store_delete,
+            binary(),
+            "يحذف المفتاح من المخزن ويرجع ما إذا كان فيه.",
+        )
+        .param("مخزن", "مخزن مفتوح.")
+        .param("مفتاح", "نص.")
+        .example("احذف_من_مخزن(مخزن، \"عدد_الزيارات\")"),
+        Registration::new(
+            Files,
+            "احفظ_مخزن",
+            store_save,
+            unary(),
+            "يكتب المخزن في ملفه كجسون، فيبقى الملف بمحتواه القديم أو الجديد كاملاً إن توقف البرنامج في أثناء ذلك.",
+        )
+        .param("مخزن", "مخزن مفتوح.")
+        .example("احفظ_مخزن(مخزن)"),
+        Registration::new(
+            Csv,
+            "حلل_csv",
+            parse_csv,
+            unary(),
+            "يحول نص CSV إلى قائمة صفوف كل منها قائمة نصوص، ويبقي الصفوف على اختلاف أطوالها.",
+        )
+        .param("نص", "حقول مفصولة بفواصل وصفوف مفصولة بأسطر، ويمكن تنصيص الحقل ليحوي فواصل أو أسطراً.")
+        .example("حلل_csv(\"الاسم,العمر\\nأحمد,30\\n\")"),
+        Registration::new(
+            Csv,
+            "إلى_csv",
+            to_csv,
+            unary(),
+            "يحول قائمة صفوف إلى نص CSV، وينصص الحقول التي فيها فواصل أو علامات تنصيص أو أسطر.",
+        )
+        .param("صفوف", "قائمة من قوائم نصوص أو أعداد.")
+        .example("إلى_csv([[\"الاسم\"، \"العمر\"]، [\"أحمد\"، 30]])"),
+        Registration::new(
+            Values,
+            "حجم",
+            size,
+            unary(),
+            "يعيد عدد حروف النص أو عناصر القائمة أو الكائن أو المجموعة.",
+        )
+        .param("قيمة", "نص أو قائمة أو كائن أو مجموعة.")
+        .example("حجم(\"قتام\")"),
+        Registration::new(Values, "طول", size, unary(), "اسم آخر لـ\"حجم\".")
+            .param("قيمة", "نص أو قائمة أو كائن أو مجموعة.")
+            .example("طول([1، 2، 3])"),
+        Registration::new(
+            Values,
+            "نوع",
+            type_of,
+            unary(),
+            "يعيد اسم نوع القيمة كما يظهر في رسائل الأخطاء.",
+        )
+        .param("قيمة", "أي قيمة.")
+        .example("نوع([]) == \"قائمة\""),
+        Registration::new(
+            Values,
+            "عناصر",
+            elements,
+            unary(),
+            "يعيد قائمة جديدة بعناصر القائمة أو أزواج [مفتاح، قيمة] الكائن أو حروف النص، دون نسخ العناصر نفسها.",
+        )
+        .param("قيمة", "نص أو قائمة أو كائن.")
+        .example("عناصر({ الاسم: \"قتام\" })"),
+        Registration::new(
+            Values,
+            "هل_قابل_للاستدعاء",
+            is_callable,
+            unary(),
+            "يتحقق إن كانت القيمة دالة أو دالة مدمجة.",
+        )
+        .param("قيمة", "أي قيمة.")
+        .example("هل_قابل_للاستدعاء(إطبع)"),
+        Registration::new(
+            Values,
+            "اقفز",
+            trampoline,
+            Arity::new(ArityType::Variadic, 1, 0),
+            "يستدعي الدالة بالوسائط، ثم يستدعي ما ترجعه ما دام دالة حتى يرجع قيمة أخرى فيرجعها، فلا يزيد عمق الاستدعاءات مع التكرار.",
+        )
+        .param("دالة", "دالة ترجع الخطوة التالية كدالة بلا معاملات، أو النتيجة.")
+        .param("وسائط", "ما تستدعى به الدالة أول مرة.")
+        .example(
+            "دالة مضروب(ع، ناتج) {\n  إن (ع <= 1) { أرجع ناتج }\n  أرجع || { أرجع مضروب(ع - 1، ع * ناتج) }\n}\nاقفز(مضروب، 20، 1)",
+        ),
+        Registration::new(
+            Values,
+            "متساويان",
+            deep_eq,
+            binary(),
+            "يقارن القوائم والكائنات بمحتواها بدلاً من مرجعها.",
+        )
+        .param("أ", "القيمة الأولى.")
+        .param("ب", "القيمة الثانية.")
+        .example("متساويان([1، [2]]، [1، [2]])"),
+        Registration::new(
+            Values,
+            "نسخة",
+            deep_clone,
+            unary(),
+            "يعيد نسخة مستقلة من القيمة بكل ما تحتويه من قوائم وكائنات.",
+        )
+        .param("قيمة", "القيمة المراد نسخها.")
+        .example("نسخة({ أ: [1، 2] })"),
+        Registration::new(
+            Values,
+            "انسخ",
+            shallow_clone,
+            unary(),
+            "يعيد نسخة من القائمة أو الكائن أو المجموعة نفسها فقط، فتبقى القوائم والكائنات التي بداخلها مشتركة مع الأصل.",
+        )
+        .param("قيمة", "القيمة المراد نسخها، تعاد كما هي إن لم تكن قائمة أو كائناً أو مجموعة.")
+        .example("انسخ([1، [2]])"),
+        Registration::new(
+            Values,
+            "خذ_أو_أنشئ",
+            get_or_insert,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يعيد قيمة المفتاح في الكائن، وإن لم يكن فيه استدعى الدالة وخزّن ما تعيده فيه وأعاده.",
+        )
+        .param("كائن", "الكائن المراد البحث فيه.")
+        .param("مفتاح", "نص.")
+        .param("دالة_منشئة", "دالة بلا معاملات تستدعى فقط إن لم يكن المفتاح موجوداً.")
+        .example("خذ_أو_أنشئ(ذاكرة_مؤقتة، \"أ\"، || { أرجع [] })"),
+        Registration::new(
+            Values,
+            "كرّر_حتى",
+            iterate_until,
+            Arity::new(ArityType::Fixed, 3, 1),
+            "يستدعي الدالة مع القيمة ثم مع ما تعيده وهكذا حتى يتحقق شرط التوقف فيعيد آخر قيمة، ويفحص الشرط قبل أول استدعاء، وإن لم يتحقق بعد الحد الأقصى من الاستدعاءات ألقى خطأ.",
+        )
+        .param("قيمة_ابتدائية", "القيمة التي يبدأ منها.")
+        .param("دالة", "دالة تأخذ القيمة وتعيد التي تليها.")
+        .param("شرط_التوقف", "دالة تأخذ القيمة وتعيد ما إذا كان يجب التوقف عندها.")
+        .param("حد_أقصى", "أكثر عدد من استدعاءات الدالة، 10000 إن لم يحدد.")
+        .example("كرّر_حتى(1، |س| { أرجع س * 2 }، |س| { أرجع س > 100 })"),
+        Registration::new(
+            Values,
+            "ذاكرة",
+            memory,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "يعيد عدد البايتات المخصصة حالياً، أو عدم إن لم يكن عدها متاحاً.",
+        )
+        .example("إطبع(ذاكرة())"),
+        Registration::new(
+            Sets,
+            "مجموعة",
+            set,
+            Arity::new(ArityType::Fixed, 0, 1),
+            "ينشئ مجموعة تحافظ على ترتيب إضافة عناصرها.",
+        )
+        .param("عناصر", "قائمة من النصوص أو الأعداد الصحيحة، ويتجاهل ما تكرر منها.")
+        .example("مجموعة([1، 2، 2، 3])"),
+        Registration::new(
+            Sets,
+            "أضف",
+            set_insert,
+            binary(),
+            "يضيف العنصر إلى المجموعة ويعيد ما إذا لم يكن فيها من قبل.",
+        )
+        .param("مجموعة", "المجموعة المراد الإضافة إليها.")
+        .param("عنصر", "نص أو عدد صحيح.")
+        .example("أضف(مجموعة()، \"أ\")"),
+        Registration::new(
+            Sets,
+            "إحذف",
+            set_remove,
+            binary(),
+            "يحذف العنصر من المجموعة ويعيد ما إذا كان فيها.",
+        )
+        .param("مجموعة", "المجموعة المراد الحذف منها.")
+        .param("عنصر", "نص أو عدد صحيح.")
+        .example("إحذف(مجموعة([1، 2])، 1)"),
+        Registration::new(
+            Sets,
+            "يحتوي",
+            set_contains,
+            binary(),
+            "يعيد ما إذا كان العنصر في المجموعة.",
+        )
+        .param("مجموعة", "المجموعة المراد البحث فيها.")
+        .param("عنصر", "نص أو عدد صحيح.")
+        .example("يحتوي(مجموعة([1، 2])، 2)"),
+        Registration::new(
+            Sets,
+            "قائمة",
+            set_to_list,
+            unary(),
+            "يعيد عناصر المجموعة في قائمة بترتيب إضافتها.",
+        )
+        .param("مجموعة", "المجموعة المراد تحويلها.")
+        .example("قائمة(مجموعة([3، 1، 3]))"),
+        Registration::new(
+            Sets,
+            "اتحاد",
+            set_union,
+            binary(),
+            "يعيد مجموعة جديدة فيها عناصر المجموعتين.",
+        )
+        .param("أ", "المجموعة الأولى.")
+        .param("ب", "المجموعة الثانية.")
+        .example("اتحاد(مجموعة([1، 2])، مجموعة([2، 3]))"),
+        Registration::new(
+            Sets,
+            "تقاطع",
+            set_intersection,
+            binary(),
+            "يعيد مجموعة جديدة فيها العناصر المشتركة بين المجموعتين.",
+        )
+        .param("أ", "المجموعة الأولى.")
+        .param("ب", "المجموعة الثانية.")
+        .example("تقاطع(مجموعة([1، 2])، مجموعة([2، 3]))"),
+        Registration::new(
+            Sets,
+            "فرق",
+            set_difference,
+            binary(),
+            "يعيد مجموعة جديدة فيها عناصر الأولى التي ليست في الثانية.",
+        )
+        .param("أ", "المجموعة الأولى.")
+        .param("ب", "المجموعة الثانية.")
+        .example("فرق(مجموعة([1، 2])، مجموعة([2، 3]))"),
+        Registration::new(
+            Lists,
+            "مصفوفة",
+            array,
+            binary(),
+            "ينشئ قائمة من نسخ القيمة بالحجم المعطى دفعة واحدة.",
+        )
+        .param("حجم", "عدد صحيح غير سالب.")
+        .param("قيمة", "القيمة التي تملأ بها القائمة.")
+        .example("مصفوفة(3، 0)"),
+        Registration::new(
+            Lists,
+            "أزل_عند",
+            remove_at,
+            binary(),
+            "يزيل العنصر الذي عند الموضع من القائمة ويعيده.",
+        )
+        .param("قائمة", "القائمة المراد الإزالة منها.")
+        .param("موضع", "عدد صحيح، والسالب يعد من النهاية.")
+        .example("أزل_عند([1، 2، 3]، -1)"),
+        Registration::new(
+            Lists,
+            "قطّع",
+            chunk,
+            binary(),
+            "يقسم القائمة إلى قوائم بالحجم المعطى، وقد تكون الأخيرة أقصر.",
+        )
+        .param("قائمة", "القائمة المراد تقسيمها.")
+        .param("حجم", "عدد صحيح موجب.")
+        .example("قطّع([1، 2، 3، 4، 5]، 2)"),
+        Registration::new(
+            Lists,
+            "ازدوج",
+            zip,
+            binary(),
+            "يعيد قائمة من أزواج عناصر القائمتين، ويتجاهل ما زاد من الأطول.",
+        )
+        .param("أ", "القائمة الأولى.")
+        .param("ب", "القائمة الثانية.")
+        .example("ازدوج([1، 2]، [\"أ\"، \"ب\"])"),
+        Registration::new(
+            Lists,
+            "أدرج_عند",
+            insert_at,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يدرج القيمة قبل الموضع ويزيح ما بعده من عناصر.",
+        )
+        .param("قائمة", "القائمة المراد الإدراج فيها.")
+        .param("موضع", "عدد صحيح، والسالب يعد من النهاية، وحجم القائمة يدرج في نهايتها.")
+        .param("قيمة", "القيمة المراد إدراجها.")
+        .example("أدرج_عند([1، 3]، 1، 2)"),
+        Registration::new(
+            Lists,
+            "جمّع_حسب",
+            group_by,
+            binary(),
+            "يعيد كائناً مفاتيحه ما تعيده الدالة لعناصر القائمة وقيمه قوائم العناصر التي أعادت لها كل مفتاح بترتيبها.",
+        )
+        .param("قائمة", "القائمة المراد تجميع عناصرها.")
+        .param("دالة_مفتاح", "دالة تأخذ العنصر وتعيد نصاً، ولا تحول القيم الأخرى إلى نصوص بل يلقى خطأ.")
+        .example("جمّع_حسب([1، 2، 3]، |س| { إن (س % 2 == 0) { أرجع \"زوجي\" } أرجع \"فردي\" })"),
+        Registration::new(
+            Lists,
+            "عدّ",
+            count,
+            unary(),
+            "يعيد كائناً مفاتيحه عناصر القائمة المختلفة وقيمه عدد مرات تكرار كل منها.",
+        )
+        .param(
+            "قائمة",
+            "قائمة من النصوص والأعداد الصحيحة، يصبح مفتاح العدد نصه فيلقى خطأ إن كان العدد ونصه فيها معاً.",
+        )
+        .example("عدّ([\"أ\"، \"ب\"، \"أ\"])")
+        .example("عدّ([1، 2، 1])"),
+        Registration::new(
+            Lists,
+            "قسّم_حسب",
+            partition,
+            binary(),
+            "يعيد قائمتين، الأولى بالعناصر التي تعيد الدالة لها قيمة صحيحة والثانية بالبقية، وكل منهما بترتيب القائمة.",
+        )
+        .param("قائمة", "القائمة المراد تقسيمها.")
+        .param("دالة", "دالة تأخذ العنصر وتعيد ما إذا كان يطابق.")
+        .example("قسّم_حسب([1، 2، 3، 4]، |س| { أرجع س % 2 == 0 })"),
+        Registration::new(
+            Lists,
+            "امسح",
+            scan,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يطوي القائمة مثل طو ويعيد القيم المتراكمة كلها، أولها القيمة الابتدائية فيزيد طولها على طول القائمة بواحد.",
+        )
+        .param("قائمة", "القائمة المراد طيها.")
+        .param("دالة", "دالة تأخذ القيمة المتراكمة والعنصر الحالي.")
+        .param("ابتدائي", "القيمة المتراكمة الأولى.")
+        .example("امسح([1، 2، 3]، |مجموع، س| { أرجع مجموع + س }، 0)"),
+        Registration::new(
+            Streams,
+            "إلى_تيار",
+            to_stream,
+            unary(),
+            "يعيد مكرراً على النص أو القائمة أو المجموعة لتركيب دوال التيارات عليه.",
+        )
+        .param("قيمة", "نص أو قائمة أو مجموعة أو مكرر.")
+        .example("إلى_تيار(\"قتام\")"),
+        Registration::new(
+            Streams,
+            "حول_كل",
+            map,
+            binary(),
+            "يعيد مكرراً يستدعي الدالة مع كل قيمة عند الوصول إليها فقط.",
+        )
+        .param("تيار", "نص أو قائمة أو مجموعة أو مكرر.")
+        .param("دالة", "دالة تأخذ القيمة وتعيد ما يحل محلها.")
+        .example("اجمع(حول_كل([1، 2، 3]، |س| { أرجع س * 2 }))"),
+        Registration::new(
+            Streams,
+            "صف",
+            filter,
+            binary(),
+            "يعيد مكرراً على القيم التي تعيد الدالة لها قيمة صحيحة.",
+        )
+        .param("تيار", "نص أو قائمة أو مجموعة أو مكرر.")
+        .param("دالة", "دالة تأخذ القيمة وتعيد ما إذا كان يجب إبقاؤها.")
+        .example("اجمع(صف([1، 2، 3، 4]، |س| { أرجع س % 2 == 0 }))"),
+        Registration::new(
+            Streams,
+            "اجمع",
+            collect,
+            unary(),
+            "يستهلك المكرر ويعيد قيمه في قائمة، وإن فشل حمل كائن الخطأ القيم المجموعة قبله في \"جزئي\".",
+        )
+        .param("تيار", "نص أو قائمة أو مجموعة أو مكرر.")
+        .example("اجمع(إلى_تيار(\"قتام\"))"),
+        Registration::new(
+            Streams,
+            "طو",
+            fold,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يستهلك المكرر مستدعياً الدالة مع القيمة المتراكمة وكل قيمة ويعيد آخر ما تعيده.",
+        )
+        .param("تيار", "نص أو قائمة أو مجموعة أو مكرر.")
+        .param("دالة", "دالة تأخذ القيمة المتراكمة والقيمة الحالية.")
+        .param("بداية", "القيمة المتراكمة الأولى.")
+        .example("طو([1، 2، 3]، |مجموع، س| { أرجع مجموع + س }، 0)"),
+        Registration::new(
+            Tasks,
+            "مهمة",
+            spawn,
+            unary(),
+            "يضيف الدالة إلى المهام التي تنفذها نفّذ_المهام بالتناوب، ولا تتشارك المهام إلا الوقت فلا تنفذ إحداها إلا إن سلّمت الأخرى أو انتهت.",
+        )
+        .param("دالة", "دالة بلا معاملات.")
+        .example("مهمة(|| { إطبع(\"من مهمة\") })"),
+        Registration::new(
+            Tasks,
+            "نفّذ_المهام",
+            run_tasks,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "ينفذ المهام بترتيب إضافتها، وكلما سلّمت واحدة انتقل إلى التالية حتى تنتهي كلها بما فيها التي تضيفها، وإن فشلت إحداها توقفت البقية.",
+        )
+        .example("مهمة(|| { إطبع(1) })\nنفّذ_المهام()"),
+        Registration::new(
+            Tasks,
+            "سلّم",
+            yield_task,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "يوقف المهمة حتى تأخذ المهام الأخرى دورها ثم يكملها، ويجب أن تستدعيه المهمة نفسها أو دالة تستدعيها لا دالة مدمجة أو مولّد.",
+        )
+        .example(
+            "مهمة(|| {\n  إطبع(1)\n  سلّم()\n  إطبع(3)\n})\nمهمة(|| { إطبع(2) })\nنفّذ_المهام()",
+        ),
+        Registration::new(
+            Tasks,
+            "قيمة_مؤجلة",
+            deferred,
+            unary(),
+            "يضيف الدالة إلى المهام مثل مهمة، ويرجع قيمة مؤجلة تكتمل بما ترجعه الدالة عند انتهائها.",
+        )
+        .param("دالة", "دالة بلا معاملات.")
+        .example("متغير م = قيمة_مؤجلة(|| { أرجع 42 })\nانتظر(م)"),
+        Registration::new(
+            Tasks,
+            "انتظر",
+            await_future,
+            unary(),
+            "يرجع ما رجعته دالة القيمة المؤجلة، فإن لم تكتمل بعد انتظرتها المهمة، وخارج المهام ينفذ المهام كلها كما ينفذها نفّذ_المهام.",
+        )
+        .param("قيمة_مؤجلة", "ما رجعته قيمة_مؤجلة.")
+        .example("انتظر(قيمة_مؤجلة(|| { أرجع 42 }))"),
+        Registration::new(
+            Tasks,
+            "خلية",
+            cell,
+            unary(),
+            "ينشئ خلية تحمل قيمة تتشاركها المهام دون نسخها، وما يكتب فيها تراه كل المهام. ولا تحتاج إلى قفل لأن المهام تتناوب على خيط واحد، فلا يتغير ما فيها بين قراءته والكتابة فيه إلا إن سلّمت المهمة بينهما.",
+        )
+        .param("قيمة_ابتدائية", "ما تحمله الخلية أولاً.")
+        .example("متغير عداد = خلية(0)"),
+        Registration::new(
+            Tasks,
+            "اقرأ_خلية",
+            read_cell,
+            unary(),
+            "يرجع ما تحمله الخلية.",
+        )
+        .param("خلية", "الخلية المراد قراءتها.")
+        .example("اقرأ_خلية(خلية(0))"),
+        Registration::new(
+            Tasks,
+            "اكتب_خلية",
+            write_cell,
+            binary(),
+            "يضع القيمة في الخلية بدلاً مما كانت تحمله.",
+        )
+        .param("خلية", "الخلية المراد الكتابة فيها.")
+        .param("قيمة", "القيمة الجديدة.")
+        .example("متغير عداد = خلية(0)\nاكتب_خلية(عداد، اقرأ_خلية(عداد) + 1)"),
+        Registration::new(
+            Tasks,
+            "قناة",
+            channel,
+            Arity::new(ArityType::Fixed, 0, 0),
+            "ينشئ قناة فارغة ترسل عبرها المهام القيم بعضها إلى بعض، فتستقبل بترتيب إرسالها.",
+        )
+        .example("متغير ق = قناة()"),
+        Registration::new(
+            Tasks,
+            "أرسل",
+            send,
+            binary(),
+            "يضيف القيمة إلى نهاية القناة دون انتظار من يستقبلها، ويفشل إن كانت القناة مغلقة.",
+        )
+        .param("قناة", "القناة المراد الإرسال عبرها.")
+        .param("قيمة", "أي قيمة، وترسل نفسها لا نسخة منها.")
+        .example("متغير ق = قناة()\nأرسل(ق، 1)"),
+        Registration::new(
+            Tasks,
+            "استقبل",
+            receive,
+            unary(),
+            "يرجع أقدم قيمة في القناة، وإن كانت فارغة انتظرت المهمة حتى ترسل إليها قيمة أو تغلق، ويرجع عدم إن كانت فارغة ومغلقة. وإن انتظرت كل المهام قنوات فارغة فشل نفّذ_المهام.",
+        )
+        .param("قناة", "القناة المراد الاستقبال منها، ولا يمكن انتظارها خارج المهام.")
+        .example("متغير ق = قناة()\nأرسل(ق، 1)\nاستقبل(ق)"),
+        Registration::new(
+            Tasks,
+            "أغلق_قناة",
+            close_channel,
+            unary(),
+            "يمنع الإرسال إلى القناة، ويبقى ما فيها ليستقبل، ثم يرجع الاستقبال منها عدم بدلاً من الانتظار.",
+        )
+        .param("قناة", "القناة المراد إغلاقها.")
+        .example("متغير ق = قناة()\nأغلق_قناة(ق)\nاستقبل(ق)"),
+        Registration::new(
+            Strings,
+            "رمز",
+            code_point,
+            unary(),
+            "يعيد رقم الحرف في يونيكود.",
+        )
+        .param("حرف", "نص من حرف واحد.")
+        .example("رمز(\"أ\")"),
+        Registration::new(
+            Strings,
+            "من_رمز",
+            from_code_point,
+            unary(),
+            "يعيد الحرف الذي رقمه في يونيكود الرقم المعطى.",
+        )
+        .param("رقم", "عدد صحيح يمثل حرفاً صالحاً.")
+        .example("من_رمز(1575)"),
+        Registration::new(
+            Strings,
+            "هل_حرف",
+            is_alphabetic,
+            unary(),
+            "يعيد ما إذا كان النص غير فارغ وكل ما فيه حروف.",
+        )
+        .param("نص", "النص المراد فحصه.")
+        .example("هل_حرف(\"قتام\")"),
+        Registration::new(
+            Strings,
+            "هل_رقم",
+            is_numeric,
+            unary(),
+            "يعيد ما إذا كان النص غير فارغ وكل ما فيه أرقام.",
+        )
+        .param("نص", "النص المراد فحصه.")
+        .example("هل_رقم(\"٤٢\")"),
+        Registration::new(
+            Strings,
+            "هل_مسافة",
+            is_whitespace,
+            unary(),
+            "يعيد ما إذا كان النص غير فارغ وكل ما فيه مسافات.",
+        )
+        .param("نص", "النص المراد فحصه.")
+        .example("هل_مسافة(\" \\t\")"),
+        Registration::new(
+            Strings,
+            "بطّن_يسار",
+            pad_start,
+            Arity::new(ArityType::Fixed, 2, 1),
+            "يضيف الحشو قبل بداية النص حتى يصل إلى الطول المعطى، وهي يسار الأرقام والنصوص اللاتينية ويمين النصوص العربية عند عرضها.",
+        )
+        .param("نص", "النص المراد تبطينه، يعاد كما هو إن كان بالطول المعطى أو أطول.")
+        .param("طول", "عدد الحروف المطلوب.")
+        .param("حشو", "نص غير فارغ يكرر ويقطع ليناسب، مسافة إن لم يعط.")
+        .example("بطّن_يسار(\"7\"، 3، \"0\")"),
+        Registration::new(
+            Strings,
+            "بطّن_يمين",
+            pad_end,
+            Arity::new(ArityType::Fixed, 2, 1),
+            "يضيف الحشو بعد نهاية النص حتى يصل إلى الطول المعطى، وهي يمين الأرقام والنصوص اللاتينية ويسار النصوص العربية عند عرضها.",
+        )
+        .param("نص", "النص المراد تبطينه، يعاد كما هو إن كان بالطول المعطى أو أطول.")
+        .param("طول", "عدد الحروف المطلوب.")
+        .param("حشو", "نص غير فارغ يكرر ويقطع ليناسب، مسافة إن لم يعط.")
+        .example("بطّن_يمين(\"اسم\"، 6، \".\")"),
+        Registration::new(
+            Strings,
+            "اقتطع",
+            truncate,
+            Arity::new(ArityType::Fixed, 2, 1),
+            "يقص النص إن تجاوز الحد وينهيه باللاحقة، وتحسب اللاحقة من الحد فلا يتجاوزه الناتج أبداً.",
+        )
+        .param("نص", "النص المراد قصه، يعاد كما هو إن لم يتجاوز الحد.")
+        .param("الحد", "أقصى عدد من الحروف.")
+        .param("اللاحقة", "ما يضاف مكان المقصوص، \"…\" إن لم تعط.")
+        .example("اقتطع(\"السلام عليكم\"، 8)"),
+        Registration::new(
+            Strings,
+            "كرّر_نص",
+            repeat_string,
+            binary(),
+            "يعيد النص مكرراً عدد المرات المعطى، ونصاً فارغاً إن كان صفراً.",
+        )
+        .param("نص", "النص المراد تكراره.")
+        .param("مرات", "عدد صحيح غير سالب.")
+        .example("كرّر_نص(\"-\"، 10)"),
+        Registration::new(
+            Strings,
+            "طبّع",
+            normalize,
+            Arity::new(ArityType::Fixed, 1, 1),
+            "يوحد تمثيل الحروف في يونيكود حتى تتساوى النصوص المتكافئة التي كتبت بطرق مختلفة.",
+        )
+        .param("نص", "النص المراد تطبيعه.")
+        .param("الصيغة", "\"NFC\" (الافتراضية) أو \"NFD\" أو \"NFKC\" أو \"NFKD\".")
+        .example("طبّع(\"ﻻ\"، \"NFKC\") == \"لا\""),
+        Registration::new(
+            Strings,
+            "ترجم",
+            translate,
+            Arity::new(ArityType::Fixed, 3, 0),
+            "يستبدل كل حرف من حروف \"من\" في النص بالحرف المقابل له في \"إلى\"، ويبقي باقي الحروف كما هي.",
+        )
+        .param("نص", "النص المراد ترجمة حروفه.")
+        .param("من", "الحروف المراد استبدالها.")
+        .param("إلى", "ما تستبدل به، بنفس عدد حروف \"من\".")
+        .example("ترجم(\"١٢٣\"، \"٠١٢٣٤٥٦٧٨٩\"، \"0123456789\")"),
+        Registration::new(
+            Strings,
+            "حروف",
+            chars,
+            unary(),
+            "يعيد قائمة بحروف النص، كل حرف في نص، وتعد الحركات حروفاً مستقلة.",
+        )
+        .param("نص", "النص المراد تقسيمه.")
+        .example("حروف(\"قَتام\")"),
+        Registration::new(
+            Strings,
+            "كلمات",
+            words,
+            unary(),
+            "يعيد قائمة بكلمات النص التي تفصل بينها المسافات وما شابهها، دون كلمات فارغة.",
+        )
+        .param("نص", "النص المراد تقسيمه.")
+        .example("كلمات(\"  السلام   عليكم \")"),
+        Registration::new(
+            Strings,
+            "سطور",
+            lines,
+            unary(),
+            "يعيد قائمة بأسطر النص أياً كانت نهاياتها (\"\\n\" أو \"\\r\\n\" أو \"\\r\")، ولا تضيف نهاية السطر الأخير سطراً فارغاً.",
+        )
+        .param("نص", "النص المراد تقسيمه.")
+        .example("سطور(\"الأول\\r\\nالثاني\\n\")"),
+        Registration::new(
+            Strings,
+            "وحد_الأسطر",
+            unify_newlines_native,
+            unary(),
+            "يعيد النص وقد استبدلت كل \"\\r\\n\" و\"\\r\" فيه بـ\"\\n\".",
+        )
+        .param("نص", "النص المراد توحيد نهايات أسطره.")
+        .example("وحد_الأسطر(\"الأول\\r\\nالثاني\") == \"الأول\\nالثاني\""),
+        Registration::new(
+            Integers,
+            "جمع_صحيح",
+            add_integers,
+            binary(),
+            "يجمع عددين صحيحين ويلقي خطأً إن لم يمكن تمثيل الناتج بدقة.",
+        )
+        .param("أ", "عدد صحيح.")
+        .param("ب", "عدد صحيح.")
+        .example("جمع_صحيح(9007199254740990، 1)"),
+        Registration::new(
+            Integers,
+            "ضرب_صحيح",
+            mul_integers,
+            binary(),
+            "يضرب عددين صحيحين ويلقي خطأً إن لم يمكن تمثيل الناتج بدقة.",
+        )
+        .param("أ", "عدد صحيح.")
+        .param("ب", "عدد صحيح.")
+        .example("ضرب_صحيح(3، 4)"),
+        Registration::new(
+            Integers,
+            "جمع_صحيح32",
+            add_integers32,
+            binary(),
+            "يجمع عددين صحيحين بعد اقتطاعهما إلى 32 بت ويلتف الناتج عند تجاوزه.",
+        )
+        .param("أ", "عدد صحيح.")
+        .param("ب", "عدد صحيح.")
+        .example("جمع_صحيح32(2147483647، 1)"),
+        Registration::new(
+            Integers,
+            "ضرب_صحيح32",
+            mul_integers32,
+            binary(),
+            "يضرب عددين صحيحين بعد اقتطاعهما إلى 32 بت ويلتف الناتج عند تجاوزه.",
+        )
+        .param("أ", "عدد صحيح.")
+        .param("ب", "عدد صحيح.")
+        .example("ضرب_صحيح32(65536، 65536)"),
+    ];
+    // A variadic native documents the parameter its extra arguments are collected in too.
+    debug_assert!(registrations.iter().all(|Registration { doc, .. }| {
+        let variadic = doc.arity.typ() == ArityType::Variadic;
+        doc.params.len() == doc.arity.required() + doc.arity.optional() + variadic as usize
+            && !doc.examples.is_empty()
+    }));
+    registrations
+}